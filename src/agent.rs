@@ -1,5 +1,35 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use crate::agent_cache::{find_in_search_path, AgentCache, CachedAgent};
+use crate::config::Config;
+
+/// Per-user directories where agent CLIs are often installed, relative to home
+const HOME_INSTALL_DIRS: &[&str] = &[
+    ".local/bin",
+    ".npm-global/bin",
+    ".volta/bin",
+    ".bun/bin",
+    ".yarn/bin",
+    ".cargo/bin",
+    ".asdf/shims",
+];
+
+/// System directories that a minimal PATH may leave out
+const SYSTEM_INSTALL_DIRS: &[&str] = &["/usr/local/bin", "/opt/homebrew/bin"];
+
+/// Node version managers keep one `bin` directory per installed version
+const NODE_VERSION_DIRS: &[(&str, &str)] = &[
+    (".nvm/versions/node", "bin"),
+    (".local/share/fnm/node-versions", "installation/bin"),
+];
 
 /// Represents an AI Agent CLI that can be detected
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -7,14 +37,19 @@ pub enum Agent {
     Amp,
     Claude,
     CodeBuddy,
+    Aider,
 }
 
 impl Agent {
+    /// Every known agent, in auto-detection preference order
+    pub const ALL: [Agent; 4] = [Agent::Amp, Agent::Claude, Agent::CodeBuddy, Agent::Aider];
+
     pub fn name(&self) -> &'static str {
         match self {
             Agent::Amp => "Amp",
             Agent::Claude => "Claude Code",
             Agent::CodeBuddy => "CodeBuddy",
+            Agent::Aider => "Aider",
         }
     }
 
@@ -23,18 +58,122 @@ impl Agent {
             Agent::Amp => "amp",
             Agent::Claude => "claude",
             Agent::CodeBuddy => "codebuddy",
+            Agent::Aider => "aider",
         }
     }
 
+    /// Command that installs this agent's CLI
+    pub fn install_command(&self) -> &'static str {
+        match self {
+            Agent::Amp => "npm install -g @sourcegraph/amp",
+            Agent::Claude => "npm install -g @anthropic-ai/claude-code",
+            Agent::CodeBuddy => "npm install -g @tencent-ai/codebuddy-code",
+            Agent::Aider => "python -m pip install aider-install && aider-install",
+        }
+    }
+
+    /// Look up a known agent by its command name (e.g. `claude`)
+    pub fn from_command(cmd: &str) -> Option<Agent> {
+        Agent::ALL
+            .into_iter()
+            .find(|agent| agent.command().eq_ignore_ascii_case(cmd))
+    }
+
     /// Returns the global skills directory for this agent
+    ///
+    /// Aider has no skills, so there is nothing to install for it.
     pub fn global_skills_dir(&self) -> Option<PathBuf> {
         match self {
             Agent::Amp => dirs::config_dir().map(|d| d.join("amp/skills")),
             Agent::Claude => dirs::home_dir().map(|d| d.join(".claude/skills")),
             Agent::CodeBuddy => dirs::home_dir().map(|d| d.join(".codebuddy/skills")),
+            Agent::Aider => None,
         }
     }
 
+    /// How to run this agent unattended for one iteration
+    ///
+    /// This table is the only place that knows how each agent is invoked.
+    pub fn invocation(&self) -> AgentInvocation {
+        match self {
+            Agent::Amp => AgentInvocation {
+                unattended_flag: Some("--dangerously-allow-all"),
+                structured_output: Some(&["--stream-json"]),
+                ..AgentInvocation::CUSTOM
+            },
+            Agent::Claude => AgentInvocation {
+                args: &["--print"],
+                unattended_flag: Some("--dangerously-skip-permissions"),
+                structured_output: Some(&["--output-format", "stream-json", "--verbose"]),
+                reports_usage: true,
+                ..AgentInvocation::CUSTOM
+            },
+            Agent::CodeBuddy => AgentInvocation {
+                args: &["-p", "--tools", "default"],
+                unattended_flag: Some("--dangerously-skip-permissions"),
+                ..AgentInvocation::CUSTOM
+            },
+            // aider is interactive unless given --message; --yes-always (formerly
+            // --yes) accepts its confirmations
+            Agent::Aider => AgentInvocation {
+                unattended_flag: Some("--yes-always"),
+                prompt: PromptDelivery::Flag("--message"),
+                ..AgentInvocation::CUSTOM
+            },
+        }
+    }
+}
+
+/// How the prompt reaches an agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptDelivery {
+    /// Written to the agent's stdin
+    Stdin,
+    /// Passed as the value of this flag
+    Flag(&'static str),
+    /// Written to a temporary file whose path is the value of this flag
+    File(&'static str),
+}
+
+/// What ralph needs to know to run an agent for one iteration
+///
+/// `run` consumes only this descriptor, so supporting a new agent means
+/// adding an entry to [`Agent::invocation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AgentInvocation {
+    /// Flags always passed, after the unattended flag and before the prompt
+    pub args: &'static [&'static str],
+    /// Flag that lets the agent act without asking for permission, if it has one
+    pub unattended_flag: Option<&'static str>,
+    pub prompt: PromptDelivery,
+    /// The agent logs progress to stderr, so stderr is shown dimmed rather than as errors
+    pub noisy_stderr: bool,
+    /// Flags switching the agent to machine-readable output, if it has them
+    pub structured_output: Option<&'static [&'static str]>,
+    /// The structured output ends with the iteration's token usage and cost
+    pub reports_usage: bool,
+}
+
+impl AgentInvocation {
+    /// Custom tools get no extra flags and read the prompt from stdin
+    pub const CUSTOM: AgentInvocation = AgentInvocation {
+        args: &[],
+        unattended_flag: None,
+        prompt: PromptDelivery::Stdin,
+        noisy_stderr: false,
+        structured_output: None,
+        reports_usage: false,
+    };
+
+    /// Invocation for a tool command, known agent or custom
+    pub fn for_tool(tool_cmd: &str) -> Self {
+        Agent::from_command(tool_cmd).map_or(Self::CUSTOM, |agent| agent.invocation())
+    }
+
+    /// Flags placed before the prompt: the unattended flag, then the base args
+    pub fn leading_args(&self) -> impl Iterator<Item = &'static str> {
+        self.unattended_flag.into_iter().chain(self.args.iter().copied())
+    }
 }
 
 /// Installation target location
@@ -59,16 +198,291 @@ impl InstallTarget {
     }
 }
 
+/// Set from the global `--refresh-detect` flag at startup
+static REFRESH_DETECTION: AtomicBool = AtomicBool::new(false);
+
+/// Make the next detection ignore the cache (global `--refresh-detect`)
+pub fn set_refresh_detection(value: bool) {
+    REFRESH_DETECTION.store(value, Ordering::SeqCst);
+}
+
 /// Detects which agent CLIs are available in PATH
+///
+/// Results are reused from `agents-cache.json` (next to the config file) for
+/// `agent_cache_ttl_hours` while PATH stays the same, so most runs don't
+/// spawn any version probes. With `--refresh-detect`, the first detection
+/// re-probes and rewrites the cache; later ones in the process use it.
 pub fn detect_agents() -> Vec<Agent> {
-    let agents = vec![Agent::Amp, Agent::Claude, Agent::CodeBuddy];
-    agents
+    let ttl_hours = Config::load()
+        .ok()
+        .and_then(|c| c.agent_cache_ttl_hours)
+        .unwrap_or(24);
+    if ttl_hours == 0 {
+        return probe_agents();
+    }
+    if REFRESH_DETECTION.swap(false, Ordering::SeqCst) {
+        return refresh_agents();
+    }
+
+    let cache_path = AgentCache::path();
+    let ttl = Duration::from_secs(ttl_hours.saturating_mul(3600));
+    let search_path = std::env::var_os("PATH").unwrap_or_default();
+    let cached = cache_path
+        .as_deref()
+        .and_then(AgentCache::load)
+        .filter(|cache| cache.is_valid(SystemTime::now(), ttl, &search_path))
+        .and_then(|cache| agents_from_cache(&cache));
+
+    match cached {
+        Some(agents) => agents,
+        None => detect_and_cache(cache_path.as_deref()),
+    }
+}
+
+/// Detect agents without using the cache, then cache the new results
+pub fn refresh_agents() -> Vec<Agent> {
+    detect_and_cache(AgentCache::path().as_deref())
+}
+
+/// Probe every known agent by running `<command> --version`
+fn probe_agents() -> Vec<Agent> {
+    Agent::ALL
         .into_iter()
         .filter(|agent| is_command_available(agent.command()))
         .collect()
 }
 
+fn detect_and_cache(cache_path: Option<&Path>) -> Vec<Agent> {
+    let agents = probe_agents();
+
+    // Only cache when every agent resolved to a binary that can be checked later
+    let entries: Option<Vec<CachedAgent>> = agents
+        .iter()
+        .map(|agent| CachedAgent::probe(agent.command()))
+        .collect();
+    if let (Some(path), Some(entries)) = (cache_path, entries) {
+        // The cache is an optimization; failing to write it is not an error
+        let _ = AgentCache::new(entries).save(path);
+    }
+
+    agents
+}
+
+/// Read the agents from a cache, also reusing the cached versions
+///
+/// Returns `None` if the cache names an unknown agent.
+fn agents_from_cache(cache: &AgentCache) -> Option<Vec<Agent>> {
+    let agents = cache
+        .agents
+        .iter()
+        .map(|cached| Agent::from_command(&cached.command))
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut versions = version_memo().lock().unwrap();
+    for cached in &cache.agents {
+        versions
+            .entry(cached.command.clone())
+            .or_insert_with(|| Some(cached.version.clone()));
+    }
+
+    Some(agents)
+}
+
+/// How to install a known agent, given its command name or a path to it
+///
+/// `/opt/bin/claude` gets the hint for `claude`; unknown tools get none.
+pub fn install_hint(tool: &str) -> Option<&'static str> {
+    let name = Path::new(tool).file_name()?.to_str()?;
+    Agent::from_command(name).map(|agent| agent.install_command())
+}
+
 /// Check if a command is available in PATH
 pub fn is_command_available(cmd: &str) -> bool {
-    Command::new(cmd).arg("--version").output().is_ok()
+    command_version(cmd).is_some()
+}
+
+/// Check a single agent or arbitrary command, returning its version if installed
+///
+/// Known agent names are matched case-insensitively; anything else is run as-is.
+pub fn check_agent(tool: &str) -> Option<String> {
+    let cmd = Agent::from_command(tool).map_or(tool, |agent| agent.command());
+    command_version(cmd)
+}
+
+/// Get the `--version` output of a command, or `None` if it cannot be run
+///
+/// The result is memoized per command, so availability checks and version
+/// capture share a single invocation.
+pub fn command_version(cmd: &str) -> Option<String> {
+    command_version_in(cmd, Path::new("."))
+}
+
+/// Like [`command_version`], but runs the command inside `dir`
+///
+/// Used for the agent a run is about to start, so anything its `--version`
+/// leaves behind lands in the ralph directory rather than the caller's.
+pub fn command_version_in(cmd: &str, dir: &Path) -> Option<String> {
+    let cache = version_memo();
+
+    if let Some(cached) = cache.lock().unwrap().get(cmd) {
+        return cached.clone();
+    }
+
+    let version = Command::new(cmd).arg("--version").current_dir(dir).output().ok().map(|output| {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        stdout
+            .lines()
+            .chain(stderr.lines())
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or("unknown")
+            .to_string()
+    });
+
+    cache.lock().unwrap().insert(cmd.to_string(), version.clone());
+    version
+}
+
+/// `--version` results per command for this process
+fn version_memo() -> &'static Mutex<HashMap<String, Option<String>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<String>>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Directories worth checking for an agent that is missing from PATH
+///
+/// Covers per-user install prefixes, every Node version installed through
+/// nvm or fnm, and common system directories.
+pub fn common_install_dirs(home: Option<&Path>) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(home) = home {
+        dirs.extend(HOME_INSTALL_DIRS.iter().map(|dir| home.join(dir)));
+        for (versions, bin) in NODE_VERSION_DIRS {
+            let Ok(entries) = fs::read_dir(home.join(versions)) else {
+                continue;
+            };
+            let mut installed: Vec<PathBuf> =
+                entries.flatten().map(|entry| entry.path().join(bin)).collect();
+            installed.sort();
+            dirs.extend(installed);
+        }
+    }
+    dirs.extend(SYSTEM_INSTALL_DIRS.iter().map(PathBuf::from));
+    dirs
+}
+
+/// Installed copies of a command that the given PATH does not reach
+pub fn find_outside_path(cmd: &str, path: &OsStr, home: Option<&Path>) -> Vec<PathBuf> {
+    let on_path: Vec<PathBuf> = std::env::split_paths(path).collect();
+    common_install_dirs(home)
+        .into_iter()
+        .filter(|dir| !on_path.contains(dir))
+        .filter_map(|dir| find_in_search_path(cmd, dir.as_os_str()))
+        .collect()
+}
+
+/// Explain why an agent could not be spawned and what to try next
+///
+/// `program` is what was spawned and `path` the PATH it was looked up in.
+/// With `verbose`, every PATH entry and every location checked is listed.
+pub fn spawn_error_guidance(
+    program: &str,
+    error: &io::Error,
+    path: &OsStr,
+    home: Option<&Path>,
+    verbose: bool,
+) -> String {
+    let is_path = program.contains(['/', std::path::MAIN_SEPARATOR]);
+    let mut lines = vec![format!("Failed to spawn {}: {}", program, error)];
+
+    match error.kind() {
+        io::ErrorKind::NotFound if is_path && Path::new(program).exists() => {
+            lines.push(format!(
+                "{} exists, so the interpreter on its #! line is probably missing.",
+                program
+            ));
+        }
+        io::ErrorKind::NotFound if is_path => {
+            lines.push(format!("There is no file at {}.", program));
+        }
+        io::ErrorKind::NotFound => {
+            lines.push(format!("{} is not on the PATH ralph sees.", program));
+            let found = find_outside_path(program, path, home);
+            match found.first() {
+                Some(first) => {
+                    lines.push("It is installed at:".to_string());
+                    lines.extend(found.iter().map(|p| format!("  {}", p.display())));
+                    lines.push(format!(
+                        "Add {} to PATH in your shell rc file (~/.bashrc, ~/.zshrc), \
+                         or pass --tool-path {}.",
+                        first.parent().unwrap_or(first).display(),
+                        first.display()
+                    ));
+                }
+                None => lines.push(
+                    "It was not found in the usual install locations either. If it comes \
+                     from a version manager such as nvm, make sure your shell rc file loads \
+                     it, or pass --tool-path with the full path."
+                        .to_string(),
+                ),
+            }
+        }
+        io::ErrorKind::PermissionDenied => match find_in_search_path(program, path) {
+            Some(target) => lines.push(format!(
+                "{} is not executable. Try: chmod +x {}",
+                target.display(),
+                target.display()
+            )),
+            None => lines.push(format!(
+                "{} or a directory leading to it is not executable.",
+                program
+            )),
+        },
+        io::ErrorKind::IsADirectory => {
+            lines.push(format!("{} is a directory, not an executable.", program));
+        }
+        io::ErrorKind::ExecutableFileBusy => {
+            lines.push(format!(
+                "{} is still being written by another process; try again.",
+                program
+            ));
+        }
+        io::ErrorKind::ArgumentListTooLong => {
+            lines.push(
+                "The prompt is too long to pass as an argument; use an agent that reads \
+                 the prompt from stdin."
+                    .to_string(),
+            );
+        }
+        _ => {
+            lines.push(format!("Check that {} runs from this shell.", program));
+        }
+    }
+    lines.push("Run 'ralph detect' to see which agents ralph can find.".to_string());
+
+    if verbose {
+        lines.push("PATH ralph saw:".to_string());
+        let entries: Vec<PathBuf> = std::env::split_paths(path)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .collect();
+        if entries.is_empty() {
+            lines.push("  (empty)".to_string());
+        }
+        lines.extend(entries.iter().map(|dir| format!("  {}", dir.display())));
+        if !is_path {
+            lines.push("Also checked:".to_string());
+            lines.extend(
+                common_install_dirs(home)
+                    .iter()
+                    .filter(|dir| !entries.contains(dir))
+                    .map(|dir| format!("  {}", dir.display())),
+            );
+        }
+    } else {
+        lines.push(
+            "Pass --verbose-spawn-errors to list the PATH and locations checked.".to_string(),
+        );
+    }
+    lines.join("\n")
 }
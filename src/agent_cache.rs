@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::agent::command_version;
+use crate::config::Config;
+
+/// Agent detection cache file, stored next to the config file
+pub const AGENT_CACHE_FILE: &str = "agents-cache.json";
+
+/// A detected agent binary and its version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedAgent {
+    pub command: String,
+    pub version: String,
+    pub path: PathBuf,
+    /// Modification time of the binary, in seconds since the epoch
+    pub modified: Option<u64>,
+}
+
+impl CachedAgent {
+    /// Probe a command, returning `None` when it is missing or not found in PATH
+    pub fn probe(cmd: &str) -> Option<Self> {
+        let path = find_in_path(cmd)?;
+        let version = command_version(cmd)?;
+        let modified = fs::metadata(&path).ok().and_then(|m| modified_secs(&m));
+        Some(Self {
+            command: cmd.to_string(),
+            version,
+            path,
+            modified,
+        })
+    }
+}
+
+/// Agents found by the last detection run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentCache {
+    /// When detection ran, in seconds since the epoch
+    pub detected_at: u64,
+    /// PATH when detection ran; another PATH can resolve other binaries
+    #[serde(default)]
+    pub search_path: Option<String>,
+    pub agents: Vec<CachedAgent>,
+}
+
+impl AgentCache {
+    /// Create a cache for agents detected just now, with the current PATH
+    pub fn new(agents: Vec<CachedAgent>) -> Self {
+        let detected_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            detected_at,
+            search_path: env::var_os("PATH").map(|path| path.to_string_lossy().into_owned()),
+            agents,
+        }
+    }
+
+    /// Location of the cache file, beside the config file
+    pub fn path() -> Option<PathBuf> {
+        Config::config_file().map(|file| file.with_file_name(AGENT_CACHE_FILE))
+    }
+
+    /// Load a cache file; a missing or corrupt file is simply no cache
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Write the cache file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, content)
+    }
+
+    /// Whether the cached results can still be used at `now` with `search_path` as PATH
+    ///
+    /// The cache expires after `ttl`, when PATH changes (a cache written
+    /// before PATH was recorded counts as changed), and as soon as a cached
+    /// binary is removed or replaced.
+    pub fn is_valid(&self, now: SystemTime, ttl: Duration, search_path: &OsStr) -> bool {
+        let detected_at = UNIX_EPOCH + Duration::from_secs(self.detected_at);
+        // A detection time in the future means the clock moved; don't trust it
+        let fresh = now
+            .duration_since(detected_at)
+            .is_ok_and(|age| age < ttl);
+
+        fresh
+            && self.search_path.as_deref() == Some(&*search_path.to_string_lossy())
+            && self.agents.iter().all(|agent| {
+                fs::metadata(&agent.path)
+                    .is_ok_and(|m| m.is_file() && modified_secs(&m) == agent.modified)
+            })
+    }
+}
+
+/// Find the executable PATH would run for `cmd`
+///
+/// Commands containing a path separator are checked as given.
+pub fn find_in_path(cmd: &str) -> Option<PathBuf> {
+    find_in_search_path(cmd, &env::var_os("PATH")?)
+}
+
+/// Like [`find_in_path`], but searching the given PATH value
+pub fn find_in_search_path(cmd: &str, path: &OsStr) -> Option<PathBuf> {
+    let cmd_path = Path::new(cmd);
+    if cmd_path.components().count() > 1 {
+        return cmd_path.is_file().then(|| cmd_path.to_path_buf());
+    }
+
+    let extensions: &[&str] = if cfg!(windows) {
+        &["", "exe", "cmd", "bat"]
+    } else {
+        &[""]
+    };
+    env::split_paths(path).find_map(|dir| {
+        extensions
+            .iter()
+            .map(|ext| match *ext {
+                "" => dir.join(cmd),
+                ext => dir.join(format!("{}.{}", cmd, ext)),
+            })
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+fn modified_secs(metadata: &fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
@@ -0,0 +1,115 @@
+use std::ops::Range;
+
+use crate::prd::Prd;
+
+/// Default AGENTS.md location, relative to the project root
+pub const AGENTS_MD_FILE: &str = "AGENTS.md";
+
+/// Marker opening the ralph-managed section
+pub const BEGIN_MARKER: &str = "<!-- ralph:begin -->";
+
+/// Marker closing the ralph-managed section
+pub const END_MARKER: &str = "<!-- ralph:end -->";
+
+/// Render ralph's AGENTS.md section, including the markers
+///
+/// The project summary comes from the PRD when one is available.
+pub fn render_section(prd: Option<&Prd>) -> String {
+    let project = match prd {
+        Some(prd) => format!(
+            "**{}** - {}\n\nBranch: `{}` ({}/{} stories complete)",
+            prd.project,
+            prd.description,
+            prd.branch_name(),
+            prd.completed_stories(),
+            prd.total_stories()
+        ),
+        None => "No PRD yet. Create `ralph/prd.json`, then run `ralph agents-md refresh`."
+            .to_string(),
+    };
+
+    format!(
+        "{BEGIN_MARKER}
+<!-- Managed by ralph; run `ralph agents-md refresh` to update. Edits inside the markers are overwritten. -->
+## Ralph
+
+### Project
+
+{project}
+
+### Workflow
+
+Ralph runs an AI agent in a loop. Each iteration the agent:
+
+1. Reads the PRD and the progress log
+2. Picks the highest priority story where `passes` is false
+3. Implements it, runs the quality checks and commits
+4. Sets the story's `passes` to true and appends to the progress log
+
+### Files
+
+- `ralph/prd.json` - the PRD with the user stories to implement
+- `ralph/progress.txt` - append-only progress log; read its Codebase Patterns section first
+- `ralph/archive/` - previous runs, archived when the branch changes
+
+### Completion markers
+
+- After finishing a story, output `<promise>STORY_PASSED:<story id></promise>`
+- When every story passes, output `<promise>COMPLETE</promise>` to end the run
+{END_MARKER}"
+    )
+}
+
+/// Locate the ralph section in `content`, from the begin marker to the end marker
+///
+/// Returns `Ok(None)` when neither marker is present, and an error when the
+/// markers are duplicated or unbalanced.
+pub fn find_section(content: &str) -> Result<Option<Range<usize>>, String> {
+    let begins: Vec<usize> = content.match_indices(BEGIN_MARKER).map(|(i, _)| i).collect();
+    let ends: Vec<usize> = content.match_indices(END_MARKER).map(|(i, _)| i).collect();
+
+    match (begins.as_slice(), ends.as_slice()) {
+        ([], []) => Ok(None),
+        ([begin], [end]) if begin < end => Ok(Some(*begin..*end + END_MARKER.len())),
+        ([_], [_]) => Err(format!("{} appears before {}", END_MARKER, BEGIN_MARKER)),
+        _ if begins.len() > 1 || ends.len() > 1 => Err(format!(
+            "found {} {} and {} {} markers; expected one of each",
+            begins.len(),
+            BEGIN_MARKER,
+            ends.len(),
+            END_MARKER
+        )),
+        _ => Err(format!(
+            "{} and {} must both be present",
+            BEGIN_MARKER, END_MARKER
+        )),
+    }
+}
+
+/// Replace the ralph section in `content`, or append it when there is none
+///
+/// Content outside the markers is left untouched, and the section is written
+/// with CRLF line endings when the file already uses them.
+pub fn splice_section(content: &str, section: &str) -> Result<String, String> {
+    let crlf = content.contains("\r\n");
+    let newline = if crlf { "\r\n" } else { "\n" };
+    let section = if crlf {
+        section.replace("\r\n", "\n").replace('\n', "\r\n")
+    } else {
+        section.to_string()
+    };
+
+    match find_section(content)? {
+        Some(range) => Ok(format!(
+            "{}{}{}",
+            &content[..range.start],
+            section,
+            &content[range.end..]
+        )),
+        None if content.trim().is_empty() => Ok(format!("{}{}", section, newline)),
+        None => {
+            let body = content.trim_end_matches(['\r', '\n']);
+            Ok(format!("{body}{newline}{newline}{section}{newline}"))
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use chrono::NaiveDate;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::metadata::{timestamp, ARCHIVE_METADATA_FILE};
+
+/// Description of the bundle written at the root of every exported archive
+pub const BUNDLE_README: &str = "README.txt";
+
+/// An archived run folder, named `<YYYY-MM-DD>-<branch>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub date: Option<NaiveDate>,
+}
+
+impl ArchiveEntry {
+    /// Build an entry from an archive folder path, parsing its date prefix
+    pub fn from_path(path: PathBuf) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let date = name
+            .get(..10)
+            .and_then(|prefix| NaiveDate::parse_from_str(prefix, "%Y-%m-%d").ok());
+        Self { name, path, date }
+    }
+
+    /// Total size of the archive folder in bytes
+    pub fn size(&self) -> u64 {
+        dir_size(&self.path)
+    }
+}
+
+/// List archive folders, newest first
+///
+/// Folders without a date prefix sort last, in name order.
+pub fn list_archives(archive_dir: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    if !archive_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(archive_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            entries.push(ArchiveEntry::from_path(entry.path()));
+        }
+    }
+    entries.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| b.name.cmp(&a.name)));
+    Ok(entries)
+}
+
+/// Retention policy for `ralph archive clean`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetentionPolicy {
+    /// Keep this many of the newest archives
+    pub keep: Option<usize>,
+    /// Keep archives dated within this many days
+    pub older_than_days: Option<i64>,
+}
+
+/// Select the archives that fall outside the retention policy
+///
+/// `archives` must be sorted newest first, as returned by [`list_archives`].
+/// An archive is kept if any rule keeps it, and archives without a
+/// parseable date are never selected.
+pub fn archives_to_prune(
+    archives: &[ArchiveEntry],
+    policy: RetentionPolicy,
+    today: NaiveDate,
+) -> Vec<&ArchiveEntry> {
+    if policy.keep.is_none() && policy.older_than_days.is_none() {
+        return Vec::new();
+    }
+
+    archives
+        .iter()
+        .filter(|a| a.date.is_some())
+        .enumerate()
+        .filter(|(index, archive)| {
+            let kept_by_count = policy.keep.is_some_and(|keep| *index < keep);
+            let kept_by_age = policy.older_than_days.is_some_and(|days| {
+                archive
+                    .date
+                    .is_some_and(|date| (today - date).num_days() <= days)
+            });
+            !kept_by_count && !kept_by_age
+        })
+        .map(|(_, archive)| archive)
+        .collect()
+}
+
+/// Folder for a failed run's snapshot, `<YYYY-MM-DD>-<name>-error`
+///
+/// A numeric suffix (`-error-2`, `-error-3`, ...) keeps earlier snapshots
+/// from the same day.
+pub fn error_archive_dir(archive_dir: &Path, date: NaiveDate, name: &str) -> PathBuf {
+    numbered_archive_dir(archive_dir, format!("{}-{}-error", date.format("%Y-%m-%d"), name))
+}
+
+/// Folder for a progress log set aside at startup, `<YYYY-MM-DD>-<name>-progress`
+///
+/// Numbered like [`error_archive_dir`].
+pub fn progress_archive_dir(archive_dir: &Path, date: NaiveDate, name: &str) -> PathBuf {
+    numbered_archive_dir(archive_dir, format!("{}-{}-progress", date.format("%Y-%m-%d"), name))
+}
+
+/// `base`, or `base-2`, `base-3`, ... when the folder already exists
+fn numbered_archive_dir(archive_dir: &Path, base: String) -> PathBuf {
+    let mut candidate = archive_dir.join(&base);
+    let mut attempt = 2;
+    while candidate.exists() {
+        candidate = archive_dir.join(format!("{}-{}", base, attempt));
+        attempt += 1;
+    }
+    candidate
+}
+
+/// Recursively compute the size of a directory in bytes
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            _ => entry.metadata().map(|m| m.len()).unwrap_or(0),
+        })
+        .sum()
+}
+
+/// Format a byte count for display, e.g. `1.5 MB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Pack an archive folder into a gzip'd tarball at `out`
+///
+/// The bundle holds the folder under its own name, next to a generated
+/// [`BUNDLE_README`] describing the contents.
+pub fn export_archive(archive_path: &Path, out: &Path) -> io::Result<()> {
+    let name = archive_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| invalid_bundle("the archive path has no folder name".to_string()))?;
+    let files = archive_files(archive_path, Path::new(""))?;
+    let readme = bundle_readme(&name, &files);
+
+    let mut builder = tar::Builder::new(GzEncoder::new(File::create(out)?, Compression::default()));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(readme.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    );
+    header.set_cksum();
+    builder.append_data(&mut header, BUNDLE_README, readme.as_bytes())?;
+    builder.append_dir_all(&name, archive_path)?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Files of an archive folder relative to it, sorted
+fn archive_files(dir: &Path, prefix: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let relative = prefix.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            files.extend(archive_files(&entry.path(), &relative)?);
+        } else {
+            files.push(relative);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Text of the README packed into an exported bundle
+pub fn bundle_readme(name: &str, files: &[PathBuf]) -> String {
+    let mut readme = format!(
+        "Ralph run archive: {}\nExported {} by ralph {}\n\nContents:\n",
+        name,
+        timestamp(),
+        env!("CARGO_PKG_VERSION")
+    );
+    for file in files {
+        let path = format!("{}/{}", name, file.display());
+        let about = match file.to_str() {
+            Some("prd.json") => "the PRD as it was when the run was archived",
+            Some("progress.txt") => "the agent's progress log",
+            Some(ARCHIVE_METADATA_FILE) => "branch, archive time and tool versions",
+            _ if file.starts_with("logs") => "agent output log",
+            _ => "",
+        };
+        if about.is_empty() {
+            readme.push_str(&format!("  {}\n", path));
+        } else {
+            readme.push_str(&format!("  {:<30} {}\n", path, about));
+        }
+    }
+    readme.push_str("\nImport it with: ralph archive import <bundle>\n");
+    readme
+}
+
+/// The archive folder a bundle holds, with its files relative to the folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleContents {
+    pub name: String,
+    pub files: Vec<PathBuf>,
+}
+
+/// Read an exported bundle and check its structure
+///
+/// A bundle holds a single archive folder with a prd.json, plus an optional
+/// [`BUNDLE_README`]. Only plain files and folders with relative paths are
+/// accepted, so unpacking can never write outside the archive directory.
+pub fn inspect_bundle(bundle: &Path) -> io::Result<BundleContents> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(bundle)?));
+    let mut name: Option<String> = None;
+    let mut files = Vec::new();
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        let kind = entry.header().entry_type();
+        if !kind.is_file() && !kind.is_dir() {
+            return Err(invalid_bundle(format!("{} is not a plain file", path.display())));
+        }
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(invalid_bundle(format!("unsafe path {}", path.display())));
+        }
+        if path == Path::new(BUNDLE_README) {
+            continue;
+        }
+
+        let mut components = path.components();
+        let Some(top) = components.next() else {
+            continue;
+        };
+        let top = top.as_os_str().to_string_lossy().to_string();
+        match &name {
+            Some(existing) if *existing != top => {
+                return Err(invalid_bundle(format!(
+                    "it holds more than one archive ({} and {})",
+                    existing, top
+                )));
+            }
+            Some(_) => {}
+            None if components.as_path().as_os_str().is_empty() && kind.is_file() => {
+                return Err(invalid_bundle(format!("unexpected file {}", top)));
+            }
+            None => name = Some(top),
+        }
+        if kind.is_file() {
+            files.push(components.as_path().to_path_buf());
+        }
+    }
+
+    let name = name.ok_or_else(|| invalid_bundle("it holds no archive folder".to_string()))?;
+    if !files.iter().any(|f| f == Path::new("prd.json")) {
+        return Err(invalid_bundle(format!("{} has no prd.json", name)));
+    }
+    files.sort();
+    Ok(BundleContents { name, files })
+}
+
+/// Unpack a bundle checked by [`inspect_bundle`] into the archive directory
+///
+/// Returns the path of the restored archive folder.
+pub fn unpack_bundle(bundle: &Path, archive_dir: &Path) -> io::Result<PathBuf> {
+    let contents = inspect_bundle(bundle)?;
+    fs::create_dir_all(archive_dir)?;
+
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(bundle)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()? == Path::new(BUNDLE_README) {
+            continue;
+        }
+        entry.unpack_in(archive_dir)?;
+    }
+    Ok(archive_dir.join(contents.name))
+}
+
+fn invalid_bundle(reason: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("not a ralph archive bundle: {}", reason),
+    )
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::git::{branch_moves, current_branch, BranchMove};
+
+/// Git branch that was checked out when the last run stopped
+pub const LAST_GIT_BRANCH_FILE: &str = ".last-git-branch";
+
+/// Evidence that the previous run's files belong to another branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchChange {
+    /// Branch the previous run worked on, which names its archive
+    pub previous: String,
+    /// What detected the change, recorded in the archive's metadata
+    pub signals: Vec<String>,
+}
+
+/// What git knows about the branch of the repository holding the ralph directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitBranchState {
+    pub current: String,
+    /// Branch from `.last-git-branch`, if the marker is there
+    pub recorded: Option<String>,
+    /// Branch moves since `progress.txt` was last written, oldest first
+    ///
+    /// Only read when the marker is missing, as the fallback for it.
+    pub moves: Vec<BranchMove>,
+}
+
+impl GitBranchState {
+    /// Read the state for `ralph_dir`, or `None` outside git or on a
+    /// detached HEAD
+    pub fn read(ralph_dir: &Path) -> Option<Self> {
+        let current = current_branch(ralph_dir)?;
+        let recorded = fs::read_to_string(ralph_dir.join(LAST_GIT_BRANCH_FILE))
+            .ok()
+            .map(|branch| branch.trim().to_string())
+            .filter(|branch| !branch.is_empty());
+
+        let last_written = fs::metadata(ralph_dir.join("progress.txt"))
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|age| age.as_secs() as i64);
+        let moves = match (&recorded, last_written) {
+            (None, Some(since)) => {
+                let mut moves: Vec<BranchMove> = branch_moves(ralph_dir)
+                    .into_iter()
+                    .filter(|m| m.at > since)
+                    .collect();
+                moves.reverse();
+                moves
+            }
+            _ => Vec::new(),
+        };
+
+        Some(Self {
+            current,
+            recorded,
+            moves,
+        })
+    }
+}
+
+/// Decide whether the work moved to another branch since the last run
+///
+/// `.last-branch` changes when the PRD's branchName does; git catches a
+/// branch renamed or switched without touching the PRD, and a `.last-branch`
+/// that was deleted. Either signal is enough. The previous branch comes
+/// from `.last-branch` when it has one, since that is the name the old
+/// PRD used.
+pub fn detect_branch_change(
+    last_branch: Option<&str>,
+    prd_branch: &str,
+    git: Option<&GitBranchState>,
+) -> Option<BranchChange> {
+    let mut previous = None;
+    let mut signals = Vec::new();
+
+    if let Some(last) = last_branch
+        .map(str::trim)
+        .filter(|last| !last.is_empty() && *last != prd_branch)
+    {
+        previous = Some(last.to_string());
+        signals.push(format!(".last-branch: {} -> {}", last, prd_branch));
+    }
+
+    if let Some(git) = git {
+        match &git.recorded {
+            Some(recorded) if *recorded != git.current => {
+                previous.get_or_insert_with(|| recorded.clone());
+                signals.push(format!("git branch: {} -> {}", recorded, git.current));
+            }
+            Some(_) => {}
+            // Moving away and back again is not a change
+            None => match git.moves.first() {
+                Some(first) if first.from != git.current => {
+                    previous.get_or_insert_with(|| first.from.clone());
+                    signals.push(format!(
+                        "git reflog ({}): {} -> {}",
+                        if first.renamed { "renamed" } else { "checkout" },
+                        first.from,
+                        git.current
+                    ));
+                }
+                _ => {}
+            },
+        }
+    }
+
+    previous.map(|previous| BranchChange { previous, signals })
+}
+
+/// Remember the checked-out git branch for the next run's check
+///
+/// Does nothing outside git or on a detached HEAD.
+pub fn record_git_branch(ralph_dir: &Path) -> io::Result<()> {
+    match current_branch(ralph_dir) {
+        Some(branch) => fs::write(ralph_dir.join(LAST_GIT_BRANCH_FILE), branch),
+        None => Ok(()),
+    }
+}
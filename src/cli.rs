@@ -1,39 +1,246 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use crate::agents_md::AGENTS_MD_FILE;
+use crate::prd::parse_json_pointer;
+use crate::usage::{parse_budget, Budget};
+
+/// Default location of the PRD, relative to the project root
+pub const DEFAULT_PRD_PATH: &str = "./ralph/prd.json";
 
 /// Ralph CLI - AI Agent aggregation tool
 ///
 /// Provides interactive skill installation, guided project initialization,
-/// and task launch experience for AI agents like Amp, Claude, and CodeBuddy.
+/// and task launch experience for AI agents like Amp, Claude, CodeBuddy, and Aider.
 #[derive(Parser)]
 #[command(name = "ralph")]
 #[command(about = "Ralph CLI - AI Agent aggregation tool")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(author = env!("CARGO_PKG_AUTHORS"))]
 pub struct Cli {
+    /// Answer yes to every confirmation prompt (or set RALPH_ASSUME_YES=1)
+    #[arg(short = 'y', long, global = true)]
+    pub yes: bool,
+
+    /// Use this config file instead of the default location
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// JSON Pointer to the PRD inside a larger prd.json document, e.g. /ralph
+    #[arg(long, global = true, value_name = "POINTER", value_parser = parse_json_pointer)]
+    pub prd_key: Option<String>,
+
+    /// When to use colored output
+    #[arg(long, global = true, value_name = "WHEN", default_value = "auto")]
+    pub color: ColorChoice,
+
+    /// Disable colored output (same as --color never)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Ignore cached agent detection results and probe the agents again
+    #[arg(long, global = true)]
+    pub refresh_detect: bool,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+/// Values of the global `--color` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Always emit colors, even when output is piped
+    Always,
+    /// Emit colors when writing to a terminal
+    Auto,
+    /// Never emit colors
+    Never,
+}
+
+/// Values of the `--format` flag on read-only commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    Table,
+    /// Pretty-printed JSON
+    Json,
+    /// YAML
+    Yaml,
+}
+
+/// Story fields `ralph story edit --field` can set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StoryField {
+    Title,
+    Description,
+    /// A positive integer; lower runs first
+    Priority,
+    Notes,
+    /// One criterion per line of the value
+    AcceptanceCriteria,
+}
+
+/// Values of `ralph run --story-order`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum StoryOrderChoice {
+    /// Lowest priority number first
+    #[default]
+    Priority,
+    /// As written in prd.json
+    File,
+    /// Shuffled, reproducible with --seed
+    Random,
+}
+
+// Parsed once per process, so the size of the `Run` variant does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// Initialize a new Ralph project
-    Init,
+    Init {
+        /// Reinitialize an existing project, regenerating its files (the old ones are backed up)
+        #[arg(long)]
+        force: bool,
+        /// Save the chosen default tool to the project's ralph/config.toml instead of the global config
+        #[arg(long)]
+        local: bool,
+    },
     /// Install skills to agents
     Install,
     /// Run Ralph tasks
     Run {
-        /// AI tool to use (amp/claude/codebuddy/auto)
+        /// AI tool to use (amp/claude/codebuddy/aider/auto)
         #[arg(long, default_value = "auto")]
         tool: String,
         /// Maximum iterations (default: 10)
         #[arg(long)]
         max_iterations: Option<u32>,
         /// Path to prd.json file
-        #[arg(long, default_value = "./ralph/prd.json")]
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
         prd: String,
+        /// Also stream JSON events to a named pipe or Unix socket (a file on Windows)
+        #[arg(long, value_name = "PATH")]
+        stream_to: Option<PathBuf>,
+        /// Set an environment variable for the agent (repeatable)
+        #[arg(long, value_name = "KEY=VALUE")]
+        env: Vec<String>,
+        /// Load environment variables for the agent from a dotenv file
+        #[arg(long, value_name = "PATH")]
+        env_file: Option<PathBuf>,
+        /// Only work on this story (e.g. US-001), stopping once it passes
+        #[arg(long, value_name = "ID")]
+        story: Option<String>,
+        /// Only work on the stories of this epic, stopping once they all pass
+        #[arg(long, value_name = "NAME", conflicts_with = "story")]
+        epic: Option<String>,
+        /// Work through stories as usual, but stop once this story passes
+        #[arg(long, visible_alias = "stop-on-story", value_name = "ID", conflicts_with = "story")]
+        until: Option<String>,
+        /// Leave this story alone for this run only (repeatable)
+        #[arg(long, value_name = "ID")]
+        exclude_story: Vec<String>,
+        /// Only work on stories with this tag (repeatable; any tag matches)
+        #[arg(long, value_name = "TAG", conflicts_with = "story")]
+        tag: Vec<String>,
+        /// Only work on stories with this priority or a more urgent one (a lower number)
+        #[arg(long, value_name = "N", conflicts_with = "story")]
+        max_priority: Option<u32>,
+        /// Archive the previous run even if the PRD now names a different project
+        #[arg(long)]
+        force_archive: bool,
+        /// Do not archive or reset the previous run when the PRD branch changed
+        #[arg(long, conflicts_with = "force_archive")]
+        ignore_branch_archive: bool,
+        /// Archive and reset progress.txt without asking when it belongs to another branch
+        #[arg(long)]
+        reset_progress: bool,
+        /// Run this agent executable instead of looking the tool up in PATH
+        #[arg(long, value_name = "PATH")]
+        tool_path: Option<PathBuf>,
+        /// Continue the iteration budget of an interrupted run without asking
+        #[arg(long)]
+        resume: bool,
+        /// Stop an iteration once the agent prints more than this many bytes (0 = no limit)
+        #[arg(long, value_name = "BYTES")]
+        max_output: Option<u64>,
+        /// Text added at the very top of the agent prompt
+        #[arg(long, value_name = "TEXT")]
+        prompt_prefix: Option<String>,
+        /// Text added at the very bottom of the agent prompt
+        #[arg(long, value_name = "TEXT")]
+        prompt_suffix: Option<String>,
+        /// Order in which pending stories are worked on
+        #[arg(long, value_name = "ORDER", default_value = "priority")]
+        story_order: StoryOrderChoice,
+        /// Seed for --story-order random, to repeat an earlier order
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Warn when an iteration changes sensitive files outside the repository
+        #[arg(long)]
+        sandbox_check: bool,
+        /// If the run fails, copy prd.json and progress.txt into a -error archive
+        #[arg(long)]
+        on_error_archive: bool,
+        /// Copy prd.json to ralph/.prd.bak before each iteration, to recover from a broken write
+        #[arg(long)]
+        snapshot_prd: bool,
+        /// Fail before starting the agent if the prompt has unknown {{placeholders}}
+        #[arg(long)]
+        strict_prompt: bool,
+        /// Print the ordered queue of actionable, blocked and completed stories, then exit
+        #[arg(long)]
+        list_stories: bool,
+        /// Print the prompt the first iteration would send and its estimated size, then exit
+        #[arg(long, conflicts_with = "list_stories")]
+        print_prompt: bool,
+        /// Show lines the agent appends to progress.txt as they are written
+        #[arg(long)]
+        show_progress: bool,
+        /// List the PATH and install locations checked when the agent fails to start
+        #[arg(long)]
+        verbose_spawn_errors: bool,
+        /// Stop once the run uses more than this many tokens (500k) or dollars ($5.00); claude only
+        #[arg(long, value_name = "TOKENS|$COST", value_parser = parse_budget)]
+        budget: Option<Budget>,
+        /// Every N iterations, re-save prd.json and copy it to ralph/checkpoints/
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        checkpoint_every: Option<u32>,
+        /// Save each iteration's prompt to ralph/logs/prompts/iter-N.md
+        #[arg(long)]
+        prompt_history: bool,
+        /// Start even if pending stories have no acceptance criteria (overrides require_criteria)
+        #[arg(long)]
+        allow_empty_criteria: bool,
+        /// Don't write progress.txt, prd.json or other run state (the agent itself still can)
+        #[arg(long)]
+        readonly: bool,
+        /// Create the PRD from the template first when it does not exist
+        #[arg(long, conflicts_with = "readonly")]
+        init_if_missing: bool,
+        /// Project name for a PRD created by --init-if-missing (asked for on a terminal)
+        #[arg(long, value_name = "NAME", requires = "init_if_missing")]
+        project_name: Option<String>,
+        /// Project description for a PRD created by --init-if-missing
+        #[arg(long, value_name = "TEXT", requires = "init_if_missing")]
+        project_description: Option<String>,
+        /// Print a one-line header instead of the startup banner (see the run_banner config)
+        #[arg(long)]
+        compact: bool,
+        /// Experimental: work on independent stories in this many git worktrees at once,
+        /// then on the rest serially
+        #[arg(
+            long,
+            value_name = "N",
+            value_parser = clap::value_parser!(u32).range(2..),
+            conflicts_with_all = ["resume", "stream_to", "readonly", "init_if_missing", "budget"]
+        )]
+        parallel: Option<u32>,
     },
     /// View or set configuration
+    #[command(args_conflicts_with_subcommands = true)]
     Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
         /// Get a specific config value
         #[arg(long)]
         get: Option<String>,
@@ -42,9 +249,346 @@ pub enum Commands {
         set: Vec<String>,
     },
     /// View project status
-    Status,
+    Status {
+        /// Show the acceptance checklist of this story (e.g. US-002)
+        #[arg(value_name = "ID")]
+        story: Option<String>,
+        /// Show each pending story's acceptance criteria as a checklist, with its notes
+        #[arg(long)]
+        acceptance: bool,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Output format
+        #[arg(long, value_name = "FORMAT", default_value = "table")]
+        format: OutputFormat,
+        /// Shorthand for --format json
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+        /// Only show the stories of this epic
+        #[arg(long, value_name = "NAME")]
+        epic: Option<String>,
+        /// Only show stories with this tag (repeatable; any tag matches)
+        #[arg(long, value_name = "TAG")]
+        tag: Vec<String>,
+        /// Only show stories with this priority or a more urgent one (a lower number)
+        #[arg(long, value_name = "N")]
+        max_priority: Option<u32>,
+        /// Leave this story out (repeatable)
+        #[arg(long, value_name = "ID")]
+        exclude_story: Vec<String>,
+    },
+    /// Search stories, progress.txt and optionally archived runs
+    Search {
+        /// Text to look for (case-insensitive)
+        query: String,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Also search the PRDs and progress logs of archived runs
+        #[arg(long)]
+        archives: bool,
+        /// Output format
+        #[arg(long, value_name = "FORMAT", default_value = "table")]
+        format: OutputFormat,
+        /// Shorthand for --format json
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
     /// Manage archives
-    Archive,
+    Archive {
+        #[command(subcommand)]
+        command: Option<ArchiveCommands>,
+        /// Path to the ralph directory
+        #[arg(long, default_value = "./ralph", global = true)]
+        ralph_dir: String,
+    },
     /// Detect installed agent CLIs
-    Detect,
+    Detect {
+        /// Only check this agent or command; exits non-zero when it is missing
+        #[arg(long, value_name = "TOOL")]
+        check: Option<String>,
+        /// Ignore cached results and probe the agents again
+        #[arg(long)]
+        refresh: bool,
+        /// Deprecated: the extra_tools commands are always listed now
+        #[arg(long, hide = true, conflicts_with = "check")]
+        all: bool,
+        /// Output format
+        #[arg(long, value_name = "FORMAT", default_value = "table")]
+        format: OutputFormat,
+    },
+    /// Check the config, agents and PRD for common problems
+    Doctor,
+    /// Inspect and maintain the PRD
+    Prd {
+        #[command(subcommand)]
+        command: PrdCommands,
+    },
+    /// Manage user stories in the PRD
+    Story {
+        #[command(subcommand)]
+        command: StoryCommands,
+    },
+    /// Maintain ralph's section of AGENTS.md
+    AgentsMd {
+        #[command(subcommand)]
+        command: AgentsMdCommands,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH, global = true)]
+        prd: String,
+        /// Path to AGENTS.md
+        #[arg(long, default_value = AGENTS_MD_FILE, global = true)]
+        path: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ArchiveCommands {
+    /// Delete old archives beyond a retention policy
+    ///
+    /// When both options are given, an archive is kept if either one keeps it.
+    Clean {
+        /// Keep this many of the newest archives
+        #[arg(long)]
+        keep: Option<usize>,
+        /// Keep archives dated within this many days
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<i64>,
+    },
+    /// Pack an archived run into a .tar.gz bundle for sharing
+    Export {
+        /// Name of the archive folder, as listed by `ralph archive`
+        name: String,
+        /// Bundle file to write
+        #[arg(long, value_name = "FILE")]
+        out: PathBuf,
+    },
+    /// Unpack a bundle made by `archive export` into the archive directory
+    Import {
+        /// Bundle file to read
+        bundle: PathBuf,
+        /// Replace an existing archive with the same name
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Print the effective config as TOML
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Apply config values from a TOML file
+    Import {
+        /// TOML file to import
+        file: PathBuf,
+        /// Keep existing values for keys the file does not set (default)
+        #[arg(long, conflicts_with = "replace")]
+        merge: bool,
+        /// Discard existing values; keys the file does not set fall back to defaults
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PrdCommands {
+    /// Check the PRD for errors and weak stories
+    Validate {
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Fix what is safe to fix (missing fields, priorities, duplicate ids) and save
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Report file paths and URLs in stories that no longer resolve
+    CheckLinks {
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Directory that referenced paths are relative to
+        #[arg(long, default_value = ".")]
+        work_dir: String,
+        /// Also check URLs with HEAD requests
+        #[arg(long)]
+        network: bool,
+        /// Exit non-zero when broken references are found
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Rewrite story ids to a consistent US-001 format, updating dependencies
+    RenumberIds {
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Print the id mapping without saving
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Change the PRD branch, adding the ralph/ prefix and replacing spaces
+    SetBranch {
+        /// New branch name, e.g. "checkout flow" or ralph/checkout-flow
+        name: String,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+    },
+    /// Give a story a new priority, shifting the stories in between
+    Move {
+        /// Id of the story (e.g. US-005)
+        id: String,
+        /// New priority, from 1 (most urgent) to the number of stories
+        #[arg(value_parser = clap::value_parser!(u32).range(1..))]
+        priority: u32,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+    },
+    /// Move stories into a new PRD file, removing them from this one
+    Split {
+        /// Move the stories with this tag (repeatable; any tag matches)
+        #[arg(long, required_unless_present = "story")]
+        tag: Vec<String>,
+        /// Move this story (repeatable)
+        #[arg(long, value_name = "ID")]
+        story: Vec<String>,
+        /// File to write the new PRD to; must not exist yet
+        #[arg(long, value_name = "PATH")]
+        out: PathBuf,
+        /// Branch for the new PRD (default: the current PRD's branch)
+        #[arg(long)]
+        branch: Option<String>,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+    },
+    /// Append acceptance criteria to a story
+    AddCriteria {
+        /// Id of the story (e.g. US-002)
+        id: String,
+        /// Criteria to add, one per argument
+        #[arg(required = true)]
+        criteria: Vec<String>,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+    },
+    /// Look for API keys and tokens pasted into stories or progress.txt
+    ScanSecrets {
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Replace each secret with a [REDACTED:<sha256 prefix>] marker and save
+        #[arg(long)]
+        redact: bool,
+    },
+    /// Generate a synthetic PRD for demos and load tests
+    #[command(hide = true)]
+    Fake {
+        /// Number of stories to generate
+        #[arg(long, default_value_t = 10)]
+        stories: usize,
+        /// Seed for the generator; the same seed always gives the same PRD
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Share of stories that already pass, from 0.0 to 1.0
+        #[arg(long, value_name = "RATIO", default_value_t = 0.0)]
+        completed: f64,
+        /// Let stories depend on higher priority stories
+        #[arg(long)]
+        dependencies: bool,
+        /// Spread the stories over this many epics
+        #[arg(long, value_name = "COUNT", default_value_t = 0)]
+        epics: usize,
+        /// Write to this file instead of stdout
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Summarize the size of the work: priorities, criteria and dependency depth
+    Stats {
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Output format
+        #[arg(long, value_name = "FORMAT", default_value = "table")]
+        format: OutputFormat,
+        /// Shorthand for --format json
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum StoryCommands {
+    /// List the stories in the PRD
+    List {
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Output format
+        #[arg(long, value_name = "FORMAT", default_value = "table")]
+        format: OutputFormat,
+    },
+    /// Remove a story and strip it from other stories' dependencies
+    Rm {
+        /// Id of the story to remove (e.g. US-004)
+        id: String,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Allow removing a story that already passes
+        #[arg(long)]
+        force: bool,
+    },
+    /// Add a timestamped entry to a story's notes
+    Note {
+        /// Id of the story (e.g. US-002)
+        id: String,
+        /// Text to add
+        text: String,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+        /// Overwrite the existing notes instead of appending
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Check off an acceptance criterion, or uncheck it if it was done
+    Check {
+        /// Id of the story (e.g. US-002)
+        id: String,
+        /// Number of the criterion, as shown by `ralph status --acceptance`
+        #[arg(value_parser = clap::value_parser!(u32).range(1..))]
+        number: u32,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+    },
+    /// Edit a story's fields, asking for each one in turn
+    Edit {
+        /// Id of the story (e.g. US-004)
+        id: String,
+        /// Set this field without prompting (requires --value)
+        #[arg(long, value_name = "FIELD", requires = "value")]
+        field: Option<StoryField>,
+        /// New value for --field
+        #[arg(long, value_name = "TEXT", requires = "field")]
+        value: Option<String>,
+        /// Path to prd.json file
+        #[arg(long, default_value = DEFAULT_PRD_PATH)]
+        prd: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AgentsMdCommands {
+    /// Add ralph's section to AGENTS.md, creating the file if needed
+    Generate,
+    /// Update ralph's section, leaving the rest of AGENTS.md untouched
+    Refresh,
 }
@@ -0,0 +1,19 @@
+use crate::cli::ColorChoice;
+
+/// Apply the `--color` setting to every crate that emits colors
+///
+/// `console` styles most of the UI and `colored` highlights agent output, and
+/// each has its own on/off switch. With `auto`, console's terminal detection
+/// (which also honors `NO_COLOR` and `CLICOLOR`) decides for both.
+pub fn apply_color_choice(choice: ColorChoice) {
+    let (stdout, stderr) = match choice {
+        ColorChoice::Always => (true, true),
+        ColorChoice::Never => (false, false),
+        ColorChoice::Auto => (console::colors_enabled(), console::colors_enabled_stderr()),
+    };
+
+    console::set_colors_enabled(stdout);
+    console::set_colors_enabled_stderr(stderr);
+    // Agent output highlighted by `colored` is printed to stdout
+    colored::control::set_override(stdout);
+}
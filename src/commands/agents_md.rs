@@ -0,0 +1,71 @@
+use console::style;
+use std::fs;
+use std::path::Path;
+
+use crate::agents_md::{find_section, render_section, splice_section};
+use crate::error::{RalphError, RalphResult};
+use crate::prd::Prd;
+
+/// Run the `agents-md generate` command to create or update AGENTS.md
+///
+/// Adds ralph's section to an existing file without markers, and creates the
+/// file when it does not exist yet.
+pub fn run_agents_md_generate(prd_path: &str, prd_key: &str, path: &str) -> RalphResult<()> {
+    let existing = read_existing(path)?.unwrap_or_default();
+    write_section(prd_path, prd_key, path, &existing)
+}
+
+/// Run the `agents-md refresh` command to update ralph's section of AGENTS.md
+pub fn run_agents_md_refresh(prd_path: &str, prd_key: &str, path: &str) -> RalphResult<()> {
+    let existing = read_existing(path)?.ok_or_else(|| {
+        RalphError::Other(format!(
+            "{} does not exist. Run `ralph agents-md generate` first.",
+            path
+        ))
+    })?;
+
+    let has_section = find_section(&existing)
+        .map_err(|e| RalphError::Other(format!("Cannot update {}: {}", path, e)))?
+        .is_some();
+    if !has_section {
+        return Err(RalphError::Other(format!(
+            "{} has no ralph section. Run `ralph agents-md generate` to add one.",
+            path
+        )));
+    }
+
+    write_section(prd_path, prd_key, path, &existing)
+}
+
+/// Render the section from the PRD and splice it into `existing`
+fn write_section(prd_path: &str, prd_key: &str, path: &str, existing: &str) -> RalphResult<()> {
+    // A missing PRD is expected right after init; the section says so
+    let prd = if Path::new(prd_path).exists() {
+        Some(Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+            RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+        })?)
+    } else {
+        None
+    };
+
+    let updated = splice_section(existing, &render_section(prd.as_ref()))
+        .map_err(|e| RalphError::Other(format!("Cannot update {}: {}", path, e)))?;
+
+    if updated == existing {
+        println!("{} {} is up to date", style("✓").green(), path);
+        return Ok(());
+    }
+
+    fs::write(path, updated)?;
+    println!("{} Updated ralph section in {}", style("✓").green(), path);
+    Ok(())
+}
+
+/// Read a file, treating a missing file as `None`
+fn read_existing(path: &str) -> RalphResult<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,148 @@
+use chrono::Local;
+use console::style;
+use std::fs;
+use std::path::Path;
+
+use crate::archive::{
+    archives_to_prune, export_archive, format_bytes, inspect_bundle, list_archives,
+    unpack_bundle, RetentionPolicy,
+};
+use crate::error::{RalphError, RalphResult};
+use crate::humanize::{elapsed_since_modified, format_relative_time, render_table, Align};
+use crate::interactive::confirm;
+
+/// Run the `archive` command without a subcommand to list archived runs
+pub fn run_archive_list(ralph_dir: &str) -> RalphResult<()> {
+    let archive_dir = Path::new(ralph_dir).join("archive");
+    let archives = list_archives(&archive_dir)?;
+
+    if archives.is_empty() {
+        println!("No archives found in {}", archive_dir.display());
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = archives
+        .iter()
+        .map(|archive| {
+            // The folder is last modified when the run is moved into it
+            let created = elapsed_since_modified(&archive.path)
+                .map(|elapsed| format!("created {}", format_relative_time(elapsed)))
+                .unwrap_or_default();
+            vec![
+                archive.name.clone(),
+                format_bytes(archive.size()),
+                style(created).dim().to_string(),
+            ]
+        })
+        .collect();
+
+    println!("{}", style("Archived runs:").bold());
+    for line in render_table(&rows, &[Align::Left, Align::Right, Align::Left]) {
+        println!("  {}", line);
+    }
+    Ok(())
+}
+
+/// Run the `archive clean` command to prune old archives
+pub fn run_archive_clean(
+    ralph_dir: &str,
+    keep: Option<usize>,
+    older_than: Option<i64>,
+) -> RalphResult<()> {
+    if keep.is_none() && older_than.is_none() {
+        return Err(RalphError::Other(
+            "Specify a retention policy with --keep <N> and/or --older-than <DAYS>".to_string(),
+        ));
+    }
+
+    let archive_dir = Path::new(ralph_dir).join("archive");
+    let archives = list_archives(&archive_dir)?;
+    let policy = RetentionPolicy {
+        keep,
+        older_than_days: older_than,
+    };
+    let to_remove = archives_to_prune(&archives, policy, Local::now().date_naive());
+
+    if to_remove.is_empty() {
+        println!("Nothing to clean: all archives are within the retention policy.");
+        return Ok(());
+    }
+
+    println!("{}", style("Archives to remove:").bold());
+    for archive in &to_remove {
+        println!("  - {}", archive.name);
+    }
+    println!();
+
+    if !confirm(&format!("Delete {} archive(s)?", to_remove.len()), false)? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let mut reclaimed = 0;
+    for archive in &to_remove {
+        let size = archive.size();
+        fs::remove_dir_all(&archive.path)?;
+        reclaimed += size;
+        println!("  {} Removed {}", style("✓").green(), archive.name);
+    }
+
+    println!();
+    println!(
+        "Removed {} archive(s), reclaimed {}",
+        to_remove.len(),
+        format_bytes(reclaimed)
+    );
+    Ok(())
+}
+
+/// Run the `archive export` command to pack an archived run into a bundle
+pub fn run_archive_export(ralph_dir: &str, name: &str, out: &Path) -> RalphResult<()> {
+    let archive_path = Path::new(ralph_dir).join("archive").join(name);
+    if name.is_empty() || name.contains(['/', '\\']) || !archive_path.is_dir() {
+        return Err(RalphError::Other(format!(
+            "Unknown archive: {} (run `ralph archive` to list them)",
+            name
+        )));
+    }
+
+    export_archive(&archive_path, out)
+        .map_err(|e| RalphError::Other(format!("Failed to write {}: {}", out.display(), e)))?;
+    let size = fs::metadata(out).map(|m| m.len()).unwrap_or(0);
+    println!(
+        "{} Exported {} to {} ({})",
+        style("✓").green(),
+        name,
+        out.display(),
+        format_bytes(size)
+    );
+    Ok(())
+}
+
+/// Run the `archive import` command to unpack a bundle into the archive directory
+pub fn run_archive_import(ralph_dir: &str, bundle: &Path, force: bool) -> RalphResult<()> {
+    let contents = inspect_bundle(bundle)
+        .map_err(|e| RalphError::Other(format!("Cannot import {}: {}", bundle.display(), e)))?;
+
+    let archive_dir = Path::new(ralph_dir).join("archive");
+    let target = archive_dir.join(&contents.name);
+    if target.exists() {
+        if !force {
+            return Err(RalphError::Other(format!(
+                "Archive {} already exists. Re-run with --force to replace it.",
+                contents.name
+            )));
+        }
+        fs::remove_dir_all(&target)?;
+    }
+
+    let imported = unpack_bundle(bundle, &archive_dir)?;
+    println!(
+        "{} Imported {} ({} files) into {}",
+        style("✓").green(),
+        contents.name,
+        contents.files.len(),
+        imported.display()
+    );
+    Ok(())
+}
@@ -1,7 +1,10 @@
 use console::style;
+use std::fs;
+use std::path::Path;
 
-use crate::config::{Config, ConfigKey};
+use crate::config::{Config, ConfigKey, CONFIG_PATH_ENV};
 use crate::error::{RalphError, RalphResult};
+use crate::interactive::confirm;
 
 /// Run the config command to view or set configuration
 pub fn run_config(get: Option<String>, set: Vec<String>) -> RalphResult<()> {
@@ -10,7 +13,7 @@ pub fn run_config(get: Option<String>, set: Vec<String>) -> RalphResult<()> {
         let key = ConfigKey::from_str(&key_str)
             .ok_or_else(|| RalphError::Other(format!("Unknown config key: {}", key_str)))?;
 
-        let config = Config::load()?;
+        let config = Config::load_or_default();
         match config.get(key) {
             Some(value) => println!("{} = {}", key_str, value),
             None => println!("{} is not set", key_str),
@@ -34,7 +37,9 @@ pub fn run_config(get: Option<String>, set: Vec<String>) -> RalphResult<()> {
 
         let mut config = Config::load()?;
         config.set(key, value).map_err(RalphError::Other)?;
-        config.save()?;
+        config
+            .save()
+            .map_err(|e| RalphError::Other(format!("Could not save config: {}", e)))?;
 
         println!("{} Set {} = {}", style("✓").green(), key_str, value);
         return Ok(());
@@ -45,16 +50,22 @@ pub fn run_config(get: Option<String>, set: Vec<String>) -> RalphResult<()> {
     println!("{}", style("===================").cyan());
     println!();
 
-    let config = Config::load()?;
+    let config = Config::load_or_default();
     let config_file = Config::config_file();
 
     println!("{}", style("Config file location:").bold());
     match &config_file {
         Some(path) => println!("  {}", path.display()),
-        None => println!(
-            "  {}",
-            style("Unknown (could not determine config directory)").yellow()
-        ),
+        None => {
+            println!(
+                "  {}",
+                style("Unknown (could not determine config directory)").yellow()
+            );
+            println!(
+                "  Set {} to choose a config file location",
+                style(CONFIG_PATH_ENV).cyan()
+            );
+        }
     }
     println!();
 
@@ -76,6 +87,92 @@ pub fn run_config(get: Option<String>, set: Vec<String>) -> RalphResult<()> {
     println!("  ralph config              # Show all config");
     println!("  ralph config --get <key>  # Get specific value");
     println!("  ralph config --set <key> <value>  # Set value");
+    println!("  ralph config export [--out <file>]  # Print settings as TOML");
+    println!("  ralph config import <file>  # Apply settings from a TOML file");
+
+    Ok(())
+}
+
+/// Write the effective config, defaults included, as TOML
+pub fn run_config_export(out: Option<&Path>) -> RalphResult<()> {
+    let config = Config::load()?.with_defaults();
+    let content = toml::to_string_pretty(&config)
+        .map_err(|e| RalphError::Other(format!("Could not serialize config: {}", e)))?;
+
+    match out {
+        Some(path) => {
+            fs::write(path, content).map_err(|e| {
+                RalphError::Other(format!("Could not write {}: {}", path.display(), e))
+            })?;
+            println!("{} Exported config to {}", style("✓").green(), path.display());
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Validate a TOML config file and apply it after showing what changes
+///
+/// With `replace`, keys the file leaves out are cleared so their defaults
+/// apply; otherwise they keep their current values.
+pub fn run_config_import(file: &Path, replace: bool) -> RalphResult<()> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| RalphError::Other(format!("Could not read {}: {}", file.display(), e)))?;
+
+    let current = Config::load()?;
+    let mut imported = if replace {
+        Config::empty()
+    } else {
+        current.clone()
+    };
+    if let Err(errors) = imported.import_toml(&content) {
+        eprintln!("{}", style(format!("Problems in {}:", file.display())).red());
+        for error in &errors {
+            eprintln!("  {} {}", style("✗").red(), error);
+        }
+        return Err(RalphError::Other(format!(
+            "{} has {} invalid entr{}; no changes were made",
+            file.display(),
+            errors.len(),
+            if errors.len() == 1 { "y" } else { "ies" }
+        )));
+    }
+
+    // Compare effective values, so clearing a key that held its default is no change
+    let (before_all, after_all) = (current.with_defaults(), imported.with_defaults());
+    let changes: Vec<_> = ConfigKey::all()
+        .iter()
+        .filter_map(|key| {
+            let before = before_all.get(*key);
+            let after = after_all.get(*key);
+            (before != after).then_some((key, before, after))
+        })
+        .collect();
+    if changes.is_empty() {
+        println!("Config already matches {}; nothing to change.", file.display());
+        return Ok(());
+    }
+
+    println!("{}", style("Changes:").bold());
+    let show = |value: Option<String>| value.unwrap_or_else(|| "not set".to_string());
+    for (key, before, after) in changes {
+        println!(
+            "  {}: {} → {}",
+            style(key.as_str()).bold(),
+            style(show(before)).red(),
+            style(show(after)).green()
+        );
+    }
+    println!();
+
+    if !confirm("Apply these changes to the config?", false)? {
+        println!("Import cancelled. No changes were made.");
+        return Ok(());
+    }
 
+    imported
+        .save()
+        .map_err(|e| RalphError::Other(format!("Could not save config: {}", e)))?;
+    println!("{} Imported config from {}", style("✓").green(), file.display());
     Ok(())
 }
@@ -1,34 +1,183 @@
 use console::style;
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
 
-use crate::agent::{detect_agents, Agent};
+use crate::agent::{
+    check_agent, command_version, detect_agents, is_command_available, refresh_agents, Agent,
+};
+use crate::cli::OutputFormat;
+use crate::config::Config;
+use crate::error::{RalphError, RalphResult};
+use crate::report::{print_report, Report};
+use crate::workspace::RALPH_DIR_NAME;
+
+/// One known agent and whether it is installed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AgentStatus {
+    pub name: String,
+    pub command: String,
+    pub installed: bool,
+    pub version: Option<String>,
+}
+
+/// Every known agent, as reported by `ralph detect`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DetectReport {
+    pub agents: Vec<AgentStatus>,
+    pub installed: usize,
+    pub total: usize,
+    /// Custom tools: the commands from the `extra_tools` config
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra_tools: Vec<AgentStatus>,
+}
+
+impl DetectReport {
+    /// Build the report from the detected agents
+    ///
+    /// Versions come from the memoized `--version` probes, so this spawns
+    /// nothing new for agents that were just detected.
+    pub fn from_detected(detected: &[Agent]) -> Self {
+        let agents: Vec<AgentStatus> = Agent::ALL
+            .iter()
+            .map(|agent| {
+                let installed = detected.contains(agent);
+                AgentStatus {
+                    name: agent.name().to_string(),
+                    command: agent.command().to_string(),
+                    installed,
+                    version: installed.then(|| command_version(agent.command())).flatten(),
+                }
+            })
+            .collect();
+        Self {
+            installed: agents.iter().filter(|a| a.installed).count(),
+            total: agents.len(),
+            agents,
+            extra_tools: Vec::new(),
+        }
+    }
+
+    /// Add custom commands to the report, with their `--version` output
+    ///
+    /// Known agents are already listed and are skipped. Custom tools are
+    /// probed like the known agents, and never cached.
+    pub fn with_extra_tools(mut self, tools: &[String]) -> Self {
+        self.extra_tools = custom_tools(tools)
+            .map(|tool| {
+                let installed = is_command_available(tool);
+                AgentStatus {
+                    name: tool.to_string(),
+                    command: tool.to_string(),
+                    installed,
+                    version: installed.then(|| command_version(tool)).flatten(),
+                }
+            })
+            .collect();
+        self
+    }
+}
+
+/// The entries of `tools` that are not known agents
+fn custom_tools(tools: &[String]) -> impl Iterator<Item = &str> {
+    tools
+        .iter()
+        .map(String::as_str)
+        .filter(|tool| Agent::from_command(tool).is_none())
+}
+
+/// The first installed custom tool from the `extra_tools` config, in config order
+///
+/// `--tool auto` falls back to it when `auto_include_custom` is set and no
+/// known agent is installed.
+pub fn first_installed_custom_tool(config: &Config) -> Option<String> {
+    let tools = parse_extra_tools(config.extra_tools.as_deref().unwrap_or_default());
+    let found = custom_tools(&tools)
+        .find(|tool| is_command_available(tool))
+        .map(str::to_string);
+    found
+}
+
+/// Split the `extra_tools` config value into command names
+///
+/// Commas separate entries; blanks and repeats are dropped.
+pub fn parse_extra_tools(value: &str) -> Vec<String> {
+    let mut tools: Vec<String> = Vec::new();
+    for tool in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tools.iter().any(|t| t == tool) {
+            tools.push(tool.to_string());
+        }
+    }
+    tools
+}
+
+impl Report for DetectReport {
+    fn to_table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Installed Agents:");
+        let _ = writeln!(out, "-----------------");
+        for agent in &self.agents {
+            let status = if agent.installed {
+                style("✓ Installed").green()
+            } else {
+                style("✗ Not found").red()
+            };
+            let _ = writeln!(out, "  {}: {}", agent.name, status);
+        }
+        let _ = writeln!(out, "-----------------");
+        let _ = writeln!(out, "Total: {}/{} agents installed", self.installed, self.total);
+        if !self.extra_tools.is_empty() {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "Custom tools:");
+            let _ = writeln!(out, "-----------------");
+            for tool in &self.extra_tools {
+                let status = match &tool.version {
+                    Some(version) => format!("{} ({})", style("✓ Installed").green(), version),
+                    None => style("✗ Not found").red().to_string(),
+                };
+                let _ = writeln!(out, "  {}: {}", tool.name, status);
+            }
+        }
+        out
+    }
+}
 
 /// Run the detect command to show installed agents
-pub fn run_detect() {
-    println!("Detecting installed AI Agent CLIs...\n");
-
-    let detected = detect_agents();
-
-    println!("Installed Agents:");
-    println!("-----------------");
-
-    let all_agents = vec![Agent::Amp, Agent::Claude, Agent::CodeBuddy];
-    let mut found_count = 0;
-
-    for agent in &all_agents {
-        let is_installed = detected.contains(agent);
-        let status = if is_installed {
-            found_count += 1;
-            style("✓ Installed").green()
-        } else {
-            style("✗ Not found").red()
-        };
-        println!("  {}: {}", agent.name(), status);
+///
+/// With `refresh`, cached detection results are ignored and replaced. The
+/// commands in the `extra_tools` config are always checked as custom tools,
+/// so the deprecated `all` flag only prints a warning.
+pub fn run_detect(refresh: bool, all: bool, format: OutputFormat) -> RalphResult<()> {
+    if all {
+        eprintln!(
+            "{}",
+            style("Warning: --all is deprecated; ralph detect always lists the extra_tools commands")
+                .yellow()
+        );
+    }
+    if format == OutputFormat::Table {
+        println!("Detecting installed AI Agent CLIs...\n");
     }
 
-    println!("-----------------");
-    println!(
-        "Total: {}/{} agents installed",
-        found_count,
-        all_agents.len()
-    );
+    let detected = if refresh {
+        refresh_agents()
+    } else {
+        detect_agents()
+    };
+
+    let config = Config::load_layered_or_default(Path::new(RALPH_DIR_NAME));
+    let extra_tools = parse_extra_tools(config.extra_tools.as_deref().unwrap_or_default());
+    let report = DetectReport::from_detected(&detected).with_extra_tools(&extra_tools);
+    print_report(&report, format)
+}
+
+/// Run `detect --check <tool>`, failing when the tool is not installed
+pub fn run_detect_check(tool: &str) -> RalphResult<()> {
+    match check_agent(tool) {
+        Some(version) => {
+            println!("{} {}: {}", style("✓").green(), tool, version);
+            Ok(())
+        }
+        None => Err(RalphError::Other(format!("{} is not installed", tool))),
+    }
 }
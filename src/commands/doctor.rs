@@ -0,0 +1,101 @@
+use console::style;
+use std::path::Path;
+
+use crate::agent::{command_version, detect_agents};
+use crate::cli::DEFAULT_PRD_PATH;
+use crate::config::{Config, CONFIG_PATH_ENV};
+use crate::error::{RalphError, RalphResult};
+use crate::prd::Prd;
+
+/// Run the doctor command to check the environment Ralph runs in
+pub fn run_doctor(prd_key: &str) -> RalphResult<()> {
+    println!("{}", style("Ralph Doctor").bold().cyan());
+    println!("{}", style("============").cyan());
+    println!();
+
+    let mut failures = 0;
+
+    // Config file: readable and writable
+    println!("{}", style("Config:").bold());
+    match Config::config_file() {
+        Some(path) => {
+            match Config::load() {
+                Ok(_) => pass(&format!("Readable: {}", path.display())),
+                Err(e) => {
+                    failures += 1;
+                    fail(&e.to_string());
+                }
+            }
+            match Config::check_writable(&path) {
+                Ok(()) => pass("Writable"),
+                Err(e) => {
+                    failures += 1;
+                    fail(&format!("Not writable: {}", e));
+                    println!(
+                        "    Pass --config <path> or set {} to a writable location",
+                        style(CONFIG_PATH_ENV).cyan()
+                    );
+                }
+            }
+        }
+        None => {
+            failures += 1;
+            fail("Could not determine the config directory");
+            println!(
+                "    Set {} to choose a config file location",
+                style(CONFIG_PATH_ENV).cyan()
+            );
+        }
+    }
+    println!();
+
+    // Agents
+    println!("{}", style("Agents:").bold());
+    let agents = detect_agents();
+    if agents.is_empty() {
+        failures += 1;
+        fail("No AI agent CLI detected");
+    }
+    for agent in agents {
+        let version = command_version(agent.command()).unwrap_or_default();
+        pass(&format!("{} {}", agent.name(), version));
+    }
+    println!();
+
+    // PRD in the current project, if any
+    println!("{}", style("Project:").bold());
+    if Path::new(DEFAULT_PRD_PATH).exists() {
+        match Prd::from_file_at(DEFAULT_PRD_PATH, prd_key) {
+            Ok(prd) => {
+                let problems = prd.validate();
+                if problems.is_empty() {
+                    pass(&format!("{} is valid", DEFAULT_PRD_PATH));
+                } else {
+                    failures += 1;
+                    fail(&format!("{} has {} problem(s)", DEFAULT_PRD_PATH, problems.len()));
+                }
+            }
+            Err(e) => {
+                failures += 1;
+                fail(&format!("Cannot load {}: {}", DEFAULT_PRD_PATH, e));
+            }
+        }
+    } else {
+        println!("  {} No {} (run `ralph init`)", style("-").dim(), DEFAULT_PRD_PATH);
+    }
+    println!();
+
+    if failures > 0 {
+        return Err(RalphError::Other(format!("{} check(s) failed", failures)));
+    }
+    println!("{} All checks passed", style("✓").green());
+    Ok(())
+}
+
+fn pass(message: &str) {
+    println!("  {} {}", style("✓").green(), message);
+}
+
+fn fail(message: &str) {
+    println!("  {} {}", style("✗").red(), message);
+}
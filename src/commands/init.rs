@@ -1,57 +1,302 @@
 use console::style;
-use dialoguer::Select;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
-use crate::agent::{detect_agents, Agent};
-use crate::error::RalphResult;
+use crate::agent::{detect_agents, is_command_available, Agent};
+use crate::agents_md::{render_section, splice_section, AGENTS_MD_FILE};
+use crate::cli::DEFAULT_PRD_PATH;
+use crate::config::{Config, ConfigKey, PROJECT_CONFIG_FILE};
+use crate::error::{RalphError, RalphResult};
+use crate::interactive::{confirm, input, is_interactive, select};
+use crate::prd::Prd;
+use crate::templates::starter_prd;
+use crate::workspace::{is_ralph_workspace_dir, RALPH_DIR_NAME};
+
+/// Whether a planned init item is a directory or a generated file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitItemKind {
+    Dir,
+    File,
+}
+
+/// What init will do with an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitAction {
+    /// Missing, so it will be created
+    Create,
+    /// Already present and left alone
+    Keep,
+    /// Already present and regenerated (after a backup) because of `--force`
+    Overwrite,
+}
+
+/// A file or directory that a complete Ralph project has
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitItem {
+    /// Path relative to the project root
+    pub path: PathBuf,
+    pub kind: InitItemKind,
+    pub action: InitAction,
+}
+
+/// Everything init will create, keep or overwrite, computed before touching disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitPlan {
+    pub items: Vec<InitItem>,
+}
+
+impl InitPlan {
+    /// Inspect `root` and decide what to do with each expected item
+    ///
+    /// Directories are never regenerated; existing files are only
+    /// overwritten when `force` is set.
+    pub fn compute(root: &Path, force: bool) -> Self {
+        let ralph_dir = PathBuf::from(RALPH_DIR_NAME);
+        let expected = [
+            (ralph_dir.clone(), InitItemKind::Dir),
+            (ralph_dir.join("tasks"), InitItemKind::Dir),
+            (PathBuf::from(AGENTS_MD_FILE), InitItemKind::File),
+        ];
+
+        let items = expected
+            .into_iter()
+            .map(|(path, kind)| {
+                let action = match (root.join(&path).exists(), kind) {
+                    (false, _) => InitAction::Create,
+                    (true, InitItemKind::File) if force => InitAction::Overwrite,
+                    (true, _) => InitAction::Keep,
+                };
+                InitItem { path, kind, action }
+            })
+            .collect();
+
+        Self { items }
+    }
+
+    /// Whether any expected item already exists
+    pub fn is_partially_initialized(&self) -> bool {
+        self.items.iter().any(|i| i.action != InitAction::Create)
+    }
+}
+
+/// Whether `root` already holds an initialized Ralph project
+///
+/// An empty `ralph/` directory does not count, so init can still fill it in.
+pub fn is_initialized_project(root: &Path) -> bool {
+    fs::read_dir(root.join(RALPH_DIR_NAME))
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false)
+}
+
+/// Copy `path` to `<path>.bak`, returning the backup location
+pub fn backup_file(path: &Path) -> std::io::Result<PathBuf> {
+    let mut backup_name = path.file_name().unwrap_or_default().to_os_string();
+    backup_name.push(".bak");
+    let backup = path.with_file_name(backup_name);
+    fs::copy(path, &backup)?;
+    Ok(backup)
+}
+
+/// How init settles the default AI tool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefaultToolStep {
+    /// default_tool is already set to an installed command, so nothing is asked
+    Configured(String),
+    /// Ask the user to pick one of the detected agents
+    Select(Vec<Agent>),
+    /// No agent is installed, so there is nothing to pick
+    NoneDetected,
+}
+
+/// Decide the default tool step from the configured tool and the detected agents
+///
+/// A configured tool that is not installed is asked about again.
+pub fn default_tool_step(
+    configured: Option<&str>,
+    detected: &[Agent],
+    is_installed: impl Fn(&str) -> bool,
+) -> DefaultToolStep {
+    match configured.map(str::trim).filter(|tool| !tool.is_empty()) {
+        Some(tool) if is_installed(tool) => DefaultToolStep::Configured(tool.to_string()),
+        _ if detected.is_empty() => DefaultToolStep::NoneDetected,
+        _ => DefaultToolStep::Select(detected.to_vec()),
+    }
+}
+
+/// Config file init saves the default tool to: the project's with `--local`,
+/// otherwise the global one
+pub fn default_tool_config_file(root: &Path, local: bool) -> Option<PathBuf> {
+    if local {
+        Some(root.join(RALPH_DIR_NAME).join(PROJECT_CONFIG_FILE))
+    } else {
+        Config::config_file()
+    }
+}
+
+/// Set `default_tool` to the agent's command in `config_file`, keeping its other keys
+///
+/// Returns the file that was written.
+pub fn save_default_tool(config_file: Option<PathBuf>, agent: Agent) -> RalphResult<PathBuf> {
+    // Start from no keys rather than the defaults, so only default_tool is added
+    let mut config = match config_file.as_deref().filter(|p| p.exists()) {
+        Some(path) => Config::load_from(Some(path))?,
+        None => Config::empty(),
+    };
+    config
+        .set(ConfigKey::DefaultTool, agent.command())
+        .map_err(RalphError::Other)?;
+    config.save_to(config_file.clone())?;
+    // save_to has already failed when there is no file to write
+    Ok(config_file.unwrap_or_default())
+}
 
 /// Run the interactive project initialization
-pub fn run_init() -> RalphResult<()> {
+pub fn run_init(force: bool, local: bool, prd_key: &str) -> RalphResult<()> {
+    let root = std::env::current_dir()?;
+
+    // Refuse to create a nested ralph/ralph/ directory
+    if is_ralph_workspace_dir(&root) {
+        return Err(RalphError::Other(
+            "You appear to be inside the ralph directory; run `ralph init` from the project root"
+                .to_string(),
+        ));
+    }
+
+    // Re-initializing must be intentional
+    if is_initialized_project(&root) {
+        if !force {
+            return Err(RalphError::Other(format!(
+                "A Ralph project is already initialized in {}/. Run `ralph status` to see its \
+                 progress, or `ralph init --force` to regenerate the scaffold.",
+                RALPH_DIR_NAME
+            )));
+        }
+        if !confirm(
+            "Reinitialize the existing Ralph project? Existing files are backed up first.",
+            false,
+        )? {
+            println!("Init cancelled. No changes were made.");
+            return Ok(());
+        }
+    }
+
     println!("{}", style("Ralph Project Initialization").bold().cyan());
     println!("{}", style("============================").cyan());
     println!();
 
-    // Step 1: Detect installed agents and select default AI tool
-    let detected_agents = detect_agents();
-    let default_tool = if detected_agents.is_empty() {
-        println!();
-        println!("{}", style("Warning: No AI Agent CLIs detected!").yellow());
-        println!(
-            "You can configure the default tool later using: ralph config --set default_tool <tool>"
-        );
-        None
-    } else {
+    // Decide what to do before changing anything on disk
+    let plan = InitPlan::compute(&root, force);
+    if plan.is_partially_initialized() {
+        println!("{}", style("Existing Ralph project detected:").bold());
+        for item in &plan.items {
+            let state = if item.action == InitAction::Create {
+                style("missing").yellow()
+            } else {
+                style("exists").green()
+            };
+            println!("  {} {}", item_label(item), state);
+        }
+        if !force {
+            println!("Only missing items will be created (use --force to regenerate files).");
+        }
         println!();
-        println!("{}", style("Select default AI tool:").bold());
+    }
 
-        let agent_names: Vec<String> =
-            detected_agents.iter().map(|a| a.name().to_string()).collect();
-        let selection = Select::new()
-            .with_prompt("Choose your default AI tool")
-            .items(&agent_names)
-            .default(0)
-            .interact()?;
+    // Step 1: Detect installed agents and select default AI tool
+    let configured = Config::load_layered_or_default(&root.join(RALPH_DIR_NAME)).default_tool;
+    let step = default_tool_step(configured.as_deref(), &detect_agents(), is_command_available);
+    let default_tool = match step {
+        DefaultToolStep::Configured(tool) => {
+            println!();
+            println!(
+                "Default AI tool: {} {}",
+                style(&tool).cyan(),
+                style("(already configured, use `ralph config --set default_tool <tool>` to change it)")
+                    .dim()
+            );
+            Agent::from_command(&tool)
+        }
+        DefaultToolStep::NoneDetected => {
+            println!();
+            println!("{}", style("Warning: No AI Agent CLIs detected!").yellow());
+            println!(
+                "You can configure the default tool later using: ralph config --set default_tool <tool>"
+            );
+            None
+        }
+        DefaultToolStep::Select(detected_agents) => {
+            println!();
+            println!("{}", style("Select default AI tool:").bold());
 
-        Some(detected_agents[selection])
-    };
+            let agent_names: Vec<String> =
+                detected_agents.iter().map(|a| a.name().to_string()).collect();
+            let selection = select("Choose your default AI tool", &agent_names, 0)?;
+            let agent = detected_agents[selection];
 
-    println!();
+            // The project works without it, so a config that cannot be saved only warns
+            match save_default_tool(default_tool_config_file(&root, local), agent) {
+                Ok(path) => println!(
+                    "Saved default_tool = {} to {}",
+                    style(agent.command()).cyan(),
+                    path.display()
+                ),
+                Err(e) => println!(
+                    "{}",
+                    style(format!(
+                        "Warning: could not save default_tool: {}. Set it later with: \
+                         ralph config --set default_tool {}",
+                        e,
+                        agent.command()
+                    ))
+                    .yellow()
+                ),
+            }
 
-    // Step 4: Create directory structure
-    println!("{}", style("Creating directory structure...").bold());
+            Some(agent)
+        }
+    };
 
-    let ralph_dir = PathBuf::from("ralph");
-    let tasks_dir = ralph_dir.join("tasks");
+    println!();
 
-    // Create ralph/ directory (main workspace for Ralph files)
-    fs::create_dir_all(&ralph_dir)?;
-    println!("  {} Created {}", style("✓").green(), ralph_dir.display());
+    // Step 4: Apply the plan
+    println!("{}", style("Setting up project...").bold());
 
-    fs::create_dir_all(&tasks_dir)?;
-    println!("  {} Created {}", style("✓").green(), tasks_dir.display());
+    let mut created = Vec::new();
+    let mut kept = Vec::new();
+    let mut overwritten = Vec::new();
+    for item in &plan.items {
+        let path = root.join(&item.path);
+        match (item.kind, item.action) {
+            (_, InitAction::Keep) => kept.push(item),
+            (InitItemKind::Dir, _) => {
+                fs::create_dir_all(&path)?;
+                created.push(item);
+            }
+            (InitItemKind::File, InitAction::Create) => {
+                // Step 5: Offer to describe the ralph workflow for agents in AGENTS.md
+                if confirm("Generate AGENTS.md describing the ralph workflow?", true)? {
+                    write_agents_md(&path, prd_key)?;
+                    created.push(item);
+                }
+            }
+            (InitItemKind::File, InitAction::Overwrite) => {
+                let backup = backup_file(&path)?;
+                println!(
+                    "  Backed up {} to {}",
+                    item.path.display(),
+                    backup.file_name().unwrap_or_default().to_string_lossy()
+                );
+                write_agents_md(&path, prd_key)?;
+                overwritten.push(item);
+            }
+        }
+    }
 
     println!();
+    print_init_report("Created", &created, style("✓").green());
+    print_init_report("Kept", &kept, style("=").dim());
+    print_init_report("Overwritten", &overwritten, style("!").yellow());
 
     // Step 6: Display next steps guide
     display_init_next_steps(default_tool);
@@ -59,6 +304,96 @@ pub fn run_init() -> RalphResult<()> {
     Ok(())
 }
 
+/// Create the PRD from the template when it does not exist yet (`run --init-if-missing`)
+///
+/// The project name and description come from the flags or, on a terminal,
+/// from prompts. An existing file is never touched, even one created in the
+/// meantime. Returns whether a PRD was created.
+pub fn init_missing_prd(
+    prd_path: &Path,
+    project_name: Option<&str>,
+    project_description: Option<&str>,
+) -> RalphResult<bool> {
+    if prd_path.exists() {
+        return Ok(false);
+    }
+
+    let name = match project_name {
+        Some(name) => name.to_string(),
+        None if is_interactive() => input("Project name", "", |name| {
+            if name.trim().is_empty() {
+                Err("The project name cannot be empty".to_string())
+            } else {
+                Ok(())
+            }
+        })?,
+        None => {
+            return Err(RalphError::Other(format!(
+                "No PRD at {}. Pass --project-name (and --project-description) to create one \
+                 without a terminal.",
+                prd_path.display()
+            )))
+        }
+    };
+    let description = match project_description {
+        Some(description) => description.to_string(),
+        None if is_interactive() => input("Project description", "", |_| Ok(()))?,
+        None => String::new(),
+    };
+    let prd = starter_prd(&name, &description).map_err(RalphError::Other)?;
+
+    if let Some(dir) = prd_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let content = serde_json::to_string_pretty(&prd)
+        .map_err(|e| RalphError::Other(format!("Failed to serialize PRD: {}", e)))?;
+    match fs::OpenOptions::new().write(true).create_new(true).open(prd_path) {
+        Ok(mut file) => {
+            file.write_all(content.as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+        Err(e) => return Err(e.into()),
+    }
+
+    println!(
+        "{} {} for {} (branch {})",
+        style("Created").green(),
+        prd_path.display(),
+        style(&prd.project).bold(),
+        style(&prd.branch_name).cyan()
+    );
+    println!();
+    Ok(true)
+}
+
+/// Write a fresh AGENTS.md holding only ralph's section
+fn write_agents_md(path: &Path, prd_key: &str) -> RalphResult<()> {
+    let prd = Prd::from_file_at(DEFAULT_PRD_PATH, prd_key).ok();
+    let content = splice_section("", &render_section(prd.as_ref())).map_err(RalphError::Other)?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+fn item_label(item: &InitItem) -> String {
+    match item.kind {
+        InitItemKind::Dir => format!("{}/", item.path.display()),
+        InitItemKind::File => item.path.display().to_string(),
+    }
+}
+
+/// Print one section of the final init report, if it has any items
+fn print_init_report(title: &str, items: &[&InitItem], marker: console::StyledObject<&str>) {
+    if items.is_empty() {
+        return;
+    }
+    println!("{}", style(format!("{}:", title)).bold());
+    for item in items {
+        println!("  {} {}", marker, item_label(item));
+    }
+    println!();
+}
+
 /// Display next steps guide after initialization
 fn display_init_next_steps(default_tool: Option<Agent>) {
     println!("{}", style("============================").green());
@@ -84,8 +419,15 @@ fn display_init_next_steps(default_tool: Option<Agent>) {
             Agent::Amp => {
                 println!("   - Use Amp to help create your PRD");
             }
+            Agent::Aider => {
+                println!("   - Use Aider to help create your PRD");
+            }
         }
         println!("   - Place the generated PRD file in the {} directory", style("ralph/").cyan());
+        println!(
+            "   - Run {} to add the project to AGENTS.md",
+            style("ralph agents-md refresh").cyan()
+        );
         println!();
     }
 
@@ -1,9 +1,11 @@
 use console::style;
-use dialoguer::{Confirm, MultiSelect, Select};
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
 use crate::agent::{detect_agents, Agent, InstallTarget};
-use crate::error::RalphResult;
+use crate::error::{RalphError, RalphResult};
+use crate::interactive::{assume_yes, confirm, is_interactive, multi_select, select};
 use crate::templates::{get_prd_skill_content, get_ralph_skill_content};
 
 /// Run the interactive skill installation
@@ -12,10 +14,13 @@ pub fn run_install() -> RalphResult<()> {
     println!("{}", style("========================").cyan());
     println!();
 
-    // Step 1: Detect available agents
-    let detected_agents = detect_agents();
+    // Step 1: Detect available agents that support skills
+    let detected_agents: Vec<Agent> = detect_agents()
+        .into_iter()
+        .filter(|agent| agent.global_skills_dir().is_some())
+        .collect();
     if detected_agents.is_empty() {
-        println!("{}", style("No AI Agent CLIs detected!").yellow());
+        println!("{}", style("No AI Agent CLIs with skill support detected!").yellow());
         println!("Please install Amp, Claude Code, or CodeBuddy first.");
         return Ok(());
     }
@@ -46,11 +51,11 @@ fn select_agents(detected_agents: &[Agent]) -> RalphResult<Vec<Agent>> {
     let agent_names: Vec<String> = detected_agents.iter().map(|a| a.name().to_string()).collect();
 
     let defaults = vec![true; detected_agents.len()];
-    let selections = MultiSelect::new()
-        .with_prompt("Select agents (space to toggle, enter to confirm)")
-        .items(&agent_names)
-        .defaults(&defaults)
-        .interact()?;
+    let selections = multi_select(
+        "Select agents (space to toggle, enter to confirm)",
+        &agent_names,
+        &defaults,
+    )?;
 
     let selected: Vec<Agent> = selections
         .into_iter()
@@ -75,42 +80,119 @@ fn select_install_location(selected_agents: &[Agent]) -> RalphResult<InstallTarg
 
     let display_names: Vec<String> = options.iter().map(|o| o.display_name()).collect();
 
-    let selection = Select::new()
-        .with_prompt("Choose installation location")
-        .items(&display_names)
-        .default(0)
-        .interact()?;
+    let selection = select("Choose installation location", &display_names, 0)?;
 
     println!();
     Ok(options[selection].clone())
 }
 
+/// A skill file `ralph install` writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkillWrite {
+    pub path: PathBuf,
+    pub display_name: &'static str,
+    pub content: String,
+}
+
+/// The skill files to write, with every overwrite question already answered
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SkillInstallPlan {
+    pub writes: Vec<SkillWrite>,
+    /// Existing skill files that are kept
+    pub kept: Vec<&'static str>,
+}
+
+impl SkillInstallPlan {
+    /// Decide which skill files to write under `skills_dir`
+    ///
+    /// `overwrite` is asked about each skill file that already exists.
+    /// Nothing is written yet, so an error from it, like Ctrl+C at the
+    /// prompt, leaves every file as it was.
+    pub fn compute(
+        skills_dir: &Path,
+        mut overwrite: impl FnMut(&Path) -> RalphResult<bool>,
+    ) -> RalphResult<Self> {
+        let skills = [
+            ("ralph/SKILL.md", get_ralph_skill_content()),
+            ("prd/SKILL.md", get_prd_skill_content()),
+        ];
+        let mut plan = Self::default();
+        for (display_name, content) in skills {
+            let path = skills_dir.join(display_name);
+            if path.exists() && !overwrite(&path)? {
+                plan.kept.push(display_name);
+            } else {
+                plan.writes.push(SkillWrite {
+                    path,
+                    display_name,
+                    content,
+                });
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Write the planned skill files
+    ///
+    /// Each file is written to a sibling temp file and renamed over the
+    /// target, so an interrupted install never leaves a truncated skill.
+    pub fn execute(&self) -> io::Result<()> {
+        for write in &self.writes {
+            if let Some(dir) = write.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let tmp_path = write.path.with_extension("md.tmp");
+            fs::write(&tmp_path, &write.content)?;
+            fs::rename(&tmp_path, &write.path)?;
+        }
+        Ok(())
+    }
+}
+
 /// Install skills to the selected location
 pub fn install_skills(_agents: &[Agent], target: &InstallTarget) -> RalphResult<()> {
-    // Get embedded skill content
-    let prd_skill = get_prd_skill_content();
-    let ralph_skill = get_ralph_skill_content();
-
     let InstallTarget::AgentGlobal(agent) = target;
 
-    // Global install: create ralph/ subdirectory and install SKILL.md files
+    // Global install: ralph/ and prd/ subdirectories, each with a SKILL.md
     let skills_dir = target.path();
-    let ralph_dir = skills_dir.join("ralph");
-    let prd_dir = skills_dir.join("prd");
 
     println!("{}", style("Installing skills...").bold());
-    println!("Target directory: {}", ralph_dir.display());
+    println!("Target directory: {}", skills_dir.join("ralph").display());
     println!();
 
-    // Install ralph.md (main skill file)
-    fs::create_dir_all(&ralph_dir)?;
-    let ralph_file = ralph_dir.join("SKILL.md");
-    install_skill_file(&ralph_file, &ralph_skill, "ralph/SKILL.md")?;
+    // Ask every question before writing anything
+    let interactive = is_interactive();
+    let plan = SkillInstallPlan::compute(&skills_dir, |path| {
+        // Without a terminal the existing file is kept unless --yes is given
+        if interactive || assume_yes() {
+            confirm(
+                &format!("Skill file {} already exists. Overwrite?", path.display()),
+                false,
+            )
+        } else {
+            Ok(false)
+        }
+    });
+    let plan = match plan {
+        Err(RalphError::Interrupted) => {
+            println!();
+            println!("Install aborted. No skill files were written.");
+            return Err(RalphError::Interrupted);
+        }
+        plan => plan?,
+    };
+    plan.execute()?;
 
-    // Install prd.md (PRD creation skill)
-    fs::create_dir_all(&prd_dir)?;
-    let prd_file = prd_dir.join("SKILL.md");
-    install_skill_file(&prd_file, &prd_skill, "prd/SKILL.md")?;
+    for write in &plan.writes {
+        println!("  {} Installed {}", style("✓").green(), write.display_name);
+    }
+    for display_name in &plan.kept {
+        if interactive {
+            println!("  Skipping {}", display_name);
+        } else {
+            println!("  Skipping {} (already exists; pass --yes to overwrite)", display_name);
+        }
+    }
 
     println!(
         "  {} Installed skills globally for {}",
@@ -122,30 +204,6 @@ pub fn install_skills(_agents: &[Agent], target: &InstallTarget) -> RalphResult<
     Ok(())
 }
 
-/// Helper function to install a single skill file with overwrite confirmation
-fn install_skill_file(file_path: &std::path::Path, content: &str, display_name: &str) -> RalphResult<()> {
-    if file_path.exists() {
-        let should_overwrite = Confirm::new()
-            .with_prompt(format!(
-                "Skill file {} already exists. Overwrite?",
-                file_path.display()
-            ))
-            .default(false)
-            .interact()?;
-
-        if should_overwrite {
-            fs::write(file_path, content)?;
-            println!("  {} Installed {}", style("✓").green(), display_name);
-        } else {
-            println!("  Skipping {}", display_name);
-        }
-    } else {
-        fs::write(file_path, content)?;
-        println!("  {} Installed {}", style("✓").green(), display_name);
-    }
-    Ok(())
-}
-
 /// Display success message and next steps
 fn display_success_message(agents: &[Agent], target: &InstallTarget) {
     println!("{}", style("========================").green());
@@ -1,5 +1,12 @@
+pub mod agents_md;
+pub mod archive;
 pub mod config;
 pub mod detect;
+pub mod doctor;
 pub mod init;
 pub mod install;
+pub mod prd;
 pub mod run;
+pub mod search;
+pub mod status;
+pub mod story;
@@ -0,0 +1,677 @@
+use console::style;
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::cli::OutputFormat;
+use crate::error::{RalphError, RalphResult};
+use crate::fake_prd::{fake_prd, FakePrdOptions};
+use crate::links::{check_story_references, Reference};
+use crate::prd::{Prd, UserStory};
+use crate::report::{print_report, Report};
+use crate::secrets::{redact_prd, scan_run_files, Finding, SecretScanner, ALLOWLIST_FILE};
+use crate::status::StoryEntry;
+
+/// Timeout for each HEAD request made by `prd check-links --network`
+const LINK_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Priorities per band in `prd stats`
+const PRIORITY_BAND_WIDTH: u32 = 5;
+
+/// Stories listed under "Longest descriptions" in `prd stats`
+const LONGEST_DESCRIPTIONS: usize = 3;
+
+/// Size and shape of the work in a PRD, as shown by `ralph prd stats`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrdStatsReport {
+    pub project: String,
+    pub total: usize,
+    pub blocked: usize,
+    /// Longest `dependsOn` chain, in dependency links
+    pub dependency_depth: usize,
+    pub priority_bands: Vec<PriorityBand>,
+    pub average_criteria: f64,
+    pub max_criteria: usize,
+    pub criteria_histogram: Vec<CriteriaCount>,
+    /// Stories with no acceptance criteria, which agents cannot verify
+    pub without_criteria: Vec<StoryEntry>,
+    pub longest_descriptions: Vec<DescriptionLength>,
+}
+
+/// Number of stories with a priority in `from..=to`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PriorityBand {
+    pub from: u32,
+    pub to: u32,
+    pub stories: usize,
+}
+
+/// Number of stories with exactly `criteria` acceptance criteria
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CriteriaCount {
+    pub criteria: usize,
+    pub stories: usize,
+}
+
+/// A story and the length of its description in characters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DescriptionLength {
+    #[serde(flatten)]
+    pub story: StoryEntry,
+    pub chars: usize,
+}
+
+impl PrdStatsReport {
+    pub fn from_prd(prd: &Prd) -> Self {
+        Self {
+            project: prd.project.clone(),
+            total: prd.total_stories(),
+            blocked: prd.blocked_stories().len(),
+            dependency_depth: prd.dependency_depth(),
+            priority_bands: prd
+                .priority_bands(PRIORITY_BAND_WIDTH)
+                .into_iter()
+                .map(|(from, stories)| PriorityBand {
+                    from,
+                    to: from + PRIORITY_BAND_WIDTH - 1,
+                    stories,
+                })
+                .collect(),
+            average_criteria: prd.average_criteria(),
+            max_criteria: prd.max_criteria(),
+            criteria_histogram: prd
+                .criteria_histogram()
+                .into_iter()
+                .map(|(criteria, stories)| CriteriaCount { criteria, stories })
+                .collect(),
+            without_criteria: prd
+                .stories_without_criteria()
+                .into_iter()
+                .map(StoryEntry::from)
+                .collect(),
+            longest_descriptions: prd
+                .longest_descriptions(LONGEST_DESCRIPTIONS)
+                .into_iter()
+                .map(|story| DescriptionLength {
+                    story: StoryEntry::from(story),
+                    chars: story.description.chars().count(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The table leaves out sections with nothing to show
+impl Report for PrdStatsReport {
+    fn to_table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{}", style(&self.project).bold().cyan());
+        if self.total == 0 {
+            let _ = writeln!(out, "No stories");
+            return out;
+        }
+        let _ = writeln!(out, "Stories: {} ({} blocked)", self.total, self.blocked);
+        let _ = writeln!(out, "Dependency depth: {}", self.dependency_depth);
+
+        push_heading(&mut out, "By priority");
+        for band in &self.priority_bands {
+            let range = format!("P{}-{}", band.from, band.to);
+            let _ = writeln!(out, "  {:<8} {}", range, band.stories);
+        }
+
+        push_heading(&mut out, "Acceptance criteria");
+        let _ = writeln!(
+            out,
+            "  {:.1} average, {} max",
+            self.average_criteria, self.max_criteria
+        );
+        for count in &self.criteria_histogram {
+            let _ = writeln!(
+                out,
+                "  {} criteria: {} {}",
+                count.criteria,
+                count.stories,
+                if count.stories == 1 { "story" } else { "stories" }
+            );
+        }
+
+        if !self.without_criteria.is_empty() {
+            push_heading(&mut out, "No acceptance criteria (risky)");
+            for story in &self.without_criteria {
+                let _ = writeln!(out, "  {} {} - {}", style("!").yellow(), story.id, story.title);
+            }
+        }
+
+        push_heading(&mut out, "Longest descriptions");
+        for entry in &self.longest_descriptions {
+            let _ = writeln!(
+                out,
+                "  {} - {} ({} chars)",
+                entry.story.id, entry.story.title, entry.chars
+            );
+        }
+        out
+    }
+}
+
+fn push_heading(out: &mut String, title: &str) {
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{}", style(title).bold());
+}
+
+/// Run the hidden `prd fake` command to write a synthetic PRD
+pub fn run_prd_fake(options: &FakePrdOptions, out: Option<&Path>) -> RalphResult<()> {
+    if !(0.0..=1.0).contains(&options.completed) {
+        return Err(RalphError::Other(format!(
+            "--completed must be between 0.0 and 1.0, got {}",
+            options.completed
+        )));
+    }
+
+    let prd = fake_prd(options);
+    match out {
+        Some(path) => {
+            // A new file holding only the PRD, like `prd split --out`
+            prd.save_to_file_at(path, "")?;
+            println!(
+                "{} Wrote {} stories to {}",
+                style("✓").green(),
+                prd.total_stories(),
+                path.display()
+            );
+        }
+        None => {
+            let json = serde_json::to_string_pretty(&prd)
+                .map_err(|e| RalphError::Other(format!("Could not serialize PRD: {}", e)))?;
+            println!("{}", json);
+        }
+    }
+    Ok(())
+}
+
+/// Run the `prd stats` command to summarize the size of the work in a PRD
+pub fn run_prd_stats(prd_path: &str, prd_key: &str, format: OutputFormat) -> RalphResult<()> {
+    let prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+    print_report(&PrdStatsReport::from_prd(&prd), format)
+}
+
+/// Run the `prd validate` command to check a PRD for problems
+///
+/// With `fix`, safe fixes are applied and saved first; see [`apply_safe_fixes`].
+pub fn run_prd_validate(prd_path: &str, prd_key: &str, fix: bool) -> RalphResult<()> {
+    let load_error =
+        |e| RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e));
+    let prd = if fix {
+        let (mut prd, filled) =
+            Prd::from_file_with_defaults_at(prd_path, prd_key).map_err(load_error)?;
+        apply_safe_fixes(&mut prd, filled, prd_path, prd_key)?;
+        prd
+    } else {
+        Prd::from_file_at(prd_path, prd_key).map_err(load_error)?
+    };
+
+    let problems = prd.validate();
+    for problem in &problems {
+        println!("{} {}", style("✗").red(), problem);
+    }
+
+    print_weak_story_warnings(&prd);
+    print_undeclared_epic_warnings(&prd);
+
+    if !problems.is_empty() {
+        return Err(RalphError::Other(format!(
+            "{} has {} problem(s)",
+            prd_path,
+            problems.len()
+        )));
+    }
+
+    println!(
+        "{} {} is valid ({} stories)",
+        style("✓").green(),
+        prd_path,
+        prd.total_stories()
+    );
+    Ok(())
+}
+
+/// Apply the fixes `prd validate --fix` considers safe, then save the PRD
+///
+/// `filled` lists the story fields that were missing from the file. Priorities
+/// are renumbered to 1..N, and ids are renumbered when some are empty or
+/// duplicated. Problems that need a human, such as empty descriptions or
+/// unknown dependencies, are left for the validation report.
+fn apply_safe_fixes(
+    prd: &mut Prd,
+    filled: Vec<String>,
+    prd_path: &str,
+    prd_key: &str,
+) -> RalphResult<()> {
+    let mut fixes = filled;
+    fixes.extend(
+        prd.normalize_priorities()
+            .into_iter()
+            .map(|(id, old, new)| format!("{}: priority {} -> {}", id, old, new)),
+    );
+    if prd.has_duplicate_or_empty_ids() {
+        fixes.extend(prd.normalize_ids().into_iter().map(|(old, new)| {
+            let old = if old.trim().is_empty() { "(empty id)" } else { old.as_str() };
+            format!("{}: renumbered to {}", old, new)
+        }));
+    }
+
+    if fixes.is_empty() {
+        println!("{} Nothing to fix", style("✓").green());
+        return Ok(());
+    }
+    println!("{}", style("Fixed:").bold());
+    for fix in &fixes {
+        println!("  {} {}", style("✓").green(), fix);
+    }
+    prd.save_to_file_at(prd_path, prd_key)?;
+    println!("Saved {} fix(es) to {}", fixes.len(), prd_path);
+    println!();
+    Ok(())
+}
+
+/// Run the `prd check-links` command to find broken file paths and URLs
+pub fn run_prd_check_links(
+    prd_path: &str,
+    prd_key: &str,
+    work_dir: &str,
+    network: bool,
+    strict: bool,
+) -> RalphResult<()> {
+    let prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+
+    let mut broken_total = 0;
+    let mut broken_stories = 0;
+    for story in &prd.user_stories {
+        let broken = check_story_references(story, Path::new(work_dir), network, LINK_CHECK_TIMEOUT);
+        if broken.is_empty() {
+            continue;
+        }
+
+        broken_stories += 1;
+        broken_total += broken.len();
+        println!("{}", style(story.display()).bold());
+        for item in &broken {
+            let kind = match item.reference {
+                Reference::Path(_) => "path",
+                Reference::Url(_) => "url",
+            };
+            println!(
+                "  {} {} {} ({})",
+                style("✗").red(),
+                kind,
+                item.reference.as_str(),
+                item.reason
+            );
+        }
+    }
+
+    if !network {
+        println!(
+            "{}",
+            style("URLs were not checked (use --network to send HEAD requests)").dim()
+        );
+    }
+
+    if broken_total == 0 {
+        println!("{} No broken references found", style("✓").green());
+        return Ok(());
+    }
+
+    let summary = format!(
+        "{} broken reference(s) in {} stories",
+        broken_total, broken_stories
+    );
+    if strict {
+        return Err(RalphError::Other(summary));
+    }
+    println!("{}", style(summary).yellow());
+    Ok(())
+}
+
+/// Run the `prd renumber-ids` command to normalize story ids
+pub fn run_prd_renumber_ids(prd_path: &str, prd_key: &str, dry_run: bool) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+
+    let changed = prd.normalize_ids();
+    if changed.is_empty() {
+        println!("{} Story ids are already normalized", style("✓").green());
+        return Ok(());
+    }
+
+    for (old_id, new_id) in &changed {
+        println!("  {} -> {}", old_id, style(new_id).cyan());
+    }
+    println!();
+
+    if dry_run {
+        println!("{}", style("Dry run: prd.json was not changed").dim());
+        return Ok(());
+    }
+
+    prd.save_to_file_at(prd_path, prd_key)?;
+    println!(
+        "{} Renumbered {} story id(s) in {}",
+        style("✓").green(),
+        changed.len(),
+        prd_path
+    );
+    println!(
+        "{}",
+        style("Note: progress.txt and task files may still mention the old ids").dim()
+    );
+    Ok(())
+}
+
+/// Run the `prd set-branch` command to change the PRD branch
+pub fn run_prd_set_branch(name: &str, prd_path: &str, prd_key: &str) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+
+    let old_branch = prd.branch_name.clone();
+    let new_branch = prd.set_branch(name).map_err(RalphError::Other)?.to_string();
+    if new_branch == old_branch {
+        println!("{} Branch is already {}", style("✓").green(), style(&new_branch).cyan());
+        return Ok(());
+    }
+
+    prd.save_to_file_at(prd_path, prd_key)?;
+    println!(
+        "{} Branch: {} -> {}",
+        style("✓").green(),
+        old_branch,
+        style(&new_branch).cyan()
+    );
+    println!(
+        "{}",
+        style("Note: the next run archives the previous one; use --ignore-branch-archive to keep it")
+            .dim()
+    );
+    Ok(())
+}
+
+/// Run the `prd move` command to give a story a new priority
+pub fn run_prd_move(story_id: &str, priority: u32, prd_path: &str, prd_key: &str) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+
+    let changed = prd.move_story(story_id, priority).map_err(RalphError::Other)?;
+    if changed.is_empty() {
+        println!("{} {} already has priority {}", style("✓").green(), story_id, priority);
+        return Ok(());
+    }
+
+    prd.save_to_file_at(prd_path, prd_key)?;
+    println!("{} Moved {} to priority {}", style("✓").green(), story_id, priority);
+    for (id, old, new) in changed.iter().filter(|(id, _, _)| id != story_id) {
+        println!("  {}", style(format!("{}: {} -> {}", id, old, new)).dim());
+    }
+    Ok(())
+}
+
+/// Run the `prd split` command to move stories into a new PRD file
+///
+/// Stories match when they have any of `tags` (case-insensitive) or are
+/// listed in `story_ids`. The new file is written before the original is
+/// saved, so a failure never loses stories.
+pub fn run_prd_split(
+    prd_path: &str,
+    prd_key: &str,
+    tags: &[String],
+    story_ids: &[String],
+    out: &Path,
+    branch: Option<&str>,
+) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+    if out.exists() {
+        return Err(RalphError::Other(format!(
+            "{} already exists; choose another --out",
+            out.display()
+        )));
+    }
+    if let Some(id) = story_ids.iter().find(|id| prd.find_story(id).is_none()) {
+        return Err(RalphError::Other(prd.unknown_story_message(id)));
+    }
+
+    let selected = |story: &UserStory| {
+        story_ids.contains(&story.id)
+            || story
+                .tags
+                .iter()
+                .any(|tag| tags.iter().any(|want| want.eq_ignore_ascii_case(tag)))
+    };
+    let moved: Vec<String> = prd
+        .user_stories
+        .iter()
+        .filter(|s| selected(s))
+        .map(|s| s.id.clone())
+        .collect();
+    if moved.is_empty() {
+        return Err(RalphError::Other("No stories match; nothing to split".to_string()));
+    }
+    let dropped = prd.cross_dependencies(selected);
+
+    let mut extracted = prd.extract(selected);
+    if let Some(branch) = branch {
+        extracted.set_branch(branch).map_err(RalphError::Other)?;
+    }
+    // The new file holds only the PRD, even with --prd-key
+    extracted.save_to_file_at(out, "")?;
+    prd.save_to_file_at(prd_path, prd_key)?;
+
+    println!(
+        "{} Moved {} {} to {}",
+        style("✓").green(),
+        moved.len(),
+        if moved.len() == 1 { "story" } else { "stories" },
+        out.display()
+    );
+    for (old, new) in moved.iter().zip(&extracted.user_stories) {
+        println!("  {} -> {}", old, new.id);
+    }
+    if !dropped.is_empty() {
+        println!(
+            "{}",
+            style("Dropped dependencies between the two PRDs:").yellow()
+        );
+        for (story, dep) in &dropped {
+            println!("  {} no longer depends on {}", story, dep);
+        }
+    }
+    if branch.is_none() {
+        println!(
+            "{}",
+            style(format!(
+                "Note: both PRDs use {}; give the new one its own with `ralph prd set-branch --prd {}`",
+                extracted.branch_name,
+                out.display()
+            ))
+            .dim()
+        );
+    }
+    Ok(())
+}
+
+/// Run the `prd add-criteria` command to append acceptance criteria to a story
+pub fn run_prd_add_criteria(
+    story_id: &str,
+    criteria: &[String],
+    prd_path: &str,
+    prd_key: &str,
+) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+
+    let mut total = 0;
+    for criterion in criteria {
+        total = prd.add_criterion(story_id, criterion).map_err(RalphError::Other)?;
+    }
+    prd.save_to_file_at(prd_path, prd_key)?;
+
+    println!(
+        "{} Added {} acceptance {} to {} ({} total)",
+        style("✓").green(),
+        criteria.len(),
+        if criteria.len() == 1 { "criterion" } else { "criteria" },
+        story_id,
+        total
+    );
+    Ok(())
+}
+
+/// Run the `prd scan-secrets` command to find credentials in stories and progress.txt
+pub fn run_prd_scan_secrets(prd_path: &str, prd_key: &str, redact: bool) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+    let ralph_dir = Path::new(prd_path)
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    let findings = scan_run_files(ralph_dir, &prd)?;
+    if findings.is_empty() {
+        println!("{} No secrets found", style("✓").green());
+        return Ok(());
+    }
+    print_secret_findings(&findings);
+
+    if !redact {
+        println!(
+            "{}",
+            style(format!(
+                "Add a fingerprint to {} to ignore a false positive",
+                ALLOWLIST_FILE
+            ))
+            .dim()
+        );
+        return Err(RalphError::Other(format!(
+            "Found {} possible secret(s); rerun with --redact to remove them",
+            findings.len()
+        )));
+    }
+
+    let scanner = SecretScanner::load(ralph_dir)?;
+    let mut redacted = redact_prd(&mut prd, &scanner);
+    if redacted > 0 {
+        prd.save_to_file_at(prd_path, prd_key)?;
+    }
+    let progress_file = ralph_dir.join("progress.txt");
+    if let Ok(progress) = std::fs::read_to_string(&progress_file) {
+        let (content, count) = scanner.redact(&progress);
+        if count > 0 {
+            std::fs::write(&progress_file, content)?;
+            redacted += count;
+        }
+    }
+
+    println!("{} Redacted {} secret(s)", style("✓").green(), redacted);
+    println!(
+        "{}",
+        style("Note: archives, git history and copies sent elsewhere may still hold them").dim()
+    );
+    Ok(())
+}
+
+/// Print where each possible secret was found, without revealing it
+pub fn print_secret_findings(findings: &[Finding]) {
+    println!("{}", style("Possible secrets found:").yellow());
+    for finding in findings {
+        println!(
+            "  - {}: {} {} [{}]",
+            finding.location,
+            finding.secret.kind.label(),
+            finding.secret.masked(),
+            finding.secret.fingerprint()
+        );
+    }
+    println!();
+}
+
+/// Print a warning for each pending story that is too vague for the agent
+pub fn print_weak_story_warnings(prd: &Prd) {
+    let weak = prd.weak_stories();
+    if weak.is_empty() {
+        return;
+    }
+
+    println!(
+        "{}",
+        style("Warning: some pending stories are weakly specified:").yellow()
+    );
+    for story in weak {
+        let reason = if story.description.trim().is_empty() {
+            "missing description"
+        } else {
+            "no acceptance criteria"
+        };
+        println!("  - {} ({})", story.display(), reason);
+    }
+    println!();
+}
+
+/// Print a warning for each story naming an epic the PRD does not declare
+pub fn print_undeclared_epic_warnings(prd: &Prd) {
+    let undeclared = prd.undeclared_epic_stories();
+    if undeclared.is_empty() {
+        return;
+    }
+
+    println!(
+        "{}",
+        style("Warning: some stories name epics missing from the epics array:").yellow()
+    );
+    for story in undeclared {
+        println!(
+            "  - {} (epic \"{}\")",
+            story.display(),
+            story.epic.as_deref().unwrap_or_default()
+        );
+    }
+    println!();
+}
+
+/// Print which pending stories are blocked on dependencies and which can start
+pub fn print_blocked_stories(prd: &Prd) {
+    let blocked = prd.blocked_stories();
+    if blocked.is_empty() {
+        return;
+    }
+
+    let actionable = prd.actionable_stories();
+    println!(
+        "{} actionable, {} blocked by unmet dependencies:",
+        style(actionable.len()).green(),
+        style(blocked.len()).yellow()
+    );
+    for story in blocked {
+        println!(
+            "  - {} (waiting on {})",
+            story.display(),
+            prd.unmet_dependencies(story).join(", ")
+        );
+    }
+    if actionable.is_empty() {
+        println!(
+            "{}",
+            style("Warning: no pending story has its dependencies satisfied").yellow()
+        );
+    }
+    println!();
+}
@@ -1,160 +1,917 @@
 use chrono::Local;
 use colored::Colorize;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::time::{Duration, Instant};
+use tokio::io::BufReader;
 use tokio::process::Command as TokioCommand;
 use tokio::signal;
 
-use crate::agent::{detect_agents, is_command_available};
-use crate::config::Config;
+use crate::agent::{
+    detect_agents, is_command_available, spawn_error_guidance, Agent, AgentInvocation,
+    PromptDelivery,
+};
+use crate::archive::{error_archive_dir, progress_archive_dir};
+use crate::branch_change::{detect_branch_change, record_git_branch, GitBranchState};
+use crate::cli::{StoryOrderChoice, DEFAULT_PRD_PATH};
+use crate::commands::detect::first_installed_custom_tool;
+use crate::commands::init::init_missing_prd;
+use crate::commands::prd::{
+    print_blocked_stories, print_secret_findings, print_weak_story_warnings,
+};
+use crate::config::{BranchChangeMode, Config, SecretScanMode};
+use crate::dotenv::{load_env_file, parse_env_assignment, EnvVar};
 use crate::error::{RalphError, RalphResult};
-use crate::prd::Prd;
-use crate::templates::get_agent_prompt;
+use crate::events::{emit, EventStream, RunEvent};
+use crate::humanize::format_duration;
+use crate::interactive::{assume_yes, confirm, is_interactive, select, ASSUME_YES_ENV};
+use crate::lanes::{plan_lanes, run_lanes, LaneRun};
+use crate::metadata::{timestamp, ArchiveMetadata, RunOutcome, RunRecord, RunState, VersionInfo};
+use crate::migration::MigrationPlan;
+use crate::output::{
+    DecodedLine, FileTail, LossyLines, MarkerScanner, OutputBuffer, OutputLimit, OutputRedactor,
+    COMPLETE_MARKER,
+};
+use crate::persistence::RunStore;
+use crate::prd::{Prd, StoryOrder, UserStory, BRANCH_PREFIX};
+use crate::sandbox_check::{default_watch_paths, parse_watch_paths, print_change_warning, Snapshot};
+use crate::secrets::scan_run_files;
+use crate::selector::{no_match_message, Selection, StorySelector};
+use crate::templates::{get_agent_prompt, prompt_token_estimate, render_prompt};
+use crate::usage::{Budget, Usage};
+use crate::workspace::nested_workspace_root;
+
+/// How the legacy migration prompt is answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationPrompt {
+    /// Ask the user at the terminal
+    Ask,
+    /// Migrate without asking (`--yes` or `RALPH_ASSUME_YES`)
+    Accept,
+    /// No terminal to ask on: leave the files alone and explain how to migrate
+    Skip,
+}
+
+/// Decide how to answer the migration prompt
+///
+/// Migration moves files, so without a terminal it only happens when the
+/// user opted in up front instead of waiting on input that never comes.
+pub fn migration_prompt(assume_yes: bool, interactive: bool) -> MigrationPrompt {
+    if assume_yes {
+        MigrationPrompt::Accept
+    } else if interactive {
+        MigrationPrompt::Ask
+    } else {
+        MigrationPrompt::Skip
+    }
+}
 
 /// Check for legacy files in old locations and offer migration
-fn check_and_offer_migration() -> RalphResult<()> {
-    let legacy_prd = Path::new("./prd.json");
-    let legacy_progress = Path::new("./progress.txt");
-    let new_dir = Path::new("./ralph");
-    let new_prd = new_dir.join("prd.json");
-
-    // Check if legacy files exist and new location doesn't
-    if legacy_prd.exists() && !new_prd.exists() {
-        println!("{}", "═══════════════════════════════════════".yellow());
-        println!("{}", "  Legacy files detected!".yellow().bold());
-        println!("{}", "═══════════════════════════════════════".yellow());
-        println!();
-        println!("Found prd.json in the old location (root directory).");
-        println!("Ralph now stores all project files in the 'ralph/' directory.");
+///
+/// Returns whether files were moved, in which case the command has to be run again.
+fn check_and_offer_migration() -> RalphResult<bool> {
+    let root = Path::new(".");
+    if !MigrationPlan::is_needed(root) {
+        return Ok(false);
+    }
+
+    println!("{}", "═══════════════════════════════════════".yellow());
+    println!("{}", "  Legacy files detected!".yellow().bold());
+    println!("{}", "═══════════════════════════════════════".yellow());
+    println!();
+    println!("Found prd.json in the old location (root directory).");
+    println!("Ralph now stores all project files in the 'ralph/' directory.");
+    println!();
+
+    let plan = MigrationPlan::compute(root)?;
+    if !plan.conflicts.is_empty() {
+        println!("These files exist in both locations with different content:");
+        for conflict in &plan.conflicts {
+            println!(
+                "  {} {} vs {}",
+                "✗".red(),
+                conflict.from.strip_prefix(root).unwrap_or(&conflict.from).display(),
+                conflict.to.strip_prefix(root).unwrap_or(&conflict.to).display()
+            );
+        }
         println!();
-        println!("Would you like to migrate your files? [Y/n]");
-
-        // Read user input
-        let mut input = String::new();
-        std::io::stdin()
-            .read_line(&mut input)
-            .map_err(RalphError::Io)?;
-
-        let input = input.trim().to_lowercase();
-        if input.is_empty() || input == "y" || input == "yes" {
-            // Perform migration
-            fs::create_dir_all(new_dir)?;
-
-            // Migrate prd.json
-            if legacy_prd.exists() {
-                fs::copy(legacy_prd, &new_prd)?;
-                fs::remove_file(legacy_prd)?;
-                println!("  ✓ Migrated prd.json → ralph/prd.json");
-            }
-
-            // Migrate progress.txt
-            if legacy_progress.exists() {
-                let new_progress = new_dir.join("progress.txt");
-                fs::copy(legacy_progress, &new_progress)?;
-                fs::remove_file(legacy_progress)?;
-                println!("  ✓ Migrated progress.txt → ralph/progress.txt");
-            }
-
-            // Migrate archive directory if it exists
-            let legacy_archive = Path::new("./archive");
-            if legacy_archive.exists() && legacy_archive.is_dir() {
-                let new_archive = new_dir.join("archive");
-                fs::create_dir_all(&new_archive)?;
-                // Move all contents from old archive to new archive
-                for entry in fs::read_dir(legacy_archive)? {
-                    let entry = entry?;
-                    let src = entry.path();
-                    let dst = new_archive.join(entry.file_name());
-                    fs::rename(&src, &dst)?;
-                }
-                fs::remove_dir(legacy_archive)?;
-                println!("  ✓ Migrated archive/ → ralph/archive/");
+        return Err(RalphError::Other(
+            "Migration blocked: keep one copy of each file listed above, then run again. No files were moved.".to_string(),
+        ));
+    }
+
+    println!("Planned steps:");
+    for step in &plan.steps {
+        println!("  - {}", step.describe(root));
+    }
+    println!();
+
+    let prompt = migration_prompt(assume_yes(), is_interactive());
+    apply_migration_plan(root, &plan, prompt, || {
+        confirm("Would you like to migrate your files?", true)
+    })?;
+    println!();
+    println!("{}", "Migration complete!".green().bold());
+    println!();
+    println!("Please run your command again.");
+    Ok(true)
+}
+
+/// Answer the migration prompt, then carry out the plan
+///
+/// `ask` is only called for [`MigrationPrompt::Ask`]. Every question is
+/// answered before the first file moves, so declining or pressing Ctrl+C
+/// leaves the legacy layout as it was.
+pub fn apply_migration_plan(
+    root: &Path,
+    plan: &MigrationPlan,
+    prompt: MigrationPrompt,
+    ask: impl FnOnce() -> RalphResult<bool>,
+) -> RalphResult<()> {
+    let accepted = match prompt {
+        MigrationPrompt::Accept => true,
+        MigrationPrompt::Ask => match ask() {
+            Err(RalphError::Interrupted) => {
+                println!();
+                println!("Migration aborted. No files were moved.");
+                return Err(RalphError::Interrupted);
             }
+            answer => answer?,
+        },
+        MigrationPrompt::Skip => {
+            println!("Migration skipped: stdin is not a terminal (or CI is set), so ralph cannot ask.");
+            return Err(RalphError::Other(format!(
+                "Migration required. Re-run with --yes (or {}=1) to migrate, or manually move files to ralph/",
+                ASSUME_YES_ENV
+            )));
+        }
+    };
+    if !accepted {
+        println!("Migration skipped. Please manually move your files to the 'ralph/' directory.");
+        return Err(RalphError::Other(
+            "Migration required. Run again and accept migration, or manually move files to ralph/".to_string()
+        ));
+    }
 
-            println!();
-            println!("{}", "Migration complete!".green().bold());
-            println!();
-            println!("Please run your command again.");
-            std::process::exit(0);
-        } else {
-            println!("Migration skipped. Please manually move your files to the 'ralph/' directory.");
-            return Err(RalphError::Other(
-                "Migration required. Run again and accept migration, or manually move files to ralph/".to_string()
-            ));
+    if let Err(failure) = plan.execute() {
+        for step in &plan.steps[..failure.completed] {
+            println!("  ✓ {}", step.describe(root));
+        }
+        let failed = &plan.steps[failure.completed];
+        println!("  {} {}: {}", "✗".red(), failed.describe(root), failure.error);
+        for step in &plan.steps[failure.completed + 1..] {
+            println!("  - {} (not started)", step.describe(root));
         }
+        println!();
+        return Err(RalphError::Other(format!(
+            "Migration stopped after {} of {} steps. Files that were not moved are still in place; run again to finish.",
+            failure.completed,
+            plan.steps.len()
+        )));
     }
 
+    for step in &plan.steps {
+        println!("  ✓ {}", step.describe(root));
+    }
     Ok(())
 }
 
+/// Options for the `ralph run` command
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// AI tool to use, or "auto"
+    pub tool: String,
+    /// Maximum iterations, overriding the config default
+    pub max_iterations: Option<u32>,
+    /// Path to prd.json
+    pub prd_path: String,
+    /// JSON Pointer of the PRD inside prd.json (`--prd-key`); empty for the whole file
+    pub prd_key: String,
+    /// Named pipe or socket to stream structured events to
+    pub stream_to: Option<PathBuf>,
+    /// `KEY=VALUE` variables to set for the agent
+    pub env: Vec<String>,
+    /// Dotenv file with variables to set for the agent
+    pub env_file: Option<PathBuf>,
+    /// Only work on this story, stopping once it passes
+    pub story: Option<String>,
+    /// Only work on the stories of this epic, stopping once they all pass
+    pub epic: Option<String>,
+    /// Work in the usual order, but stop as soon as this story passes
+    pub until: Option<String>,
+    /// Stories to leave alone for this run (not saved to the PRD)
+    pub exclude_story: Vec<String>,
+    /// Only work on stories with one of these tags
+    pub tag: Vec<String>,
+    /// Only work on stories with this priority or a more urgent one
+    pub max_priority: Option<u32>,
+    /// Archive the previous run even if the PRD looks like it belongs to another project
+    pub force_archive: bool,
+    /// Keep the previous run's files when the branch changed, only recording the new branch
+    pub ignore_branch_archive: bool,
+    /// Archive and reset a progress.txt written for another branch without asking
+    pub reset_progress: bool,
+    /// Exact agent executable to run instead of looking the tool up in PATH
+    pub tool_path: Option<PathBuf>,
+    /// Continue an interrupted run without asking
+    pub resume: bool,
+    /// Bytes an agent may print per iteration, overriding the config default
+    pub max_output: Option<u64>,
+    /// Text added before the prompt
+    pub prompt_prefix: Option<String>,
+    /// Text added after the prompt
+    pub prompt_suffix: Option<String>,
+    /// Order in which pending stories are worked on
+    pub story_order: StoryOrderChoice,
+    /// Seed for a random story order; a fresh one is picked when unset
+    pub seed: Option<u64>,
+    /// Warn when an iteration changes watched files outside the repository
+    pub sandbox_check: bool,
+    /// Snapshot prd.json and progress.txt into the archive if the run fails
+    pub on_error_archive: bool,
+    /// Copy prd.json to `ralph/.prd.bak` before each iteration
+    pub snapshot_prd: bool,
+    /// Fail instead of warning when the prompt has unknown placeholders
+    pub strict_prompt: bool,
+    /// Print the story queue the run would work through, then exit
+    pub list_stories: bool,
+    /// Print the prompt of the first iteration with its token estimate, then exit
+    pub print_prompt: bool,
+    /// Print lines the agent appends to progress.txt while it runs
+    pub show_progress: bool,
+    /// List the PATH and install locations checked when the agent fails to start
+    pub verbose_spawn_errors: bool,
+    /// Stop the run once the usage the agent reports goes over this cap
+    pub budget: Option<Budget>,
+    /// Checkpoint prd.json after every this many iterations
+    pub checkpoint_every: Option<u32>,
+    /// Save each iteration's prompt under `logs/prompts/`
+    pub prompt_history: bool,
+    /// Start even if the `require_criteria` config is set and pending stories lack criteria
+    pub allow_empty_criteria: bool,
+    /// Work on independent stories in this many git worktrees at once
+    pub parallel: Option<u32>,
+    /// Write no progress, PRD or run state; also on when the `readonly_ok` config sees a write fail
+    pub readonly: bool,
+    /// Create the PRD from the template first when it is missing
+    pub init_if_missing: bool,
+    /// Project name for that PRD; asked for on a terminal when not given
+    pub project_name: Option<String>,
+    /// Project description for that PRD; asked for on a terminal when not given
+    pub project_description: Option<String>,
+    /// Print the one-line header instead of the startup banner
+    pub compact: bool,
+}
+
+/// Settings shared by every agent iteration of a run
+pub struct IterationContext<'a> {
+    /// Tool command, for messages
+    pub tool_cmd: &'a str,
+    /// How the tool is invoked: flags, prompt delivery and output handling
+    pub invocation: AgentInvocation,
+    /// Executable to spawn: the tool command itself, or `--tool-path`
+    pub program: &'a OsStr,
+    pub ralph_dir: &'a Path,
+    pub prd_path: &'a Path,
+    /// JSON Pointer of the PRD inside prd.json
+    pub prd_key: &'a str,
+    pub env: &'a [EnvVar],
+    pub events: Option<&'a EventStream>,
+    /// Stories the run is restricted to, and the ones it leaves alone
+    pub selector: &'a StorySelector,
+    /// How long agent output may sit in the buffer before being printed
+    pub flush_interval: Duration,
+    /// Bytes of stdout and stderr allowed per iteration (0 = no limit)
+    pub max_output: u64,
+    /// Masks tokens in agent output before it is streamed
+    pub redactor: &'a OutputRedactor,
+    /// Mask the terminal copy of agent output as well
+    pub redact_terminal: bool,
+    /// Text wrapped around the prompt (`--prompt-prefix`/`--prompt-suffix`)
+    pub prompt_prefix: Option<&'a str>,
+    pub prompt_suffix: Option<&'a str>,
+    /// Order the agent is asked to work through pending stories in
+    pub story_order: StoryOrder,
+    /// Refuse to spawn the agent when the prompt has unknown placeholders
+    pub strict_prompt: bool,
+    /// Print lines appended to progress.txt alongside the agent output
+    pub show_progress: bool,
+    /// Include the PATH and checked locations in spawn failure guidance
+    pub verbose_spawn_errors: bool,
+    /// Switch the agent to structured output and read its usage from it
+    pub track_usage: bool,
+    /// Save the prompt to `logs/prompts/iter-N.md` before spawning the agent
+    pub prompt_history: bool,
+    /// Where prd.json write-backs and prompt copies go, unless the run is read-only
+    pub store: &'a RunStore,
+}
+
+/// How a single agent iteration ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct IterationOutcome {
+    /// The agent printed `<promise>COMPLETE</promise>`
+    pub completed: bool,
+    /// Exit code of the agent, or `None` when it was ended by a signal
+    pub exit_code: Option<i32>,
+    /// Bytes the agent wrote to stdout and stderr
+    pub bytes_out: usize,
+    pub duration: Duration,
+    /// Stories marked as passing by `<promise>STORY_PASSED:id</promise>` signals, in order
+    pub stories_passed: Vec<String>,
+    /// The agent was stopped for exceeding the output limit
+    pub output_limit_exceeded: bool,
+    /// Usage the agent reported, when it was tracked and reported
+    pub usage: Option<Usage>,
+}
+
+/// First line of every progress file
+const PROGRESS_TITLE: &str = "# Ralph Progress Log";
+
+/// Copy of prd.json taken before each iteration with `--snapshot-prd`
+pub const PRD_BACKUP_FILE: &str = ".prd.bak";
+
+/// Where an unreadable prd.json is kept when the backup is restored
+pub const PRD_CORRUPT_FILE: &str = ".prd.corrupt";
+
+/// Failure reason recorded for iterations stopped by `--max-output`
+const OUTPUT_LIMIT_REASON: &str = "output limit exceeded";
+
+/// Directory under the ralph directory holding `--checkpoint-every` copies of prd.json
+pub const CHECKPOINT_DIR: &str = "checkpoints";
+
+/// Directory under the ralph directory holding `--prompt-history` copies of each prompt
+pub const PROMPT_HISTORY_DIR: &str = "logs/prompts";
+
+/// Tag in front of progress.txt lines shown with `--show-progress`
+const PROGRESS_TAG: &str = "[progress]";
+
+/// How often progress.txt is checked for new lines with `--show-progress`
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Where to snapshot the run state if `--on-error-archive` is set and the run fails
+struct ErrorArchive {
+    ralph_dir: PathBuf,
+    prd_path: PathBuf,
+    /// PRD branch, once the PRD has loaded
+    branch: Option<String>,
+    store: RunStore,
+}
+
 /// Run the Ralph task execution command
-pub async fn run_run(
-    tool: String,
-    max_iterations: Option<u32>,
-    prd_path: String,
-) -> RalphResult<()> {
-    // Load configuration
-    let config = Config::load()?;
+///
+/// With `on_error_archive`, a run that fails after its ralph directory is
+/// known copies prd.json and progress.txt into a `-error` archive folder
+/// before returning the error.
+///
+/// Returns how the run ended; runs that had nothing to do are `Complete`,
+/// and a run that moved legacy files into `ralph/` stops at `Migrated`.
+pub async fn run_run(options: RunOptions) -> RalphResult<RunOutcome> {
+    let on_error_archive = options.on_error_archive;
+    let mut error_archive = None;
+    let result = run_with_options(options, &mut error_archive).await;
 
-    // Determine max iterations
-    let max_iter = max_iterations.or(config.max_iterations).unwrap_or(10);
+    // Ctrl+C at a prompt is an abort, not a failure worth archiving
+    let failed = matches!(&result, Err(e) if !matches!(e, RalphError::Interrupted));
+    if let (true, true, Some(target)) = (failed, on_error_archive, error_archive) {
+        match target.store.write("the failed run archive", || archive_failed_run(&target)) {
+            Ok(None) => {}
+            Ok(Some(dir)) => eprintln!(
+                "{}",
+                format!("Archived the failed run for post-mortem: {}", dir.display()).yellow()
+            ),
+            Err(e) => eprintln!(
+                "{}",
+                format!("Warning: could not archive the failed run: {}", e).yellow()
+            ),
+        }
+    }
+    result
+}
+
+async fn run_with_options(
+    options: RunOptions,
+    error_archive: &mut Option<ErrorArchive>,
+) -> RalphResult<RunOutcome> {
+    let RunOptions {
+        tool,
+        max_iterations,
+        prd_path,
+        prd_key,
+        stream_to,
+        env,
+        env_file,
+        story,
+        epic,
+        mut until,
+        mut exclude_story,
+        tag,
+        max_priority,
+        force_archive,
+        ignore_branch_archive,
+        reset_progress,
+        tool_path,
+        resume,
+        max_output,
+        prompt_prefix,
+        prompt_suffix,
+        story_order,
+        seed,
+        sandbox_check,
+        on_error_archive: _,
+        snapshot_prd,
+        strict_prompt,
+        list_stories,
+        print_prompt,
+        show_progress,
+        verbose_spawn_errors,
+        budget,
+        checkpoint_every,
+        prompt_history,
+        allow_empty_criteria,
+        parallel,
+        readonly,
+        init_if_missing,
+        project_name,
+        project_description,
+        compact,
+    } = options;
 
-    // Check for legacy files and offer migration
-    check_and_offer_migration()?;
+    // Lanes and a created PRD are written outside the read-only run store
+    if readonly && parallel.is_some() {
+        return Err(RalphError::Other(
+            "--parallel cannot be used with --readonly; lanes write worktrees and prd.json"
+                .to_string(),
+        ));
+    }
+    // Every lane would get the whole cap, so the run could spend N times it
+    if budget.is_some() && parallel.is_some() {
+        return Err(RalphError::Other(
+            "--budget cannot be used with --parallel; each lane would spend up to the whole budget"
+                .to_string(),
+        ));
+    }
+    if readonly && init_if_missing {
+        return Err(RalphError::Other(
+            "--init-if-missing cannot be used with --readonly; it writes prd.json".to_string(),
+        ));
+    }
+
+    // Collect agent environment: the env file first, then --env overrides
+    let mut agent_env = match &env_file {
+        Some(path) => load_env_file(path)?,
+        None => Vec::new(),
+    };
+    for assignment in &env {
+        agent_env.push(
+            parse_env_assignment(assignment)
+                .map_err(|e| RalphError::Other(format!("Invalid --env value: {}", e)))?,
+        );
+    }
+
+    // Running from inside ralph/ would look for ralph/ralph/prd.json
+    if prd_path == DEFAULT_PRD_PATH {
+        let cwd = std::env::current_dir()?;
+        if let Some(root) = nested_workspace_root(&cwd) {
+            println!(
+                "{}",
+                format!(
+                    "Note: you are inside the ralph directory; running from the project root {}",
+                    root.display()
+                )
+                .yellow()
+            );
+            std::env::set_current_dir(&root)?;
+        }
+    }
+
+    // Check for legacy files and offer migration, which moves them
+    if !readonly && check_and_offer_migration()? {
+        return Ok(RunOutcome::Migrated);
+    }
 
     // Get the directory containing prd.json (the ralph working directory)
     let prd_file_path = PathBuf::from(&prd_path);
+    if init_if_missing {
+        init_missing_prd(
+            &prd_file_path,
+            project_name.as_deref(),
+            project_description.as_deref(),
+        )?;
+    }
     let ralph_dir = prd_file_path
         .parent()
+        .filter(|p| !p.as_os_str().is_empty())
         .map(|p| p.to_path_buf())
         .unwrap_or_else(|| PathBuf::from("."));
+    let ralph_dir = resolve_ralph_dir(&ralph_dir)?;
 
-    // Ensure the ralph directory exists
-    if !ralph_dir.exists() {
-        return Err(RalphError::Other(format!(
-            "Ralph directory does not exist: {}. Run 'ralph init' to initialize.",
-            ralph_dir.display()
-        )));
+    // Load configuration: global, then the project's ralph/config.toml
+    let config = Config::load_layered_or_default(&ralph_dir);
+
+    // Compile the redaction patterns before any agent output is streamed
+    let redactor = OutputRedactor::from_config(config.redact_patterns.as_deref())
+        .map_err(|e| RalphError::Other(format!("Invalid redact_patterns: {}", e)))?;
+
+    // Determine max iterations
+    let max_iter = max_iterations.or(config.max_iterations).unwrap_or(10);
+
+    // Every state write below goes through the store, which skips them when read-only
+    let store = RunStore::new(readonly, config.readonly_ok.unwrap_or(false));
+    if readonly {
+        println!(
+            "{}",
+            "Read-only mode: progress.txt, prd.json and run state will not be written".yellow()
+        );
     }
 
+    *error_archive = Some(ErrorArchive {
+        ralph_dir: ralph_dir.clone(),
+        prd_path: prd_file_path.clone(),
+        branch: None,
+        store: store.clone(),
+    });
+
+    // Offer the last snapshot if the agent left prd.json unreadable
+    store.write("the prd.json restore", || offer_prd_restore(&prd_file_path, &prd_key, &ralph_dir))?;
+
     // Load PRD
-    let prd = Prd::from_file(&prd_path).map_err(|e| {
+    let prd = Prd::from_file_at(&prd_path, &prd_key).map_err(|e| {
         RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
     })?;
+    if let Some(target) = error_archive.as_mut() {
+        target.branch = Some(prd.branch_name.clone());
+    }
+    // Counts for the startup display; recomputed only after the PRD is reloaded
+    let stats = prd.stats();
+    check_excluded_stories(&prd, &exclude_story, story.as_deref().or(until.as_deref()))?;
+    let mut selector = StorySelector {
+        story: story.clone(),
+        epic: epic.clone(),
+        tags: tag,
+        max_priority,
+        excluded: exclude_story.clone(),
+    };
+
+    // Preview the queue without needing an agent
+    if list_stories {
+        let order = resolve_story_order(story_order, seed);
+        let queue = WorkQueue::from_prd(&prd, order, &selector);
+        print!("{}", queue.render());
+        if let StoryOrder::Random(seed) = order {
+            println!("Repeat this order with --story-order random --seed {}", seed);
+        }
+        return Ok(RunOutcome::Complete);
+    }
 
-    // Determine which tool to use
-    let tool_cmd = determine_tool(&tool, &config)?;
+    // Show what the agent would be sent, also without needing an agent
+    if print_prompt {
+        let (prompt, unknown) = assemble_prompt(
+            &prd,
+            &selector,
+            resolve_story_order(story_order, seed),
+            prompt_prefix.as_deref(),
+            prompt_suffix.as_deref(),
+        );
+        check_unknown_placeholders(&unknown, strict_prompt)?;
+        print!("{}", prompt);
+        // On stderr, so the prompt itself can be piped to a file
+        eprintln!("{}", prompt_size_summary(&prompt).dimmed());
+        return Ok(RunOutcome::Complete);
+    }
+
+    // Determine which tool to use, and the executable that runs it
+    let (tool_cmd, program) = match &tool_path {
+        Some(path) => {
+            validate_tool_path(path)?;
+            // The agent runs inside the ralph directory, so resolve relative paths now
+            (tool_type_for_path(&tool, path), std::path::absolute(path)?)
+        }
+        None => {
+            let tool_cmd = determine_tool(&tool, &config)?;
+            let program = PathBuf::from(&tool_cmd);
+            (tool_cmd, program)
+        }
+    };
+    // A budget can only be enforced when the agent reports what it used
+    let invocation = AgentInvocation::for_tool(&tool_cmd);
+    if budget.is_some() && !invocation.reports_usage {
+        return Err(RalphError::Other(format!(
+            "--budget needs an agent that reports token usage (claude); {} does not",
+            tool_cmd
+        )));
+    }
+    let started_at = timestamp();
 
     // Display startup information
-    println!("{}", "Ralph Task Runner".bold().cyan());
-    println!("{}", "=================".cyan());
-    println!();
-    println!("Project: {}", prd.project.bold());
-    println!("Branch: {}", prd.branch_name().cyan());
-    println!("Tool: {}", tool_cmd.cyan());
-    println!();
-    println!(
-        "Progress: {}/{} stories completed",
-        prd.completed_stories().to_string().green(),
-        prd.total_stories()
-    );
+    let story_order = resolve_story_order(story_order, seed);
+    let compact = compact || !config.run_banner.unwrap_or(true);
+    if compact {
+        println!(
+            "{}",
+            compact_header(&prd.project, prd.branch_name(), &tool_cmd, stats.completed, stats.total)
+        );
+        // The seed is the only way to repeat a random order, so it is always shown
+        if let StoryOrder::Random(seed) = story_order {
+            println!("Story order: random (repeat with --story-order random --seed {})", seed);
+        }
+    } else {
+        println!("{}", "Ralph Task Runner".bold().cyan());
+        println!("{}", "=================".cyan());
+        println!();
+        println!("Project: {}", prd.project.bold());
+        println!("Branch: {}", prd.branch_name().cyan());
+        println!("Tool: {}", tool_cmd.cyan());
+        if tool_path.is_some() {
+            println!("Tool path: {}", program.display());
+        }
+        if let Some(budget) = &budget {
+            println!("Budget: {}", budget.to_string().cyan());
+        }
+        match story_order {
+            StoryOrder::Priority => {}
+            StoryOrder::File => println!("Story order: as written in the PRD"),
+            StoryOrder::Random(seed) => {
+                println!("Story order: random (repeat with --story-order random --seed {})", seed)
+            }
+        }
+        println!();
+        println!(
+            "Progress: {}/{} stories completed",
+            stats.completed.to_string().green(),
+            stats.total
+        );
+    }
     println!();
 
     // Check if all stories are complete
-    if prd.pending_stories() == 0 {
+    if stats.pending == 0 {
         println!("{}", "All stories are complete!".green().bold());
-        return Ok(());
+        return Ok(RunOutcome::Complete);
+    }
+    if !exclude_story.is_empty() {
+        println!("Excluded for this run: {}", exclude_story.join(", ").yellow());
+        println!();
+        if rest_passed(&prd, &exclude_story) {
+            println!("{}", "All stories outside --exclude-story are complete!".green().bold());
+            return Ok(RunOutcome::Complete);
+        }
     }
 
+    // Check the targeted story before doing any work
+    if let Some(story_id) = &story {
+        let target = prd
+            .find_story(story_id)
+            .ok_or_else(|| RalphError::Other(format!("Unknown story id: {}", story_id)))?;
+        if target.passes {
+            println!("{}", format!("Story {} is already complete", story_id).green().bold());
+            return Ok(RunOutcome::Complete);
+        }
+        println!("Target story: {}", target.display().cyan());
+        println!();
+    }
+
+    // The story a bounded run stops at must still be pending
+    if let Some(story_id) = &until {
+        let stop_at = prd
+            .find_story(story_id)
+            .ok_or_else(|| RalphError::Other(format!("Unknown story id: {}", story_id)))?;
+        if stop_at.passes {
+            return Err(RalphError::Other(format!(
+                "Story {} already passes; nothing to run --until",
+                story_id
+            )));
+        }
+        println!("Running until: {}", stop_at.display().cyan());
+        println!();
+    }
+
+    // Check the targeted epic the same way
+    if let Some(name) = &epic {
+        let stories = prd.epic_stories(name);
+        if stories.is_empty() {
+            return Err(RalphError::Other(if prd.epics.iter().any(|e| &e.name == name) {
+                format!("Epic \"{}\" has no stories", name)
+            } else {
+                format!("Unknown epic: {}", name)
+            }));
+        }
+        let completed = stories.iter().filter(|s| s.passes).count();
+        if completed == stories.len() {
+            println!("{}", format!("Epic \"{}\" is already complete", name).green().bold());
+            return Ok(RunOutcome::Complete);
+        }
+        println!(
+            "Target epic: {} ({}/{} stories completed)",
+            name.cyan(),
+            completed,
+            stories.len()
+        );
+        println!();
+    }
+
+    // Filters can leave nothing to do while stories are still pending
+    match selector.select(&prd) {
+        Selection::NoMatch { pending } => {
+            println!("{}", no_match_message(pending).yellow().bold());
+            println!("Active filters: {}", selector.describe());
+            return Ok(RunOutcome::NoMatchingStories);
+        }
+        Selection::Pending { matching, pending } if selector.has_attribute_filters() => {
+            println!(
+                "Filters: {} ({} of {} pending stories match)",
+                selector.describe().cyan(),
+                matching,
+                pending
+            );
+            println!();
+        }
+        _ => {}
+    }
+
+    // Only probe the agent once there is work for it, and from where it will run
+    let mut versions = VersionInfo::capture(&program.to_string_lossy(), &ralph_dir);
+    versions.tool = tool_cmd.clone();
+    if !compact {
+        println!(
+            "{}",
+            format!(
+                "Tool version: {}",
+                versions.tool_version.as_deref().unwrap_or("unknown")
+            )
+            .dimmed()
+        );
+        println!();
+    }
+
+    // Warn about stories that are likely to produce poor agent results
+    print_weak_story_warnings(&prd);
+    check_required_criteria(
+        &prd,
+        config.require_criteria.unwrap_or(false) && !allow_empty_criteria,
+    )?;
+
+    // Explain which stories cannot start yet
+    print_blocked_stories(&prd);
+
+    // Catch pasted credentials before they reach the agent or an archive
+    check_secrets(&ralph_dir, &prd, config.scan_secrets.unwrap_or_default())?;
+
+    // Hand independent stories to lanes in their own worktrees
+    if let Some(max_lanes) = parallel {
+        // A bounded run covers the stories up to its --until story, in run order
+        let mut scope = selector.clone();
+        if let Some(stop_at) = &until {
+            let pending = selector.pending(&prd, story_order);
+            if let Some(position) = pending.iter().position(|s| &s.id == stop_at) {
+                scope.excluded.extend(pending[position + 1..].iter().map(|s| s.id.clone()));
+            }
+        }
+        let plan = plan_lanes(&prd, max_lanes as usize, &scope);
+        if plan.is_parallel() {
+            let mut forwarded_args: Vec<OsString> = Vec::new();
+            for assignment in &env {
+                forwarded_args.extend(["--env".into(), assignment.into()]);
+            }
+            if let Some(path) = &env_file {
+                forwarded_args.extend(["--env-file".into(), std::path::absolute(path)?.into()]);
+            }
+            if let Some(bytes) = max_output {
+                forwarded_args.extend(["--max-output".into(), bytes.to_string().into()]);
+            }
+            if let Some(text) = &prompt_prefix {
+                forwarded_args.extend(["--prompt-prefix".into(), text.into()]);
+            }
+            if let Some(text) = &prompt_suffix {
+                forwarded_args.extend(["--prompt-suffix".into(), text.into()]);
+            }
+            match story_order {
+                StoryOrder::Priority => {}
+                StoryOrder::File => forwarded_args.extend(["--story-order".into(), "file".into()]),
+                // The resolved seed, so every lane and the log agree on it
+                StoryOrder::Random(seed) => forwarded_args.extend([
+                    "--story-order".into(),
+                    "random".into(),
+                    "--seed".into(),
+                    seed.to_string().into(),
+                ]),
+            }
+            if let Some(every) = checkpoint_every {
+                forwarded_args.extend(["--checkpoint-every".into(), every.to_string().into()]);
+            }
+            let flags = [
+                (sandbox_check, "--sandbox-check"),
+                (snapshot_prd, "--snapshot-prd"),
+                (strict_prompt, "--strict-prompt"),
+                (show_progress, "--show-progress"),
+                (verbose_spawn_errors, "--verbose-spawn-errors"),
+                (prompt_history, "--prompt-history"),
+                (allow_empty_criteria, "--allow-empty-criteria"),
+                (compact, "--compact"),
+            ];
+            for (_, flag) in flags.iter().filter(|(set, _)| *set) {
+                forwarded_args.push(flag.into());
+            }
+            println!(
+                "{}",
+                format!("Running {} lanes in parallel (experimental)", plan.lanes.len()).bold()
+            );
+            let lane_stories = plan.lanes.concat();
+            let serial = plan.serial.clone();
+            let outcome = run_lanes(LaneRun {
+                prd: &prd,
+                prd_path: &prd_file_path,
+                prd_key: &prd_key,
+                ralph_dir: &ralph_dir,
+                plan,
+                tool_cmd: &tool_cmd,
+                tool_path: tool_path.is_some().then_some(program.as_path()),
+                max_iterations: max_iter,
+                forwarded_args,
+                store: &store,
+            })
+            .await?;
+            if serial.is_empty() {
+                return Ok(outcome);
+            }
+            if outcome != RunOutcome::Complete {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Not running the stories left for a serial run, since the lanes did not \
+                         all finish: {}. Run them without --parallel once the lanes are merged.",
+                        serial.join(", ")
+                    )
+                    .yellow()
+                );
+                return Ok(outcome);
+            }
+            // The lane stories now pass; the serial run covers the rest of the scope
+            println!();
+            println!("{}", format!("Running serially: {}", serial.join(", ")).bold());
+            println!();
+            scope.excluded.extend(lane_stories);
+            exclude_story = scope.excluded.clone();
+            selector = scope;
+            until = None;
+        } else {
+            println!(
+                "{}",
+                "Note: --parallel found no independent lanes (stories need contextFiles, \
+                 and lanes cannot share files or dependencies); running serially"
+                    .yellow()
+            );
+            println!();
+        }
+    }
+
+    // Continue the iteration budget of an interrupted run, if there is one
+    let resumed = resume_interrupted_run(&ralph_dir, &prd, resume, &store)?;
+    let (max_iter, first_iteration) = match &resumed {
+        Some(state) => (state.max_iterations, state.iterations_used + 1),
+        None => (max_iter, 1),
+    };
+
     // Handle archive logic if branch changed
-    handle_archive(&ralph_dir, &prd)?;
+    let progress_file = ralph_dir.join("progress.txt");
+    if ignore_branch_archive {
+        store.write(".last-branch", || record_current_branch(&ralph_dir, &prd))?;
+        store.write("progress.txt", || restamp_progress_file(&progress_file, &prd))?;
+    } else {
+        let archive_required = config.archive_required.unwrap_or(false);
+        store.write("the branch archive and .last-branch", || {
+            handle_archive(&ralph_dir, &prd, force_archive, archive_required)
+        })?;
+    }
+
+    // Make sure the progress log the agent reads belongs to this PRD
+    check_progress_header(&ralph_dir, &prd, reset_progress, &store)?;
 
     // Initialize progress file if it doesn't exist
-    let progress_file = ralph_dir.join("progress.txt");
-    init_progress_file(&progress_file)?;
+    store.write("progress.txt", || {
+        init_progress_file(&progress_file, &prd)?;
+        append_run_header(&progress_file, &started_at, &versions)
+    })?;
+
+    // Start streaming structured events if requested
+    let events = match stream_to {
+        Some(path) => Some(EventStream::start(path.clone()).map_err(|e| {
+            RalphError::Other(format!("Failed to open event stream {}: {}", path.display(), e))
+        })?),
+        None => None,
+    };
+    emit(
+        events.as_ref(),
+        RunEvent::RunStarted {
+            project: prd.project.clone(),
+            branch: prd.branch_name.clone(),
+            tool: tool_cmd.clone(),
+            max_iterations: max_iter,
+        },
+    );
 
     // Setup Ctrl+C handler
     let running = Arc::new(AtomicBool::new(true));
@@ -168,217 +925,1248 @@ pub async fn run_run(
         }
     });
 
-    // Run iterations
-    let mut current_iteration = 1;
+    let context = IterationContext {
+        tool_cmd: &tool_cmd,
+        invocation,
+        program: program.as_os_str(),
+        ralph_dir: &ralph_dir,
+        prd_path: &prd_file_path,
+        prd_key: &prd_key,
+        env: &agent_env,
+        events: events.as_ref(),
+        selector: &selector,
+        flush_interval: Duration::from_millis(config.flush_interval_ms.unwrap_or(50)),
+        max_output: max_output
+            .or(config.max_output_bytes)
+            .unwrap_or(50 * 1024 * 1024),
+        redactor: &redactor,
+        redact_terminal: config.redact_terminal.unwrap_or(false),
+        prompt_prefix: prompt_prefix.as_deref(),
+        prompt_suffix: prompt_suffix.as_deref(),
+        story_order,
+        strict_prompt,
+        show_progress,
+        verbose_spawn_errors,
+        track_usage: budget.is_some(),
+        prompt_history,
+        store: &store,
+    };
+
+    // Track progress so an interrupted run can be resumed
+    let mut run_state = RunState {
+        project: prd.project.clone(),
+        branch: prd.branch_name.clone(),
+        tool: tool_cmd.clone(),
+        target_story: story.clone(),
+        started_at: resumed.map_or_else(|| started_at.clone(), |state| state.started_at),
+        updated_at: timestamp(),
+        iterations_used: first_iteration - 1,
+        max_iterations: max_iter,
+    };
+
+    // Snapshot sensitive files so changes made by the agent can be reported
+    let mut sandbox = sandbox_check.then(|| {
+        let paths = config
+            .sandbox_watch_paths
+            .as_deref()
+            .map(parse_watch_paths)
+            .unwrap_or_else(default_watch_paths);
+        let snapshot = Snapshot::take(&paths);
+        println!(
+            "{}",
+            format!(
+                "Sandbox check: watching {} files under {} paths",
+                snapshot.file_count(),
+                paths.len()
+            )
+            .dimmed()
+        );
+        snapshot
+    });
+    let mut sandbox_changes = 0;
+
+    // Run iterations
+    let run_started = Instant::now();
+    let mut current_iteration = first_iteration;
+    let mut signaled_complete = false;
+    let mut output_limit_hits = 0;
+    let mut usage = Usage::default();
+    let mut budget_exceeded = false;
+    let mut no_match = false;
+    let branch_change = config.branch_change.unwrap_or_default();
+    let mut branch_changed_to = None;
+
+    while current_iteration <= max_iter && running.load(Ordering::SeqCst) {
+        println!(
+            "\n{} {} / {}",
+            "Iteration".bold(),
+            current_iteration,
+            max_iter
+        );
+        println!("{}", "-".repeat(40).dimmed());
+        emit(
+            events.as_ref(),
+            RunEvent::IterationStarted {
+                iteration: current_iteration,
+            },
+        );
+
+        // Reload the PRD so the prompt reflects the agent's latest updates
+        let current_prd = Prd::from_file_at(&prd_path, &prd_key).unwrap_or_else(|_| prd.clone());
+        check_branch_change(&prd.branch_name, &current_prd, &mut branch_changed_to, branch_change)?;
+        if snapshot_prd {
+            if let Err(e) =
+                store.write("the prd.json snapshot", || snapshot_prd_file(&prd_file_path, &prd_key, &ralph_dir))
+            {
+                eprintln!("{}", format!("Warning: failed to snapshot prd.json: {}", e).yellow());
+            }
+        }
+
+        // Run the agent
+        let outcome = run_agent_iteration(&context, &current_prd, current_iteration, running.clone()).await?;
+        println!(
+            "{}",
+            format!(
+                "Iteration {} took {}",
+                current_iteration,
+                format_duration(outcome.duration)
+            )
+            .dimmed()
+        );
+        // The agent may have checked out the PRD's branch; that is not a
+        // branch change for the next run
+        if let Err(e) = store.write(".last-git-branch", || Ok(record_git_branch(&ralph_dir)?)) {
+            eprintln!("{}", format!("Warning: failed to record the git branch: {}", e).yellow());
+        }
+        if checkpoint_every.is_some_and(|every| current_iteration.is_multiple_of(every)) {
+            match store
+                .write("the prd.json checkpoint", || {
+                    checkpoint_prd(&prd_file_path, &prd_key, &ralph_dir, current_iteration)
+                })
+            {
+                Ok(None) => {}
+                Ok(Some(Some(path))) => {
+                    println!("{}", format!("Checkpoint saved: {}", path.display()).dimmed())
+                }
+                Ok(Some(None)) => eprintln!(
+                    "{}",
+                    "Warning: prd.json is unreadable, skipping this checkpoint".yellow()
+                ),
+                Err(e) => eprintln!(
+                    "{}",
+                    format!("Warning: failed to checkpoint prd.json: {}", e).yellow()
+                ),
+            }
+        }
+        if let Some(snapshot) = &mut sandbox {
+            let changes = snapshot.refresh();
+            if !changes.is_empty() {
+                print_change_warning(current_iteration, &changes);
+                sandbox_changes += changes.len();
+            }
+        }
+        let signaled = outcome.completed;
+        if outcome.output_limit_exceeded {
+            output_limit_hits += 1;
+        }
+        if let Some(budget) = &budget {
+            match &outcome.usage {
+                Some(used) => usage.add(used),
+                None => eprintln!(
+                    "{}",
+                    format!(
+                        "Warning: {} reported no usage for iteration {}; it is not counted against the budget",
+                        tool_cmd, current_iteration
+                    )
+                    .yellow()
+                ),
+            }
+            println!("{}", format!("Usage so far: {} of {}", usage, budget).dimmed());
+            budget_exceeded = budget.is_exceeded(&usage);
+        }
+
+        // A targeted run is done as soon as its story passes, an epic run once
+        // all of its stories pass, and a bounded run once its --until story passes
+        let updated_prd = Prd::from_file_at(&prd_path, &prd_key).ok();
+        if let Some(updated) = &updated_prd {
+            check_branch_change(&prd.branch_name, updated, &mut branch_changed_to, branch_change)?;
+        }
+        let story_passed = |story_id: &str| {
+            updated_prd
+                .as_ref()
+                .and_then(|p| p.find_story(story_id))
+                .is_some_and(|s| s.passes)
+        };
+        let target_passed = story.as_deref().is_some_and(story_passed);
+        let until_passed = until.as_deref().is_some_and(story_passed);
+        let epic_passed = epic.as_deref().is_some_and(|name| {
+            updated_prd
+                .as_ref()
+                .is_some_and(|p| p.epic_stories(name).iter().all(|s| s.passes))
+        });
+        // With --exclude-story, the agent cannot finish every story
+        let others_passed = !exclude_story.is_empty()
+            && updated_prd.as_ref().is_some_and(|p| rest_passed(p, &exclude_story));
+        let completed = signaled || target_passed || epic_passed || until_passed || others_passed;
+        // --tag and --max-priority have no target to pass; the run ends when
+        // nothing in scope is pending
+        let filtered_out = match updated_prd.as_ref().map(|p| selector.select(p)) {
+            Some(Selection::NoMatch { pending }) if !completed && selector.has_attribute_filters() => {
+                Some(pending)
+            }
+            _ => None,
+        };
+
+        run_state.iterations_used = current_iteration;
+        run_state.updated_at = timestamp();
+        if let Err(e) = store.write("run state", || Ok(run_state.save(&ralph_dir)?)) {
+            eprintln!("{}", format!("Warning: failed to save run state: {}", e).yellow());
+        }
+
+        emit(
+            events.as_ref(),
+            RunEvent::IterationFinished {
+                iteration: current_iteration,
+                completed,
+                failure: outcome
+                    .output_limit_exceeded
+                    .then(|| OUTPUT_LIMIT_REASON.to_string()),
+                exit_code: outcome.exit_code,
+                duration_ms: outcome.duration.as_millis() as u64,
+                stories_passed: outcome.stories_passed,
+            },
+        );
+
+        if completed {
+            println!();
+            if signaled {
+                println!("{}", "✓ Agent signaled completion!".green().bold());
+            } else if epic_passed {
+                println!("{}", "✓ Target epic complete!".green().bold());
+            } else if until_passed {
+                let story_id = until.as_deref().unwrap_or_default();
+                println!("{}", format!("✓ {} passed, stopping (--until)", story_id).green().bold());
+            } else if others_passed && !target_passed {
+                let message = "✓ All stories outside --exclude-story passed!";
+                println!("{}", message.green().bold());
+            } else {
+                println!("{}", "✓ Target story passed!".green().bold());
+            }
+            signaled_complete = true;
+            break;
+        }
+        if let Some(pending) = filtered_out {
+            println!();
+            println!("{}", no_match_message(pending).yellow().bold());
+            no_match = true;
+            break;
+        }
+
+        // The iteration that crossed the cap is allowed to finish, but no other starts
+        if budget_exceeded {
+            println!();
+            println!(
+                "{}",
+                format!(
+                    "Budget exceeded: used {} (budget {}), stopping",
+                    usage,
+                    budget.map(|b| b.to_string()).unwrap_or_default()
+                )
+                .red()
+                .bold()
+            );
+            break;
+        }
+
+        current_iteration += 1;
+    }
+
+    // Display summary
+    println!();
+    println!("{}", "=================".cyan());
+    println!("{}", "Run Summary".bold().cyan());
+    println!("{}", "=================".cyan());
+    let iterations_run = if signaled_complete || budget_exceeded || no_match {
+        current_iteration
+    } else {
+        (current_iteration - 1).min(max_iter)
+    };
+    println!("Iterations completed: {}/{}", iterations_run, max_iter);
+    println!("Run took {}", format_duration(run_started.elapsed()));
+    if output_limit_hits > 0 {
+        println!(
+            "{}",
+            format!("Iterations stopped by the output limit: {}", output_limit_hits).red()
+        );
+    }
+    if sandbox_changes > 0 {
+        println!(
+            "{}",
+            format!("Files changed outside the repository: {}", sandbox_changes).red()
+        );
+    }
+    if let Some(budget) = &budget {
+        println!("Usage: {} (budget {})", usage, budget);
+    }
+    if let Some(changed_to) = &branch_changed_to {
+        println!(
+            "{}",
+            format!(
+                "Branch changed by the agent: {} -> {} (restore branchName, or the next run archives this one)",
+                prd.branch_name, changed_to
+            )
+            .red()
+        );
+    }
+
+    // Reload PRD to get updated status
+    let final_prd = Prd::from_file_at(&prd_path, &prd_key).unwrap_or_else(|_| prd.clone());
+    let final_stats = final_prd.stats();
+    println!(
+        "Stories completed: {}/{}",
+        final_stats.completed,
+        final_stats.total
+    );
+
+    let outcome = if signaled_complete {
+        RunOutcome::Complete
+    } else if budget_exceeded {
+        println!("{}", "Budget exceeded".red());
+        RunOutcome::BudgetExceeded
+    } else if no_match {
+        RunOutcome::NoMatchingStories
+    } else if !running.load(Ordering::SeqCst) {
+        println!("{}", "Run interrupted by user".yellow());
+        RunOutcome::Interrupted
+    } else {
+        println!("{}", "Maximum iterations reached".yellow());
+        RunOutcome::MaxIterations
+    };
+
+    let finished_at = timestamp();
+    if config.log_run_summary.unwrap_or(true) {
+        let newly_passed: Vec<String> = final_prd
+            .user_stories
+            .iter()
+            .filter(|s| s.passes && prd.find_story(&s.id).is_none_or(|before| !before.passes))
+            .map(|s| s.id.clone())
+            .collect();
+        let items = run_summary_items(iterations_run, max_iter, &newly_passed, outcome);
+        let heading = format!("Ralph run summary - {}", finished_at);
+        if let Err(e) =
+            store.write("progress.txt", || append_progress_entry(&progress_file, &heading, &items))
+        {
+            eprintln!("{}", format!("Warning: failed to log the run summary: {}", e).yellow());
+        }
+    }
+
+    // Only an interrupted run can be resumed
+    if outcome != RunOutcome::Interrupted {
+        if let Err(e) = store.write("run state", || Ok(RunState::clear(&ralph_dir)?)) {
+            eprintln!("{}", format!("Warning: failed to clear run state: {}", e).yellow());
+        }
+    }
+
+    emit(
+        events.as_ref(),
+        RunEvent::RunFinished {
+            outcome,
+            iterations: iterations_run,
+            completed_stories: final_stats.completed,
+            total_stories: final_stats.total,
+        },
+    );
+    if let Some(events) = events {
+        let dropped = events.finish().await;
+        if dropped > 0 {
+            println!(
+                "{}",
+                format!("Stream events dropped (slow or absent reader): {}", dropped).yellow()
+            );
+        }
+    }
+
+    let record = RunRecord {
+        versions,
+        branch: prd.branch_name.clone(),
+        started_at,
+        finished_at,
+        iterations: iterations_run,
+        completed_stories: final_stats.completed,
+        total_stories: final_stats.total,
+        outcome,
+        output_limit_hits,
+        usage: budget.is_some().then_some(usage),
+        branch_changed_to,
+    };
+    if let Err(e) = store.write("the last run record", || Ok(record.save(&ralph_dir)?)) {
+        eprintln!("{}", format!("Warning: failed to record last run: {}", e).yellow());
+    }
+    if store.is_readonly() {
+        println!(
+            "{}",
+            format!(
+                "Read-only run: no state was saved (skipped: {})",
+                store.skipped().join(", ")
+            )
+            .yellow()
+            .bold()
+        );
+    }
+
+    Ok(outcome)
+}
+
+/// Notice the agent changing branchName in the PRD during the run
+///
+/// The run keeps the branch it started with for its own bookkeeping; only the
+/// reloaded PRD carries the new name. Each new name is reported once, and
+/// `branch_change = abort` ends the run instead.
+fn check_branch_change(
+    original: &str,
+    reloaded: &Prd,
+    changed_to: &mut Option<String>,
+    mode: BranchChangeMode,
+) -> RalphResult<()> {
+    let current = reloaded.branch_name.as_str();
+    if current == original {
+        if changed_to.take().is_some() {
+            println!("{}", format!("branchName is back to {}", original).green());
+        }
+        return Ok(());
+    }
+    if changed_to.as_deref() == Some(current) {
+        return Ok(());
+    }
+
+    if mode == BranchChangeMode::Abort {
+        return Err(RalphError::Other(format!(
+            "Run aborted: branchName in prd.json changed from {} to {} during the run \
+             (branch_change = abort). Restore it before running again.",
+            original, current
+        )));
+    }
+    eprintln!();
+    eprintln!(
+        "{}",
+        format!(
+            "Warning: branchName in prd.json changed from {} to {} during the run!",
+            original, current
+        )
+        .red()
+        .bold()
+    );
+    eprintln!(
+        "{}",
+        format!(
+            "This run keeps using {}. Unless branchName is restored, the next run archives \
+             this one and resets progress.txt (or pass --ignore-branch-archive).",
+            original
+        )
+        .yellow()
+    );
+    *changed_to = Some(current.to_string());
+    Ok(())
+}
+
+/// Scan the PRD and progress.txt for secrets according to `scan_secrets`
+fn check_secrets(ralph_dir: &Path, prd: &Prd, mode: SecretScanMode) -> RalphResult<()> {
+    if mode == SecretScanMode::Off {
+        return Ok(());
+    }
+    let findings = scan_run_files(ralph_dir, prd)?;
+    if findings.is_empty() {
+        return Ok(());
+    }
+
+    print_secret_findings(&findings);
+    if mode == SecretScanMode::Block {
+        return Err(RalphError::Other(format!(
+            "Refusing to run with {} possible secret(s); remove them with `ralph prd scan-secrets --redact`",
+            findings.len()
+        )));
+    }
+    println!(
+        "{}",
+        "Warning: the agent will see these values; run `ralph prd scan-secrets --redact` to remove them"
+            .yellow()
+    );
+    println!();
+    Ok(())
+}
+
+/// Canonicalize the ralph directory so every path derived from it agrees
+///
+/// A symlinked `ralph/` resolves to its target; a missing directory or a
+/// broken symlink is reported instead.
+fn resolve_ralph_dir(ralph_dir: &Path) -> RalphResult<PathBuf> {
+    match fs::canonicalize(ralph_dir) {
+        Ok(resolved) if resolved.is_dir() => Ok(resolved),
+        Ok(resolved) => Err(RalphError::Other(format!(
+            "Ralph directory {} is not a directory",
+            resolved.display()
+        ))),
+        Err(_) if ralph_dir.is_symlink() => {
+            let target = fs::read_link(ralph_dir)
+                .map(|t| t.display().to_string())
+                .unwrap_or_else(|_| "an unknown location".to_string());
+            Err(RalphError::Other(format!(
+                "Ralph directory {} is a broken symlink to {}. Fix or remove the link, \
+                 or run 'ralph init' to initialize.",
+                ralph_dir.display(),
+                target
+            )))
+        }
+        Err(_) => Err(RalphError::Other(format!(
+            "Ralph directory does not exist: {}. Run 'ralph init' to initialize.",
+            ralph_dir.display()
+        ))),
+    }
+}
+
+/// How long an interrupted run stays resumable
+const RESUME_MAX_AGE_HOURS: i64 = 24;
+
+/// Offer to continue an interrupted run recorded in `.run-state.json`
+///
+/// With `resume` the run is continued without asking. Without a terminal
+/// (and without `--yes`) a fresh run is started. State from another branch or
+/// project, or older than a day, is discarded.
+fn resume_interrupted_run(
+    ralph_dir: &Path,
+    prd: &Prd,
+    resume: bool,
+    store: &RunStore,
+) -> RalphResult<Option<RunState>> {
+    let Some(state) = RunState::load(ralph_dir) else {
+        if resume {
+            println!("{}", "No interrupted run to resume; starting a new run".dimmed());
+        }
+        return Ok(None);
+    };
+
+    if let Some(reason) = state.mismatch(prd) {
+        println!(
+            "{}",
+            format!("Note: ignoring saved run state because {}", reason).yellow()
+        );
+        store.write("run state", || Ok(RunState::clear(ralph_dir)?))?;
+        return Ok(None);
+    }
+
+    let max_age = chrono::TimeDelta::hours(RESUME_MAX_AGE_HOURS);
+    if !state.is_recent(Local::now().naive_local(), max_age) || state.remaining() == 0 {
+        store.write("run state", || Ok(RunState::clear(ralph_dir)?))?;
+        return Ok(None);
+    }
+
+    let summary = format!(
+        "{} of {} iterations remain",
+        state.remaining(),
+        state.max_iterations
+    );
+    let accepted = if resume {
+        true
+    } else if is_interactive() || assume_yes() {
+        confirm(
+            &format!(
+                "Resume the run interrupted at {} ({})?",
+                state.updated_at, summary
+            ),
+            true,
+        )?
+    } else {
+        println!(
+            "{}",
+            format!(
+                "Note: starting a new run; pass --resume to continue the interrupted one ({})",
+                summary
+            )
+            .dimmed()
+        );
+        false
+    };
+
+    if !accepted {
+        store.write("run state", || Ok(RunState::clear(ralph_dir)?))?;
+        return Ok(None);
+    }
+
+    println!("{} {}", "Resuming interrupted run:".green(), summary);
+    println!();
+    Ok(Some(state))
+}
+
+/// Handle archive logic when branch changes
+///
+/// The change is seen in `.last-branch` or, inside a git repository, in the
+/// checked-out branch (see [`detect_branch_change`]).
+///
+/// Archiving is a convenience, so a failure to copy the previous run only
+/// warns, unless `archive_required` is set. `.last-branch` is then left
+/// alone, so the next run tries again.
+fn handle_archive(
+    ralph_dir: &Path,
+    prd: &Prd,
+    force_archive: bool,
+    archive_required: bool,
+) -> RalphResult<()> {
+    let last_branch_file = ralph_dir.join(".last-branch");
+    let last_branch = if last_branch_file.exists() {
+        Some(fs::read_to_string(&last_branch_file)?)
+    } else {
+        None
+    };
+    let git_state = GitBranchState::read(ralph_dir);
+
+    // Check if there's a previous branch to archive
+    if let Some(change) =
+        detect_branch_change(last_branch.as_deref(), &prd.branch_name, git_state.as_ref())
+    {
+        let last_branch = change.previous.as_str();
+
+        // A prd.json copied from another project would file this
+        // project's progress under the wrong name
+        if let Some(last_project) = changed_project(ralph_dir, prd) {
+            if !force_archive {
+                confirm_project_swap(&last_project, &prd.project, last_branch)?;
+            }
+        }
+
+        // Branch changed, archive the previous run
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        let folder_name = last_branch.strip_prefix(BRANCH_PREFIX).unwrap_or(last_branch);
+        let archive_dir = ralph_dir.join("archive").join(format!("{}-{}", date, folder_name));
+
+        println!(
+            "Archiving previous run: {} -> {}",
+            last_branch.cyan(),
+            archive_dir.display()
+        );
+        for signal in &change.signals {
+            println!("  {}", format!("Detected by {}", signal).dimmed());
+        }
+
+        let prd_file = ralph_dir.join("prd.json");
+        if let Err(e) =
+            archive_run_files(ralph_dir, &prd_file, &archive_dir, last_branch, &change.signals)
+        {
+            if archive_required {
+                return Err(RalphError::Other(format!(
+                    "Could not archive the previous run to {}: {} (archive_required is set)",
+                    archive_dir.display(),
+                    e
+                )));
+            }
+            print_archive_failure(&archive_dir, &e);
+            return Ok(());
+        }
+
+        // Reset progress file for new run
+        reset_progress_file(&ralph_dir.join("progress.txt"), prd)?;
+    }
+
+    record_current_branch(ralph_dir, prd)
+}
+
+/// Warn that the previous run was not archived, and that the run goes on
+fn print_archive_failure(archive_dir: &Path, error: &RalphError) {
+    println!();
+    println!(
+        "{}",
+        format!("Warning: could not archive the previous run: {}", error).yellow().bold()
+    );
+    println!("  Skipped archive: {}", archive_dir.display());
+    println!("  progress.txt was kept; archiving is retried on the next run.");
+    println!(
+        "  {}",
+        "Set archive_required = true in the config to stop the run instead.".dimmed()
+    );
+    println!();
+}
+
+/// Remember the PRD's branch and project, and the git branch, for the next
+/// run's archive check
+fn record_current_branch(ralph_dir: &Path, prd: &Prd) -> RalphResult<()> {
+    fs::write(ralph_dir.join(".last-branch"), &prd.branch_name)?;
+    fs::write(ralph_dir.join(".last-project"), &prd.project)?;
+    record_git_branch(ralph_dir)?;
+    Ok(())
+}
+
+/// Copy a run's PRD and progress log into an archive folder, with its metadata
+fn archive_run_files(
+    ralph_dir: &Path,
+    prd_file: &Path,
+    archive_dir: &Path,
+    branch: &str,
+    branch_change: &[String],
+) -> RalphResult<()> {
+    fs::create_dir_all(archive_dir)?;
+
+    // Copy prd.json if it exists
+    if prd_file.exists() {
+        fs::copy(prd_file, archive_dir.join("prd.json"))?;
+    }
+
+    // Copy progress.txt if it exists
+    let progress_file = ralph_dir.join("progress.txt");
+    if progress_file.exists() {
+        fs::copy(&progress_file, archive_dir.join("progress.txt"))?;
+    }
+
+    // Record which versions produced the archived run
+    let mut metadata = ArchiveMetadata::new(branch, RunRecord::load(ralph_dir));
+    metadata.branch_change = branch_change.to_vec();
+    metadata.save(archive_dir)?;
+    Ok(())
+}
+
+/// Snapshot the state of a failed run into a `-error` archive folder
+///
+/// The branch comes from the PRD, or `.last-branch` when the PRD could not be
+/// loaded. The run's files are copied, never moved or reset.
+fn archive_failed_run(target: &ErrorArchive) -> RalphResult<PathBuf> {
+    let branch = target
+        .branch
+        .clone()
+        .or_else(|| fs::read_to_string(target.ralph_dir.join(".last-branch")).ok())
+        .map(|b| b.trim().to_string())
+        .filter(|b| !b.is_empty())
+        .unwrap_or_else(|| "run".to_string());
+    let folder_name = branch.strip_prefix(BRANCH_PREFIX).unwrap_or(&branch);
+    let archive_dir = error_archive_dir(
+        &target.ralph_dir.join("archive"),
+        Local::now().date_naive(),
+        folder_name,
+    );
+
+    archive_run_files(&target.ralph_dir, &target.prd_path, &archive_dir, &branch, &[])?;
+    Ok(archive_dir)
+}
+
+/// Get the project name of the last run when it differs from the PRD's
+///
+/// Returns `None` when the names match or no `.last-project` was recorded.
+pub fn changed_project(ralph_dir: &Path, prd: &Prd) -> Option<String> {
+    let last_project = fs::read_to_string(ralph_dir.join(".last-project")).ok()?;
+    let last_project = last_project.trim();
+    (!last_project.is_empty() && last_project != prd.project.trim())
+        .then(|| last_project.to_string())
+}
+
+/// Ask before archiving when the PRD now describes a different project
+fn confirm_project_swap(last_project: &str, project: &str, last_branch: &str) -> RalphResult<()> {
+    println!(
+        "{}",
+        format!(
+            "Warning: the PRD project changed from \"{}\" to \"{}\".",
+            last_project, project
+        )
+        .yellow()
+    );
+    println!(
+        "If prd.json was copied from another project, the current progress would be archived as {}.",
+        last_branch.cyan()
+    );
+    println!("Pass --force-archive to skip this check for an intentional swap.");
 
-    while current_iteration <= max_iter && running.load(Ordering::SeqCst) {
-        println!(
-            "\n{} {} / {}",
-            "Iteration".bold(),
-            current_iteration,
-            max_iter
-        );
-        println!("{}", "-".repeat(40).dimmed());
+    if !confirm("Archive the previous run anyway?", false)? {
+        return Err(RalphError::Other(
+            "Archive cancelled. Restore the previous prd.json, or re-run with --force-archive if the swap is intentional."
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
 
-        // Run the agent
-        let completed = run_agent_iteration(&tool_cmd, &ralph_dir, running.clone()).await?;
+/// Copy prd.json to the backup file in the ralph directory
+///
+/// A PRD that does not parse is left alone, so the backup always holds the
+/// last readable version. Returns whether a copy was made.
+pub fn snapshot_prd_file(prd_path: &Path, prd_key: &str, ralph_dir: &Path) -> RalphResult<bool> {
+    if Prd::from_file_at(prd_path, prd_key).is_err() {
+        return Ok(false);
+    }
+    fs::copy(prd_path, ralph_dir.join(PRD_BACKUP_FILE))?;
+    Ok(true)
+}
 
-        if completed {
-            println!();
-            println!("{}", "✓ Agent signaled completion!".green().bold());
-            break;
-        }
+/// Re-save prd.json and copy it to `checkpoints/prd-NNNN.json` for `iteration`
+///
+/// The PRD is parsed and saved through the usual atomic write first, so the
+/// checkpoint is always a PRD ralph can load. Returns the checkpoint path, or
+/// `None` when prd.json cannot be parsed and nothing was written.
+pub fn checkpoint_prd(
+    prd_path: &Path,
+    prd_key: &str,
+    ralph_dir: &Path,
+    iteration: u32,
+) -> RalphResult<Option<PathBuf>> {
+    let Ok(prd) = Prd::from_file_at(prd_path, prd_key) else {
+        return Ok(None);
+    };
+    prd.save_to_file_at(prd_path, prd_key)?;
+    let dir = ralph_dir.join(CHECKPOINT_DIR);
+    fs::create_dir_all(&dir)?;
+    let checkpoint = dir.join(format!("prd-{:04}.json", iteration));
+    fs::copy(prd_path, &checkpoint)?;
+    Ok(Some(checkpoint))
+}
 
-        current_iteration += 1;
+/// Write the prompt sent in `iteration` to `logs/prompts/iter-N.md`
+///
+/// A prompt saved by an earlier run for the same iteration is replaced.
+pub fn write_prompt_history(ralph_dir: &Path, iteration: u32, prompt: &str) -> RalphResult<PathBuf> {
+    let dir = ralph_dir.join(PROMPT_HISTORY_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("iter-{}.md", iteration));
+    fs::write(&path, prompt)?;
+    Ok(path)
+}
+
+/// The backup of an existing prd.json that no longer parses, if one can be read
+pub fn restorable_prd_backup(prd_path: &Path, prd_key: &str, ralph_dir: &Path) -> Option<PathBuf> {
+    if !prd_path.exists() || Prd::from_file_at(prd_path, prd_key).is_ok() {
+        return None;
     }
+    let backup = ralph_dir.join(PRD_BACKUP_FILE);
+    Prd::from_file_at(&backup, prd_key).is_ok().then_some(backup)
+}
+
+/// Put the backup in place of prd.json, keeping the unreadable file next to it
+pub fn restore_prd_backup(prd_path: &Path, ralph_dir: &Path) -> RalphResult<()> {
+    fs::copy(prd_path, ralph_dir.join(PRD_CORRUPT_FILE))?;
+    fs::copy(ralph_dir.join(PRD_BACKUP_FILE), prd_path)?;
+    Ok(())
+}
+
+/// Offer to restore the `--snapshot-prd` backup when prd.json fails to parse
+///
+/// The broken file is kept as `.prd.corrupt`, so restoring is safe to accept
+/// without a terminal. A missing prd.json is reported by the normal load.
+fn offer_prd_restore(prd_path: &Path, prd_key: &str, ralph_dir: &Path) -> RalphResult<()> {
+    let Some(backup) = restorable_prd_backup(prd_path, prd_key, ralph_dir) else {
+        return Ok(());
+    };
 
-    // Display summary
-    println!();
-    println!("{}", "=================".cyan());
-    println!("{}", "Run Summary".bold().cyan());
-    println!("{}", "=================".cyan());
     println!(
-        "Iterations completed: {}/{}",
-        (current_iteration - 1).min(max_iter),
-        max_iter
+        "{}",
+        format!("Warning: {} could not be parsed.", prd_path.display()).yellow().bold()
     );
+    println!("A snapshot taken before the last iteration is at {}.", backup.display());
+    if !confirm("Restore prd.json from the snapshot?", true)? {
+        return Ok(());
+    }
 
-    // Reload PRD to get updated status
-    let final_prd = Prd::from_file(&prd_path).unwrap_or(prd);
+    restore_prd_backup(prd_path, ralph_dir)?;
     println!(
-        "Stories completed: {}/{}",
-        final_prd.completed_stories(),
-        final_prd.total_stories()
+        "{} (the unreadable file was kept as {})",
+        "✓ Restored prd.json from the snapshot".green(),
+        ralph_dir.join(PRD_CORRUPT_FILE).display()
     );
+    Ok(())
+}
 
-    if !running.load(Ordering::SeqCst) {
-        println!("{}", "Run interrupted by user".yellow());
-    } else if current_iteration > max_iter {
-        println!("{}", "Maximum iterations reached".yellow());
+/// Initialize progress file if it doesn't exist
+fn init_progress_file(progress_file: &Path, prd: &Prd) -> RalphResult<()> {
+    if !progress_file.exists() {
+        reset_progress_file(progress_file, prd)?;
     }
-
     Ok(())
 }
 
-/// Handle archive logic when branch changes
-fn handle_archive(ralph_dir: &Path, prd: &Prd) -> RalphResult<()> {
-    let last_branch_file = ralph_dir.join(".last-branch");
-    let current_branch = &prd.branch_name;
+/// Start a fresh progress file whose header names the PRD's project and branch
+fn reset_progress_file(progress_file: &Path, prd: &Prd) -> RalphResult<()> {
+    let content = format!(
+        "{}\nProject: {}\nBranch: {}\nStarted: {}\n---\n",
+        PROGRESS_TITLE,
+        prd.project,
+        prd.branch_name,
+        Local::now().format("%Y-%m-%d %H:%M:%S")
+    );
+    fs::write(progress_file, content)?;
+    Ok(())
+}
 
-    // Check if there's a previous branch to archive
-    if last_branch_file.exists() {
-        let last_branch = fs::read_to_string(&last_branch_file)?;
-        let last_branch = last_branch.trim();
+/// Project and branch recorded at the top of progress.txt
+///
+/// Files written by older ralph versions have neither field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgressHeader {
+    pub project: Option<String>,
+    pub branch: Option<String>,
+}
 
-        if !last_branch.is_empty() && last_branch != current_branch {
-            // Branch changed, archive the previous run
-            let date = Local::now().format("%Y-%m-%d").to_string();
-            let folder_name = last_branch.strip_prefix("ralph/").unwrap_or(last_branch);
-            let archive_dir = ralph_dir.join("archive").join(format!("{}-{}", date, folder_name));
+impl ProgressHeader {
+    /// Read the fields between the title line and the first `---`
+    pub fn parse(content: &str) -> Self {
+        let mut header = Self::default();
+        for line in header_lines(content) {
+            if let Some(project) = line.strip_prefix("Project:") {
+                header.project = Some(project.trim().to_string()).filter(|p| !p.is_empty());
+            } else if let Some(branch) = line.strip_prefix("Branch:") {
+                header.branch = Some(branch.trim().to_string()).filter(|b| !b.is_empty());
+            }
+        }
+        header
+    }
 
-            println!(
-                "Archiving previous run: {} -> {}",
-                last_branch.cyan(),
-                archive_dir.display()
-            );
+    /// Describe how the header disagrees with the PRD, if it does
+    ///
+    /// A missing field never counts as a mismatch.
+    pub fn mismatch(&self, prd: &Prd) -> Option<String> {
+        let mut differences = Vec::new();
+        if let Some(branch) = self.branch.as_deref().filter(|b| *b != prd.branch_name) {
+            differences.push(format!("branch {} (the PRD targets {})", branch, prd.branch_name));
+        }
+        if let Some(project) = self.project.as_deref().filter(|p| *p != prd.project.trim()) {
+            differences.push(format!("project \"{}\" (the PRD is \"{}\")", project, prd.project));
+        }
+        (!differences.is_empty()).then(|| differences.join(" and "))
+    }
+}
 
-            fs::create_dir_all(&archive_dir)?;
+/// Lines of the header block: after the title, up to the first `---`
+///
+/// Empty when the file does not start with the progress title.
+fn header_lines(content: &str) -> impl Iterator<Item = &str> {
+    let mut lines = content.lines();
+    let titled = lines.next().is_some_and(|l| l.trim() == PROGRESS_TITLE);
+    lines.filter(move |_| titled).take_while(|l| l.trim() != "---")
+}
 
-            // Copy prd.json if it exists
-            let prd_file = ralph_dir.join("prd.json");
-            if prd_file.exists() {
-                fs::copy(&prd_file, archive_dir.join("prd.json"))?;
+/// Rewrite the project and branch fields of a progress header for the PRD
+///
+/// Returns `None` when the header has no fields to update, as in files
+/// written by older versions.
+pub fn restamp_progress_header(content: &str, prd: &Prd) -> Option<String> {
+    let header_len = header_lines(content).count();
+    let mut changed = false;
+    let lines: Vec<String> = content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            // Line 0 is the title
+            if index == 0 || index > header_len {
+                line.to_string()
+            } else if line.starts_with("Project:") {
+                changed = true;
+                format!("Project: {}", prd.project)
+            } else if line.starts_with("Branch:") {
+                changed = true;
+                format!("Branch: {}", prd.branch_name)
+            } else {
+                line.to_string()
             }
+        })
+        .collect();
+    changed.then(|| lines.join("\n") + if content.ends_with('\n') { "\n" } else { "" })
+}
+
+/// Point an existing progress header at the PRD, keeping the log itself
+fn restamp_progress_file(progress_file: &Path, prd: &Prd) -> RalphResult<()> {
+    let Ok(content) = fs::read_to_string(progress_file) else {
+        return Ok(());
+    };
+    if let Some(updated) = restamp_progress_header(&content, prd) {
+        fs::write(progress_file, updated)?;
+    }
+    Ok(())
+}
+
+/// Compare the progress header with the PRD before the agent reads the log
+///
+/// On a mismatch the user can continue, archive the log and start a fresh
+/// one, or abort. `reset_progress` archives and resets without asking;
+/// without a terminal the run continues after the warning.
+fn check_progress_header(
+    ralph_dir: &Path,
+    prd: &Prd,
+    reset_progress: bool,
+    store: &RunStore,
+) -> RalphResult<()> {
+    let progress_file = ralph_dir.join("progress.txt");
+    let Ok(content) = fs::read_to_string(&progress_file) else {
+        return Ok(());
+    };
+    let header = ProgressHeader::parse(&content);
+    let Some(mismatch) = header.mismatch(prd) else {
+        return Ok(());
+    };
 
-            // Copy progress.txt if it exists
-            let progress_file = ralph_dir.join("progress.txt");
-            if progress_file.exists() {
+    println!();
+    println!(
+        "{}",
+        format!("Warning: progress.txt was written for {}.", mismatch).yellow().bold()
+    );
+    println!("The agent would read notes from another run as context.");
+
+    let choice = if store.is_readonly() {
+        println!("Read-only mode: continuing with this progress.txt.");
+        0
+    } else if reset_progress {
+        1
+    } else {
+        println!("Pass --reset-progress to archive it and start a fresh log without asking.");
+        let options = [
+            "Continue with this progress.txt",
+            "Archive it and start a fresh one",
+            "Abort",
+        ]
+        .map(String::from);
+        select("How should ralph continue?", &options, 0)?
+    };
+
+    match choice {
+        0 => Ok(()),
+        1 => {
+            let branch = header.branch.as_deref().unwrap_or(&prd.branch_name);
+            let folder_name = branch.strip_prefix(BRANCH_PREFIX).unwrap_or(branch);
+            let archive_dir = progress_archive_dir(
+                &ralph_dir.join("archive"),
+                Local::now().date_naive(),
+                folder_name,
+            );
+            let archived = store.write("the progress archive", || {
+                fs::create_dir_all(&archive_dir)?;
                 fs::copy(&progress_file, archive_dir.join("progress.txt"))?;
+                reset_progress_file(&progress_file, prd)
+            })?;
+            if archived.is_some() {
+                println!("Archived progress.txt to {}", archive_dir.display());
             }
-
-            // Reset progress file for new run
-            init_progress_file(&progress_file)?;
+            Ok(())
         }
+        _ => Err(RalphError::Other(
+            "Run aborted: progress.txt belongs to another run. Re-run with --reset-progress to archive it and start fresh."
+                .to_string(),
+        )),
     }
+}
 
-    // Track current branch
-    fs::write(&last_branch_file, current_branch)?;
-
-    Ok(())
+/// Append a header for this run to the progress file
+fn append_run_header(progress_file: &Path, started_at: &str, versions: &VersionInfo) -> RalphResult<()> {
+    append_progress_entry(
+        progress_file,
+        &format!("Ralph run - {}", started_at),
+        &[versions.summary()],
+    )
 }
 
-/// Initialize progress file if it doesn't exist
-fn init_progress_file(progress_file: &Path) -> RalphResult<()> {
-    if !progress_file.exists() {
-        let content = format!(
-            "# Ralph Progress Log\nStarted: {}\n---\n",
-            Local::now().format("%Y-%m-%d %H:%M:%S")
-        );
-        fs::write(progress_file, content)?;
+/// Append an entry to the progress file: a `## ` heading, `- ` items and `---`
+pub fn append_progress_entry(
+    progress_file: &Path,
+    heading: &str,
+    items: &[String],
+) -> RalphResult<()> {
+    use std::io::Write;
+
+    let mut file = fs::OpenOptions::new().append(true).open(progress_file)?;
+    writeln!(file, "## {}", heading)?;
+    for item in items {
+        writeln!(file, "- {}", item)?;
     }
+    writeln!(file, "---")?;
     Ok(())
 }
 
+/// Items of the summary entry appended to the progress file when a run ends
+pub fn run_summary_items(
+    iterations: u32,
+    max_iterations: u32,
+    newly_passed: &[String],
+    outcome: RunOutcome,
+) -> Vec<String> {
+    let stories = if newly_passed.is_empty() {
+        "none".to_string()
+    } else {
+        newly_passed.join(", ")
+    };
+    vec![
+        format!("Iterations: {}/{}", iterations, max_iterations),
+        format!("Stories completed this run: {}", stories),
+        format!("Ended: {}", outcome.reason()),
+    ]
+}
+
 /// Determine which tool command to use
 pub fn determine_tool(tool: &str, config: &Config) -> Result<String, crate::error::RalphError> {
     match tool {
         "auto" => {
-            // Try to use default_tool from config, otherwise auto-detect
+            // Use default_tool from config when it is available
             if let Some(ref default) = config.default_tool {
-                // Verify the tool is available
                 if is_command_available(default) {
-                    Ok(default.clone())
-                } else {
-                    // Try to detect any available agent
-                    let detected = detect_agents();
-                    if let Some(first) = detected.first() {
-                        Ok(first.command().to_string())
-                    } else {
-                        Err(RalphError::Other(
-                            "No AI agent CLI detected. Please install Amp, Claude Code, or CodeBuddy.".to_string()
-                        ))
-                    }
+                    return Ok(default.clone());
                 }
-            } else {
-                // Auto-detect
-                let detected = detect_agents();
-                if let Some(first) = detected.first() {
-                    Ok(first.command().to_string())
-                } else {
-                    Err(RalphError::Other(
-                        "No AI agent CLI detected. Please install Amp, Claude Code, or CodeBuddy.".to_string()
-                    ))
+            }
+            // Otherwise auto-detect, custom tools last and only when opted in
+            if let Some(first) = detect_agents().first() {
+                return Ok(first.command().to_string());
+            }
+            if config.auto_include_custom.unwrap_or(false) {
+                if let Some(custom) = first_installed_custom_tool(config) {
+                    return Ok(custom);
                 }
             }
+            Err(RalphError::Other(
+                "No AI agent CLI detected. Please install Amp, Claude Code, CodeBuddy, or Aider.".to_string()
+            ))
         }
         "amp" => Ok("amp".to_string()),
         "claude" => Ok("claude".to_string()),
         "codebuddy" => Ok("codebuddy".to_string()),
+        "aider" => Ok("aider".to_string()),
         _ => Ok(tool.to_string()), // Allow custom tool commands
     }
 }
 
+/// Check that `--tool-path` points at an executable file
+pub fn validate_tool_path(path: &Path) -> RalphResult<()> {
+    let metadata = fs::metadata(path).map_err(|e| {
+        RalphError::Other(format!("Tool path {} is not usable: {}", path.display(), e))
+    })?;
+    if !metadata.is_file() {
+        return Err(RalphError::Other(format!(
+            "Tool path {} is not a file",
+            path.display()
+        )));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(RalphError::Other(format!(
+                "Tool path {} is not executable",
+                path.display()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Pick the tool type for `--tool-path` from `--tool`, or else from the file name
+///
+/// `/opt/claude-2.1/bin/claude` is run with the Claude flags; unknown names
+/// are treated as custom tools.
+pub fn tool_type_for_path(tool: &str, path: &Path) -> String {
+    if tool != "auto" {
+        return tool.to_string();
+    }
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(Agent::from_command)
+        .map(|agent| agent.command().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned())
+}
+
 /// Run a single agent iteration
-async fn run_agent_iteration(
-    tool_cmd: &str,
-    ralph_dir: &Path,
+pub async fn run_agent_iteration(
+    context: &IterationContext<'_>,
+    prd: &Prd,
+    iteration: u32,
     running: Arc<AtomicBool>,
-) -> RalphResult<bool> {
-    // Get the embedded prompt content
-    let prompt_content = get_agent_prompt();
+) -> RalphResult<IterationOutcome> {
+    let started = Instant::now();
+    let IterationContext {
+        tool_cmd,
+        invocation,
+        program,
+        ralph_dir,
+        prd_path,
+        prd_key,
+        env,
+        events,
+        selector,
+        flush_interval,
+        max_output,
+        redactor,
+        redact_terminal,
+        prompt_prefix,
+        prompt_suffix,
+        story_order,
+        strict_prompt,
+        show_progress,
+        verbose_spawn_errors,
+        track_usage,
+        prompt_history,
+        store,
+    } = *context;
+
+    let (prompt_content, unknown) = assemble_prompt(
+        prd,
+        selector,
+        story_order,
+        prompt_prefix,
+        prompt_suffix,
+    );
+    check_unknown_placeholders(&unknown, strict_prompt)?;
 
-    // Build the command based on the tool
-    let mut cmd = TokioCommand::new(tool_cmd);
+    if prompt_history {
+        match store.write("the prompt history", || {
+            write_prompt_history(ralph_dir, iteration, &prompt_content)
+        }) {
+            Ok(None) => {}
+            Ok(Some(path)) => println!("{}", format!("Prompt saved: {}", path.display()).dimmed()),
+            Err(e) => eprintln!(
+                "{}",
+                format!("Warning: failed to save the prompt: {}", e).yellow()
+            ),
+        }
+    }
+
+    // Build the command from the tool's invocation
+    let mut cmd = build_agent_command(&invocation, program, &prompt_content).map_err(|e| {
+        RalphError::Other(format!("Failed to write the prompt file: {}", e))
+    })?;
+    if track_usage {
+        cmd.args(invocation.structured_output.unwrap_or_default());
+    }
 
     // Set the working directory to the ralph directory
     cmd.current_dir(ralph_dir);
 
-    // Configure command based on tool type
-    match tool_cmd {
-        "amp" => {
-            // amp: read skill file from stdin with --dangerously-allow-all flag
-            cmd.arg("--dangerously-allow-all");
-            cmd.stdin(std::process::Stdio::piped());
-            cmd.stdout(std::process::Stdio::piped());
-            cmd.stderr(std::process::Stdio::piped());
-        }
-        "claude" => {
-            // claude: use --dangerously-skip-permissions and --print, read from stdin
-            cmd.arg("--dangerously-skip-permissions");
-            cmd.arg("--print");
-            cmd.stdin(std::process::Stdio::piped());
-            cmd.stdout(std::process::Stdio::piped());
-            cmd.stderr(std::process::Stdio::piped());
-        }
-        "codebuddy" => {
-            // codebuddy: use -p --dangerously-skip-permissions --tools default, read from stdin
-            cmd.arg("-p");
-            cmd.arg("--dangerously-skip-permissions");
-            cmd.arg("--tools");
-            cmd.arg("default");
-            cmd.stdin(std::process::Stdio::piped());
-            cmd.stdout(std::process::Stdio::piped());
-            cmd.stderr(std::process::Stdio::piped());
-        }
-        _ => {
-            // For custom tools, use basic stdin redirection
-            cmd.stdin(std::process::Stdio::piped());
-            cmd.stdout(std::process::Stdio::piped());
-            cmd.stderr(std::process::Stdio::piped());
-        }
-    }
+    // Apply variables from --env and --env-file
+    cmd.envs(env.iter().map(|(k, v)| (k, v)));
 
     // Spawn the process
     let mut child = cmd.spawn().map_err(|e| {
-        RalphError::Other(format!("Failed to spawn {}: {}", tool_cmd, e))
+        // The agent sees PATH from --env when it is set there
+        let path = env
+            .iter()
+            .rev()
+            .find(|(key, _)| key == "PATH")
+            .map(|(_, value)| OsString::from(value))
+            .or_else(|| std::env::var_os("PATH"))
+            .unwrap_or_default();
+        let tool = program.to_string_lossy().into_owned();
+        let guidance = spawn_error_guidance(
+            &tool,
+            &e,
+            &path,
+            dirs::home_dir().as_deref(),
+            verbose_spawn_errors,
+        );
+        // The first line repeats the error itself, which is printed last
+        for line in guidance.lines().skip(1) {
+            eprintln!("{}", line);
+        }
+        RalphError::AgentSpawn { tool, source: e }
     })?;
 
-    // Write prompt content to stdin
+    // Write prompt content to stdin, unless it was passed as an argument
     if let Some(mut stdin) = child.stdin.take() {
         use tokio::io::AsyncWriteExt;
-        stdin.write_all(prompt_content.as_bytes()).await.map_err(|e| {
-            RalphError::Other(format!("Failed to write to stdin: {}", e))
-        })?;
+        match stdin.write_all(prompt_content.as_bytes()).await {
+            // The agent exited or closed stdin without reading all of the
+            // prompt; its output and exit status tell the rest
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            result => result.map_err(|e| {
+                RalphError::Other(format!("Failed to write to stdin: {}", e))
+            })?,
+        }
         // Close stdin to signal EOF
         // stdin is dropped here, which closes the pipe
     }
@@ -386,51 +2174,169 @@ async fn run_agent_iteration(
     let stdout = child.stdout.take().expect("Failed to capture stdout");
     let stderr = child.stderr.take().expect("Failed to capture stderr");
 
-    let mut stdout_reader = BufReader::new(stdout).lines();
-    let mut stderr_reader = BufReader::new(stderr).lines();
+    let mut stdout_reader = LossyLines::new(BufReader::new(stdout));
+    let mut stderr_reader = LossyLines::new(BufReader::new(stderr));
+
+    let mut complete = MarkerScanner::new(COMPLETE_MARKER);
+    let mut stories_passed = Vec::new();
+    let mut usage = None;
+    // Lines with bytes that were not valid UTF-8, on either stream
+    let mut lossy_lines = 0;
+    // Both streams count towards the limit on runaway output
+    let mut output_limit = OutputLimit::new(max_output);
+
+    // Batch stdout so chatty agents are not slowed down by per-line writes
+    let mut output = OutputBuffer::stdout(flush_interval);
+    let mut flush_tick = tokio::time::interval(flush_interval.max(Duration::from_millis(10)));
+
+    // With --show-progress, follow what the agent appends to progress.txt
+    let mut progress_tail = show_progress.then(|| FileTail::new(ralph_dir.join("progress.txt")));
+    let mut progress_tick = tokio::time::interval(PROGRESS_POLL_INTERVAL);
 
-    let mut found_complete = false;
+    // Read both streams to the end, so stderr written just before exit is kept
+    let mut stdout_done = false;
+    let mut stderr_done = false;
 
     // Stream output with color highlighting
-    loop {
+    while !(stdout_done && stderr_done) {
         if !running.load(Ordering::SeqCst) {
-            // User interrupted, kill the child process
+            // User interrupted: show what the agent printed, then kill it
+            output.flush()?;
             let _ = child.kill().await;
             break;
         }
+        if output_limit.is_exceeded() {
+            output.flush()?;
+            let _ = child.kill().await;
+            eprintln!(
+                "{}",
+                format!(
+                    "Output limit exceeded: {} printed {} bytes (limit {}); stopping this iteration",
+                    tool_cmd,
+                    output_limit.bytes(),
+                    max_output
+                )
+                .red()
+            );
+            break;
+        }
 
         tokio::select! {
-            result = stdout_reader.next_line() => {
+            result = stdout_reader.next_line(), if !stdout_done => {
                 match result {
-                    Ok(Some(line)) => {
-                        // Check for completion signal
-                        if line.contains("<promise>COMPLETE</promise>") {
-                            found_complete = true;
+                    Ok(Some(DecodedLine { text: line, lossy })) => {
+                        lossy_lines += usize::from(lossy);
+                        output_limit.add_line(&line);
+                        // Check for completion signal, even split over lines
+                        complete.push(&line);
+                        if track_usage {
+                            usage = Usage::from_stream_json(&line).or(usage);
+                        }
+                        // Check for per-story completion signals
+                        let signal = parse_story_passed(&line);
+                        if let (Some(id), true) = (signal, store.is_readonly()) {
+                            let message =
+                                format!("{} passed (read-only, prd.json not updated)", id).yellow();
+                            output.push_line(&message.to_string())?;
+                        }
+                        let applied = match signal {
+                            Some(_) => store
+                                .write("prd.json", || apply_story_passed_signal(&line, prd_path, prd_key))
+                                .map(Option::flatten),
+                            None => Ok(None),
+                        };
+                        match applied {
+                            Ok(Some(id)) => {
+                                let message = format!("✓ Marked {} as passing", id).green();
+                                output.push_line(&message.to_string())?;
+                                stories_passed.push(id);
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                output.flush()?;
+                                eprintln!(
+                                    "{}",
+                                    format!("Warning: failed to update PRD: {}", e).yellow()
+                                );
+                            }
                         }
                         // Print with color highlighting
-                        println!("{}", colorize_output(&line));
+                        let (shown, streamed) =
+                            output_line_views(&line, redactor, redact_terminal);
+                        output.push_line(&colorize_output(&shown))?;
+                        emit(
+                            events,
+                            RunEvent::Output {
+                                stream: "stdout",
+                                line: streamed.into_owned(),
+                            },
+                        );
                     }
-                    Ok(None) => break,
-                    Err(_) => break,
+                    Ok(None) | Err(_) => stdout_done = true,
                 }
             }
-            result = stderr_reader.next_line() => {
+            result = stderr_reader.next_line(), if !stderr_done => {
                 match result {
-                    Ok(Some(line)) => {
-                        // Print stderr in red
-                        eprintln!("{}", line.red());
+                    Ok(Some(DecodedLine { text: line, lossy })) => {
+                        lossy_lines += usize::from(lossy);
+                        output_limit.add_line(&line);
+                        // Keep stdout and stderr in order, then print stderr in red,
+                        // or dimmed for agents that log progress there
+                        output.flush()?;
+                        let (shown, streamed) =
+                            output_line_views(&line, redactor, redact_terminal);
+                        if invocation.noisy_stderr {
+                            eprintln!("{}", shown.dimmed());
+                        } else {
+                            eprintln!("{}", shown.red());
+                        }
+                        emit(
+                            events,
+                            RunEvent::Output {
+                                stream: "stderr",
+                                line: streamed.into_owned(),
+                            },
+                        );
                     }
-                    Ok(None) => break,
-                    Err(_) => break,
+                    Ok(None) | Err(_) => stderr_done = true,
+                }
+            }
+            _ = flush_tick.tick() => {
+                output.flush_if_due()?;
+            }
+            _ = progress_tick.tick(), if progress_tail.is_some() => {
+                if let Some(tail) = progress_tail.as_mut() {
+                    show_progress_lines(tail, &mut output, redactor, redact_terminal)?;
                 }
             }
         }
     }
+    // Pick up what the agent wrote to progress.txt just before exiting
+    if let Some(tail) = progress_tail.as_mut() {
+        show_progress_lines(tail, &mut output, redactor, redact_terminal)?;
+    }
+    output.flush()?;
+    if lossy_lines > 0 {
+        eprintln!(
+            "{}",
+            format!(
+                "Warning: {} printed {} {} that were not valid UTF-8; invalid bytes are shown as \u{FFFD}",
+                tool_cmd,
+                lossy_lines,
+                if lossy_lines == 1 { "line" } else { "lines" }
+            )
+            .yellow()
+        );
+    }
 
     // Wait for the process to complete
     let status: std::process::ExitStatus = child.wait().await.map_err(RalphError::Io)?;
+    if let PromptDelivery::File(_) = invocation.prompt {
+        let _ = fs::remove_file(prompt_file_path());
+    }
 
-    if !status.success() && running.load(Ordering::SeqCst) {
+    let output_limit_exceeded = output_limit.is_exceeded();
+    if !status.success() && running.load(Ordering::SeqCst) && !output_limit_exceeded {
         eprintln!(
             "{}",
             format!(
@@ -442,7 +2348,412 @@ async fn run_agent_iteration(
         );
     }
 
-    Ok(found_complete)
+    Ok(IterationOutcome {
+        completed: complete.found(),
+        exit_code: status.code(),
+        bytes_out: output_limit.bytes() as usize,
+        duration: started.elapsed(),
+        stories_passed,
+        output_limit_exceeded,
+        usage,
+    })
+}
+
+/// Print the lines added to progress.txt since the last poll, tagged `[progress]`
+fn show_progress_lines(
+    tail: &mut FileTail,
+    output: &mut OutputBuffer<std::io::Stdout>,
+    redactor: &OutputRedactor,
+    redact_terminal: bool,
+) -> std::io::Result<()> {
+    // The file may be mid-rewrite; the lines turn up on a later poll
+    let lines = tail.poll().unwrap_or_default();
+    for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+        let (shown, _) = output_line_views(line, redactor, redact_terminal);
+        output.push_line(&format!("{} {}", PROGRESS_TAG.magenta(), shown))?;
+    }
+    Ok(())
+}
+
+/// Build the agent command from its invocation
+///
+/// `program` is the executable that is spawned. Agents that take the prompt
+/// as an argument or a file get it here; the rest read it from stdin.
+pub fn build_agent_command(
+    invocation: &AgentInvocation,
+    program: &OsStr,
+    prompt: &str,
+) -> std::io::Result<TokioCommand> {
+    let mut cmd = TokioCommand::new(program);
+    cmd.args(invocation.leading_args());
+
+    match invocation.prompt {
+        PromptDelivery::Stdin => {
+            cmd.stdin(std::process::Stdio::piped());
+        }
+        PromptDelivery::Flag(flag) => {
+            cmd.arg(flag).arg(prompt);
+            cmd.stdin(std::process::Stdio::null());
+        }
+        PromptDelivery::File(flag) => {
+            let path = prompt_file_path();
+            fs::write(&path, prompt)?;
+            cmd.arg(flag).arg(path);
+            cmd.stdin(std::process::Stdio::null());
+        }
+    }
+    cmd.stdout(std::process::Stdio::piped());
+    cmd.stderr(std::process::Stdio::piped());
+
+    Ok(cmd)
+}
+
+/// The stories a run would consider, as shown by `ralph run --list-stories`
+#[derive(Debug, Clone)]
+pub struct WorkQueue<'a> {
+    pub project: &'a str,
+    /// Pending stories with their dependencies met, in the order they are worked on
+    pub actionable: Vec<&'a UserStory>,
+    /// Pending stories with the dependencies they are waiting on, in the same order
+    pub blocked: Vec<(&'a UserStory, Vec<&'a str>)>,
+    /// Passed stories, in PRD order
+    pub completed: Vec<&'a UserStory>,
+}
+
+impl<'a> WorkQueue<'a> {
+    /// Sort the stories in scope into buckets
+    ///
+    /// `selector` narrows the scope the same way it narrows a run. Stories
+    /// waiting on an excluded story stay blocked.
+    pub fn from_prd(prd: &'a Prd, order: StoryOrder, selector: &StorySelector) -> Self {
+        let mut actionable = Vec::new();
+        let mut blocked = Vec::new();
+        for story in selector.pending(prd, order) {
+            let waiting_on = prd.unmet_dependencies(story);
+            if waiting_on.is_empty() {
+                actionable.push(story);
+            } else {
+                blocked.push((story, waiting_on));
+            }
+        }
+        let completed = prd
+            .user_stories
+            .iter()
+            .filter(|s| s.passes && selector.matches(s))
+            .collect();
+
+        Self {
+            project: &prd.project,
+            actionable,
+            blocked,
+            completed,
+        }
+    }
+
+    /// Numbered queue followed by the blocked and completed stories
+    pub fn render(&self) -> String {
+        let mut out = format!("{}\n", format!("Work queue for {}", self.project).bold());
+
+        out.push_str(&format!("\nActionable ({}):\n", self.actionable.len()));
+        if self.actionable.is_empty() {
+            out.push_str(&format!("  {}\n", "nothing can start yet".dimmed()));
+        }
+        for (index, story) in self.actionable.iter().enumerate() {
+            out.push_str(&format!("  {}. {}\n", index + 1, story.display().cyan()));
+        }
+
+        if !self.blocked.is_empty() {
+            out.push_str(&format!("\nBlocked ({}):\n", self.blocked.len()));
+            for (story, waiting_on) in &self.blocked {
+                let waiting = format!("(waiting on {})", waiting_on.join(", "));
+                out.push_str(&format!("  - {} {}\n", story.display(), waiting.yellow()));
+            }
+        }
+
+        if !self.completed.is_empty() {
+            out.push_str(&format!("\nCompleted ({}):\n", self.completed.len()));
+            for story in &self.completed {
+                out.push_str(&format!("  {} {}\n", "✓".green(), story.display().dimmed()));
+            }
+        }
+        out
+    }
+}
+
+/// Check the `--exclude-story` ids against the PRD and the `--story` or
+/// `--until` target
+pub fn check_excluded_stories(
+    prd: &Prd,
+    excluded: &[String],
+    target_story: Option<&str>,
+) -> RalphResult<()> {
+    for id in excluded {
+        if prd.find_story(id).is_none() {
+            return Err(RalphError::Other(format!(
+                "{} (--exclude-story)",
+                prd.unknown_story_message(id)
+            )));
+        }
+        if target_story == Some(id.as_str()) {
+            return Err(RalphError::Other(format!(
+                "Story {} is excluded but is also the target of this run",
+                id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Whether every story outside `excluded` passes
+fn rest_passed(prd: &Prd, excluded: &[String]) -> bool {
+    prd.user_stories
+        .iter()
+        .all(|s| s.passes || excluded.contains(&s.id))
+}
+
+/// One-line run header for `--compact` or `run_banner = false`
+///
+/// e.g. `Ralph: My App [ralph/my-app] via claude — 2/5 done`
+pub fn compact_header(
+    project: &str,
+    branch: &str,
+    tool: &str,
+    completed: usize,
+    total: usize,
+) -> String {
+    format!("Ralph: {} [{}] via {} — {}/{} done", project, branch, tool, completed, total)
+}
+
+/// Size of a prompt in characters and estimated tokens
+pub fn prompt_size_summary(prompt: &str) -> String {
+    format!(
+        "Prompt size: {} characters, ~{} tokens (estimated)",
+        prompt.chars().count(),
+        prompt_token_estimate(prompt)
+    )
+}
+
+/// Warn about unknown prompt placeholders, or fail with `strict`
+pub fn check_unknown_placeholders(unknown: &[String], strict: bool) -> RalphResult<()> {
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    let list = unknown
+        .iter()
+        .map(|name| format!("{{{{{}}}}}", name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if strict {
+        return Err(RalphError::Other(format!(
+            "The prompt has unknown placeholders: {} (--strict-prompt)",
+            list
+        )));
+    }
+    eprintln!(
+        "{}",
+        format!("Warning: unknown prompt placeholders left as-is: {}", list).yellow()
+    );
+    Ok(())
+}
+
+/// Temporary file holding the prompt for agents that read it from a file
+pub fn prompt_file_path() -> PathBuf {
+    std::env::temp_dir().join(format!("ralph-prompt-{}.md", std::process::id()))
+}
+
+/// Refuse to start while pending stories have no acceptance criteria, if `required`
+///
+/// Without criteria the agent has nothing to verify its work against, and
+/// tends to report vague stories as done.
+pub fn check_required_criteria(prd: &Prd, required: bool) -> RalphResult<()> {
+    let missing = prd.pending_without_criteria();
+    if !required || missing.is_empty() {
+        return Ok(());
+    }
+    let ids: Vec<&str> = missing.iter().map(|s| s.id.as_str()).collect();
+    Err(RalphError::Other(format!(
+        "require_criteria is set and {} pending {} no acceptance criteria: {}. \
+         Add some with 'ralph story edit <ID>', \
+         or run with --allow-empty-criteria",
+        missing.len(),
+        if missing.len() == 1 { "story has" } else { "stories have" },
+        ids.join(", ")
+    )))
+}
+
+/// Turn the `--story-order` and `--seed` flags into a story order
+///
+/// A random order without a seed gets one from the clock.
+pub fn resolve_story_order(choice: StoryOrderChoice, seed: Option<u64>) -> StoryOrder {
+    match choice {
+        StoryOrderChoice::Priority => StoryOrder::Priority,
+        StoryOrderChoice::File => StoryOrder::File,
+        StoryOrderChoice::Random => StoryOrder::Random(seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        })),
+    }
+}
+
+/// Assemble the prompt sent to the agent
+///
+/// From top to bottom: the prefix, the embedded prompt rendered with values
+/// from the PRD, the story order, target story, target epic or story filter
+/// section, the excluded stories, and the suffix. Excluded stories are left
+/// out of the rendered values and story lists. Returns the prompt and the
+/// unknown placeholders left in it.
+pub fn assemble_prompt(
+    prd: &Prd,
+    selector: &StorySelector,
+    order: StoryOrder,
+    prefix: Option<&str>,
+    suffix: Option<&str>,
+) -> (String, Vec<String>) {
+    let excluded = &selector.excluded;
+    let mut scoped = prd.clone();
+    scoped.user_stories.retain(|s| !excluded.contains(&s.id));
+    let prd = &scoped;
+    let (rendered, unknown) = render_prompt(get_agent_prompt(), prd, order);
+
+    let mut prompt = String::new();
+    if let Some(prefix) = prefix {
+        prompt.push_str(prefix.trim_end());
+        prompt.push_str("\n\n");
+    }
+    prompt.push_str(&rendered);
+    let in_scope = || -> Vec<&str> {
+        selector.pending(prd, order).iter().map(|s| s.id.as_str()).collect()
+    };
+    match (selector.story.as_deref(), selector.epic.as_deref()) {
+        (Some(story_id), _) => prompt.push_str(&target_story_instructions(story_id)),
+        (None, Some(epic)) => prompt.push_str(&target_epic_instructions(epic, &in_scope())),
+        (None, None) if selector.has_attribute_filters() => {
+            prompt.push_str(&story_filter_instructions(&in_scope()));
+        }
+        // The embedded prompt already asks for the highest priority story
+        (None, None) if order != StoryOrder::Priority => {
+            let ids: Vec<&str> = prd.pending_ordered(order).iter().map(|s| s.id.as_str()).collect();
+            prompt.push_str(&story_order_instructions(&ids));
+        }
+        (None, None) => {}
+    }
+    if !excluded.is_empty() {
+        prompt.push_str(&excluded_stories_instructions(excluded));
+    }
+    if let Some(suffix) = suffix {
+        prompt.push_str(if prompt.ends_with('\n') { "\n" } else { "\n\n" });
+        prompt.push_str(suffix.trim_end());
+        prompt.push('\n');
+    }
+
+    (prompt, unknown)
+}
+
+/// Prompt section replacing priority order with an explicit story order
+pub fn story_order_instructions(story_ids: &[&str]) -> String {
+    format!(
+        "\n\n## Story Order\n\n\
+         Ignore story priorities for this run. Pick the first story in this list whose \
+         dependencies have passed: {}.\n",
+        story_ids.join(", ")
+    )
+}
+
+/// Prompt section restricting the agent to a single story
+pub fn target_story_instructions(story_id: &str) -> String {
+    format!(
+        "\n\n## Target Story\n\n\
+         This run is restricted to story {id}. Work on {id} only, even if another \
+         story has a higher priority, and do not start any other story. When {id} \
+         passes, output <promise>STORY_PASSED:{id}</promise>.\n",
+        id = story_id
+    )
+}
+
+/// Prompt section restricting the agent to the pending stories of one epic
+pub fn target_epic_instructions(epic: &str, story_ids: &[&str]) -> String {
+    format!(
+        "\n\n## Target Epic\n\n\
+         This run is restricted to the \"{}\" epic. Only work on its pending stories, \
+         picking the first one in this list whose dependencies have passed: {}. Do not \
+         start stories outside the epic.\n",
+        epic,
+        story_ids.join(", ")
+    )
+}
+
+/// Prompt section restricting the agent to the stories matching `--tag` or
+/// `--max-priority`
+pub fn story_filter_instructions(story_ids: &[&str]) -> String {
+    format!(
+        "\n\n## Story Filter\n\n\
+         This run is restricted to these pending stories: {}. Pick the first one in \
+         this list whose dependencies have passed, and do not start any other story.\n",
+        story_ids.join(", ")
+    )
+}
+
+/// Prompt section telling the agent to leave some stories alone
+pub fn excluded_stories_instructions(story_ids: &[String]) -> String {
+    format!(
+        "\n\n## Excluded Stories\n\n\
+         Do not work on these stories in this run, even if they are pending and \
+         have the highest priority: {}. Leave their entries in prd.json unchanged.\n",
+        story_ids.join(", ")
+    )
+}
+
+/// An agent output line as shown in the terminal and as streamed
+///
+/// The streamed copy is always redacted; the terminal copy only when
+/// `redact_terminal` is set.
+pub fn output_line_views<'a>(
+    line: &'a str,
+    redactor: &OutputRedactor,
+    redact_terminal: bool,
+) -> (Cow<'a, str>, Cow<'a, str>) {
+    let streamed = redactor.redact(line);
+    let shown = if redact_terminal {
+        streamed.clone()
+    } else {
+        Cow::Borrowed(line)
+    };
+    (shown, streamed)
+}
+
+/// Extract the story id from a `<promise>STORY_PASSED:<id></promise>` line
+pub fn parse_story_passed(line: &str) -> Option<&str> {
+    const START: &str = "<promise>STORY_PASSED:";
+    const END: &str = "</promise>";
+
+    let rest = &line[line.find(START)? + START.len()..];
+    let id = rest[..rest.find(END)?].trim();
+    (!id.is_empty()).then_some(id)
+}
+
+/// Mark a story as passing in the PRD file when the line carries a story signal
+///
+/// The PRD is re-read from disk first so edits made by the agent during the
+/// iteration are preserved. Returns the id of the story that was marked, or
+/// `None` when it already passed; an id that is not in the PRD is an error.
+pub fn apply_story_passed_signal(
+    line: &str,
+    prd_path: &Path,
+    prd_key: &str,
+) -> RalphResult<Option<String>> {
+    let Some(id) = parse_story_passed(line) else {
+        return Ok(None);
+    };
+
+    let mut prd = Prd::from_file_at(prd_path, prd_key)?;
+    match prd.mark_story_passed_at(id, prd_path, prd_key) {
+        Ok(marked) => Ok(marked.then(|| id.to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+            Err(RalphError::Other(e.to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
 }
 
 /// Apply color highlighting to output lines
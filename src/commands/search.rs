@@ -0,0 +1,111 @@
+use console::style;
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::cli::OutputFormat;
+use crate::error::{RalphError, RalphResult};
+use crate::prd::Prd;
+use crate::report::{print_report, Report};
+use crate::search::{search_archives, search_dir, Query, SearchMatch};
+
+/// Matches for `ralph search`, active run first, then archives newest first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchReport {
+    pub query: String,
+    pub total: usize,
+    pub matches: Vec<SearchMatch>,
+}
+
+impl SearchReport {
+    pub fn new(query: &str, matches: Vec<SearchMatch>) -> Self {
+        Self {
+            query: query.to_string(),
+            total: matches.len(),
+            matches,
+        }
+    }
+}
+
+/// Run the `search` command over the PRD, progress log and optionally the archives
+pub fn run_search(
+    query: &str,
+    prd_path: &str,
+    prd_key: &str,
+    archives: bool,
+    format: OutputFormat,
+) -> RalphResult<()> {
+    let matcher = Query::new(query)
+        .ok_or_else(|| RalphError::Other("The search query is empty".to_string()))?;
+    let prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+    let ralph_dir = Path::new(prd_path)
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    let mut matches = search_dir(ralph_dir, Some(&prd), prd_key, &matcher)?;
+    if archives {
+        matches.extend(search_archives(&ralph_dir.join("archive"), prd_key, &matcher)?);
+    }
+    print_report(&SearchReport::new(query.trim(), matches), format)
+}
+
+/// Matches grouped under a heading per file, with the match highlighted
+impl Report for SearchReport {
+    fn to_table(&self) -> String {
+        let mut out = String::new();
+        if self.matches.is_empty() {
+            let _ = writeln!(out, "No matches for \"{}\"", self.query);
+            return out;
+        }
+
+        let mut current: Option<(Option<&str>, &str)> = None;
+        for m in &self.matches {
+            let group = (m.archive.as_deref(), m.file.as_str());
+            if current != Some(group) {
+                if current.is_some() {
+                    let _ = writeln!(out);
+                }
+                let heading = match group.0 {
+                    Some(archive) => format!("archive/{}/{}", archive, group.1),
+                    None => group.1.to_string(),
+                };
+                let _ = writeln!(out, "{}", style(heading).bold().cyan());
+                current = Some(group);
+            }
+            let _ = writeln!(out, "  {}: {}", location(m), highlight(m));
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "{} match{} for \"{}\"",
+            self.total,
+            if self.total == 1 { "" } else { "es" },
+            self.query
+        );
+        out
+    }
+}
+
+/// Where in the file a match is, e.g. `US-002 notes` or `line 14 (US-002)`
+fn location(m: &SearchMatch) -> String {
+    let label = match (&m.line, &m.story, &m.field) {
+        (Some(line), Some(story), _) => format!("line {} ({})", line, story),
+        (Some(line), None, _) => format!("line {}", line),
+        (None, Some(story), Some(field)) => format!("{} {}", story, field),
+        (None, story, _) => story.clone().unwrap_or_default(),
+    };
+    style(label).dim().to_string()
+}
+
+fn highlight(m: &SearchMatch) -> String {
+    format!(
+        "{}{}{}",
+        &m.excerpt[..m.start],
+        style(&m.excerpt[m.start..m.end]).yellow().bold(),
+        &m.excerpt[m.end..]
+    )
+}
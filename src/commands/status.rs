@@ -0,0 +1,213 @@
+use console::style;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::cli::OutputFormat;
+use crate::error::{RalphError, RalphResult};
+use crate::humanize::{elapsed_since_modified, format_relative_time};
+use crate::prd::Prd;
+use crate::report::{print_report, Report};
+use crate::selector::{no_match_message, StorySelector};
+use crate::status::{AcceptanceReport, StatusReport, StoryEntry};
+
+/// Run the status command to summarize the PRD's stories
+///
+/// With filters, the sections only list the stories `selector` matches.
+pub fn run_status(
+    prd_path: &str,
+    prd_key: &str,
+    format: OutputFormat,
+    selector: &StorySelector,
+) -> RalphResult<()> {
+    let prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+    let mut report = StatusReport::from_prd(&prd, selector);
+    report.prd_age = elapsed_since_modified(Path::new(prd_path));
+
+    print_report(&report, format)
+}
+
+/// Run `status --acceptance` or `status <id>` to show acceptance checklists
+pub fn run_acceptance(
+    prd_path: &str,
+    prd_key: &str,
+    format: OutputFormat,
+    selector: &StorySelector,
+    story_id: Option<&str>,
+) -> RalphResult<()> {
+    let prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+    let report = AcceptanceReport::from_prd(&prd, selector, story_id).map_err(RalphError::Other)?;
+    print_report(&report, format)
+}
+
+impl Report for AcceptanceReport {
+    fn to_table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{}", style(&self.project).bold().cyan());
+        if self.stories.is_empty() {
+            let _ = writeln!(out, "No pending stories");
+        }
+        for story in &self.stories {
+            let done = story.criteria.iter().filter(|c| c.done).count();
+            let _ = writeln!(out);
+            let _ = writeln!(
+                out,
+                "{} {} - {} ({}/{} criteria done)",
+                if story.passes { style("✓").green() } else { style("·").dim() },
+                style(&story.id).bold(),
+                story.title,
+                done,
+                story.criteria.len()
+            );
+            if story.criteria.is_empty() {
+                let _ = writeln!(out, "  {}", style("No acceptance criteria").yellow());
+            }
+            for item in &story.criteria {
+                let mark = if item.done { style("[x]").green() } else { style("[ ]").dim() };
+                let _ = writeln!(out, "  {} {}. {}", mark, item.number, item.text);
+            }
+            if !story.notes.trim().is_empty() {
+                let _ = writeln!(out, "  {}", style("Notes:").dim());
+                for line in story.notes.trim().lines() {
+                    let _ = writeln!(out, "    {}", line);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// The table leaves out sections with no stories
+impl Report for StatusReport {
+    fn to_table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{}", style(&self.project).bold().cyan());
+        let _ = writeln!(
+            out,
+            "Branch: {} ({}/{} stories complete)",
+            self.branch,
+            self.counts.completed,
+            self.total
+        );
+        if let Some(weighted) = self.weighted_percentage {
+            let _ = writeln!(out, "Weighted by estimate: {:.0}% complete", weighted);
+        }
+        // A lone story without an estimate says nothing the up next list does not
+        if let Some(path) = self
+            .critical_path
+            .as_ref()
+            .filter(|p| p.stories.len() > 1 || self.weighted_percentage.is_some())
+        {
+            let _ = writeln!(
+                out,
+                "Critical path: {} (~{} estimated)",
+                path.stories.join(" → "),
+                format_estimate(path.estimate)
+            );
+        }
+        if let Some(filter) = &self.filter {
+            let _ = writeln!(
+                out,
+                "Filtered by {}: {} of {} stories match ({}/{} complete)",
+                style(&filter.filters).cyan(),
+                filter.matching,
+                self.total,
+                filter.completed,
+                filter.matching
+            );
+            if filter.pending == 0 && self.counts.pending > 0 {
+                let _ = writeln!(out, "{}", style(no_match_message(self.counts.pending)).yellow());
+            }
+        }
+        if let Some(elapsed) = self.prd_age {
+            let _ = writeln!(
+                out,
+                "{}",
+                style(format!("PRD updated {}", format_relative_time(elapsed))).dim()
+            );
+        }
+
+        if !self.completed.is_empty() {
+            push_heading(&mut out, "Completed this branch");
+            for story in &self.completed {
+                let _ = writeln!(out, "  {} {}", style("✓").green(), entry_label(story));
+            }
+        }
+
+        if let Some(story) = &self.in_progress {
+            push_heading(&mut out, "In progress");
+            let _ = writeln!(out, "  {} {}", style("→").cyan(), entry_label(story));
+        }
+
+        if !self.blocked.is_empty() {
+            push_heading(&mut out, "Blocked");
+            for blocked in &self.blocked {
+                let _ = writeln!(
+                    out,
+                    "  {} {} (waiting on {})",
+                    style("✗").yellow(),
+                    entry_label(&blocked.story),
+                    blocked.waiting_on.join(", ")
+                );
+            }
+        }
+
+        if !self.up_next.is_empty() {
+            push_heading(&mut out, "Up next");
+            for story in &self.up_next {
+                let _ = writeln!(out, "  - {}", entry_label(story));
+            }
+        }
+
+        if !self.without_criteria.is_empty() {
+            push_heading(&mut out, "No acceptance criteria");
+            for story in &self.without_criteria {
+                let _ = writeln!(out, "  {} {}", style("!").yellow(), entry_label(story));
+            }
+        }
+
+        if !self.epics.is_empty() {
+            push_heading(&mut out, "By epic");
+            for epic in &self.epics {
+                let _ = writeln!(
+                    out,
+                    "  {} ({}/{} complete)",
+                    style(&epic.name).bold(),
+                    epic.completed,
+                    epic.total
+                );
+                for entry in &epic.stories {
+                    let marker = if entry.passes {
+                        style("✓").green()
+                    } else {
+                        style("·").dim()
+                    };
+                    let _ = writeln!(out, "    {} {}", marker, entry_label(&entry.story));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// An estimate without a trailing `.0`, e.g. `3` or `2.5`
+fn format_estimate(estimate: f64) -> String {
+    let rounded = (estimate * 10.0).round() / 10.0;
+    if rounded.fract() == 0.0 {
+        format!("{:.0}", rounded)
+    } else {
+        format!("{:.1}", rounded)
+    }
+}
+
+fn push_heading(out: &mut String, title: &str) {
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{}", style(title).bold());
+}
+
+fn entry_label(story: &StoryEntry) -> String {
+    format!("{} - {}", story.id, story.title)
+}
@@ -0,0 +1,414 @@
+use chrono::Local;
+use console::style;
+use serde::Serialize;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{OutputFormat, StoryField};
+use crate::config::Config;
+use crate::error::{RalphError, RalphResult};
+use crate::humanize::{render_table, Align};
+use crate::interactive::{assume_yes, confirm, input, is_interactive, select};
+use crate::prd::{Prd, UserStory};
+use crate::report::{print_report, Report};
+
+/// A story as shown by `ralph story list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoryRow {
+    pub id: String,
+    pub title: String,
+    pub priority: u32,
+    pub passes: bool,
+    pub depends_on: Vec<String>,
+}
+
+impl From<&UserStory> for StoryRow {
+    fn from(story: &UserStory) -> Self {
+        Self {
+            id: story.id.clone(),
+            title: story.title.clone(),
+            priority: story.priority,
+            passes: story.passes,
+            depends_on: story.depends_on.clone(),
+        }
+    }
+}
+
+/// Every story in the PRD, in PRD order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoryList {
+    pub project: String,
+    pub stories: Vec<StoryRow>,
+}
+
+impl StoryList {
+    pub fn from_prd(prd: &Prd) -> Self {
+        Self {
+            project: prd.project.clone(),
+            stories: prd.user_stories.iter().map(StoryRow::from).collect(),
+        }
+    }
+}
+
+impl Report for StoryList {
+    fn to_table(&self) -> String {
+        if self.stories.is_empty() {
+            return format!("{} has no stories", self.project);
+        }
+
+        let rows: Vec<Vec<String>> = self
+            .stories
+            .iter()
+            .map(|story| {
+                let mark = if story.passes {
+                    style("✓").green()
+                } else {
+                    style("·").dim()
+                };
+                let after = if story.depends_on.is_empty() {
+                    String::new()
+                } else {
+                    style(format!("after {}", story.depends_on.join(", "))).dim().to_string()
+                };
+                vec![
+                    mark.to_string(),
+                    story.id.clone(),
+                    format!("P{}", story.priority),
+                    story.title.clone(),
+                    after,
+                ]
+            })
+            .collect();
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{}", style(&self.project).bold().cyan());
+        let aligns = [Align::Left, Align::Left, Align::Right, Align::Left, Align::Left];
+        for line in render_table(&rows, &aligns) {
+            let _ = writeln!(out, "  {}", line);
+        }
+        out
+    }
+}
+
+/// Run the `story list` command
+pub fn run_story_list(prd_path: &str, prd_key: &str, format: OutputFormat) -> RalphResult<()> {
+    let prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+    print_report(&StoryList::from_prd(&prd), format)
+}
+
+/// Run the `story rm` command to remove a story from the PRD
+pub fn run_story_rm(story_id: &str, prd_path: &str, prd_key: &str, force: bool) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+
+    let story = prd
+        .find_story(story_id)
+        .ok_or_else(|| RalphError::Other(prd.unknown_story_message(story_id)))?;
+
+    if story.passes && !force {
+        return Err(RalphError::Other(format!(
+            "Story {} already passes. Use --force to remove it anyway.",
+            story_id
+        )));
+    }
+
+    println!("{}", style("Removing story:").bold());
+    println!("  {}", story.display());
+    println!();
+
+    let dependents = prd.dependents_of(story_id);
+    if !dependents.is_empty() {
+        println!(
+            "{}",
+            style("Stories depending on it (the reference will be removed):").yellow()
+        );
+        for dependent in &dependents {
+            println!("  - {}", dependent.display());
+        }
+        println!();
+    }
+
+    let ralph_dir = Path::new(prd_path)
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."));
+    let mentions = progress_mentions(&ralph_dir.join("progress.txt"), story_id);
+    if !mentions.is_empty() {
+        println!("{}", style("progress.txt entries mentioning it:").yellow());
+        for line in &mentions {
+            println!("  {}", line);
+        }
+        println!();
+    }
+
+    if !confirm(&format!("Remove story {}?", story_id), false)? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    prd.remove_story(story_id);
+    prd.save_to_file_at(prd_path, prd_key)?;
+
+    println!("{} Removed {}", style("✓").green(), story_id);
+    Ok(())
+}
+
+/// Run the `story note` command to add a timestamped entry to a story's notes
+pub fn run_story_note(
+    story_id: &str,
+    text: &str,
+    prd_path: &str,
+    prd_key: &str,
+    replace: bool,
+) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+
+    let Some(story) = prd.find_story_mut(story_id) else {
+        return Err(RalphError::Other(prd.unknown_story_message(story_id)));
+    };
+
+    let entry = format!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M"), text.trim());
+    if replace {
+        story.notes = entry;
+    } else {
+        story.append_note(&entry);
+    }
+    let notes_len = story.notes.chars().count();
+
+    prd.save_to_file_at(prd_path, prd_key)?;
+    println!(
+        "{} {} notes for {}",
+        style("✓").green(),
+        if replace { "Replaced" } else { "Updated" },
+        story_id
+    );
+
+    let ralph_dir = Path::new(prd_path)
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let config = Config::load_layered_or_default(ralph_dir);
+    let warn_length = config.notes_warn_length.unwrap_or(500);
+    if notes_len > warn_length {
+        println!(
+            "{}",
+            style(format!(
+                "Warning: {} notes are {} characters long (limit {}). \
+                 Consider moving the details to a task file in ralph/tasks/.",
+                story_id, notes_len, warn_length
+            ))
+            .yellow()
+        );
+    }
+    Ok(())
+}
+
+/// Run the `story check` command to toggle one acceptance criterion
+///
+/// Checking off the last open criterion of a pending story offers to mark
+/// the story as passing; without a terminal that needs `--yes`.
+pub fn run_story_check(
+    story_id: &str,
+    number: usize,
+    prd_path: &str,
+    prd_key: &str,
+) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+
+    let Some(story) = prd.find_story_mut(story_id) else {
+        return Err(RalphError::Other(prd.unknown_story_message(story_id)));
+    };
+    let done = story.toggle_criterion(number).map_err(RalphError::Other)?;
+    let criterion = &story.acceptance_criteria[number - 1];
+    let done_count = story.done_criteria_count();
+    let total = story.acceptance_criteria.len();
+    println!(
+        "{} {} {}. {} ({}/{} done)",
+        if done { style("✓").green() } else { style("·").dim() },
+        if done { "Checked" } else { "Unchecked" },
+        number,
+        criterion,
+        done_count,
+        total
+    );
+
+    if done && story.all_criteria_done() && !story.passes {
+        if is_interactive() || assume_yes() {
+            let prompt = format!("All acceptance criteria of {} are done. Mark it as passing?", story_id);
+            if confirm(&prompt, false)? {
+                story.passes = true;
+                println!("{} Marked {} as passing", style("✓").green(), story_id);
+            }
+        } else {
+            println!(
+                "All acceptance criteria of {} are done; check the last one with --yes to mark it as passing",
+                story_id
+            );
+        }
+    }
+
+    prd.save_to_file_at(prd_path, prd_key)?;
+    Ok(())
+}
+
+/// Run the `story edit` command
+///
+/// With `edit`, that one field is set from the given text. Otherwise each
+/// field is shown with its current value to keep or replace, which needs a
+/// terminal. The PRD is only saved when the story actually changed.
+pub fn run_story_edit(
+    story_id: &str,
+    edit: Option<(StoryField, String)>,
+    prd_path: &str,
+    prd_key: &str,
+) -> RalphResult<()> {
+    let mut prd = Prd::from_file_at(prd_path, prd_key).map_err(|e| {
+        RalphError::Other(format!("Failed to load PRD from {}: {}", prd_path, e))
+    })?;
+
+    let Some(story) = prd.find_story_mut(story_id) else {
+        return Err(RalphError::Other(prd.unknown_story_message(story_id)));
+    };
+
+    let changed = match edit {
+        Some((field, value)) => set_story_field(story, field, &value).map_err(RalphError::Other)?,
+        None if is_interactive() => edit_story_interactively(story)?,
+        None => {
+            return Err(RalphError::Other(
+                "story edit asks for each field and needs a terminal; \
+                 use --field and --value to edit without one"
+                    .to_string(),
+            ))
+        }
+    };
+
+    if !changed {
+        println!("No changes to {}", story_id);
+        return Ok(());
+    }
+    prd.save_to_file_at(prd_path, prd_key)?;
+    println!("{} Updated {}", style("✓").green(), story_id);
+    Ok(())
+}
+
+/// Parse a story priority, which must be a positive integer
+pub fn parse_priority(value: &str) -> Result<u32, String> {
+    match value.trim().parse::<u32>() {
+        Ok(priority) if priority > 0 => Ok(priority),
+        _ => Err(format!("Priority must be a positive integer, not \"{}\"", value.trim())),
+    }
+}
+
+/// Set one field of a story from text, as `story edit --field` does
+///
+/// Acceptance criteria take one criterion per line; blank lines are dropped.
+/// Returns whether the story changed.
+pub fn set_story_field(story: &mut UserStory, field: StoryField, value: &str) -> Result<bool, String> {
+    let before = story.clone();
+    match field {
+        StoryField::Title => story.title = non_empty(value, "title")?,
+        StoryField::Description => story.description = value.trim().to_string(),
+        StoryField::Priority => story.priority = parse_priority(value)?,
+        StoryField::Notes => story.notes = value.trim().to_string(),
+        StoryField::AcceptanceCriteria => {
+            story.acceptance_criteria = value
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect();
+            story.forget_removed_criteria();
+        }
+    }
+    Ok(*story != before)
+}
+
+/// Trimmed text, or an error naming `what` when nothing is left
+fn non_empty(value: &str, what: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("The {} cannot be empty", what));
+    }
+    Ok(value.to_string())
+}
+
+/// Ask for each field of a story in turn, returning whether anything changed
+fn edit_story_interactively(story: &mut UserStory) -> RalphResult<bool> {
+    let before = story.clone();
+    println!("{} {}", style("Editing").bold(), story.display());
+    println!("Press Enter to keep a value.");
+    println!();
+
+    let title = input("Title", &story.title, |t| non_empty(t, "title").map(|_| ()))?;
+    set_story_field(story, StoryField::Title, &title).map_err(RalphError::Other)?;
+    let description = input("Description", &story.description, |_| Ok(()))?;
+    set_story_field(story, StoryField::Description, &description).map_err(RalphError::Other)?;
+    let priority = input("Priority", &story.priority.to_string(), |p| {
+        parse_priority(p).map(|_| ())
+    })?;
+    set_story_field(story, StoryField::Priority, &priority).map_err(RalphError::Other)?;
+    let notes = input("Notes", &story.notes, |_| Ok(()))?;
+    set_story_field(story, StoryField::Notes, &notes).map_err(RalphError::Other)?;
+    edit_criteria(&mut story.acceptance_criteria)?;
+    story.forget_removed_criteria();
+
+    Ok(*story != before)
+}
+
+/// Add, edit and remove acceptance criteria until the user is done
+fn edit_criteria(criteria: &mut Vec<String>) -> RalphResult<()> {
+    let criterion = |text: &str| non_empty(text, "criterion").map(|_| ());
+    loop {
+        println!();
+        println!("{}", style("Acceptance criteria:").bold());
+        if criteria.is_empty() {
+            println!("  (none)");
+        }
+        for (index, item) in criteria.iter().enumerate() {
+            println!("  {}. {}", index + 1, item);
+        }
+
+        let mut actions = vec!["Done".to_string(), "Add a criterion".to_string()];
+        if !criteria.is_empty() {
+            actions.push("Edit a criterion".to_string());
+            actions.push("Remove a criterion".to_string());
+        }
+        match select("Acceptance criteria", &actions, 0)? {
+            0 => return Ok(()),
+            1 => {
+                let text = input("New criterion", "", criterion)?;
+                criteria.push(text.trim().to_string());
+            }
+            action => {
+                let index = select("Which criterion?", criteria, 0)?;
+                if action == 2 {
+                    let text = input("Criterion", &criteria[index], criterion)?;
+                    criteria[index] = text.trim().to_string();
+                } else {
+                    criteria.remove(index);
+                }
+            }
+        }
+    }
+}
+
+/// Collect progress log lines that mention the given story id
+fn progress_mentions(progress_file: &Path, story_id: &str) -> Vec<String> {
+    fs::read_to_string(progress_file)
+        .map(|content| {
+            content
+                .lines()
+                .filter(|line| line.contains(story_id))
+                .map(|line| line.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
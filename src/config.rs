@@ -1,147 +1,538 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use toml::Spanned;
 
-/// Ralph CLI configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Config {
-    /// Default AI tool to use (amp, claude, codebuddy)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub default_tool: Option<String>,
+/// Environment variable overriding the config file location
+pub const CONFIG_PATH_ENV: &str = "RALPH_CONFIG_PATH";
 
-    /// Default maximum iterations for task execution
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub max_iterations: Option<u32>,
+/// Project config inside the ralph directory, checked in with the repository
+pub const PROJECT_CONFIG_FILE: &str = "config.toml";
+
+/// Set from the global `--config` flag at startup
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use the given config file instead of the default location (global `--config`)
+pub fn set_config_path_override(path: PathBuf) {
+    let _ = CONFIG_PATH_OVERRIDE.set(path);
+}
+
+/// Declare every config key in one place
+///
+/// Each entry generates the `Config` field, its default, the `ConfigKey`
+/// variant with its name and description, and the `get`/`set` arms, so a key
+/// can never be settable without also being displayable.
+macro_rules! config_keys {
+    ($(
+        $(#[doc = $doc:literal])*
+        $variant:ident => $field:ident: $ty:ty = $default:expr, $description:literal;
+    )*) => {
+        /// Ralph CLI configuration
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub struct Config {
+            $(
+                $(#[doc = $doc])*
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub $field: Option<$ty>,
+            )*
+        }
+
+        impl Default for Config {
+            fn default() -> Self {
+                Self {
+                    $($field: $default,)*
+                }
+            }
+        }
+
+        /// Configuration keys that can be get/set
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ConfigKey {
+            $($variant,)*
+        }
+
+        impl ConfigKey {
+            /// Get all available config keys
+            pub fn all() -> &'static [ConfigKey] {
+                &[$(ConfigKey::$variant,)*]
+            }
+
+            /// Get the string name of the key
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(ConfigKey::$variant => stringify!($field),)*
+                }
+            }
 
+            /// Get description of the key
+            pub fn description(&self) -> &'static str {
+                match self {
+                    $(ConfigKey::$variant => $description,)*
+                }
+            }
+
+            /// Parse a config key from string
+            #[allow(clippy::should_implement_trait)]
+            pub fn from_str(s: &str) -> Option<Self> {
+                Self::all().iter().copied().find(|key| key.as_str() == s)
+            }
+        }
+
+        impl Config {
+            /// A config with no keys set, as if loaded from an empty file
+            pub fn empty() -> Self {
+                Self {
+                    $($field: None,)*
+                }
+            }
+
+            /// Fill unset keys with their defaults, giving the values commands use
+            pub fn with_defaults(&self) -> Self {
+                let defaults = Self::default();
+                Self {
+                    $($field: self.$field.clone().or(defaults.$field),)*
+                }
+            }
+
+            /// Lay another config over this one: keys it sets win, the rest are kept
+            pub fn overlay(&self, top: &Self) -> Self {
+                Self {
+                    $($field: top.$field.clone().or_else(|| self.$field.clone()),)*
+                }
+            }
+
+            /// Get a config value by key
+            pub fn get(&self, key: ConfigKey) -> Option<String> {
+                match key {
+                    $(ConfigKey::$variant => self.$field.as_ref().map(ConfigValue::format_value),)*
+                }
+            }
+
+            /// Set a config value by key
+            pub fn set(&mut self, key: ConfigKey, value: &str) -> Result<(), String> {
+                match key {
+                    $(ConfigKey::$variant => {
+                        self.$field = Some(ConfigValue::parse_value(key.as_str(), value)?);
+                    })*
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+config_keys! {
+    /// Default AI tool to use (amp, claude, codebuddy, aider)
+    DefaultTool => default_tool: String = None,
+        "Default AI tool (amp, claude, codebuddy, aider)";
+    /// Default maximum iterations for task execution
+    MaxIterations => max_iterations: u32 = Some(10),
+        "Default maximum iterations for task execution";
     /// Whether to auto archive history
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub auto_archive: Option<bool>,
+    AutoArchive => auto_archive: bool = Some(true),
+        "Auto archive history on branch switch";
+    /// Note length above which `story note` suggests a task file instead
+    NotesWarnLength => notes_warn_length: usize = Some(500),
+        "Warn when a story's notes grow beyond this many characters";
+    /// How often buffered agent output is flushed to the terminal (0 = every line)
+    FlushIntervalMs => flush_interval_ms: u64 = Some(50),
+        "Milliseconds between flushes of agent output (0 flushes every line)";
+    /// How long detected agents are reused before probing again (0 = always probe)
+    AgentCacheTtlHours => agent_cache_ttl_hours: u64 = Some(24),
+        "Hours to reuse cached agent detection results (0 disables the cache)";
+    /// Bytes of output an agent may print in one iteration before it is stopped (0 = no limit)
+    MaxOutputBytes => max_output_bytes: u64 = Some(50 * 1024 * 1024),
+        "Stop an iteration once the agent prints this many bytes (0 disables the limit)";
+    /// Comma-separated paths watched by `run --sandbox-check` (unset = built-in list)
+    SandboxWatchPaths => sandbox_watch_paths: String = None,
+        "Comma-separated paths `run --sandbox-check` watches (default: home dotfiles, config dir)";
+    /// Whitespace-separated regexes masked in agent output written to `--stream-to`
+    RedactPatterns => redact_patterns: String = None,
+        "Regexes masked as *** in streamed agent output (default: common token formats, empty: off)";
+    /// Whether redaction also applies to agent output shown in the terminal
+    RedactTerminal => redact_terminal: bool = Some(false),
+        "Also mask redact_patterns in agent output shown in the terminal";
+    /// What `ralph run` does about secrets found in the PRD or progress log
+    ScanSecrets => scan_secrets: SecretScanMode = Some(SecretScanMode::Off),
+        "Scan the PRD and progress.txt for secrets when a run starts (warn, block, off)";
+    /// Whether a failure to archive the previous run stops `ralph run`
+    ArchiveRequired => archive_required: bool = Some(false),
+        "Stop the run when the previous run cannot be archived (default: warn and continue)";
+    /// Whether `ralph run` appends a summary entry to progress.txt when it ends
+    LogRunSummary => log_run_summary: bool = Some(true),
+        "Append a summary of each run (iterations, stories completed, outcome) to progress.txt";
+    /// Whether `ralph run` refuses to start while pending stories lack acceptance criteria
+    RequireCriteria => require_criteria: bool = Some(false),
+        "Refuse to run while pending stories have no acceptance criteria (--allow-empty-criteria overrides)";
+    /// Comma-separated custom agent commands `ralph detect` checks besides the known agents
+    ExtraTools => extra_tools: String = None,
+        "Comma-separated custom agent commands `ralph detect` also checks";
+    /// Whether `--tool auto` falls back to an installed custom tool
+    AutoIncludeCustom => auto_include_custom: bool = Some(false),
+        "Let --tool auto pick an installed extra_tools command when no known agent is installed";
+    /// Whether `ralph run` keeps going read-only when its state files cannot be written
+    ReadonlyOk => readonly_ok: bool = Some(false),
+        "Keep running read-only when progress.txt or other state cannot be written (e.g. a read-only checkout)";
+    /// What `ralph run` does when the agent changes branchName in the PRD
+    BranchChange => branch_change: BranchChangeMode = Some(BranchChangeMode::Warn),
+        "What to do when the agent changes branchName in prd.json during a run (warn, abort)";
+    /// Whether `ralph run` starts with the full banner rather than a one-line header
+    RunBanner => run_banner: bool = Some(true),
+        "Print the full startup banner for ralph run; false prints a one-line header like --compact";
+}
+
+/// Values of the `scan_secrets` config key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretScanMode {
+    /// Print the findings and continue
+    Warn,
+    /// Refuse to start the run
+    Block,
+    /// Do not scan
+    #[default]
+    Off,
+}
+
+/// Values of the `branch_change` config key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BranchChangeMode {
+    /// Warn, keep the original branch for this run and note it in the summary
+    #[default]
+    Warn,
+    /// End the run
+    Abort,
+}
+
+/// A type that can be stored in a config key
+pub trait ConfigValue: Sized {
+    /// Parse a value given on the command line
+    fn parse_value(key: &str, value: &str) -> Result<Self, String>;
+
+    /// Format the value for display
+    fn format_value(&self) -> String;
+}
+
+impl ConfigValue for String {
+    fn parse_value(_key: &str, value: &str) -> Result<Self, String> {
+        Ok(value.to_string())
+    }
+
+    fn format_value(&self) -> String {
+        self.clone()
+    }
+}
+
+macro_rules! integer_config_value {
+    ($($ty:ty),*) => {
+        $(
+            impl ConfigValue for $ty {
+                fn parse_value(key: &str, value: &str) -> Result<Self, String> {
+                    value
+                        .parse()
+                        .map_err(|_| format!("{} must be a positive integer", key))
+                }
+
+                fn format_value(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            default_tool: None,
-            max_iterations: Some(10),
-            auto_archive: Some(true),
+integer_config_value!(u32, u64, usize);
+
+impl ConfigValue for bool {
+    fn parse_value(key: &str, value: &str) -> Result<Self, String> {
+        value
+            .parse()
+            .map_err(|_| format!("{} must be true or false", key))
+    }
+
+    fn format_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ConfigValue for SecretScanMode {
+    fn parse_value(key: &str, value: &str) -> Result<Self, String> {
+        match value {
+            "warn" => Ok(SecretScanMode::Warn),
+            "block" => Ok(SecretScanMode::Block),
+            "off" => Ok(SecretScanMode::Off),
+            _ => Err(format!("{} must be warn, block or off", key)),
         }
     }
+
+    fn format_value(&self) -> String {
+        match self {
+            SecretScanMode::Warn => "warn",
+            SecretScanMode::Block => "block",
+            SecretScanMode::Off => "off",
+        }
+        .to_string()
+    }
+}
+
+impl ConfigValue for BranchChangeMode {
+    fn parse_value(key: &str, value: &str) -> Result<Self, String> {
+        match value {
+            "warn" => Ok(BranchChangeMode::Warn),
+            "abort" => Ok(BranchChangeMode::Abort),
+            _ => Err(format!("{} must be warn or abort", key)),
+        }
+    }
+
+    fn format_value(&self) -> String {
+        match self {
+            BranchChangeMode::Warn => "warn",
+            BranchChangeMode::Abort => "abort",
+        }
+        .to_string()
+    }
+}
+
+/// A rejected entry in an imported config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    /// 1-based line of the entry
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
 }
 
 impl Config {
+    /// Set every entry of a TOML config file, validating each like `set`
+    ///
+    /// Either every entry is applied or, if any is rejected, none are and all
+    /// problems are returned.
+    pub fn import_toml(&mut self, content: &str) -> Result<(), Vec<ImportError>> {
+        let line_of = |offset: usize| content[..offset].matches('\n').count() + 1;
+        let entries: BTreeMap<Spanned<String>, Spanned<toml::Value>> = toml::from_str(content)
+            .map_err(|e| {
+                vec![ImportError {
+                    line: e.span().map_or(1, |span| line_of(span.start)),
+                    message: e.message().to_string(),
+                }]
+            })?;
+
+        let mut imported = self.clone();
+        let mut errors = Vec::new();
+        for (key, value) in &entries {
+            let line = line_of(key.span().start);
+            let Some(config_key) = ConfigKey::from_str(key.get_ref()) else {
+                errors.push(ImportError {
+                    line,
+                    message: format!("unknown config key `{}`", key.get_ref()),
+                });
+                continue;
+            };
+            let text = match value.get_ref() {
+                toml::Value::String(s) => s.clone(),
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                other => {
+                    errors.push(ImportError {
+                        line,
+                        message: format!(
+                            "{} must be a string, integer or boolean, not {}",
+                            key.get_ref(),
+                            other.type_str()
+                        ),
+                    });
+                    continue;
+                }
+            };
+            if let Err(message) = imported.set(config_key, &text) {
+                errors.push(ImportError { line, message });
+            }
+        }
+
+        if !errors.is_empty() {
+            errors.sort_by_key(|e| e.line);
+            return Err(errors);
+        }
+        *self = imported;
+        Ok(())
+    }
+
     /// Get the path to the config directory
     pub fn config_dir() -> Option<PathBuf> {
         dirs::config_dir().map(|d| d.join("ralph"))
     }
 
-    /// Get the path to the config file
+    /// Get the path to the config file, honoring `--config` and `RALPH_CONFIG_PATH`
     pub fn config_file() -> Option<PathBuf> {
-        Self::config_dir().map(|d| d.join("config.toml"))
+        let override_path = CONFIG_PATH_OVERRIDE
+            .get()
+            .cloned()
+            .or_else(|| std::env::var_os(CONFIG_PATH_ENV).map(PathBuf::from));
+        Self::resolve_config_file(override_path, Self::config_dir())
+    }
+
+    /// Pick the config file from an explicit override or the config directory
+    pub fn resolve_config_file(
+        override_path: Option<PathBuf>,
+        config_dir: Option<PathBuf>,
+    ) -> Option<PathBuf> {
+        override_path
+            .filter(|p| !p.as_os_str().is_empty())
+            .or_else(|| config_dir.map(|d| d.join("config.toml")))
     }
 
     /// Load config from file, or return default if file doesn't exist
     pub fn load() -> io::Result<Self> {
-        match Self::config_file() {
-            Some(path) if path.exists() => {
-                let content = fs::read_to_string(&path)?;
-                let config: Config = toml::from_str(&content)
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                Ok(config)
-            }
-            _ => Ok(Self::default()),
+        Self::load_from(Self::config_file().as_deref())
+    }
+
+    /// Load the config in `config_file` alone, or the defaults when there is none
+    pub fn load_from(config_file: Option<&Path>) -> io::Result<Self> {
+        match config_file {
+            Some(path) => Ok(Self::read_file(path)?.unwrap_or_default()),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Load the global config with the project's `ralph/config.toml` laid over it
+    ///
+    /// Precedence, highest first: command-line flags (applied by the caller),
+    /// the project config in `ralph_dir`, the global config, then the
+    /// built-in defaults. Keys the project config leaves out keep their
+    /// global values.
+    pub fn load_layered(ralph_dir: &Path) -> io::Result<Self> {
+        Self::load_layered_from(Self::config_file().as_deref(), ralph_dir)
+    }
+
+    /// Like [`Config::load_layered`], with the global config read from `config_file`
+    pub fn load_layered_from(config_file: Option<&Path>, ralph_dir: &Path) -> io::Result<Self> {
+        let global = Self::load_from(config_file)?;
+        match Self::read_file(&ralph_dir.join(PROJECT_CONFIG_FILE))? {
+            Some(project) => Ok(global.overlay(&project)),
+            None => Ok(global),
+        }
+    }
+
+    /// Parse a config file; `None` when it does not exist
+    fn read_file(path: &Path) -> io::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
         }
+        let content = fs::read_to_string(path).map_err(|e| {
+            io::Error::new(e.kind(), format!("Cannot read {}: {}", path.display(), e))
+        })?;
+        let config: Config = toml::from_str(&content).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid config {}: {}", path.display(), e),
+            )
+        })?;
+        Ok(Some(config))
+    }
+
+    /// Load config for commands that only read it
+    ///
+    /// An unreadable or invalid config file is not fatal here: a warning is
+    /// printed and the defaults are used instead.
+    pub fn load_or_default() -> Self {
+        Self::or_default(Self::load())
+    }
+
+    /// Load the layered config (see [`Config::load_layered`]) for commands that only read it
+    ///
+    /// Like [`Config::load_or_default`], a bad file only prints a warning.
+    pub fn load_layered_or_default(ralph_dir: &Path) -> Self {
+        Self::or_default(Self::load_layered(ralph_dir))
+    }
+
+    fn or_default(loaded: io::Result<Self>) -> Self {
+        loaded.unwrap_or_else(|e| {
+            eprintln!(
+                "{}",
+                console::style(format!("Warning: {}; using default settings", e)).yellow()
+            );
+            Self::default()
+        })
     }
 
     /// Save config to file
     pub fn save(&self) -> io::Result<()> {
-        let config_dir = Self::config_dir()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not determine config directory"))?;
-        let config_file = Self::config_file()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not determine config file path"))?;
+        self.save_to(Self::config_file())
+    }
+
+    /// Save config to the given file, or explain how to fix a missing location
+    pub fn save_to(&self, config_file: Option<PathBuf>) -> io::Result<()> {
+        let config_file = config_file.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Could not determine the config directory on this system. \
+                     Set {} to a writable file path (e.g. {}=$HOME/.ralph.toml) and try again.",
+                    CONFIG_PATH_ENV, CONFIG_PATH_ENV
+                ),
+            )
+        })?;
+
+        let unwritable = |e: io::Error| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "Cannot write {}: {}. Pass --config <path> or set {} to a writable location.",
+                    config_file.display(),
+                    e,
+                    CONFIG_PATH_ENV
+                ),
+            )
+        };
 
         // Create config directory if it doesn't exist
-        fs::create_dir_all(&config_dir)?;
+        if let Some(config_dir) = config_file.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(config_dir).map_err(unwritable)?;
+        }
 
         // Serialize and write config
         let content = toml::to_string_pretty(self)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        fs::write(&config_file, content)?;
+        fs::write(&config_file, content).map_err(unwritable)?;
 
         Ok(())
     }
 
-    /// Get a config value by key
-    pub fn get(&self, key: ConfigKey) -> Option<String> {
-        match key {
-            ConfigKey::DefaultTool => self.default_tool.clone(),
-            ConfigKey::MaxIterations => self.max_iterations.map(|v| v.to_string()),
-            ConfigKey::AutoArchive => self.auto_archive.map(|v| v.to_string()),
+    /// Check that the config file could be saved without writing it
+    ///
+    /// An existing file is opened for appending; otherwise a probe file is
+    /// created in the nearest existing ancestor directory.
+    pub fn check_writable(config_file: &Path) -> io::Result<()> {
+        if config_file.exists() {
+            fs::OpenOptions::new().append(true).open(config_file)?;
+            return Ok(());
         }
-    }
 
-    /// Set a config value by key
-    pub fn set(&mut self, key: ConfigKey, value: &str) -> Result<(), String> {
-        match key {
-            ConfigKey::DefaultTool => {
-                self.default_tool = Some(value.to_string());
-            }
-            ConfigKey::MaxIterations => {
-                let val: u32 = value
-                    .parse()
-                    .map_err(|_| "max_iterations must be a positive integer".to_string())?;
-                self.max_iterations = Some(val);
+        let mut dir = config_file.parent();
+        while let Some(d) = dir {
+            if d.as_os_str().is_empty() {
+                dir = Some(Path::new("."));
+                break;
             }
-            ConfigKey::AutoArchive => {
-                let val: bool = value
-                    .parse()
-                    .map_err(|_| "auto_archive must be true or false".to_string())?;
-                self.auto_archive = Some(val);
+            if d.exists() {
+                break;
             }
+            dir = d.parent();
         }
-        Ok(())
-    }
-}
-
-/// Configuration keys that can be get/set
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ConfigKey {
-    DefaultTool,
-    MaxIterations,
-    AutoArchive,
-}
-
-impl ConfigKey {
-    /// Get all available config keys
-    pub fn all() -> &'static [ConfigKey] {
-        &[ConfigKey::DefaultTool, ConfigKey::MaxIterations, ConfigKey::AutoArchive]
-    }
-
-    /// Get the string name of the key
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            ConfigKey::DefaultTool => "default_tool",
-            ConfigKey::MaxIterations => "max_iterations",
-            ConfigKey::AutoArchive => "auto_archive",
-        }
-    }
+        let dir = dir.unwrap_or(Path::new("."));
 
-    /// Get description of the key
-    pub fn description(&self) -> &'static str {
-        match self {
-            ConfigKey::DefaultTool => "Default AI tool (amp, claude, codebuddy)",
-            ConfigKey::MaxIterations => "Default maximum iterations for task execution",
-            ConfigKey::AutoArchive => "Auto archive history on branch switch",
-        }
-    }
-
-    /// Parse a config key from string
-    pub fn from_str(s: &str) -> Option<Self> {
-        match s {
-            "default_tool" => Some(ConfigKey::DefaultTool),
-            "max_iterations" => Some(ConfigKey::MaxIterations),
-            "auto_archive" => Some(ConfigKey::AutoArchive),
-            _ => None,
-        }
+        let probe = dir.join(".ralph-write-test");
+        fs::write(&probe, b"")?;
+        fs::remove_file(&probe)
     }
 }
@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::{RalphError, RalphResult};
+
+/// An environment variable to set on the agent process
+pub type EnvVar = (String, String);
+
+/// Parse a single `KEY=VALUE` assignment, as given to `--env`
+pub fn parse_env_assignment(assignment: &str) -> Result<EnvVar, String> {
+    let assignment = assignment.trim();
+    let assignment = assignment
+        .strip_prefix("export ")
+        .map(str::trim_start)
+        .unwrap_or(assignment);
+
+    let (key, value) = assignment
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", assignment))?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("invalid variable name '{}'", key));
+    }
+
+    Ok((key.to_string(), unquote(value.trim())))
+}
+
+/// Parse the contents of a `.env` file
+///
+/// Supports `KEY=VALUE` lines, `#` comments, blank lines, an optional
+/// `export` prefix, and single- or double-quoted values.
+pub fn parse_dotenv(content: &str) -> Result<Vec<EnvVar>, String> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(index, line)| {
+            parse_env_assignment(line).map_err(|e| format!("line {}: {}", index + 1, e))
+        })
+        .collect()
+}
+
+/// Load variables from a dotenv file
+pub fn load_env_file(path: &Path) -> RalphResult<Vec<EnvVar>> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        RalphError::Other(format!("Failed to read env file {}: {}", path.display(), e))
+    })?;
+    parse_dotenv(&content)
+        .map_err(|e| RalphError::Other(format!("Invalid env file {}: {}", path.display(), e)))
+}
+
+/// Strip matching quotes, or a trailing ` # comment` from an unquoted value
+fn unquote(value: &str) -> String {
+    let quoted = |q: char| value.len() >= 2 && value.starts_with(q) && value.ends_with(q);
+
+    if quoted('"') {
+        value[1..value.len() - 1]
+            .replace("\\n", "\n")
+            .replace("\\\"", "\"")
+    } else if quoted('\'') {
+        value[1..value.len() - 1].to_string()
+    } else {
+        match value.find(" #") {
+            Some(index) => value[..index].trim_end().to_string(),
+            None => value.to_string(),
+        }
+    }
+}
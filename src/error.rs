@@ -1,5 +1,7 @@
 use std::io;
 
+use crate::agent::install_hint;
+
 /// Custom result type for Ralph CLI operations
 pub type RalphResult<T> = Result<T, RalphError>;
 
@@ -8,20 +10,64 @@ pub type RalphResult<T> = Result<T, RalphError>;
 pub enum RalphError {
     Io(io::Error),
     Dialoguer(dialoguer::Error),
+    /// The agent process could not be started
+    AgentSpawn {
+        /// What was spawned: a tool name or the path given to `--tool-path`
+        tool: String,
+        source: io::Error,
+    },
+    /// The user pressed Ctrl+C at a prompt; nothing was changed after it
+    Interrupted,
     Other(String),
 }
 
+impl RalphError {
+    /// Process exit code for this error: 130 when interrupted, like a shell, else 1
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RalphError::Interrupted => 130,
+            _ => 1,
+        }
+    }
+
+    /// Whether this is an agent that could not be started because it does not exist
+    pub fn is_agent_not_found(&self) -> bool {
+        matches!(
+            self,
+            RalphError::AgentSpawn { source, .. } if source.kind() == io::ErrorKind::NotFound
+        )
+    }
+}
+
 impl std::fmt::Display for RalphError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             RalphError::Io(e) => write!(f, "IO error: {}", e),
             RalphError::Dialoguer(e) => write!(f, "Dialog error: {}", e),
+            RalphError::AgentSpawn { tool, source } if source.kind() == io::ErrorKind::NotFound => {
+                write!(f, "Failed to spawn {}: command not found", tool)?;
+                match install_hint(tool) {
+                    Some(hint) => write!(f, ". Install it with: {}", hint),
+                    None => Ok(()),
+                }
+            }
+            RalphError::AgentSpawn { tool, source } => {
+                write!(f, "Failed to spawn {}: {}", tool, source)
+            }
+            RalphError::Interrupted => write!(f, "Aborted by user"),
             RalphError::Other(s) => write!(f, "{}", s),
         }
     }
 }
 
-impl std::error::Error for RalphError {}
+impl std::error::Error for RalphError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RalphError::AgentSpawn { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
 impl From<io::Error> for RalphError {
     fn from(e: io::Error) -> Self {
@@ -30,7 +76,13 @@ impl From<io::Error> for RalphError {
 }
 
 impl From<dialoguer::Error> for RalphError {
+    /// Ctrl+C at a prompt reaches dialoguer as an interrupted read
     fn from(e: dialoguer::Error) -> Self {
-        RalphError::Dialoguer(e)
+        match e {
+            dialoguer::Error::IO(e) if e.kind() == io::ErrorKind::Interrupted => {
+                RalphError::Interrupted
+            }
+            e => RalphError::Dialoguer(e),
+        }
     }
 }
@@ -0,0 +1,363 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::metadata::{timestamp, RunOutcome};
+
+/// Maximum number of events buffered for a slow or absent reader
+pub const EVENT_BUFFER_CAPACITY: usize = 1024;
+
+/// How long a finished run waits for the writer to flush remaining events
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// How often the writer retries when no reader is connected
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Last chance for a waiting reader to connect once the run has finished
+const FINAL_CONNECT_WAIT: Duration = Duration::from_millis(100);
+
+/// A structured event emitted during `ralph run`, serialized as one JSON line
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunEvent {
+    RunStarted {
+        project: String,
+        branch: String,
+        tool: String,
+        max_iterations: u32,
+    },
+    IterationStarted {
+        iteration: u32,
+    },
+    Output {
+        stream: &'static str,
+        line: String,
+    },
+    IterationFinished {
+        iteration: u32,
+        completed: bool,
+        /// Why the iteration failed, if it did
+        #[serde(skip_serializing_if = "Option::is_none")]
+        failure: Option<String>,
+        /// Exit code of the agent; absent when it was ended by a signal
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        duration_ms: u64,
+        /// Stories the agent marked as passing during the iteration
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        stories_passed: Vec<String>,
+    },
+    RunFinished {
+        outcome: RunOutcome,
+        iterations: u32,
+        completed_stories: usize,
+        total_stories: usize,
+    },
+}
+
+impl RunEvent {
+    /// Serialize the event as a JSON line with a timestamp
+    pub fn to_json_line(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(map) = value.as_object_mut() {
+            map.insert("time".to_string(), timestamp().into());
+        }
+        format!("{}\n", value)
+    }
+}
+
+/// Bounded queue that drops the oldest event when full
+pub struct EventQueue {
+    events: Mutex<VecDeque<String>>,
+    capacity: usize,
+    dropped: AtomicU64,
+}
+
+impl EventQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Add an event, evicting the oldest one if the queue is full
+    pub fn push(&self, event: String) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() >= self.capacity {
+            events.pop_front();
+            self.dropped.fetch_add(1, Ordering::SeqCst);
+        }
+        events.push_back(event);
+    }
+
+    /// Take every queued event
+    pub fn drain(&self) -> Vec<String> {
+        self.events.lock().unwrap().drain(..).collect()
+    }
+
+    /// Put events back at the front, e.g. after a failed write
+    pub fn requeue(&self, unsent: Vec<String>) {
+        let mut events = self.events.lock().unwrap();
+        for event in unsent.into_iter().rev() {
+            if events.len() >= self.capacity {
+                self.dropped.fetch_add(1, Ordering::SeqCst);
+                continue;
+            }
+            events.push_front(event);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of events dropped so far
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::SeqCst)
+    }
+
+    fn add_dropped(&self, count: u64) {
+        self.dropped.fetch_add(count, Ordering::SeqCst);
+    }
+}
+
+/// Streams run events to an external reader from a background task
+///
+/// On Unix the target is a named pipe or Unix domain socket. An existing FIFO
+/// or listening socket is written to; a missing path is bound as a socket that
+/// readers can connect to. On other platforms the events are appended to a
+/// file. Emitting never blocks the run.
+pub struct EventStream {
+    queue: Arc<EventQueue>,
+    notify: Arc<Notify>,
+    shutdown: Arc<Notify>,
+    closed: Arc<AtomicBool>,
+    writer: JoinHandle<()>,
+}
+
+impl EventStream {
+    /// Start the writer task for the given path
+    pub fn start(path: PathBuf) -> std::io::Result<Self> {
+        let sink = Sink::open(path)?;
+        let queue = Arc::new(EventQueue::new(EVENT_BUFFER_CAPACITY));
+        let notify = Arc::new(Notify::new());
+        let shutdown = Arc::new(Notify::new());
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let writer = tokio::spawn(write_events(
+            sink,
+            queue.clone(),
+            notify.clone(),
+            shutdown.clone(),
+            closed.clone(),
+        ));
+
+        Ok(Self {
+            queue,
+            notify,
+            shutdown,
+            closed,
+            writer,
+        })
+    }
+
+    /// Queue an event for the reader
+    pub fn emit(&self, event: &RunEvent) {
+        self.queue.push(event.to_json_line());
+        self.notify.notify_one();
+    }
+
+    /// Flush what can be flushed, stop the writer, and return the dropped count
+    pub async fn finish(self) -> u64 {
+        self.closed.store(true, Ordering::SeqCst);
+        self.notify.notify_one();
+        self.shutdown.notify_one();
+
+        let mut writer = self.writer;
+        if tokio::time::timeout(SHUTDOWN_GRACE, &mut writer).await.is_err() {
+            writer.abort();
+        }
+        self.queue.add_dropped(self.queue.drain().len() as u64);
+        self.queue.dropped()
+    }
+}
+
+/// Emit an event if streaming is enabled
+pub fn emit(stream: Option<&EventStream>, event: RunEvent) {
+    if let Some(stream) = stream {
+        stream.emit(&event);
+    }
+}
+
+type Writer = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Where streamed events are written
+enum Sink {
+    #[cfg(unix)]
+    Fifo(PathBuf),
+    #[cfg(unix)]
+    Socket(PathBuf),
+    #[cfg(unix)]
+    Listener(tokio::net::UnixListener),
+    #[cfg(not(unix))]
+    File(PathBuf),
+}
+
+impl Sink {
+    #[cfg(unix)]
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        match std::fs::metadata(&path) {
+            Ok(meta) if meta.file_type().is_fifo() => Ok(Sink::Fifo(path)),
+            Ok(meta) if meta.file_type().is_socket() => {
+                if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                    Ok(Sink::Socket(path))
+                } else {
+                    // Stale socket left behind by an earlier run
+                    std::fs::remove_file(&path)?;
+                    Ok(Sink::Listener(tokio::net::UnixListener::bind(&path)?))
+                }
+            }
+            Ok(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!(
+                    "{} exists and is not a named pipe or socket",
+                    path.display()
+                ),
+            )),
+            Err(_) => Ok(Sink::Listener(tokio::net::UnixListener::bind(&path)?)),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        Ok(Sink::File(path))
+    }
+
+    /// Try to obtain a writer, waiting at most until the next retry or shutdown
+    ///
+    /// When `closing`, only a reader that is already waiting is picked up.
+    async fn connect(&self, shutdown: &Notify, closing: bool) -> Option<Writer> {
+        let attempt = async {
+            match self {
+                #[cfg(unix)]
+                Sink::Fifo(path) => tokio::net::unix::pipe::OpenOptions::new()
+                    .open_sender(path)
+                    .ok()
+                    .map(|w| Box::new(w) as Writer),
+                #[cfg(unix)]
+                Sink::Socket(path) => tokio::net::UnixStream::connect(path)
+                    .await
+                    .ok()
+                    .map(|w| Box::new(w) as Writer),
+                #[cfg(unix)]
+                Sink::Listener(listener) => listener
+                    .accept()
+                    .await
+                    .ok()
+                    .map(|(w, _)| Box::new(w) as Writer),
+                #[cfg(not(unix))]
+                Sink::File(path) => tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .ok()
+                    .map(|w| Box::new(w) as Writer),
+            }
+        };
+
+        if closing {
+            return tokio::time::timeout(FINAL_CONNECT_WAIT, attempt)
+                .await
+                .ok()
+                .flatten();
+        }
+
+        tokio::select! {
+            writer = attempt => {
+                if writer.is_none() {
+                    tokio::select! {
+                        _ = tokio::time::sleep(RECONNECT_INTERVAL) => {}
+                        _ = shutdown.notified() => {}
+                    }
+                }
+                writer
+            }
+            _ = shutdown.notified() => None,
+        }
+    }
+}
+
+impl Drop for Sink {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Sink::Listener(listener) = self {
+            if let Ok(addr) = listener.local_addr() {
+                if let Some(path) = addr.as_pathname() {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+    }
+}
+
+/// Background task moving queued events to the sink
+async fn write_events(
+    sink: Sink,
+    queue: Arc<EventQueue>,
+    notify: Arc<Notify>,
+    shutdown: Arc<Notify>,
+    closed: Arc<AtomicBool>,
+) {
+    let mut writer: Option<Writer> = None;
+
+    loop {
+        let shutting_down = closed.load(Ordering::SeqCst);
+
+        if writer.is_none() {
+            writer = sink.connect(&shutdown, shutting_down).await;
+            if writer.is_none() && shutting_down {
+                break;
+            }
+        }
+
+        if let Some(w) = writer.as_mut() {
+            let batch = queue.drain();
+            for (index, line) in batch.iter().enumerate() {
+                if w.write_all(line.as_bytes()).await.is_err() {
+                    // Reader went away; keep the unsent events for the next one
+                    queue.requeue(batch[index..].to_vec());
+                    writer = None;
+                    break;
+                }
+            }
+            if let Some(w) = writer.as_mut() {
+                if w.flush().await.is_err() {
+                    writer = None;
+                }
+            }
+        }
+
+        if shutting_down && queue.is_empty() {
+            break;
+        }
+
+        if writer.is_some() && queue.is_empty() && !closed.load(Ordering::SeqCst) {
+            notify.notified().await;
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::prd::{Epic, Prd, SplitMix64, UserStory, BRANCH_PREFIX};
+
+const VERBS: &[&str] = &[
+    "Add", "Improve", "Fix", "Refactor", "Document", "Validate", "Cache", "Paginate",
+];
+
+const FEATURES: &[&str] = &[
+    "login flow",
+    "invoice export",
+    "search results",
+    "user settings",
+    "notification emails",
+    "audit log",
+    "dashboard charts",
+    "file uploads",
+    "billing page",
+    "API rate limits",
+];
+
+const ROLES: &[&str] = &["user", "admin", "developer", "support agent", "new customer"];
+
+const BENEFITS: &[&str] = &[
+    "I can finish my work faster",
+    "mistakes are caught early",
+    "the page stays responsive",
+    "I trust the numbers I see",
+    "I do not have to ask support",
+];
+
+const CRITERIA: &[&str] = &[
+    "Shows an error message for invalid input",
+    "Works on narrow screens",
+    "Covered by a unit test",
+    "Loading state is visible while data is fetched",
+    "Changes are saved without a page reload",
+    "Empty state explains what to do next",
+];
+
+const EPICS: &[&str] = &["Onboarding", "Billing", "Reporting", "Platform", "Security", "Search"];
+
+/// Settings for a synthetic PRD, as given to `ralph prd fake`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakePrdOptions {
+    pub stories: usize,
+    /// The same seed always produces the same PRD
+    pub seed: u64,
+    /// Share of stories that pass, from 0.0 to 1.0
+    pub completed: f64,
+    /// Let stories depend on earlier ones
+    pub dependencies: bool,
+    /// Number of epics to spread the stories over (0 = none)
+    pub epics: usize,
+}
+
+impl Default for FakePrdOptions {
+    fn default() -> Self {
+        Self {
+            stories: 10,
+            seed: 0,
+            completed: 0.0,
+            dependencies: false,
+            epics: 0,
+        }
+    }
+}
+
+/// Generate a valid PRD with randomized but repeatable stories
+///
+/// Priorities are a shuffled `1..=N`. The highest priority stories are the
+/// ones that pass, and dependencies only point at higher priority stories, so
+/// the PRD never has a passing story waiting on a pending one or a cycle.
+pub fn fake_prd(options: &FakePrdOptions) -> Prd {
+    let mut rng = SplitMix64::new(options.seed);
+    let count = options.stories;
+    let width = count.to_string().len().max(3);
+    let ids: Vec<String> = (1..=count)
+        .map(|n| format!("US-{:0width$}", n, width = width))
+        .collect();
+
+    let mut priorities: Vec<u32> = (1..=count as u32).collect();
+    rng.shuffle(&mut priorities);
+    let passing = (count as f64 * options.completed.clamp(0.0, 1.0)).round() as u32;
+
+    // Ids by priority, so dependencies can point at higher priority stories
+    let mut by_priority = vec![String::new(); count];
+    for (id, priority) in ids.iter().zip(&priorities) {
+        by_priority[*priority as usize - 1] = id.clone();
+    }
+
+    let epics: Vec<Epic> = (0..options.epics)
+        .map(|n| Epic {
+            name: match EPICS.get(n) {
+                Some(name) => name.to_string(),
+                None => format!("Epic {}", n + 1),
+            },
+            description: String::new(),
+        })
+        .collect();
+
+    let user_stories = ids
+        .iter()
+        .zip(&priorities)
+        .map(|(id, &priority)| {
+            let verb = rng.pick(VERBS);
+            let feature = rng.pick(FEATURES);
+            let criteria_count = 1 + rng.below(3) as usize;
+            let mut acceptance_criteria: Vec<String> =
+                (0..criteria_count).map(|_| rng.pick(CRITERIA).to_string()).collect();
+            acceptance_criteria.push("Typecheck passes".to_string());
+
+            let depends_on = if options.dependencies && priority > 1 && rng.below(3) == 0 {
+                let earlier = rng.below(u64::from(priority) - 1) as usize;
+                vec![by_priority[earlier].clone()]
+            } else {
+                Vec::new()
+            };
+            let epic = (!epics.is_empty()).then(|| rng.pick(&epics).name.clone());
+
+            UserStory {
+                id: id.clone(),
+                title: format!("{} {}", verb, feature),
+                description: format!(
+                    "As a {}, I want to {} the {} so that {}.",
+                    rng.pick(ROLES),
+                    verb.to_lowercase(),
+                    feature,
+                    rng.pick(BENEFITS)
+                ),
+                acceptance_criteria,
+                priority,
+                passes: priority <= passing,
+                notes: String::new(),
+                depends_on,
+                epic,
+                context_files: Vec::new(),
+                tags: Vec::new(),
+                estimate: None,
+                criteria_done: Vec::new(),
+            }
+        })
+        .collect();
+
+    Prd {
+        project: format!("Fake Project {}", options.seed),
+        branch_name: format!("{}fake-{}", BRANCH_PREFIX, options.seed),
+        description: format!("Synthetic PRD with {} stories", count),
+        epics,
+        user_stories,
+    }
+}
@@ -0,0 +1,103 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run git in `dir` and return its trimmed stdout
+///
+/// A non-zero exit is an error carrying git's own message.
+pub fn git(dir: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git").args(args).current_dir(dir).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            stderr.trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Top-level directory of the git repository containing `dir`, if any
+pub fn repo_root(dir: &Path) -> Option<PathBuf> {
+    git(dir, &["rev-parse", "--show-toplevel"])
+        .ok()
+        .map(PathBuf::from)
+}
+
+/// Whether the working tree has no uncommitted or untracked changes
+pub fn is_clean(dir: &Path) -> io::Result<bool> {
+    Ok(git(dir, &["status", "--porcelain"])?.is_empty())
+}
+
+/// Create a worktree at `path` on a new branch started from the current commit
+pub fn add_worktree(repo: &Path, path: &Path, branch: &str) -> io::Result<()> {
+    git(
+        repo,
+        &["worktree", "add", "-b", branch, &path.to_string_lossy()],
+    )
+    .map(|_| ())
+}
+
+/// Name of the branch checked out in `dir`, or `None` outside git or on a
+/// detached HEAD
+pub fn current_branch(dir: &Path) -> Option<String> {
+    git(dir, &["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .ok()
+        .filter(|branch| !branch.is_empty())
+}
+
+/// A change of the checked-out branch, as recorded in HEAD's reflog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchMove {
+    /// Unix time of the move
+    pub at: i64,
+    pub from: String,
+    pub to: String,
+    /// `git branch -m` rather than a checkout
+    pub renamed: bool,
+}
+
+/// Branch moves in the reflog of HEAD, newest first
+///
+/// An empty list when there is no reflog, e.g. in a fresh clone.
+pub fn branch_moves(dir: &Path) -> Vec<BranchMove> {
+    git(dir, &["reflog", "show", "--date=unix", "--format=%gd%x09%gs", "HEAD"])
+        .map(|log| log.lines().filter_map(parse_branch_move).collect())
+        .unwrap_or_default()
+}
+
+/// Parse a `HEAD@{<unix time>}\t<subject>` reflog line into a branch move
+///
+/// Checkouts (`checkout: moving from a to b`) and renames (`Branch: renamed
+/// refs/heads/a to refs/heads/b`) are moves; commits, resets and the like
+/// are not.
+pub fn parse_branch_move(line: &str) -> Option<BranchMove> {
+    let (selector, subject) = line.split_once('\t')?;
+    let at = selector
+        .strip_prefix("HEAD@{")?
+        .strip_suffix('}')?
+        .parse()
+        .ok()?;
+
+    let (from, to, renamed) = if let Some(rest) = subject.strip_prefix("checkout: moving from ") {
+        let (from, to) = rest.split_once(" to ")?;
+        (from, to, false)
+    } else if let Some(rest) = subject.strip_prefix("Branch: renamed ") {
+        let (from, to) = rest.split_once(" to ")?;
+        (
+            from.strip_prefix("refs/heads/").unwrap_or(from),
+            to.strip_prefix("refs/heads/").unwrap_or(to),
+            true,
+        )
+    } else {
+        return None;
+    };
+
+    (from != to).then(|| BranchMove {
+        at,
+        from: from.to_string(),
+        to: to.to_string(),
+        renamed,
+    })
+}
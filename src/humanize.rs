@@ -0,0 +1,109 @@
+use console::{measure_text_width, pad_str, Alignment};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
+/// Format a duration with its two largest units, e.g. `4m 32s` or `1h 12m`
+///
+/// Durations under a second are shown in milliseconds and durations under a
+/// minute in whole seconds.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs == 0 {
+        return format!("{}ms", duration.subsec_millis());
+    }
+    if secs < MINUTE {
+        return format!("{}s", secs);
+    }
+
+    let (major, major_unit, minor, minor_unit) = if secs < HOUR {
+        (secs / MINUTE, "m", secs % MINUTE, "s")
+    } else if secs < DAY {
+        (secs / HOUR, "h", secs % HOUR / MINUTE, "m")
+    } else {
+        (secs / DAY, "d", secs % DAY / HOUR, "h")
+    };
+
+    if minor == 0 {
+        format!("{}{}", major, major_unit)
+    } else {
+        format!("{}{} {}{}", major, major_unit, minor, minor_unit)
+    }
+}
+
+/// Describe how long ago something happened, e.g. `3 weeks ago`
+///
+/// Anything under a minute is `just now`. Months are 30 days and years 365.
+pub fn format_relative_time(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let (count, unit) = match secs {
+        s if s < MINUTE => return "just now".to_string(),
+        s if s < HOUR => (s / MINUTE, "minute"),
+        s if s < DAY => (s / HOUR, "hour"),
+        s if s < WEEK => (s / DAY, "day"),
+        s if s < MONTH => (s / WEEK, "week"),
+        s if s < YEAR => (s / MONTH, "month"),
+        s => (s / YEAR, "year"),
+    };
+
+    if count == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", count, unit)
+    }
+}
+
+/// Time since a file or directory was last modified, if it can be read
+pub fn elapsed_since_modified(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+/// Column alignment for [`render_table`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// Lay out rows as aligned columns separated by two spaces
+///
+/// Numbers read best in `Align::Right` columns. Widths ignore ANSI styling, so
+/// cells may be styled before rendering. Columns missing from `aligns` are
+/// left-aligned, and trailing padding is trimmed.
+pub fn render_table(rows: &[Vec<String>], aligns: &[Align]) -> Vec<String> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|col| {
+            rows.iter()
+                .filter_map(|row| row.get(col))
+                .map(|cell| measure_text_width(cell))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(col, cell)| {
+                    let align = match aligns.get(col) {
+                        Some(Align::Right) => Alignment::Right,
+                        _ => Alignment::Left,
+                    };
+                    pad_str(cell, widths[col], align, None).into_owned()
+                })
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect()
+}
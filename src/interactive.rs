@@ -0,0 +1,215 @@
+use dialoguer::{Confirm, Input, MultiSelect, Select};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{RalphError, RalphResult};
+
+/// Environment variable that acts like `--yes`, for CI and scripts
+pub const ASSUME_YES_ENV: &str = "RALPH_ASSUME_YES";
+
+/// Environment variable CI services set; when on, ralph never prompts
+pub const CI_ENV: &str = "CI";
+
+/// Set from the global `--yes` flag or `RALPH_ASSUME_YES` at startup
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Make every confirmation auto-accept (global `-y/--yes`)
+pub fn set_assume_yes(value: bool) {
+    ASSUME_YES.store(value, Ordering::SeqCst);
+}
+
+/// Whether the global `--yes` flag is in effect
+pub fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::SeqCst)
+}
+
+/// Whether `RALPH_ASSUME_YES` is set to a true value
+pub fn assume_yes_from_env() -> bool {
+    env_flag(std::env::var(ASSUME_YES_ENV).ok().as_deref())
+}
+
+/// Any value but empty, `0`, `false`, `no` or `off` turns the variable on
+fn env_flag(value: Option<&str>) -> bool {
+    value.is_some_and(|v| {
+        let v = v.trim().to_ascii_lowercase();
+        !matches!(v.as_str(), "" | "0" | "false" | "no" | "off")
+    })
+}
+
+/// Whether prompts can be shown to a user
+///
+/// Every prompt goes through this module and checks it, so nothing waits on
+/// stdin in a pipeline: stdin must be a terminal and `CI` must not be set.
+/// Otherwise the prompts fall back to their safe answers or fail, asking
+/// for an explicit flag.
+pub fn is_interactive() -> bool {
+    detect_interactive(
+        std::io::stdin().is_terminal(),
+        std::env::var(CI_ENV).ok().as_deref(),
+    )
+}
+
+/// Decide interactivity from whether stdin is a terminal and the `CI` variable
+fn detect_interactive(stdin_is_terminal: bool, ci: Option<&str>) -> bool {
+    stdin_is_terminal && !env_flag(ci)
+}
+
+/// Ask the user a yes/no question
+///
+/// `default` doubles as the safety marker: a `true` default means accepting
+/// is safe and may happen without a user, while a `false` default means
+/// accepting is destructive and only an explicit answer or `--yes` may do it.
+pub fn confirm(prompt: &str, default: bool) -> RalphResult<bool> {
+    if let Some(answer) = auto_answer(prompt, assume_yes(), is_interactive(), default) {
+        return answer;
+    }
+
+    let answer = Confirm::new()
+        .with_prompt(prompt)
+        .default(default)
+        .interact()?;
+    Ok(answer)
+}
+
+/// Let the user pick one item, falling back to `default` when prompts are disabled
+pub fn select(prompt: &str, items: &[String], default: usize) -> RalphResult<usize> {
+    if assume_yes() || !is_interactive() {
+        return Ok(default);
+    }
+
+    let selection = Select::new()
+        .with_prompt(prompt)
+        .items(items)
+        .default(default)
+        .interact()?;
+    Ok(selection)
+}
+
+/// Let the user pick several items, falling back to `defaults` when prompts are disabled
+pub fn multi_select(prompt: &str, items: &[String], defaults: &[bool]) -> RalphResult<Vec<usize>> {
+    if assume_yes() || !is_interactive() {
+        return Ok(defaults
+            .iter()
+            .enumerate()
+            .filter(|(_, selected)| **selected)
+            .map(|(idx, _)| idx)
+            .collect());
+    }
+
+    let selections = MultiSelect::new()
+        .with_prompt(prompt)
+        .items(items)
+        .defaults(defaults)
+        .interact()?;
+    Ok(selections)
+}
+
+/// Ask for a line of text, pre-filled with `initial`
+///
+/// `validate` returns an error message to show before asking again. Unlike
+/// the other prompts there is no sensible fallback, so callers should check
+/// [`is_interactive`] first and offer flags instead; without a terminal this
+/// is an error rather than a wait on stdin.
+pub fn input(
+    prompt: &str,
+    initial: &str,
+    validate: impl Fn(&str) -> Result<(), String>,
+) -> RalphResult<String> {
+    if !is_interactive() {
+        return Err(RalphError::Other(format!(
+            "Cannot ask for \"{}\" without a terminal",
+            prompt
+        )));
+    }
+    let text = Input::<String>::new()
+        .with_prompt(prompt)
+        .with_initial_text(initial)
+        .allow_empty(true)
+        .validate_with(|text: &String| validate(text))
+        .interact_text()?;
+    Ok(text)
+}
+
+/// Decide a confirmation without prompting, or `None` when the user must be asked
+fn auto_answer(
+    prompt: &str,
+    assume_yes: bool,
+    interactive: bool,
+    default: bool,
+) -> Option<RalphResult<bool>> {
+    if assume_yes {
+        return Some(Ok(true));
+    }
+    if interactive {
+        return None;
+    }
+    if default {
+        Some(Ok(true))
+    } else {
+        Some(Err(RalphError::Other(format!(
+            "Cannot confirm \"{}\" without a terminal (or with CI set). Re-run with --yes to accept.",
+            prompt
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_answer_yes_flag_accepts_everything() {
+        assert!(auto_answer("Delete?", true, true, false).unwrap().unwrap());
+        assert!(auto_answer("Delete?", true, false, false).unwrap().unwrap());
+    }
+
+    #[test]
+    fn test_auto_answer_interactive_asks_user() {
+        assert!(auto_answer("Migrate?", false, true, true).is_none());
+        assert!(auto_answer("Delete?", false, true, false).is_none());
+    }
+
+    #[test]
+    fn test_auto_answer_non_interactive_safe_default() {
+        assert!(auto_answer("Migrate?", false, false, true).unwrap().unwrap());
+    }
+
+    #[test]
+    fn test_auto_answer_non_interactive_destructive_fails() {
+        let err = auto_answer("Delete?", false, false, false).unwrap().unwrap_err();
+        assert!(err.to_string().contains("--yes"));
+    }
+
+    #[test]
+    fn test_parse_assume_yes_env() {
+        assert!(env_flag(Some("1")));
+        assert!(env_flag(Some("true")));
+        assert!(env_flag(Some("YES")));
+        assert!(!env_flag(None));
+        assert!(!env_flag(Some("")));
+        assert!(!env_flag(Some("0")));
+        assert!(!env_flag(Some(" False ")));
+        assert!(!env_flag(Some("off")));
+    }
+
+    #[test]
+    fn test_terminal_outside_ci_is_interactive() {
+        assert!(detect_interactive(true, None));
+        assert!(detect_interactive(true, Some("")));
+        assert!(detect_interactive(true, Some("false")));
+    }
+
+    #[test]
+    fn test_ci_disables_prompts_even_on_a_terminal() {
+        assert!(!detect_interactive(true, Some("true")));
+        assert!(!detect_interactive(true, Some("1")));
+        // Some services set CI to their own name
+        assert!(!detect_interactive(true, Some("woodpecker")));
+    }
+
+    #[test]
+    fn test_no_terminal_is_never_interactive() {
+        assert!(!detect_interactive(false, None));
+        assert!(!detect_interactive(false, Some("false")));
+    }
+}
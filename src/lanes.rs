@@ -0,0 +1,463 @@
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command as TokioCommand;
+use tokio::signal;
+use tokio::task::JoinSet;
+
+use crate::config::{Config, CONFIG_PATH_ENV};
+use crate::error::{RalphError, RalphResult};
+use crate::git;
+use crate::interactive::assume_yes;
+use crate::metadata::RunOutcome;
+use crate::persistence::RunStore;
+use crate::prd::{Prd, StoryOrder, UserStory};
+use crate::selector::StorySelector;
+
+/// Pending stories split into lanes that can be worked on side by side
+///
+/// Built by [`plan_lanes`]; every pending story in scope is in exactly one
+/// lane or in `serial`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanePlan {
+    /// Story ids per lane, in priority order; never a single lane
+    pub lanes: Vec<Vec<String>>,
+    /// Story ids left for a normal serial run after the lanes, in priority order
+    pub serial: Vec<String>,
+}
+
+impl LanePlan {
+    /// Whether there is anything to run in parallel
+    pub fn is_parallel(&self) -> bool {
+        self.lanes.len() > 1
+    }
+}
+
+/// Split the pending stories `selector` covers into at most `max_lanes` independent lanes
+///
+/// Stories that depend on each other (directly or through other pending
+/// stories) or list a common file in `contextFiles` conflict, and conflicting
+/// stories always share a lane. A group containing a story without
+/// `contextFiles` cannot be checked for overlaps, so it goes to `serial`.
+/// Groups are spread over the lanes largest first, each onto the lane with
+/// the fewest stories. When fewer than two lanes end up with work, every
+/// pending story falls back to `serial`.
+pub fn plan_lanes(prd: &Prd, max_lanes: usize, selector: &StorySelector) -> LanePlan {
+    let pending = selector.pending(prd, StoryOrder::Priority);
+    let all_serial = || LanePlan {
+        lanes: Vec::new(),
+        serial: pending.iter().map(|s| s.id.clone()).collect(),
+    };
+    if max_lanes < 2 {
+        return all_serial();
+    }
+
+    let index_of: HashMap<&str, usize> = pending
+        .iter()
+        .enumerate()
+        .map(|(index, story)| (story.id.as_str(), index))
+        .collect();
+    let mut groups = UnionFind::new(pending.len());
+    let mut file_owner: HashMap<String, usize> = HashMap::new();
+    for (index, story) in pending.iter().enumerate() {
+        for dep in &story.depends_on {
+            if let Some(&dep_index) = index_of.get(dep.as_str()) {
+                groups.union(index, dep_index);
+            }
+        }
+        for file in &story.context_files {
+            let owner = *file_owner.entry(normalize_path(file)).or_insert(index);
+            groups.union(index, owner);
+        }
+    }
+
+    // Members of each group, in priority order
+    let mut members: Vec<Vec<&UserStory>> = Vec::new();
+    let mut group_slot: HashMap<usize, usize> = HashMap::new();
+    for (index, story) in pending.iter().enumerate() {
+        let root = groups.find(index);
+        let slot = *group_slot.entry(root).or_insert_with(|| {
+            members.push(Vec::new());
+            members.len() - 1
+        });
+        members[slot].push(story);
+    }
+
+    let (mut plannable, unplannable): (Vec<_>, Vec<_>) = members
+        .into_iter()
+        .partition(|group| group.iter().all(|s| !s.context_files.is_empty()));
+    // Largest first; the sort is stable, so equal sizes keep priority order
+    plannable.sort_by_key(|group| std::cmp::Reverse(group.len()));
+
+    let mut lanes: Vec<Vec<&UserStory>> = vec![Vec::new(); max_lanes];
+    for group in plannable {
+        let lane = lanes
+            .iter_mut()
+            .min_by_key(|lane| lane.len())
+            .expect("at least two lanes");
+        lane.extend(group);
+    }
+    lanes.retain(|lane| !lane.is_empty());
+    if lanes.len() < 2 {
+        return all_serial();
+    }
+
+    let priority_order = |stories: Vec<&UserStory>| {
+        let mut ids: Vec<(usize, String)> = stories
+            .into_iter()
+            .map(|s| (index_of[s.id.as_str()], s.id.clone()))
+            .collect();
+        ids.sort();
+        ids.into_iter().map(|(_, id)| id).collect::<Vec<_>>()
+    };
+    LanePlan {
+        lanes: lanes.into_iter().map(priority_order).collect(),
+        serial: priority_order(unplannable.into_iter().flatten().collect()),
+    }
+}
+
+/// Compare `contextFiles` entries loosely: `./src/a.rs` and `src/a.rs` are one file
+fn normalize_path(path: &str) -> String {
+    let path = path.trim().replace('\\', "/");
+    let mut path = path.as_str();
+    while let Some(rest) = path.strip_prefix("./") {
+        path = rest;
+    }
+    path.trim_end_matches('/').to_string()
+}
+
+/// Disjoint sets over `0..n`
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b.max(a)] = a.min(b);
+        }
+    }
+}
+
+/// Everything a lane's `ralph run` needs from the primary run
+pub struct LaneRun<'a> {
+    pub prd: &'a Prd,
+    pub prd_path: &'a Path,
+    /// JSON Pointer of the PRD inside the primary prd.json (`--prd-key`)
+    pub prd_key: &'a str,
+    pub ralph_dir: &'a Path,
+    pub plan: LanePlan,
+    pub tool_cmd: &'a str,
+    pub tool_path: Option<&'a Path>,
+    pub max_iterations: u32,
+    /// Other `run` arguments passed through unchanged, e.g. `--env`
+    pub forwarded_args: Vec<OsString>,
+    /// Merges lane results into the primary prd.json
+    pub store: &'a RunStore,
+}
+
+/// A lane with its worktree set up, ready to run
+struct Lane {
+    number: usize,
+    stories: Vec<String>,
+    branch: String,
+    worktree: PathBuf,
+    prd_path: PathBuf,
+}
+
+/// Run each lane of `run.plan` in its own git worktree, side by side
+///
+/// Every lane gets a worktree under `<ralph dir>/lanes/lane-N` on a new
+/// branch `<PRD branch>-lane-N`, a copy of the PRD naming that branch and a
+/// copy of progress.txt. A child `ralph run` works there on the lane's
+/// stories only, with its output prefixed `[lane N]`. As each lane finishes,
+/// the stories it completed are marked as passing in the primary prd.json.
+/// The `serial` stories are left to the caller.
+///
+/// Requires a clean working tree, so every lane starts from the same commit.
+/// Returns `Complete` once every lane story passes, and `Interrupted` after
+/// Ctrl+C.
+pub async fn run_lanes(run: LaneRun<'_>) -> RalphResult<RunOutcome> {
+    let repo = git::repo_root(run.ralph_dir).ok_or_else(|| {
+        RalphError::Other("--parallel needs the project to be a git repository".to_string())
+    })?;
+    if !git::is_clean(&repo)? {
+        return Err(RalphError::Other(
+            "--parallel needs a clean working tree; commit or stash your changes first"
+                .to_string(),
+        ));
+    }
+    let ralph_rel = run
+        .ralph_dir
+        .strip_prefix(&repo)
+        .map_err(|_| {
+            RalphError::Other(format!(
+                "--parallel needs the ralph directory {} inside the repository {}",
+                run.ralph_dir.display(),
+                repo.display()
+            ))
+        })?
+        .to_path_buf();
+
+    let lanes_dir = run.ralph_dir.join(LANES_DIR);
+    fs::create_dir_all(&lanes_dir)?;
+    // Keep the worktrees out of the primary checkout's `git status`
+    fs::write(lanes_dir.join(".gitignore"), "*\n")?;
+
+    let mut lanes = Vec::new();
+    for (index, stories) in run.plan.lanes.iter().enumerate() {
+        let lane = prepare_lane(&run, &repo, &ralph_rel, &lanes_dir, index + 1, stories)?;
+        println!(
+            "{} {} on {}: {}",
+            lane_tag(lane.number),
+            lane.worktree.display(),
+            lane.branch.cyan(),
+            lane.stories.join(", ")
+        );
+        lanes.push(lane);
+    }
+    if !run.plan.serial.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "Left for a serial run after the lanes (no contextFiles, or tied to such a story): {}",
+                run.plan.serial.join(", ")
+            )
+            .yellow()
+        );
+    }
+    println!();
+
+    // Each child stops on Ctrl+C itself; wait for them to wind down
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = interrupted.clone();
+    tokio::spawn(async move {
+        if signal::ctrl_c().await.is_ok() {
+            flag.store(true, Ordering::SeqCst);
+            println!();
+            println!("{}", "Received interrupt signal, waiting for the lanes to stop...".yellow());
+        }
+    });
+
+    let mut running = JoinSet::new();
+    for (slot, lane) in lanes.iter().enumerate() {
+        let mut command = lane_command(&run, lane)?;
+        running.spawn(async move { (slot, run_lane_process(&mut command, slot + 1).await) });
+    }
+
+    let mut failed = Vec::new();
+    while let Some(joined) = running.join_next().await {
+        let (slot, status) = joined.map_err(|e| RalphError::Other(e.to_string()))?;
+        let lane = &lanes[slot];
+        match status {
+            Ok(status) if !status.success() => failed.push(lane.number),
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{} {}", lane_tag(lane.number), format!("could not run: {}", e).red());
+                failed.push(lane.number);
+                continue;
+            }
+        }
+        let merged = run
+            .store
+            .write("prd.json", || merge_lane_status(lane, run.prd_path, run.prd_key))?
+            .unwrap_or_default();
+        println!(
+            "{} finished; marked {} of {} stories as passing in {}",
+            lane_tag(lane.number),
+            merged,
+            lane.stories.len(),
+            run.prd_path.display()
+        );
+    }
+
+    println!();
+    println!("Lane branches to review and merge:");
+    for lane in &lanes {
+        println!("  {} ({})", lane.branch.cyan(), lane.worktree.display());
+    }
+    println!("Remove a worktree once merged with: git worktree remove <path>");
+    if !failed.is_empty() {
+        let numbers: Vec<String> = failed.iter().map(usize::to_string).collect();
+        eprintln!("{}", format!("Lanes that failed: {}", numbers.join(", ")).yellow());
+    }
+
+    let prd = Prd::from_file_at(run.prd_path, run.prd_key)?;
+    let all_passed = run
+        .plan
+        .lanes
+        .iter()
+        .flatten()
+        .all(|id| prd.find_story(id).is_some_and(|s| s.passes));
+    Ok(if interrupted.load(Ordering::SeqCst) {
+        RunOutcome::Interrupted
+    } else if all_passed {
+        RunOutcome::Complete
+    } else {
+        RunOutcome::MaxIterations
+    })
+}
+
+/// Directory under the ralph directory holding the lane worktrees
+pub const LANES_DIR: &str = "lanes";
+
+fn lane_tag(number: usize) -> String {
+    format!("[lane {}]", number).cyan().to_string()
+}
+
+/// Create the worktree, PRD and progress log of one lane
+fn prepare_lane(
+    run: &LaneRun<'_>,
+    repo: &Path,
+    ralph_rel: &Path,
+    lanes_dir: &Path,
+    number: usize,
+    stories: &[String],
+) -> RalphResult<Lane> {
+    let worktree = lanes_dir.join(format!("lane-{}", number));
+    if worktree.exists() {
+        return Err(RalphError::Other(format!(
+            "Lane worktree {} already exists; merge its branch and run \
+             'git worktree remove {}' first",
+            worktree.display(),
+            worktree.display()
+        )));
+    }
+    let branch = format!("{}-lane-{}", run.prd.branch_name(), number);
+    git::add_worktree(repo, &worktree, &branch)?;
+
+    let lane_ralph_dir = worktree.join(ralph_rel);
+    fs::create_dir_all(&lane_ralph_dir)?;
+    // Untracked state the lane would otherwise be missing
+    for file in ["progress.txt", "config.toml"] {
+        let source = run.ralph_dir.join(file);
+        let target = lane_ralph_dir.join(file);
+        if source.is_file() && !target.exists() {
+            fs::copy(&source, &target)?;
+        }
+    }
+
+    // Always a whole-file PRD, even when the primary one sits under --prd-key
+    let prd_path = lane_ralph_dir.join("prd.json");
+    let mut lane_prd = run.prd.clone();
+    lane_prd.branch_name = branch.clone();
+    if prd_path.exists() {
+        fs::remove_file(&prd_path)?;
+    }
+    lane_prd.save_to_file_at(&prd_path, "")?;
+
+    Ok(Lane {
+        number,
+        stories: stories.to_vec(),
+        branch,
+        worktree,
+        prd_path,
+    })
+}
+
+/// The `ralph run` that works through one lane
+fn lane_command(run: &LaneRun<'_>, lane: &Lane) -> RalphResult<TokioCommand> {
+    let mut command = TokioCommand::new(std::env::current_exe()?);
+    command
+        .arg("run")
+        .arg("--prd")
+        .arg(&lane.prd_path)
+        .arg("--tool")
+        .arg(run.tool_cmd)
+        .arg("--max-iterations")
+        .arg(run.max_iterations.to_string())
+        // The lane branch is new; there is no previous run to archive
+        .arg("--ignore-branch-archive");
+    if let Some(path) = run.tool_path {
+        command.arg("--tool-path").arg(path);
+    }
+    // Lanes cannot prompt (stdin is closed), so only a --yes run answers for them
+    if assume_yes() {
+        command.arg("--yes");
+    }
+    // Lanes run in their worktrees, so the config file (from --config too) goes as an absolute path
+    if let Some(config_file) = Config::config_file() {
+        command.env(CONFIG_PATH_ENV, std::path::absolute(config_file)?);
+    }
+    // Every pending story outside this lane is left to another lane or a serial run
+    let lane_stories: HashSet<&str> = lane.stories.iter().map(String::as_str).collect();
+    for story in run.prd.user_stories.iter().filter(|s| !s.passes) {
+        if !lane_stories.contains(story.id.as_str()) {
+            command.arg("--exclude-story").arg(&story.id);
+        }
+    }
+    command
+        .args(&run.forwarded_args)
+        .current_dir(&lane.worktree)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    Ok(command)
+}
+
+/// Run a lane to the end, printing its output prefixed with the lane number
+async fn run_lane_process(
+    command: &mut TokioCommand,
+    number: usize,
+) -> std::io::Result<std::process::ExitStatus> {
+    let mut child = command.spawn()?;
+    let stdout = child.stdout.take().map(|out| {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(out).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                println!("{} {}", lane_tag(number), line);
+            }
+        })
+    });
+    let stderr = child.stderr.take().map(|err| {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(err).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                eprintln!("{} {}", lane_tag(number), line);
+            }
+        })
+    });
+    let status = child.wait().await?;
+    for reader in [stdout, stderr].into_iter().flatten() {
+        let _ = reader.await;
+    }
+    Ok(status)
+}
+
+/// Mark the lane's passing stories as passing in the primary PRD
+///
+/// Returns how many stories were newly marked.
+fn merge_lane_status(lane: &Lane, prd_path: &Path, prd_key: &str) -> RalphResult<usize> {
+    let lane_prd = Prd::from_file_at(&lane.prd_path, "")?;
+    let mut prd = Prd::from_file_at(prd_path, prd_key)?;
+    let mut merged = 0;
+    for id in &lane.stories {
+        if lane_prd.find_story(id).is_some_and(|s| s.passes)
+            && prd.mark_story_passed_at(id, prd_path, prd_key)?
+        {
+            merged += 1;
+        }
+    }
+    Ok(merged)
+}
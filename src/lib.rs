@@ -0,0 +1,416 @@
+//! Ralph CLI - AI Agent aggregation tool
+//!
+//! The `ralph` binary is a thin wrapper around [`run_cli`]. The PRD, config
+//! and agent detection modules can also be used directly, and [`run`] starts
+//! an agent run without going through the command line.
+
+use console::style;
+
+pub mod agent;
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod prd;
+pub mod usage;
+
+pub(crate) mod agent_cache;
+pub(crate) mod agents_md;
+pub(crate) mod archive;
+pub(crate) mod branch_change;
+pub(crate) mod color;
+pub(crate) mod commands;
+pub(crate) mod dotenv;
+pub(crate) mod events;
+pub(crate) mod fake_prd;
+pub(crate) mod git;
+pub(crate) mod humanize;
+pub(crate) mod interactive;
+pub(crate) mod lanes;
+pub(crate) mod links;
+pub(crate) mod metadata;
+pub(crate) mod migration;
+pub(crate) mod output;
+pub(crate) mod persistence;
+pub(crate) mod report;
+pub(crate) mod sandbox_check;
+pub(crate) mod search;
+pub(crate) mod selector;
+pub(crate) mod secrets;
+pub(crate) mod status;
+pub(crate) mod templates;
+pub(crate) mod workspace;
+
+/// Run the agent loop over a PRD, as `ralph run` does
+pub mod run {
+    pub use crate::commands::run::{run_run, RunOptions};
+}
+
+use cli::{
+    AgentsMdCommands, ArchiveCommands, Cli, ColorChoice, Commands, ConfigCommands, OutputFormat,
+    PrdCommands, StoryCommands,
+};
+
+/// Execute a parsed command line
+///
+/// This is the whole `ralph` binary; errors are printed and turned into a
+/// non-zero exit status.
+pub fn run_cli(cli: Cli) {
+    color::apply_color_choice(if cli.no_color {
+        ColorChoice::Never
+    } else {
+        cli.color
+    });
+    interactive::set_assume_yes(cli.yes || interactive::assume_yes_from_env());
+    agent::set_refresh_detection(cli.refresh_detect);
+    if let Some(path) = cli.config {
+        config::set_config_path_override(path);
+    }
+    // JSON Pointer of the PRD inside prd.json; empty for the whole document
+    let prd_key = cli.prd_key.unwrap_or_default();
+
+    match cli.command {
+        Some(Commands::Init { force, local }) => {
+            if let Err(e) = commands::init::run_init(force, local, &prd_key) {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::Install) => {
+            if let Err(e) = commands::install::run_install() {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::Run {
+            tool,
+            max_iterations,
+            prd,
+            stream_to,
+            env,
+            env_file,
+            story,
+            epic,
+            until,
+            exclude_story,
+            tag,
+            max_priority,
+            force_archive,
+            ignore_branch_archive,
+            reset_progress,
+            tool_path,
+            resume,
+            max_output,
+            prompt_prefix,
+            prompt_suffix,
+            story_order,
+            seed,
+            sandbox_check,
+            on_error_archive,
+            snapshot_prd,
+            strict_prompt,
+            list_stories,
+            print_prompt,
+            show_progress,
+            verbose_spawn_errors,
+            budget,
+            checkpoint_every,
+            prompt_history,
+            allow_empty_criteria,
+            parallel,
+            readonly,
+            init_if_missing,
+            project_name,
+            project_description,
+            compact,
+        }) => {
+            let options = commands::run::RunOptions {
+                tool,
+                max_iterations,
+                prd_path: prd,
+                prd_key,
+                stream_to,
+                env,
+                env_file,
+                story,
+                epic,
+                until,
+                exclude_story,
+                tag,
+                max_priority,
+                force_archive,
+                ignore_branch_archive,
+                reset_progress,
+                tool_path,
+                resume,
+                max_output,
+                prompt_prefix,
+                prompt_suffix,
+                story_order,
+                seed,
+                sandbox_check,
+                on_error_archive,
+                snapshot_prd,
+                strict_prompt,
+                list_stories,
+                print_prompt,
+                show_progress,
+                verbose_spawn_errors,
+                budget,
+                checkpoint_every,
+                prompt_history,
+                allow_empty_criteria,
+                parallel,
+                readonly,
+                init_if_missing,
+                project_name,
+                project_description,
+                compact,
+            };
+            let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+            match rt.block_on(commands::run::run_run(options)) {
+                Ok(outcome) if outcome.exit_code() != 0 => std::process::exit(outcome.exit_code()),
+                Ok(_) => {}
+                Err(e) => {
+                    exit_with_error(&e);
+                }
+            }
+        }
+        Some(Commands::Config { command, get, set }) => {
+            let result = match command {
+                None => commands::config::run_config(get, set),
+                Some(ConfigCommands::Export { out }) => {
+                    commands::config::run_config_export(out.as_deref())
+                }
+                Some(ConfigCommands::Import { file, replace, .. }) => {
+                    commands::config::run_config_import(&file, replace)
+                }
+            };
+            if let Err(e) = result {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::Status {
+            story,
+            acceptance,
+            prd,
+            format,
+            json,
+            epic,
+            tag,
+            max_priority,
+            exclude_story,
+        }) => {
+            let format = if json { OutputFormat::Json } else { format };
+            let selector = selector::StorySelector {
+                story: None,
+                epic,
+                tags: tag,
+                max_priority,
+                excluded: exclude_story,
+            };
+            let result = if acceptance || story.is_some() {
+                commands::status::run_acceptance(&prd, &prd_key, format, &selector, story.as_deref())
+            } else {
+                commands::status::run_status(&prd, &prd_key, format, &selector)
+            };
+            if let Err(e) = result {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::Search {
+            query,
+            prd,
+            archives,
+            format,
+            json,
+        }) => {
+            let format = if json { OutputFormat::Json } else { format };
+            if let Err(e) = commands::search::run_search(&query, &prd, &prd_key, archives, format) {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::Archive { command, ralph_dir }) => {
+            let result = match command {
+                None => commands::archive::run_archive_list(&ralph_dir),
+                Some(ArchiveCommands::Clean { keep, older_than }) => {
+                    commands::archive::run_archive_clean(&ralph_dir, keep, older_than)
+                }
+                Some(ArchiveCommands::Export { name, out }) => {
+                    commands::archive::run_archive_export(&ralph_dir, &name, &out)
+                }
+                Some(ArchiveCommands::Import { bundle, force }) => {
+                    commands::archive::run_archive_import(&ralph_dir, &bundle, force)
+                }
+            };
+            if let Err(e) = result {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::Detect {
+            check,
+            refresh,
+            all,
+            format,
+        }) => {
+            let result = match check {
+                Some(tool) => commands::detect::run_detect_check(&tool),
+                None => commands::detect::run_detect(refresh, all, format),
+            };
+            if let Err(e) = result {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::Doctor) => {
+            if let Err(e) = commands::doctor::run_doctor(&prd_key) {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::Prd { command }) => {
+            let result = match command {
+                PrdCommands::Validate { prd, fix } => commands::prd::run_prd_validate(&prd, &prd_key, fix),
+                PrdCommands::CheckLinks {
+                    prd,
+                    work_dir,
+                    network,
+                    strict,
+                } => commands::prd::run_prd_check_links(&prd, &prd_key, &work_dir, network, strict),
+                PrdCommands::RenumberIds { prd, dry_run } => {
+                    commands::prd::run_prd_renumber_ids(&prd, &prd_key, dry_run)
+                }
+                PrdCommands::SetBranch { name, prd } => {
+                    commands::prd::run_prd_set_branch(&name, &prd, &prd_key)
+                }
+                PrdCommands::Move { id, priority, prd } => {
+                    commands::prd::run_prd_move(&id, priority, &prd, &prd_key)
+                }
+                PrdCommands::Split {
+                    tag,
+                    story,
+                    out,
+                    branch,
+                    prd,
+                } => commands::prd::run_prd_split(&prd, &prd_key, &tag, &story, &out, branch.as_deref()),
+                PrdCommands::AddCriteria { id, criteria, prd } => {
+                    commands::prd::run_prd_add_criteria(&id, &criteria, &prd, &prd_key)
+                }
+                PrdCommands::ScanSecrets { prd, redact } => {
+                    commands::prd::run_prd_scan_secrets(&prd, &prd_key, redact)
+                }
+                PrdCommands::Fake {
+                    stories,
+                    seed,
+                    completed,
+                    dependencies,
+                    epics,
+                    out,
+                } => {
+                    let options = fake_prd::FakePrdOptions {
+                        stories,
+                        seed,
+                        completed,
+                        dependencies,
+                        epics,
+                    };
+                    commands::prd::run_prd_fake(&options, out.as_deref())
+                }
+                PrdCommands::Stats { prd, format, json } => {
+                    let format = if json { OutputFormat::Json } else { format };
+                    commands::prd::run_prd_stats(&prd, &prd_key, format)
+                }
+            };
+            if let Err(e) = result {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::Story { command }) => {
+            let result = match command {
+                StoryCommands::List { prd, format } => {
+                    commands::story::run_story_list(&prd, &prd_key, format)
+                }
+                StoryCommands::Rm { id, prd, force } => {
+                    commands::story::run_story_rm(&id, &prd, &prd_key, force)
+                }
+                StoryCommands::Note {
+                    id,
+                    text,
+                    prd,
+                    replace,
+                } => commands::story::run_story_note(&id, &text, &prd, &prd_key, replace),
+                StoryCommands::Check { id, number, prd } => {
+                    commands::story::run_story_check(&id, number as usize, &prd, &prd_key)
+                }
+                StoryCommands::Edit {
+                    id,
+                    field,
+                    value,
+                    prd,
+                } => commands::story::run_story_edit(&id, field.zip(value), &prd, &prd_key),
+            };
+            if let Err(e) = result {
+                exit_with_error(&e);
+            }
+        }
+        Some(Commands::AgentsMd { command, prd, path }) => {
+            let result = match command {
+                AgentsMdCommands::Generate => {
+                    commands::agents_md::run_agents_md_generate(&prd, &prd_key, &path)
+                }
+                AgentsMdCommands::Refresh => {
+                    commands::agents_md::run_agents_md_refresh(&prd, &prd_key, &path)
+                }
+            };
+            if let Err(e) = result {
+                exit_with_error(&e);
+            }
+        }
+        None => {
+            // When no subcommand is provided, clap will show help due to the derive macro
+        }
+    }
+}
+
+/// Print a command's error and exit with its exit code
+///
+/// Ctrl+C at a prompt is not a failure, so it is reported without the
+/// "Error:" prefix, after showing the cursor the prompt had hidden.
+fn exit_with_error(e: &error::RalphError) -> ! {
+    match e {
+        error::RalphError::Interrupted => {
+            let _ = console::Term::stderr().show_cursor();
+            eprintln!("{}", style(e).yellow());
+        }
+        _ => eprintln!("{} {}", style("Error:").red().bold(), e),
+    }
+    std::process::exit(e.exit_code());
+}
+
+#[cfg(test)]
+mod tests {
+    mod agent_detection_tests;
+    mod agents_md_tests;
+    mod archive_tests;
+    mod branch_change_tests;
+    mod config_management_tests;
+    mod dotenv_tests;
+    mod error_handling_tests;
+    mod event_stream_tests;
+    mod fake_prd_tests;
+    mod fixtures;
+    mod humanize_tests;
+    mod lane_planning_tests;
+    mod link_check_tests;
+    mod metadata_tests;
+    mod migration_tests;
+    mod output_buffer_tests;
+    mod persistence_tests;
+    mod prd_parsing_tests;
+    mod project_init_tests;
+    mod report_tests;
+    mod sandbox_check_tests;
+    mod search_tests;
+    mod selector_tests;
+    mod skill_install_tests;
+    mod secret_scan_tests;
+    mod status_tests;
+    mod story_edit_tests;
+    mod task_execution_tests;
+    mod usage_tests;
+}
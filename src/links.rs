@@ -0,0 +1,168 @@
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crate::prd::UserStory;
+
+/// http(s) URLs, stopping at whitespace, quotes, brackets and markdown delimiters
+static URL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"https?://[^\s<>()\[\]{}"'`]+"#).unwrap());
+
+/// Inline code spans, which often hold paths
+static CODE_SPAN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`([^`\s]+)`").unwrap());
+
+/// Bare relative paths: `./x`, `../x`, or `dir/file.ext`
+static PATH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[\s(\[:,])((?:\.{1,2}/[\w./-]+)|(?:[\w-]+/)+[\w.-]*\w\.[A-Za-z0-9]+)").unwrap()
+});
+
+/// Extensions that mark a bare word as a file name
+const KNOWN_EXTENSIONS: &[&str] = &[
+    "rs", "md", "toml", "json", "jsonl", "yaml", "yml", "ts", "tsx", "js", "jsx", "py", "go",
+    "java", "kt", "rb", "sh", "css", "scss", "html", "sql", "txt", "lock",
+];
+
+/// Bare file names with a well-known extension, e.g. `README.md`
+static FILE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?:^|[\s(\[:,])([\w-]+\.(?:{}))\b",
+        KNOWN_EXTENSIONS.join("|")
+    ))
+    .unwrap()
+});
+
+/// A file path or URL referenced in a story
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    Path(String),
+    Url(String),
+}
+
+impl Reference {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Reference::Path(p) | Reference::Url(p) => p,
+        }
+    }
+}
+
+/// A reference that failed its check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenReference {
+    pub reference: Reference,
+    pub reason: String,
+}
+
+/// Extract the URLs from a piece of text
+pub fn extract_urls(text: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    for m in URL_RE.find_iter(text) {
+        let url = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', '*', '_']);
+        push_unique(&mut urls, url);
+    }
+    urls
+}
+
+/// Extract path-like tokens from a piece of text, ignoring URLs
+pub fn extract_paths(text: &str) -> Vec<String> {
+    let without_urls = URL_RE.replace_all(text, " ");
+    let mut paths = Vec::new();
+
+    for caps in CODE_SPAN_RE.captures_iter(&without_urls) {
+        let span = &caps[1];
+        if looks_like_path(span) {
+            push_unique(&mut paths, span);
+        }
+    }
+
+    let without_code = CODE_SPAN_RE.replace_all(&without_urls, " ");
+    for re in [&*PATH_RE, &*FILE_RE] {
+        for caps in re.captures_iter(&without_code) {
+            let path = caps[1].trim_end_matches(['.', ',', ';', ':']);
+            push_unique(&mut paths, path);
+        }
+    }
+    paths
+}
+
+/// Extract every reference from a story's text fields
+pub fn story_references(story: &UserStory) -> Vec<Reference> {
+    let mut texts = vec![story.title.as_str(), story.description.as_str(), story.notes.as_str()];
+    texts.extend(story.acceptance_criteria.iter().map(String::as_str));
+
+    let mut refs = Vec::new();
+    for text in texts {
+        for url in extract_urls(text) {
+            let reference = Reference::Url(url);
+            if !refs.contains(&reference) {
+                refs.push(reference);
+            }
+        }
+        for path in extract_paths(text) {
+            let reference = Reference::Path(path);
+            if !refs.contains(&reference) {
+                refs.push(reference);
+            }
+        }
+    }
+    refs
+}
+
+/// Check a story's references, returning the broken ones
+///
+/// Paths are resolved against `work_dir`. URLs are only checked when
+/// `network` is set, using a HEAD request with the given timeout.
+pub fn check_story_references(
+    story: &UserStory,
+    work_dir: &Path,
+    network: bool,
+    timeout: Duration,
+) -> Vec<BrokenReference> {
+    story_references(story)
+        .into_iter()
+        .filter_map(|reference| {
+            let reason = match &reference {
+                Reference::Path(path) => {
+                    (!work_dir.join(path).exists()).then(|| "not found".to_string())
+                }
+                Reference::Url(url) if network => check_url(url, timeout).err(),
+                Reference::Url(_) => None,
+            };
+            reason.map(|reason| BrokenReference { reference, reason })
+        })
+        .collect()
+}
+
+/// Send a HEAD request and report a failure reason for non-success responses
+fn check_url(url: &str, timeout: Duration) -> Result<(), String> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .http_status_as_error(false)
+        .build()
+        .into();
+
+    match agent.head(url).call() {
+        Ok(response) if response.status().is_success() || response.status().is_redirection() => {
+            Ok(())
+        }
+        Ok(response) => Err(format!("HTTP {}", response.status().as_u16())),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Whether an inline code span looks like a file path rather than code
+fn looks_like_path(span: &str) -> bool {
+    let has_separator = span.contains('/');
+    let has_extension = span
+        .rsplit_once('.')
+        .is_some_and(|(stem, ext)| !stem.is_empty() && KNOWN_EXTENSIONS.contains(&ext));
+    let code_like = span.contains(['(', ')', '=', '<', '>', '{', '}', '$', '*', '|', ';']);
+    (has_separator || has_extension) && !code_like && !span.starts_with("--")
+}
+
+fn push_unique(items: &mut Vec<String>, item: &str) {
+    if !item.is_empty() && !items.iter().any(|i| i == item) {
+        items.push(item.to_string());
+    }
+}
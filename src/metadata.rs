@@ -0,0 +1,235 @@
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::agent::command_version_in;
+use crate::prd::Prd;
+use crate::usage::Usage;
+
+/// File in the ralph directory describing the most recent run
+pub const LAST_RUN_FILE: &str = "last-run.json";
+
+/// File in the ralph directory tracking a run that has not finished
+pub const RUN_STATE_FILE: &str = ".run-state.json";
+
+/// File written into each archive folder
+pub const ARCHIVE_METADATA_FILE: &str = "metadata.json";
+
+/// Versions of ralph and the agent tool that produced a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    pub ralph_version: String,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_version: Option<String>,
+}
+
+impl VersionInfo {
+    /// Capture the running ralph version and the `--version` of the tool, run inside `dir`
+    pub fn capture(tool_cmd: &str, dir: &Path) -> Self {
+        Self {
+            ralph_version: env!("CARGO_PKG_VERSION").to_string(),
+            tool: tool_cmd.to_string(),
+            tool_version: command_version_in(tool_cmd, dir),
+        }
+    }
+
+    /// One-line summary, e.g. `ralph 0.1.0, claude 2.0.1 (Claude Code)`
+    pub fn summary(&self) -> String {
+        format!(
+            "ralph {}, {} {}",
+            self.ralph_version,
+            self.tool,
+            self.tool_version.as_deref().unwrap_or("unknown")
+        )
+    }
+}
+
+/// How a run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Complete,
+    Interrupted,
+    MaxIterations,
+    /// `--budget` was used up
+    BudgetExceeded,
+    /// Stories are pending, but none match `--tag`/`--max-priority` and the other filters
+    NoMatchingStories,
+    /// Legacy files were moved into `ralph/`; the command has to be run again
+    Migrated,
+}
+
+impl RunOutcome {
+    /// Why the run ended, as written to the progress log
+    pub fn reason(&self) -> &'static str {
+        match self {
+            RunOutcome::Complete => "complete",
+            RunOutcome::Interrupted => "interrupted by user",
+            RunOutcome::MaxIterations => "maximum iterations reached",
+            RunOutcome::BudgetExceeded => "budget exceeded",
+            RunOutcome::NoMatchingStories => "no pending stories match the filters",
+            RunOutcome::Migrated => "legacy files migrated",
+        }
+    }
+
+    /// Exit status of `ralph run` for this outcome
+    ///
+    /// Only a run stopped by its budget (3) or left with nothing its filters
+    /// match (4) exits non-zero, so scripts can tell them apart from both
+    /// success and errors (1).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunOutcome::BudgetExceeded => 3,
+            RunOutcome::NoMatchingStories => 4,
+            _ => 0,
+        }
+    }
+}
+
+/// Record of the most recent run, stored as `last-run.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    #[serde(flatten)]
+    pub versions: VersionInfo,
+    pub branch: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub iterations: u32,
+    pub completed_stories: usize,
+    pub total_stories: usize,
+    pub outcome: RunOutcome,
+    /// Iterations stopped for printing more than the output limit
+    #[serde(default)]
+    pub output_limit_hits: u32,
+    /// Usage reported by the agent, when the run had a `--budget`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// branchName the agent changed the PRD to during the run; `branch` is the original
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_changed_to: Option<String>,
+}
+
+impl RunRecord {
+    /// Load the last run record from the ralph directory, if present and readable
+    pub fn load(ralph_dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(ralph_dir.join(LAST_RUN_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Save the record as the ralph directory's last run
+    pub fn save(&self, ralph_dir: &Path) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(ralph_dir.join(LAST_RUN_FILE), content)
+    }
+}
+
+/// Progress of an unfinished run, stored as `.run-state.json`
+///
+/// Written after every iteration and removed when the run ends normally, so
+/// a file left behind means the run was interrupted and can be resumed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunState {
+    pub project: String,
+    pub branch: String,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_story: Option<String>,
+    pub started_at: String,
+    pub updated_at: String,
+    pub iterations_used: u32,
+    pub max_iterations: u32,
+}
+
+impl RunState {
+    /// Load the run state from the ralph directory, if present and readable
+    pub fn load(ralph_dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(ralph_dir.join(RUN_STATE_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Save the state into the ralph directory
+    pub fn save(&self, ralph_dir: &Path) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(ralph_dir.join(RUN_STATE_FILE), content)
+    }
+
+    /// Remove the state file; a missing file is not an error
+    pub fn clear(ralph_dir: &Path) -> io::Result<()> {
+        match fs::remove_file(ralph_dir.join(RUN_STATE_FILE)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Iterations left in the run's budget
+    pub fn remaining(&self) -> u32 {
+        self.max_iterations.saturating_sub(self.iterations_used)
+    }
+
+    /// Whether the state was updated no longer than `max_age` before `now`
+    pub fn is_recent(&self, now: NaiveDateTime, max_age: TimeDelta) -> bool {
+        NaiveDateTime::parse_from_str(&self.updated_at, TIMESTAMP_FORMAT)
+            .is_ok_and(|updated| now - updated <= max_age)
+    }
+
+    /// Explain why this state belongs to a different PRD, if it does
+    pub fn mismatch(&self, prd: &Prd) -> Option<String> {
+        if self.branch != prd.branch_name {
+            Some(format!("it was written for branch {}", self.branch))
+        } else if self.project != prd.project {
+            Some(format!("it was written for project {}", self.project))
+        } else {
+            None
+        }
+    }
+}
+
+/// Metadata stored with an archived run, as `metadata.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveMetadata {
+    pub branch: String,
+    pub archived_at: String,
+    pub ralph_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<RunRecord>,
+    /// What detected the branch change that caused the archive
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub branch_change: Vec<String>,
+}
+
+impl ArchiveMetadata {
+    /// Describe an archive of `branch`, taking versions from its last run
+    pub fn new(branch: &str, last_run: Option<RunRecord>) -> Self {
+        Self {
+            branch: branch.to_string(),
+            archived_at: timestamp(),
+            ralph_version: env!("CARGO_PKG_VERSION").to_string(),
+            last_run,
+            branch_change: Vec::new(),
+        }
+    }
+
+    /// Write the metadata into an archive folder
+    pub fn save(&self, archive_dir: &Path) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(archive_dir.join(ARCHIVE_METADATA_FILE), content)
+    }
+}
+
+/// Current local time in the format used across ralph's files
+pub fn timestamp() -> String {
+    Local::now().format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// Format of [`timestamp`]
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
@@ -0,0 +1,220 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::workspace::RALPH_DIR_NAME;
+
+/// Legacy files that used to live in the project root
+const LEGACY_PRD: &str = "prd.json";
+const LEGACY_PROGRESS: &str = "progress.txt";
+const LEGACY_ARCHIVE: &str = "archive";
+
+/// What a migration step does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepAction {
+    /// Move the legacy file or folder to its new location
+    Move,
+    /// The new location already holds identical content; delete the legacy copy
+    RemoveDuplicate,
+    /// Delete the legacy folder once everything in it has moved
+    RemoveEmptyDir,
+}
+
+/// One step of moving a legacy layout into `ralph/`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStep {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub action: StepAction,
+}
+
+impl MigrationStep {
+    /// Describe the step with paths relative to the project root
+    pub fn describe(&self, root: &Path) -> String {
+        let relative = |p: &Path| p.strip_prefix(root).unwrap_or(p).display().to_string();
+        match self.action {
+            StepAction::Move => format!("{} → {}", relative(&self.from), relative(&self.to)),
+            StepAction::RemoveDuplicate => format!(
+                "remove {} (identical to {})",
+                relative(&self.from),
+                relative(&self.to)
+            ),
+            StepAction::RemoveEmptyDir => format!("remove empty {}/", relative(&self.from)),
+        }
+    }
+}
+
+/// A legacy path whose destination already exists with different content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationConflict {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Where a migration stopped
+#[derive(Debug)]
+pub struct MigrationFailure {
+    /// Number of steps that completed before the failing one
+    pub completed: usize,
+    pub error: io::Error,
+}
+
+/// Moves from the legacy root layout into `ralph/`, computed up front
+///
+/// Archive entries move first and `prd.json` moves last, so a migration that
+/// stops early still looks unmigrated and the next run picks up where it
+/// left off. Content that already reached its destination is de-duplicated
+/// rather than copied again.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationPlan {
+    pub steps: Vec<MigrationStep>,
+    pub conflicts: Vec<MigrationConflict>,
+}
+
+impl MigrationPlan {
+    /// Whether the project root still has a legacy `prd.json`
+    pub fn is_needed(root: &Path) -> bool {
+        root.join(LEGACY_PRD).is_file()
+    }
+
+    /// Plan the migration of `root` without changing anything
+    pub fn compute(root: &Path) -> io::Result<Self> {
+        let new_dir = root.join(RALPH_DIR_NAME);
+        let mut plan = Self::default();
+
+        let legacy_archive = root.join(LEGACY_ARCHIVE);
+        if legacy_archive.is_dir() {
+            let mut entries = fs::read_dir(&legacy_archive)?
+                .map(|entry| entry.map(|e| e.file_name()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            for name in entries {
+                let to = new_dir.join(LEGACY_ARCHIVE).join(&name);
+                plan.add(legacy_archive.join(&name), to)?;
+            }
+            plan.steps.push(MigrationStep {
+                from: legacy_archive,
+                to: new_dir.join(LEGACY_ARCHIVE),
+                action: StepAction::RemoveEmptyDir,
+            });
+        }
+
+        for name in [LEGACY_PROGRESS, LEGACY_PRD] {
+            let from = root.join(name);
+            if from.exists() {
+                plan.add(from, new_dir.join(name))?;
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Add a move, a de-duplication, or a conflict for one legacy path
+    fn add(&mut self, from: PathBuf, to: PathBuf) -> io::Result<()> {
+        let action = if !to.exists() {
+            StepAction::Move
+        } else if same_content(&from, &to)? {
+            StepAction::RemoveDuplicate
+        } else {
+            self.conflicts.push(MigrationConflict { from, to });
+            return Ok(());
+        };
+        self.steps.push(MigrationStep { from, to, action });
+        Ok(())
+    }
+
+    /// Run the steps in order, stopping at the first failure
+    ///
+    /// Call only when there are no conflicts.
+    pub fn execute(&self) -> Result<(), MigrationFailure> {
+        for (completed, step) in self.steps.iter().enumerate() {
+            run_step(step).map_err(|error| MigrationFailure { completed, error })?;
+        }
+        Ok(())
+    }
+}
+
+fn run_step(step: &MigrationStep) -> io::Result<()> {
+    match step.action {
+        StepAction::Move => move_path(&step.from, &step.to),
+        StepAction::RemoveDuplicate => remove_path(&step.from),
+        StepAction::RemoveEmptyDir => fs::remove_dir(&step.from),
+    }
+}
+
+/// Move a file or folder, copying and verifying when a rename is not possible
+///
+/// Renames fail across filesystems; the copy is then checked against the
+/// source before the source is deleted, and removed again if anything fails.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    let copied = copy_path(from, to).and_then(|()| {
+        if same_content(from, to)? {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("copy of {} does not match the original", from.display()),
+            ))
+        }
+    });
+    if let Err(e) = copied {
+        let _ = remove_path(to);
+        return Err(e);
+    }
+    remove_path(from)
+}
+
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Whether two files, or two folders recursively, hold the same content
+pub fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    match (a.is_dir(), b.is_dir()) {
+        (false, false) => Ok(fs::read(a)? == fs::read(b)?),
+        (true, true) => {
+            let names = |dir: &Path| -> io::Result<Vec<_>> {
+                let mut names = fs::read_dir(dir)?
+                    .map(|entry| entry.map(|e| e.file_name()))
+                    .collect::<io::Result<Vec<_>>>()?;
+                names.sort();
+                Ok(names)
+            };
+            let a_names = names(a)?;
+            if a_names != names(b)? {
+                return Ok(false);
+            }
+            for name in a_names {
+                if !same_content(&a.join(&name), &b.join(&name))? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
@@ -0,0 +1,337 @@
+use regex::Regex;
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// Printed by the agent once every story passes
+pub const COMPLETE_MARKER: &str = "<promise>COMPLETE</promise>";
+
+/// Completion markers that force a flush so the user sees them immediately
+const FLUSH_MARKERS: &[&str] = &[COMPLETE_MARKER, "<promise>STORY_PASSED:"];
+
+/// Token formats redacted when `redact_patterns` is not configured
+pub const DEFAULT_REDACT_PATTERNS: &[&str] = &[
+    r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b",
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}",
+    r"\bgithub_pat_[A-Za-z0-9_]{22,}",
+    r"\bglpat-[A-Za-z0-9_-]{20,}",
+    r"\bxox[abprs]-[A-Za-z0-9-]{10,}",
+    r"\bsk-[A-Za-z0-9_-]{20,}",
+    r"(?i)\bbearer\s+[A-Za-z0-9._~+/-]{16,}=*",
+];
+
+/// Text that replaces each redacted match
+pub const REDACTED: &str = "***";
+
+/// Masks tokens in agent output before it leaves the process
+#[derive(Debug, Clone, Default)]
+pub struct OutputRedactor {
+    patterns: Vec<Regex>,
+}
+
+impl OutputRedactor {
+    /// Compile the given patterns, naming the first invalid one on error
+    pub fn new<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let patterns = patterns
+            .into_iter()
+            .map(|p| Regex::new(p).map_err(|e| format!("invalid pattern `{}`: {}", p, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Build from the `redact_patterns` config value
+    ///
+    /// The value is a whitespace-separated list of regexes (use `\s` for a
+    /// space inside one); unset means the defaults, and an empty value turns
+    /// redaction off.
+    pub fn from_config(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            Some(value) => Self::new(value.split_whitespace()),
+            None => Self::new(DEFAULT_REDACT_PATTERNS.iter().copied()),
+        }
+    }
+
+    /// Replace every match with `***`, borrowing the line when nothing matched
+    pub fn redact<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let mut line = Cow::Borrowed(line);
+        for pattern in &self.patterns {
+            if let Cow::Owned(redacted) = pattern.replace_all(&line, REDACTED) {
+                line = Cow::Owned(redacted);
+            }
+        }
+        line
+    }
+}
+
+/// Batches agent output lines and writes them out periodically
+///
+/// Chatty agents can print thousands of lines per second; writing each one
+/// through a locked, line-flushed stdout is slow. Lines are collected here and
+/// written in one go once `interval` has elapsed, when a completion marker
+/// arrives, or when [`OutputBuffer::flush`] is called. A zero interval writes
+/// every line straight away.
+pub struct OutputBuffer<W: Write> {
+    writer: W,
+    pending: Vec<u8>,
+    interval: Duration,
+    last_flush: Instant,
+}
+
+impl OutputBuffer<io::Stdout> {
+    /// Buffer output destined for stdout
+    pub fn stdout(interval: Duration) -> Self {
+        Self::new(io::stdout(), interval)
+    }
+}
+
+impl<W: Write> OutputBuffer<W> {
+    pub fn new(writer: W, interval: Duration) -> Self {
+        Self {
+            writer,
+            pending: Vec::new(),
+            interval,
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Queue a line, flushing if the interval elapsed or it holds a marker
+    pub fn push_line(&mut self, line: &str) -> io::Result<()> {
+        self.pending.extend_from_slice(line.as_bytes());
+        self.pending.push(b'\n');
+
+        if FLUSH_MARKERS.iter().any(|m| line.contains(m)) {
+            self.flush()
+        } else {
+            self.flush_if_due()
+        }
+    }
+
+    /// Flush when the interval has elapsed since the last flush
+    pub fn flush_if_due(&mut self) -> io::Result<()> {
+        if self.last_flush.elapsed() >= self.interval {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Write out everything that is buffered
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.writer.write_all(&self.pending)?;
+        self.pending.clear();
+        self.writer.flush()
+    }
+
+    /// Whether any lines are waiting to be written
+    #[cfg(test)]
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Get the underlying writer
+    #[cfg(test)]
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+}
+
+/// Running total of the bytes an agent printed, checked against a limit
+#[derive(Debug, Clone, Copy)]
+pub struct OutputLimit {
+    limit: u64,
+    bytes: u64,
+}
+
+impl OutputLimit {
+    /// Allow up to `limit` bytes; zero means no limit
+    pub fn new(limit: u64) -> Self {
+        Self { limit, bytes: 0 }
+    }
+
+    /// Count a line and its newline
+    pub fn add_line(&mut self, line: &str) {
+        self.bytes += line.len() as u64 + 1;
+    }
+
+    /// Whether more than the allowed bytes have been counted
+    pub fn is_exceeded(&self) -> bool {
+        self.limit > 0 && self.bytes > self.limit
+    }
+
+    /// Bytes counted so far
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+/// Follows the lines appended to a file, like `tail -f`
+///
+/// Each poll checks the file's size and reads only what was added since the
+/// last one. The file is opened just for that read and closed again, so the
+/// agent appending to it is never blocked (std opens files with full sharing
+/// on Windows). A file that shrank was rewritten and is followed from its new
+/// end.
+#[derive(Debug)]
+pub struct FileTail {
+    path: PathBuf,
+    offset: u64,
+    /// Bytes after the last newline, waiting for the rest of their line
+    partial: Vec<u8>,
+}
+
+impl FileTail {
+    /// Follow a file from its current end, or from the start once it is created
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let offset = fs::metadata(&path).map_or(0, |m| m.len());
+        Self {
+            path,
+            offset,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Complete lines appended since the last poll, without their newlines
+    pub fn poll(&mut self) -> io::Result<Vec<String>> {
+        let len = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        if len < self.offset {
+            self.offset = len;
+            self.partial.clear();
+        }
+        if len == self.offset {
+            return Ok(Vec::new());
+        }
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let read = file.take(len - self.offset).read_to_end(&mut self.partial)?;
+        self.offset += read as u64;
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            lines.push(line.trim_end_matches('\r').to_string());
+        }
+        Ok(lines)
+    }
+}
+
+/// A line of agent output, decoded even when it is not valid UTF-8
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedLine {
+    /// The line without its line ending; invalid bytes become U+FFFD
+    pub text: String,
+    /// Some bytes were invalid UTF-8 and were replaced
+    pub lossy: bool,
+}
+
+/// Splits an agent stream into lines without failing on invalid UTF-8
+///
+/// `AsyncBufReadExt::lines` errors on the first invalid byte, which used to
+/// end the stream and with it the iteration. Progress bars cut mid escape
+/// sequence and latin-1 log lines are decoded lossily here instead. Like
+/// `next_line`, [`LossyLines::next_line`] is cancel safe: a partly read line
+/// is kept for the next call.
+pub struct LossyLines<R> {
+    reader: R,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncBufRead + Unpin> LossyLines<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+        }
+    }
+
+    /// The next line, or `None` once the stream has ended
+    ///
+    /// A last line without a trailing newline is still returned.
+    pub async fn next_line(&mut self) -> io::Result<Option<DecodedLine>> {
+        self.reader.read_until(b'\n', &mut self.buf).await?;
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+        let mut line = std::mem::take(&mut self.buf);
+        if line.last() == Some(&b'\n') {
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(match String::from_utf8(line) {
+            Ok(text) => DecodedLine { text, lossy: false },
+            Err(e) => DecodedLine {
+                text: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                lossy: true,
+            },
+        }))
+    }
+}
+
+/// Looks for a marker across the lines of a stream
+///
+/// Checking each line on its own misses a marker that a line break splits,
+/// as when an agent wraps long output or streams it in pieces. The end of
+/// the previous output is kept and checked together with the next line.
+#[derive(Debug, Clone)]
+pub struct MarkerScanner {
+    marker: &'static str,
+    /// The last `marker.len() - 1` bytes seen, at most
+    tail: String,
+    found: bool,
+}
+
+impl MarkerScanner {
+    pub fn new(marker: &'static str) -> Self {
+        Self {
+            marker,
+            tail: String::new(),
+            found: false,
+        }
+    }
+
+    /// Scan the next line, returning whether the marker has been seen so far
+    pub fn push(&mut self, line: &str) -> bool {
+        if self.found {
+            return true;
+        }
+        let mut window = std::mem::take(&mut self.tail);
+        window.push_str(line);
+        if window.contains(self.marker) {
+            self.found = true;
+            return true;
+        }
+        let mut start = window.len().saturating_sub(self.marker.len() - 1);
+        while !window.is_char_boundary(start) {
+            start += 1;
+        }
+        self.tail = window.split_off(start);
+        false
+    }
+
+    /// Whether the marker has been seen
+    pub fn found(&self) -> bool {
+        self.found
+    }
+}
+
+impl<W: Write> Drop for OutputBuffer<W> {
+    fn drop(&mut self) {
+        // Never lose output on an early return; there is nowhere to report errors
+        let _ = self.flush();
+    }
+}
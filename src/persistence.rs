@@ -0,0 +1,87 @@
+use colored::Colorize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{RalphError, RalphResult};
+
+/// The single path for the state `ralph run` keeps between runs
+///
+/// progress.txt, prd.json write-backs, `.last-branch`, run state, snapshots
+/// and archives are all written through [`RunStore::write`], so read-only
+/// mode (`--readonly`) covers every one of them. With `readonly_ok`, the
+/// first write that fails switches the run to read-only instead of ending
+/// it, for checkouts mounted read-only.
+#[derive(Debug, Clone, Default)]
+pub struct RunStore {
+    inner: Arc<StoreState>,
+}
+
+#[derive(Debug, Default)]
+struct StoreState {
+    readonly: AtomicBool,
+    readonly_ok: bool,
+    /// What was not written, in order, without repeats
+    skipped: Mutex<Vec<&'static str>>,
+}
+
+impl RunStore {
+    pub fn new(readonly: bool, readonly_ok: bool) -> Self {
+        Self {
+            inner: Arc::new(StoreState {
+                readonly: AtomicBool::new(readonly),
+                readonly_ok,
+                skipped: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Whether state writes are skipped, from the start or since one failed
+    pub fn is_readonly(&self) -> bool {
+        self.inner.readonly.load(Ordering::SeqCst)
+    }
+
+    /// Write `what` with `write`, unless the run is read-only
+    ///
+    /// Returns `Ok(None)` when the write was skipped. With `readonly_ok`, an
+    /// I/O error is reported as a warning and turns read-only mode on.
+    pub fn write<T>(
+        &self,
+        what: &'static str,
+        write: impl FnOnce() -> RalphResult<T>,
+    ) -> RalphResult<Option<T>> {
+        if self.is_readonly() {
+            self.skip(what);
+            return Ok(None);
+        }
+        match write() {
+            Ok(value) => Ok(Some(value)),
+            Err(RalphError::Io(e)) if self.inner.readonly_ok => {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Warning: could not write {}: {}. Continuing read-only (readonly_ok is set); \
+                         no further state is saved this run.",
+                        what, e
+                    )
+                    .yellow()
+                );
+                self.inner.readonly.store(true, Ordering::SeqCst);
+                self.skip(what);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// What read-only mode kept from being written so far
+    pub fn skipped(&self) -> Vec<&'static str> {
+        self.inner.skipped.lock().unwrap().clone()
+    }
+
+    fn skip(&self, what: &'static str) {
+        let mut skipped = self.inner.skipped.lock().unwrap();
+        if !skipped.contains(&what) {
+            skipped.push(what);
+        }
+    }
+}
@@ -1,8 +1,86 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::path::Path;
 
+/// Prefix every PRD branch name is expected to carry
+pub const BRANCH_PREFIX: &str = "ralph/";
+
+/// Check that a `--prd-key` value is a JSON Pointer (RFC 6901), e.g. `/ralph`
+pub fn parse_json_pointer(pointer: &str) -> Result<String, String> {
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return Err(format!("\"{}\" is not a JSON Pointer; it must start with /", pointer));
+    }
+    let bytes = pointer.as_bytes();
+    let bad_escape = pointer
+        .match_indices('~')
+        .any(|(i, _)| !matches!(bytes.get(i + 1), Some(b'0' | b'1')));
+    if bad_escape {
+        return Err(format!(
+            "\"{}\" is not a JSON Pointer; ~ must be followed by 0 or 1",
+            pointer
+        ));
+    }
+    Ok(pointer.to_string())
+}
+
+/// Take the value at a JSON Pointer out of a parsed prd.json
+fn value_at(mut document: serde_json::Value, pointer: &str) -> io::Result<serde_json::Value> {
+    parse_json_pointer(pointer).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    document.pointer_mut(pointer).map(serde_json::Value::take).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no PRD at JSON Pointer {}", pointer),
+        )
+    })
+}
+
+/// Order in which pending stories are worked on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StoryOrder {
+    /// Lowest `priority` number first, keeping PRD order between equal priorities
+    #[default]
+    Priority,
+    /// As written in prd.json
+    File,
+    /// Shuffled with a seed; the same seed always gives the same order
+    Random(u64),
+}
+
+/// Story counts and the next story, computed in a single pass over the PRD
+///
+/// Compute once with [`Prd::stats`] and reuse it until the PRD is reloaded,
+/// rather than calling the counting methods repeatedly on large PRDs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrdStats {
+    pub total: usize,
+    pub completed: usize,
+    pub pending: usize,
+    /// Id of the highest priority pending story, as `highest_priority_pending`
+    pub next_story: Option<String>,
+}
+
+impl PrdStats {
+    /// Share of stories that pass, from 0 to 100 (0 for a PRD without stories)
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.completed as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// The longest chain of pending stories through `dependsOn`, by estimate
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CriticalPath {
+    /// Story ids in the order they can be done, dependencies first
+    pub stories: Vec<String>,
+    /// Sum of the chain's estimates, counting stories without one as 1
+    pub estimate: f64,
+}
+
 /// PRD (Product Requirements Document) structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Prd {
@@ -10,24 +88,130 @@ pub struct Prd {
     #[serde(rename = "branchName")]
     pub branch_name: String,
     pub description: String,
+    /// Declared epics, in display order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub epics: Vec<Epic>,
     #[serde(rename = "userStories")]
     pub user_stories: Vec<UserStory>,
 }
 
+/// A named group of stories, declared in the PRD's `epics` array
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Epic {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+/// Name of the group holding stories without an epic
+pub const UNGROUPED_EPIC: &str = "Ungrouped";
+
+/// Stories of one epic, by priority
+#[derive(Debug, Clone)]
+pub struct EpicGroup<'a> {
+    pub name: &'a str,
+    /// Description from the `epics` array; empty for undeclared epics
+    pub description: &'a str,
+    pub stories: Vec<&'a UserStory>,
+}
+
+impl EpicGroup<'_> {
+    /// Number of stories in the group that pass
+    pub fn completed(&self) -> usize {
+        self.stories.iter().filter(|s| s.passes).count()
+    }
+}
+
 impl Prd {
     /// Load PRD from a JSON file
     pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_file_at(path, "")
+    }
+
+    /// Load the PRD found at a JSON Pointer of a file (`""` is the whole file)
+    pub fn from_file_at<P: AsRef<Path>>(path: P, pointer: &str) -> io::Result<Self> {
         let content = fs::read_to_string(path)?;
-        let prd: Prd = serde_json::from_str(&content)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let prd: Prd = if pointer.is_empty() {
+            serde_json::from_str(&content)
+        } else {
+            let document = serde_json::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            serde_json::from_value(value_at(document, pointer)?)
+        }
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
         Ok(prd)
     }
 
+    /// Load a PRD, filling in story fields that are missing from the file
+    ///
+    /// `notes`, `acceptanceCriteria` and `passes` get empty defaults. Returns
+    /// the PRD and one line per filled field, e.g. `US-002: added missing notes`.
+    pub fn from_file_with_defaults<P: AsRef<Path>>(path: P) -> io::Result<(Self, Vec<String>)> {
+        Self::from_file_with_defaults_at(path, "")
+    }
+
+    /// [`Prd::from_file_with_defaults`] for the PRD at a JSON Pointer of a file
+    pub fn from_file_with_defaults_at<P: AsRef<Path>>(
+        path: P,
+        pointer: &str,
+    ) -> io::Result<(Self, Vec<String>)> {
+        let content = fs::read_to_string(path)?;
+        let document: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut value = value_at(document, pointer)?;
+
+        let mut filled = Vec::new();
+        let stories = value
+            .get_mut("userStories")
+            .and_then(serde_json::Value::as_array_mut);
+        for story in stories.into_iter().flatten() {
+            let Some(fields) = story.as_object_mut() else {
+                continue;
+            };
+            let id = fields
+                .get("id")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or("?")
+                .to_string();
+            let defaults = [
+                ("notes", serde_json::json!("")),
+                ("acceptanceCriteria", serde_json::json!([])),
+                ("passes", serde_json::json!(false)),
+            ];
+            for (field, default) in defaults {
+                if fields.get(field).is_none_or(serde_json::Value::is_null) {
+                    fields.insert(field.to_string(), default);
+                    filled.push(format!("{}: added missing {}", id, field));
+                }
+            }
+        }
+
+        let prd = serde_json::from_value(value)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok((prd, filled))
+    }
+
     /// Get the branch name
     pub fn branch_name(&self) -> &str {
         &self.branch_name
     }
 
+    /// Set the branch name, normalized to `ralph/<name>`
+    ///
+    /// Runs of whitespace become a single hyphen and the `ralph/` prefix is
+    /// added when missing. Returns the new branch name, or an error when
+    /// nothing is left after normalizing.
+    pub fn set_branch(&mut self, name: &str) -> Result<&str, String> {
+        let trimmed = name.trim();
+        let unprefixed = trimmed.strip_prefix(BRANCH_PREFIX).unwrap_or(trimmed);
+        let slug = unprefixed.split_whitespace().collect::<Vec<_>>().join("-");
+        if slug.trim_matches('/').is_empty() {
+            return Err(format!("\"{}\" is not a valid branch name", trimmed));
+        }
+        self.branch_name = format!("{}{}", BRANCH_PREFIX, slug);
+        Ok(&self.branch_name)
+    }
+
     /// Count total user stories
     pub fn total_stories(&self) -> usize {
         self.user_stories.len()
@@ -43,8 +227,26 @@ impl Prd {
         self.user_stories.iter().filter(|s| !s.passes).count()
     }
 
+    /// Compute story counts and the next story in one pass
+    pub fn stats(&self) -> PrdStats {
+        let mut completed = 0;
+        let mut next: Option<&UserStory> = None;
+        for story in &self.user_stories {
+            if story.passes {
+                completed += 1;
+            } else if next.is_none_or(|n| story.priority < n.priority) {
+                next = Some(story);
+            }
+        }
+        PrdStats {
+            total: self.user_stories.len(),
+            completed,
+            pending: self.user_stories.len() - completed,
+            next_story: next.map(|s| s.id.clone()),
+        }
+    }
+
     /// Get the highest priority pending story
-    #[allow(dead_code)]
     pub fn highest_priority_pending(&self) -> Option<&UserStory> {
         self.user_stories
             .iter()
@@ -52,45 +254,951 @@ impl Prd {
             .min_by_key(|s| s.priority)
     }
 
+    /// Get the pending stories in the given order
+    ///
+    /// A random order shuffles every story before dropping the passed ones, so
+    /// the remaining stories keep their relative order as others pass.
+    pub fn pending_ordered(&self, order: StoryOrder) -> Vec<&UserStory> {
+        let mut stories: Vec<&UserStory> = self.user_stories.iter().collect();
+        match order {
+            StoryOrder::Priority => stories.sort_by_key(|s| s.priority),
+            StoryOrder::File => {}
+            StoryOrder::Random(seed) => shuffle(&mut stories, seed),
+        }
+        stories.retain(|s| !s.passes);
+        stories
+    }
+
     /// Update a story's passes field and save back to file
-    #[allow(dead_code)]
-    pub fn mark_story_passed<P: AsRef<Path>>(&mut self, story_id: &str, path: P) -> io::Result<()> {
-        if let Some(story) = self.user_stories.iter_mut().find(|s| s.id == story_id) {
-            story.passes = true;
-            self.save_to_file(path)?;
+    ///
+    /// Returns whether the story was updated; a story that already passes is
+    /// left alone and the file is not rewritten. An unknown id is an
+    /// `InvalidInput` error listing the valid ids.
+    pub fn mark_story_passed<P: AsRef<Path>>(
+        &mut self,
+        story_id: &str,
+        path: P,
+    ) -> io::Result<bool> {
+        self.mark_story_passed_at(story_id, path, "")
+    }
+
+    /// [`Prd::mark_story_passed`] for the PRD at a JSON Pointer of a file
+    pub fn mark_story_passed_at<P: AsRef<Path>>(
+        &mut self,
+        story_id: &str,
+        path: P,
+        pointer: &str,
+    ) -> io::Result<bool> {
+        let Some(story) = self.find_story_mut(story_id) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                self.unknown_story_message(story_id),
+            ));
+        };
+        if story.passes {
+            return Ok(false);
         }
-        Ok(())
+        story.passes = true;
+        self.save_to_file_at(path, pointer)?;
+        Ok(true)
+    }
+
+    /// Error message for a story id that is not in the PRD, listing the valid ids
+    pub fn unknown_story_message(&self, story_id: &str) -> String {
+        let ids: Vec<&str> = self.user_stories.iter().map(|s| s.id.as_str()).collect();
+        if ids.is_empty() {
+            format!("Unknown story id: {} (the PRD has no stories)", story_id)
+        } else {
+            format!("Unknown story id: {} (valid ids: {})", story_id, ids.join(", "))
+        }
+    }
+
+    /// Get pending stories that are too vague to give the agent good results
+    ///
+    /// A story is weak when its description is blank or it has no
+    /// acceptance criteria.
+    pub fn weak_stories(&self) -> Vec<&UserStory> {
+        self.user_stories
+            .iter()
+            .filter(|s| !s.passes && s.is_weak())
+            .collect()
+    }
+
+    /// Check the PRD for structural problems
+    ///
+    /// Returns a list of human-readable problems; an empty list means the PRD
+    /// is valid.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.branch_name.trim().is_empty() {
+            problems.push("branchName is empty".to_string());
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for story in &self.user_stories {
+            if story.id.trim().is_empty() {
+                problems.push(format!("Story \"{}\" has an empty id", story.title));
+            } else if !seen.insert(story.id.as_str()) {
+                problems.push(format!("Duplicate story id: {}", story.id));
+            }
+        }
+
+        for story in &self.user_stories {
+            for dep in &story.depends_on {
+                if dep == &story.id {
+                    problems.push(format!("{} depends on itself", story.id));
+                } else if self.find_story(dep).is_none() {
+                    problems.push(format!("{} depends on unknown story {}", story.id, dep));
+                }
+            }
+        }
+
+        if let Some(cycle) = self.dependency_cycle() {
+            problems.push(format!("Dependency cycle: {}", cycle.join(" → ")));
+        }
+
+        problems
+    }
+
+    /// A `dependsOn` cycle through two or more stories, if there is one
+    ///
+    /// The cycle is listed from its first story back to that story again,
+    /// e.g. `[US-001, US-002, US-001]`. Self-dependencies are left to
+    /// [`Prd::validate`], which reports them on their own.
+    pub fn dependency_cycle(&self) -> Option<Vec<String>> {
+        let mut done = HashSet::new();
+        for story in &self.user_stories {
+            let mut chain = Vec::new();
+            if let Some(cycle) = self.find_cycle(&story.id, &mut chain, &mut done) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    fn find_cycle<'a>(
+        &'a self,
+        id: &'a str,
+        chain: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Option<Vec<String>> {
+        if let Some(start) = chain.iter().position(|&c| c == id) {
+            let mut cycle: Vec<String> = chain[start..].iter().map(|c| c.to_string()).collect();
+            cycle.push(id.to_string());
+            return Some(cycle);
+        }
+        if done.contains(id) {
+            return None;
+        }
+        let story = self.find_story(id)?;
+        chain.push(id);
+        for dep in story.depends_on.iter().filter(|dep| **dep != story.id) {
+            if let Some(cycle) = self.find_cycle(dep, chain, done) {
+                return Some(cycle);
+            }
+        }
+        chain.pop();
+        done.insert(id);
+        None
+    }
+
+    /// Whether any story has an estimate
+    pub fn has_estimates(&self) -> bool {
+        self.user_stories.iter().any(|s| s.estimate.is_some())
+    }
+
+    /// Share of the estimated work that passes, from 0 to 100
+    ///
+    /// `None` when no story has an estimate; stories without one weigh 1.
+    pub fn weighted_percentage(&self) -> Option<f64> {
+        if !self.has_estimates() {
+            return None;
+        }
+        let total: f64 = self.user_stories.iter().map(UserStory::weight).sum();
+        let done: f64 = self
+            .user_stories
+            .iter()
+            .filter(|s| s.passes)
+            .map(UserStory::weight)
+            .sum();
+        Some(if total > 0.0 { done * 100.0 / total } else { 0.0 })
+    }
+
+    /// The heaviest chain of pending stories through `dependsOn`
+    ///
+    /// Passed and unknown dependencies do not extend a chain, and a cycle
+    /// stops it instead of looping. A story without pending dependencies is
+    /// a chain of its own, so disconnected stories compete on their own
+    /// weight. Ties go to the story that comes first in the PRD. `None` when
+    /// nothing is pending.
+    pub fn critical_path(&self) -> Option<CriticalPath> {
+        let mut chains = HashMap::new();
+        let mut best: Option<(f64, &str)> = None;
+        for story in self.user_stories.iter().filter(|s| !s.passes) {
+            let Some((weight, _)) = self.chain_to(&story.id, &mut chains, &mut HashSet::new())
+            else {
+                continue;
+            };
+            if best.is_none_or(|(heaviest, _)| weight > heaviest) {
+                best = Some((weight, story.id.as_str()));
+            }
+        }
+
+        let (estimate, mut id) = best?;
+        let mut stories = vec![id.to_string()];
+        while let Some((_, Some(previous))) = chains.get(id) {
+            stories.push(previous.to_string());
+            id = previous;
+        }
+        stories.reverse();
+        Some(CriticalPath { stories, estimate })
+    }
+
+    /// Weight of the heaviest pending chain ending at `id`, and the story
+    /// before it; `None` for stories that are unknown, passed or already on
+    /// the chain being followed
+    fn chain_to<'a>(
+        &'a self,
+        id: &'a str,
+        chains: &mut HashMap<&'a str, (f64, Option<&'a str>)>,
+        visiting: &mut HashSet<&'a str>,
+    ) -> Option<(f64, Option<&'a str>)> {
+        if let Some(&chain) = chains.get(id) {
+            return Some(chain);
+        }
+        let story = self.find_story(id).filter(|s| !s.passes)?;
+        if !visiting.insert(id) {
+            return None;
+        }
+        let mut heaviest: Option<(f64, &str)> = None;
+        for dep in &story.depends_on {
+            if let Some((weight, _)) = self.chain_to(dep, chains, visiting) {
+                if heaviest.is_none_or(|(w, _)| weight > w) {
+                    heaviest = Some((weight, dep.as_str()));
+                }
+            }
+        }
+        visiting.remove(id);
+        let chain = (
+            story.weight() + heaviest.map_or(0.0, |(w, _)| w),
+            heaviest.map(|(_, dep)| dep),
+        );
+        chains.insert(id, chain);
+        Some(chain)
+    }
+
+    /// Whether the PRD declares epics or any story names one
+    pub fn has_epics(&self) -> bool {
+        !self.epics.is_empty() || self.user_stories.iter().any(|s| s.epic.is_some())
+    }
+
+    /// Group the stories by epic
+    ///
+    /// Declared epics come first in declaration order, even when empty, then
+    /// undeclared epics in the order stories first name them, then an
+    /// "Ungrouped" bucket for stories without an epic. Stories within a
+    /// group are sorted by priority, keeping PRD order between equal ones.
+    pub fn epic_groups(&self) -> Vec<EpicGroup<'_>> {
+        let mut groups: Vec<EpicGroup<'_>> = self
+            .epics
+            .iter()
+            .map(|epic| EpicGroup {
+                name: &epic.name,
+                description: &epic.description,
+                stories: Vec::new(),
+            })
+            .collect();
+        let mut ungrouped = Vec::new();
+
+        for story in &self.user_stories {
+            let Some(name) = story.epic.as_deref() else {
+                ungrouped.push(story);
+                continue;
+            };
+            match groups.iter_mut().find(|g| g.name == name) {
+                Some(group) => group.stories.push(story),
+                None => groups.push(EpicGroup {
+                    name,
+                    description: "",
+                    stories: vec![story],
+                }),
+            }
+        }
+        if !ungrouped.is_empty() {
+            groups.push(EpicGroup {
+                name: UNGROUPED_EPIC,
+                description: "",
+                stories: ungrouped,
+            });
+        }
+
+        for group in &mut groups {
+            group.stories.sort_by_key(|s| s.priority);
+        }
+        groups
+    }
+
+    /// Get the stories of an epic, in PRD order
+    pub fn epic_stories(&self, epic: &str) -> Vec<&UserStory> {
+        self.user_stories
+            .iter()
+            .filter(|s| s.epic.as_deref() == Some(epic))
+            .collect()
+    }
+
+    /// Get the stories naming an epic missing from the `epics` array
+    pub fn undeclared_epic_stories(&self) -> Vec<&UserStory> {
+        self.user_stories
+            .iter()
+            .filter(|s| {
+                s.epic
+                    .as_deref()
+                    .is_some_and(|name| !self.epics.iter().any(|e| e.name == name))
+            })
+            .collect()
+    }
+
+    /// Get the dependency ids of a story that have not passed yet
+    ///
+    /// Dependencies on stories missing from the PRD count as unmet.
+    pub fn unmet_dependencies<'a>(&self, story: &'a UserStory) -> Vec<&'a str> {
+        story
+            .depends_on
+            .iter()
+            .filter(|dep| !self.find_story(dep).is_some_and(|s| s.passes))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Get pending stories whose dependencies are all satisfied
+    pub fn actionable_stories(&self) -> Vec<&UserStory> {
+        self.user_stories
+            .iter()
+            .filter(|s| !s.passes && self.unmet_dependencies(s).is_empty())
+            .collect()
+    }
+
+    /// Get pending stories that are waiting on unmet dependencies
+    pub fn blocked_stories(&self) -> Vec<&UserStory> {
+        self.user_stories
+            .iter()
+            .filter(|s| !s.passes && !self.unmet_dependencies(s).is_empty())
+            .collect()
+    }
+
+    /// Count stories per priority band of `width` priorities
+    ///
+    /// Bands start at 1 (`1..=width`, `width+1..=2*width`, ...). Returns the
+    /// first priority of each non-empty band with its story count, lowest first.
+    pub fn priority_bands(&self, width: u32) -> Vec<(u32, usize)> {
+        let width = width.max(1);
+        let mut bands: BTreeMap<u32, usize> = BTreeMap::new();
+        for story in &self.user_stories {
+            let start = story.priority.saturating_sub(1) / width * width + 1;
+            *bands.entry(start).or_default() += 1;
+        }
+        bands.into_iter().collect()
+    }
+
+    /// Number of stories by acceptance criteria count
+    pub fn criteria_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for story in &self.user_stories {
+            *histogram.entry(story.acceptance_criteria.len()).or_default() += 1;
+        }
+        histogram
+    }
+
+    /// Average number of acceptance criteria per story (0 for an empty PRD)
+    pub fn average_criteria(&self) -> f64 {
+        if self.user_stories.is_empty() {
+            return 0.0;
+        }
+        let total: usize = self.user_stories.iter().map(|s| s.acceptance_criteria.len()).sum();
+        total as f64 / self.user_stories.len() as f64
+    }
+
+    /// Largest number of acceptance criteria on a single story
+    pub fn max_criteria(&self) -> usize {
+        self.user_stories
+            .iter()
+            .map(|s| s.acceptance_criteria.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Get stories with no acceptance criteria, which agents cannot verify
+    pub fn stories_without_criteria(&self) -> Vec<&UserStory> {
+        self.user_stories
+            .iter()
+            .filter(|s| s.acceptance_criteria.is_empty())
+            .collect()
+    }
+
+    /// Get pending stories with no acceptance criteria, in PRD order
+    pub fn pending_without_criteria(&self) -> Vec<&UserStory> {
+        self.user_stories
+            .iter()
+            .filter(|s| !s.passes && s.acceptance_criteria.is_empty())
+            .collect()
+    }
+
+    /// Get up to `limit` stories with the longest descriptions, longest first
+    pub fn longest_descriptions(&self, limit: usize) -> Vec<&UserStory> {
+        let mut stories: Vec<&UserStory> = self.user_stories.iter().collect();
+        stories.sort_by_key(|s| std::cmp::Reverse(s.description.chars().count()));
+        stories.truncate(limit);
+        stories
+    }
+
+    /// Length of the longest `dependsOn` chain, in dependency links
+    ///
+    /// Stories without dependencies have depth 0. Dependencies on unknown
+    /// stories are ignored, and a cycle stops the chain instead of looping.
+    pub fn dependency_depth(&self) -> usize {
+        let mut depths = HashMap::new();
+        self.user_stories
+            .iter()
+            .filter_map(|s| self.story_depth(&s.id, &mut depths, &mut HashSet::new()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Dependency depth of one story, or `None` for unknown stories and
+    /// stories already on the chain being followed
+    fn story_depth<'a>(
+        &'a self,
+        id: &'a str,
+        depths: &mut HashMap<&'a str, usize>,
+        visiting: &mut HashSet<&'a str>,
+    ) -> Option<usize> {
+        if let Some(&depth) = depths.get(id) {
+            return Some(depth);
+        }
+        let story = self.find_story(id)?;
+        if !visiting.insert(id) {
+            return None;
+        }
+        let depth = story
+            .depends_on
+            .iter()
+            .filter_map(|dep| self.story_depth(dep, depths, visiting))
+            .map(|depth| depth + 1)
+            .max()
+            .unwrap_or(0);
+        visiting.remove(id);
+        depths.insert(id, depth);
+        Some(depth)
+    }
+
+    /// Rewrite story ids to a zero-padded `US-001` sequence in PRD order
+    ///
+    /// `dependsOn` references are updated to match. Returns the `(old, new)`
+    /// pairs for the ids that changed. With duplicate ids, references resolve
+    /// to the first story using the id.
+    pub fn normalize_ids(&mut self) -> Vec<(String, String)> {
+        let width = self.user_stories.len().to_string().len().max(3);
+        let mut mapping: HashMap<String, String> = HashMap::new();
+        let mut changed = Vec::new();
+
+        for (index, story) in self.user_stories.iter_mut().enumerate() {
+            let new_id = format!("US-{:0width$}", index + 1, width = width);
+            mapping
+                .entry(story.id.clone())
+                .or_insert_with(|| new_id.clone());
+            if story.id != new_id {
+                changed.push((std::mem::replace(&mut story.id, new_id.clone()), new_id));
+            }
+        }
+
+        for story in &mut self.user_stories {
+            for dep in &mut story.depends_on {
+                if let Some(new_id) = mapping.get(dep.as_str()) {
+                    *dep = new_id.clone();
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// Whether any story id is empty or used twice
+    pub fn has_duplicate_or_empty_ids(&self) -> bool {
+        let mut seen = HashSet::new();
+        self.user_stories
+            .iter()
+            .any(|s| s.id.trim().is_empty() || !seen.insert(s.id.as_str()))
+    }
+
+    /// Renumber priorities to 1..N, removing duplicates and gaps
+    ///
+    /// Stories keep their relative order; equal priorities keep PRD order.
+    /// Returns `(id, old, new)` for each story whose priority changed, in the
+    /// new priority order.
+    pub fn normalize_priorities(&mut self) -> Vec<(String, u32, u32)> {
+        let mut order: Vec<usize> = (0..self.user_stories.len()).collect();
+        order.sort_by_key(|&index| self.user_stories[index].priority);
+
+        let mut changed = Vec::new();
+        for (rank, index) in order.into_iter().enumerate() {
+            let story = &mut self.user_stories[index];
+            let new_priority = rank as u32 + 1;
+            if story.priority != new_priority {
+                changed.push((story.id.clone(), story.priority, new_priority));
+                story.priority = new_priority;
+            }
+        }
+        changed
+    }
+
+    /// Give a story a new priority, shifting the stories in between
+    ///
+    /// The stories are ranked by priority (equal priorities in PRD order),
+    /// the story is taken out and put back at `new_priority`, and everyone is
+    /// renumbered 1..N. Returns `(id, old, new)` for each story whose
+    /// priority changed, like [`Prd::normalize_priorities`]; moving a story to
+    /// where it already is changes nothing.
+    pub fn move_story(
+        &mut self,
+        story_id: &str,
+        new_priority: u32,
+    ) -> Result<Vec<(String, u32, u32)>, String> {
+        let Some(index) = self.user_stories.iter().position(|s| s.id == story_id) else {
+            return Err(self.unknown_story_message(story_id));
+        };
+        let count = self.user_stories.len() as u32;
+        if !(1..=count).contains(&new_priority) {
+            return Err(format!("Priority must be between 1 and {}", count));
+        }
+
+        let mut order: Vec<usize> = (0..self.user_stories.len()).collect();
+        order.sort_by_key(|&i| self.user_stories[i].priority);
+        order.retain(|&i| i != index);
+        order.insert(new_priority as usize - 1, index);
+
+        let mut changed = Vec::new();
+        for (rank, i) in order.into_iter().enumerate() {
+            let story = &mut self.user_stories[i];
+            let priority = rank as u32 + 1;
+            if story.priority != priority {
+                changed.push((story.id.clone(), story.priority, priority));
+                story.priority = priority;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Find a story by id
+    pub fn find_story(&self, story_id: &str) -> Option<&UserStory> {
+        self.user_stories.iter().find(|s| s.id == story_id)
+    }
+
+    /// Find a story by id for modification
+    pub fn find_story_mut(&mut self, story_id: &str) -> Option<&mut UserStory> {
+        self.user_stories.iter_mut().find(|s| s.id == story_id)
+    }
+
+    /// Get the stories that list the given id in their `dependsOn`
+    pub fn dependents_of(&self, story_id: &str) -> Vec<&UserStory> {
+        self.user_stories
+            .iter()
+            .filter(|s| s.depends_on.iter().any(|d| d == story_id))
+            .collect()
+    }
+
+    /// Append an acceptance criterion to a story
+    ///
+    /// Returns the story's new number of criteria, or an error when the id is
+    /// unknown or the text is blank.
+    pub fn add_criterion(&mut self, story_id: &str, text: &str) -> Result<usize, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Acceptance criterion is empty".to_string());
+        }
+        let Some(story) = self.find_story_mut(story_id) else {
+            return Err(self.unknown_story_message(story_id));
+        };
+        story.acceptance_criteria.push(text.to_string());
+        Ok(story.acceptance_criteria.len())
+    }
+
+    /// Remove a story and strip its id from every other story's `dependsOn`
+    pub fn remove_story(&mut self, story_id: &str) -> Option<UserStory> {
+        let index = self.user_stories.iter().position(|s| s.id == story_id)?;
+        let removed = self.user_stories.remove(index);
+        for story in &mut self.user_stories {
+            story.depends_on.retain(|d| d != story_id);
+        }
+        Some(removed)
+    }
+
+    /// Move the stories matching `predicate` into a new PRD
+    ///
+    /// The new PRD keeps the project, branch and description, and the
+    /// declared epics its stories use. Dependencies between the two PRDs are
+    /// dropped on both sides, as neither file can resolve them (see
+    /// [`Prd::cross_dependencies`]), and so are unknown ones in the new PRD,
+    /// which could clash with the new ids. The extracted stories are then renumbered
+    /// to `US-001`.. in PRD order and to priorities 1..N in priority order.
+    pub fn extract(&mut self, predicate: impl Fn(&UserStory) -> bool) -> Prd {
+        let (mut extracted, kept): (Vec<UserStory>, Vec<UserStory>) =
+            std::mem::take(&mut self.user_stories)
+                .into_iter()
+                .partition(|s| predicate(s));
+        self.user_stories = kept;
+
+        let extracted_ids: HashSet<String> = extracted.iter().map(|s| s.id.clone()).collect();
+        for story in &mut extracted {
+            story.depends_on.retain(|d| extracted_ids.contains(d));
+        }
+        for story in &mut self.user_stories {
+            story.depends_on.retain(|d| !extracted_ids.contains(d));
+        }
+
+        let epics = self
+            .epics
+            .iter()
+            .filter(|e| extracted.iter().any(|s| s.epic.as_deref() == Some(e.name.as_str())))
+            .cloned()
+            .collect();
+        let mut prd = Prd {
+            project: self.project.clone(),
+            branch_name: self.branch_name.clone(),
+            description: self.description.clone(),
+            epics,
+            user_stories: extracted,
+        };
+        prd.normalize_ids();
+        prd.normalize_priorities();
+        prd
+    }
+
+    /// `(story, dependency)` pairs that would cross between the stories
+    /// matching `predicate` and the rest, which [`Prd::extract`] drops
+    pub fn cross_dependencies(&self, predicate: impl Fn(&UserStory) -> bool) -> Vec<(String, String)> {
+        self.user_stories
+            .iter()
+            .flat_map(|story| {
+                let inside = predicate(story);
+                story
+                    .depends_on
+                    .iter()
+                    .filter(|dep| {
+                        self.find_story(dep)
+                            .is_some_and(|dep_story| predicate(dep_story) != inside)
+                    })
+                    .map(|dep| (story.id.clone(), dep.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
     }
 
     /// Save PRD to a JSON file
-    #[allow(dead_code)]
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        let content = serde_json::to_string_pretty(self)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        fs::write(path, content)?;
+        self.save_to_file_at(path, "")
+    }
+
+    /// Whether saving would change the file, i.e. the PRD differs from the one on disk
+    pub fn is_dirty<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        self.is_dirty_at(path, "")
+    }
+
+    /// Whether saving at a JSON Pointer of a file would change it
+    ///
+    /// Only the PRD at `pointer` is compared; the rest of the document is ignored.
+    pub fn is_dirty_at<P: AsRef<Path>>(&self, path: P, pointer: &str) -> io::Result<bool> {
+        Ok(self.updated_content(path.as_ref(), pointer)?.is_some())
+    }
+
+    /// Save the PRD at a JSON Pointer of a file, keeping the rest of the document
+    ///
+    /// Nothing is written when the file already holds this PRD, so the file's
+    /// bytes and mtime only change with its content. The comparison is made
+    /// on the parsed PRD rather than the text: a hand-formatted prd.json keeps
+    /// its layout until a field actually changes.
+    ///
+    /// A rewrite keeps the file's style so diffs stay small: its indent (two
+    /// spaces when none can be detected) and the order of the keys already
+    /// in it. New keys follow in [`Prd`]'s field order, and the file always
+    /// ends with a newline.
+    ///
+    /// The content is written to a sibling temp file first and then renamed
+    /// over the target, so an interrupted save never leaves a truncated PRD.
+    pub fn save_to_file_at<P: AsRef<Path>>(&self, path: P, pointer: &str) -> io::Result<()> {
+        let path = path.as_ref();
+        let Some(content) = self.updated_content(path, pointer)? else {
+            return Ok(());
+        };
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, path)?;
         Ok(())
     }
+
+    /// The file content holding this PRD at `pointer`, or `None` when the file
+    /// already holds an equal PRD
+    fn updated_content(&self, path: &Path, pointer: &str) -> io::Result<Option<String>> {
+        let invalid = |e: serde_json::Error| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut value = serde_json::to_value(self).map_err(invalid)?;
+        let existing = match fs::read_to_string(path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound && pointer.is_empty() => None,
+            Err(e) => return Err(e),
+        };
+        let indent = existing.as_deref().map_or(DEFAULT_INDENT, detect_indent);
+
+        let document = if pointer.is_empty() {
+            let on_disk: Option<serde_json::Value> =
+                existing.as_deref().and_then(|content| serde_json::from_str(content).ok());
+            if let Some(on_disk) = on_disk {
+                if same_prd(&on_disk, &value) {
+                    return Ok(None);
+                }
+                keep_key_order(&mut value, &on_disk);
+            }
+            value
+        } else {
+            let mut document: serde_json::Value =
+                serde_json::from_str(existing.as_deref().unwrap_or_default()).map_err(invalid)?;
+            let slot = document.pointer_mut(pointer).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("no PRD at JSON Pointer {}", pointer),
+                )
+            })?;
+            if same_prd(slot, &value) {
+                return Ok(None);
+            }
+            keep_key_order(&mut value, slot);
+            *slot = value;
+            document
+        };
+        to_json(&document, indent).map(Some)
+    }
+}
+
+/// Indent used for a new prd.json, or one whose indent cannot be detected
+const DEFAULT_INDENT: &str = "  ";
+
+/// Whether a stored PRD value reads back as the serialized PRD `prd`
+///
+/// Layout, key order, fields ralph does not know and defaults written out
+/// in full (like an empty `dependsOn`) do not count as differences.
+fn same_prd(stored: &serde_json::Value, prd: &serde_json::Value) -> bool {
+    serde_json::from_value::<Prd>(stored.clone())
+        .and_then(serde_json::to_value)
+        .is_ok_and(|stored| &stored == prd)
+}
+
+/// The indent of a pretty-printed JSON file: the leading whitespace of its
+/// first indented line
+fn detect_indent(content: &str) -> &str {
+    content
+        .lines()
+        .skip(1)
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or(DEFAULT_INDENT)
+}
+
+/// Reorder the object keys of `value` to follow those in `original`
+///
+/// Keys found in both come first, in the original order; keys that only
+/// `value` has follow in their own order. Nested objects and arrays are
+/// matched by key and index.
+fn keep_key_order(value: &mut serde_json::Value, original: &serde_json::Value) {
+    match (value, original) {
+        (serde_json::Value::Object(map), serde_json::Value::Object(original)) => {
+            let mut fields = std::mem::take(map);
+            for (key, original_value) in original {
+                if let Some(mut field) = fields.shift_remove(key) {
+                    keep_key_order(&mut field, original_value);
+                    map.insert(key.clone(), field);
+                }
+            }
+            map.extend(fields);
+        }
+        (serde_json::Value::Array(items), serde_json::Value::Array(original)) => {
+            for (item, original_item) in items.iter_mut().zip(original) {
+                keep_key_order(item, original_item);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Pretty-print JSON with the given indent and a trailing newline
+fn to_json(value: &serde_json::Value, indent: &str) -> io::Result<String> {
+    let mut out = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+    value
+        .serialize(&mut serializer)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    out.push(b'\n');
+    String::from_utf8(out).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
 
 /// User Story structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `id`, `title`, `priority` and `passes` are required. Every other field
+/// defaults to empty when a PRD leaves it out, so fields added later do not
+/// break older PRDs. A missing description or acceptance criteria still
+/// makes the story weak (see [`UserStory::is_weak`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UserStory {
     pub id: String,
     pub title: String,
+    #[serde(default)]
     pub description: String,
-    #[serde(rename = "acceptanceCriteria")]
+    #[serde(rename = "acceptanceCriteria", default)]
     pub acceptance_criteria: Vec<String>,
     pub priority: u32,
     pub passes: bool,
+    #[serde(default)]
     pub notes: String,
+    /// Ids of stories that must pass before this one
+    #[serde(rename = "dependsOn", default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Name of the epic the story belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epic: Option<String>,
+    /// Files the story is expected to touch, used to plan `run --parallel` lanes
+    #[serde(rename = "contextFiles", default, skip_serializing_if = "Vec::is_empty")]
+    pub context_files: Vec<String>,
+    /// Free-form labels, e.g. `backend`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Size of the story, in points or hours
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<f64>,
+    /// Acceptance criteria checked off with `ralph story check`, by their text
+    #[serde(rename = "criteriaDone", default, skip_serializing_if = "Vec::is_empty")]
+    pub criteria_done: Vec<String>,
 }
 
 impl UserStory {
+    /// Weight of the story for progress and the critical path: its estimate,
+    /// or 1 when it has none (or a non-positive one)
+    pub fn weight(&self) -> f64 {
+        self.estimate.filter(|e| e.is_finite() && *e > 0.0).unwrap_or(1.0)
+    }
+
     /// Get formatted display string for the story
-    #[allow(dead_code)]
     pub fn display(&self) -> String {
         format!("{} - {}", self.id, self.title)
     }
+
+    /// Append an entry to the notes on a new line, keeping what is already there
+    pub fn append_note(&mut self, entry: &str) {
+        if self.notes.trim().is_empty() {
+            self.notes = entry.to_string();
+        } else {
+            self.notes = format!("{}\n{}", self.notes.trim_end_matches('\n'), entry);
+        }
+    }
+
+    /// Whether an acceptance criterion is checked off
+    pub fn is_criterion_done(&self, criterion: &str) -> bool {
+        self.criteria_done.iter().any(|done| done == criterion)
+    }
+
+    /// Check off criterion `number` (1-based), or uncheck it if it was done
+    ///
+    /// Returns whether the criterion is done now.
+    pub fn toggle_criterion(&mut self, number: usize) -> Result<bool, String> {
+        let count = self.acceptance_criteria.len();
+        let criterion = number
+            .checked_sub(1)
+            .and_then(|index| self.acceptance_criteria.get(index))
+            .ok_or_else(|| match count {
+                0 => format!("Story {} has no acceptance criteria", self.id),
+                _ => format!(
+                    "Story {} has {} acceptance criteria; pick a number from 1 to {}",
+                    self.id, count, count
+                ),
+            })?
+            .clone();
+        if self.is_criterion_done(&criterion) {
+            self.criteria_done.retain(|done| *done != criterion);
+            Ok(false)
+        } else {
+            self.criteria_done.push(criterion);
+            Ok(true)
+        }
+    }
+
+    /// Number of acceptance criteria checked off
+    pub fn done_criteria_count(&self) -> usize {
+        self.acceptance_criteria
+            .iter()
+            .filter(|criterion| self.is_criterion_done(criterion))
+            .count()
+    }
+
+    /// Whether the story has acceptance criteria and all are checked off
+    pub fn all_criteria_done(&self) -> bool {
+        !self.acceptance_criteria.is_empty()
+            && self.done_criteria_count() == self.acceptance_criteria.len()
+    }
+
+    /// Drop check marks of criteria that were edited or removed
+    pub fn forget_removed_criteria(&mut self) {
+        let criteria = &self.acceptance_criteria;
+        self.criteria_done.retain(|done| criteria.contains(done));
+    }
+
+    /// Whether the story lacks a description or acceptance criteria
+    pub fn is_weak(&self) -> bool {
+        self.description.trim().is_empty() || self.acceptance_criteria.is_empty()
+    }
+}
+
+/// Fisher-Yates shuffle driven by a SplitMix64 generator
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    SplitMix64::new(seed).shuffle(items);
+}
+
+/// Small deterministic random number generator; the same seed always gives
+/// the same sequence
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`; `bound` must not be zero
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// Fisher-Yates shuffle
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    /// Pick an item of a non-empty slice
+    pub(crate) fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
 }
 
 #[cfg(test)]
@@ -123,3 +1231,4 @@ mod tests {
         assert_eq!(prd.completed_stories(), 0);
     }
 }
+
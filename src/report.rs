@@ -0,0 +1,30 @@
+use serde::Serialize;
+
+use crate::cli::OutputFormat;
+use crate::error::{RalphError, RalphResult};
+
+/// Output of a read-only command, printable in every `--format`
+///
+/// Commands build the report once and let [`render`] pick the view, so the
+/// table and the JSON/YAML output always describe the same data.
+pub trait Report: Serialize {
+    /// Human-readable view for `--format table`
+    fn to_table(&self) -> String;
+}
+
+/// Render a report in the requested format
+pub fn render<R: Report>(report: &R, format: OutputFormat) -> RalphResult<String> {
+    match format {
+        OutputFormat::Table => Ok(report.to_table()),
+        OutputFormat::Json => serde_json::to_string_pretty(report)
+            .map_err(|e| RalphError::Other(format!("Could not serialize output: {}", e))),
+        OutputFormat::Yaml => serde_yaml_ng::to_string(report)
+            .map_err(|e| RalphError::Other(format!("Could not serialize output: {}", e))),
+    }
+}
+
+/// Print a report in the requested format
+pub fn print_report<R: Report>(report: &R, format: OutputFormat) -> RalphResult<()> {
+    println!("{}", render(report, format)?.trim_end());
+    Ok(())
+}
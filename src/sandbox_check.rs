@@ -0,0 +1,235 @@
+use console::style;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::Hasher;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::Config;
+
+/// Files and folders in the home directory watched by default
+pub const DEFAULT_WATCHED_DOTFILES: &[&str] = &[
+    ".bashrc",
+    ".bash_profile",
+    ".profile",
+    ".zshrc",
+    ".zprofile",
+    ".gitconfig",
+    ".npmrc",
+    ".netrc",
+    ".ssh",
+    ".aws",
+];
+
+/// Files larger than this are compared by size and mtime only
+const MAX_HASHED_BYTES: u64 = 1024 * 1024;
+
+/// Paths watched when `sandbox_watch_paths` is not configured
+///
+/// Sensitive home dotfiles plus the ralph config directory.
+pub fn default_watch_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match dirs::home_dir() {
+        Some(home) => DEFAULT_WATCHED_DOTFILES.iter().map(|name| home.join(name)).collect(),
+        None => Vec::new(),
+    };
+    if let Some(dir) = Config::config_file().and_then(|f| f.parent().map(Path::to_path_buf)) {
+        paths.push(dir);
+    }
+    paths
+}
+
+/// Parse a comma-separated list of paths, expanding a leading `~/`
+pub fn parse_watch_paths(value: &str) -> Vec<PathBuf> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| match (p.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ if p == "~" => dirs::home_dir().unwrap_or_else(|| PathBuf::from(p)),
+            _ => PathBuf::from(p),
+        })
+        .collect()
+}
+
+/// How a watched path changed between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl ChangeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Removed => "removed",
+        }
+    }
+}
+
+/// A watched file that changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+/// What is known about a file without reading it, plus its hash when small
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+    hash: Option<u64>,
+}
+
+impl FileStamp {
+    fn read(path: &Path, metadata: &fs::Metadata) -> Self {
+        Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            hash: hash_file(path, metadata),
+        }
+    }
+}
+
+/// State of the watched paths at one point in time
+///
+/// Comparing is cheap: files whose size and mtime are unchanged are never
+/// read. Small files are hashed when first seen, so a file whose mtime moved
+/// but whose content did not (merely touched) is not reported.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    roots: Vec<PathBuf>,
+    files: BTreeMap<PathBuf, FileStamp>,
+}
+
+impl Snapshot {
+    /// Record every file under the given paths; missing paths are watched for creation
+    pub fn take(roots: &[PathBuf]) -> Self {
+        let mut files = BTreeMap::new();
+        for root in roots {
+            walk(root, &mut |path, metadata| {
+                files.insert(path.to_path_buf(), FileStamp::read(path, metadata));
+            });
+        }
+        Self {
+            roots: roots.to_vec(),
+            files,
+        }
+    }
+
+    /// Number of files currently recorded
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Compare the watched paths with this snapshot, then record their new state
+    ///
+    /// Each change is reported once, by the first call that sees it.
+    pub fn refresh(&mut self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let mut seen = BTreeMap::new();
+        let files = &self.files;
+        for root in &self.roots {
+            walk(root, &mut |path, metadata| {
+                let stamp = match files.get(path) {
+                    None => {
+                        changes.push(Change {
+                            path: path.to_path_buf(),
+                            kind: ChangeKind::Created,
+                        });
+                        FileStamp::read(path, metadata)
+                    }
+                    Some(old) => {
+                        let (stamp, changed) = refreshed_stamp(path, metadata, old);
+                        if changed {
+                            changes.push(Change {
+                                path: path.to_path_buf(),
+                                kind: ChangeKind::Modified,
+                            });
+                        }
+                        stamp
+                    }
+                };
+                seen.insert(path.to_path_buf(), stamp);
+            });
+        }
+        for path in self.files.keys().filter(|p| !seen.contains_key(*p)) {
+            changes.push(Change {
+                path: path.clone(),
+                kind: ChangeKind::Removed,
+            });
+        }
+
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        self.files = seen;
+        changes
+    }
+}
+
+/// New stamp for a known file and whether it changed
+///
+/// The file is only read when its size and mtime disagree with the old stamp.
+/// A new mtime with the same size is confirmed by hash when one is available.
+fn refreshed_stamp(path: &Path, metadata: &fs::Metadata, old: &FileStamp) -> (FileStamp, bool) {
+    let modified = metadata.modified().ok();
+    if metadata.len() == old.len && modified == old.modified {
+        return (old.clone(), false);
+    }
+    let stamp = FileStamp::read(path, metadata);
+    let changed = stamp.len != old.len || old.hash.is_none() || stamp.hash != old.hash;
+    (stamp, changed)
+}
+
+/// Visit every file under `path` without following symlinks
+fn walk(path: &Path, visit: &mut dyn FnMut(&Path, &fs::Metadata)) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return;
+    };
+    if metadata.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            walk(&entry.path(), visit);
+        }
+    } else {
+        visit(path, &metadata);
+    }
+}
+
+fn hash_file(path: &Path, metadata: &fs::Metadata) -> Option<u64> {
+    if !metadata.is_file() || metadata.len() > MAX_HASHED_BYTES {
+        return None;
+    }
+    let mut content = Vec::new();
+    fs::File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut content))
+        .ok()?;
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&content);
+    Some(hasher.finish())
+}
+
+/// Print a prominent warning listing watched paths the agent changed
+pub fn print_change_warning(iteration: u32, changes: &[Change]) {
+    eprintln!();
+    eprintln!(
+        "{}",
+        style(format!(
+            "⚠ Sandbox check: iteration {} changed files outside the repository",
+            iteration
+        ))
+        .red()
+        .bold()
+    );
+    for change in changes {
+        eprintln!("  {} {}", style(change.kind.label()).red(), change.path.display());
+    }
+    eprintln!();
+}
@@ -0,0 +1,204 @@
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::archive::list_archives;
+use crate::prd::Prd;
+
+/// Characters of context kept on each side of a match in long lines
+const EXCERPT_CONTEXT: usize = 60;
+
+/// Story ids like `US-012` in progress.txt entry headings
+static STORY_ID_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b[A-Z][A-Z0-9]*-\d+\b").unwrap());
+
+/// One place a query was found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchMatch {
+    /// Archive folder holding the file; `None` for the active run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<String>,
+    /// `prd.json` or `progress.txt`
+    pub file: String,
+    /// Story the match belongs to: the story itself, or the progress entry's story
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub story: Option<String>,
+    /// Story field for PRD matches, e.g. `notes` or `acceptanceCriteria[2]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// 1-based line for progress.txt matches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// The matching line, shortened around the match when long
+    pub excerpt: String,
+    /// Byte range of the first match in `excerpt`
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Case-insensitive matcher for a literal query
+#[derive(Debug, Clone)]
+pub struct Query {
+    re: Regex,
+}
+
+impl Query {
+    /// Build a matcher; an empty or blank query is rejected
+    pub fn new(query: &str) -> Option<Self> {
+        let query = query.trim();
+        if query.is_empty() {
+            return None;
+        }
+        let re = RegexBuilder::new(&regex::escape(query))
+            .case_insensitive(true)
+            .build()
+            .ok()?;
+        Some(Self { re })
+    }
+
+    /// The excerpt of `text` around the first match, with the match's range in it
+    fn find(&self, text: &str) -> Option<(String, usize, usize)> {
+        let m = self.re.find(text)?;
+        Some(excerpt(text, m.start(), m.end()))
+    }
+}
+
+/// Cut a line down to the match and some context on each side
+fn excerpt(text: &str, start: usize, end: usize) -> (String, usize, usize) {
+    let from = floor_char_boundary(text, start.saturating_sub(EXCERPT_CONTEXT));
+    let to = ceil_char_boundary(text, (end + EXCERPT_CONTEXT).min(text.len()));
+
+    let mut out = String::new();
+    if from > 0 {
+        out.push('…');
+    }
+    let prefix = out.len();
+    out.push_str(&text[from..to]);
+    if to < text.len() {
+        out.push('…');
+    }
+    (out, start - from + prefix, end - from + prefix)
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+/// Search every story text field of a PRD, in story order
+pub fn search_prd(prd: &Prd, query: &Query) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    for story in &prd.user_stories {
+        let mut fields = vec![
+            ("title".to_string(), &story.title),
+            ("description".to_string(), &story.description),
+        ];
+        for (index, criterion) in story.acceptance_criteria.iter().enumerate() {
+            fields.push((format!("acceptanceCriteria[{}]", index + 1), criterion));
+        }
+        fields.push(("notes".to_string(), &story.notes));
+
+        for (field, text) in fields {
+            // Multi-line notes report the first matching line
+            let Some((excerpt, start, end)) = text.lines().find_map(|line| query.find(line))
+            else {
+                continue;
+            };
+            matches.push(SearchMatch {
+                archive: None,
+                file: "prd.json".to_string(),
+                story: Some(story.id.clone()),
+                field: Some(field),
+                line: None,
+                excerpt,
+                start,
+                end,
+            });
+        }
+    }
+    matches
+}
+
+/// Search the lines of a progress log
+///
+/// Each match is tied to the story named in the `## ` heading of its entry,
+/// if there is one. Headings themselves are searched too.
+pub fn search_progress(content: &str, query: &Query) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    let mut story: Option<String> = None;
+    for (index, line) in content.lines().enumerate() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            story = STORY_ID_RE.find(heading).map(|m| m.as_str().to_string());
+        }
+        let Some((excerpt, start, end)) = query.find(line) else {
+            continue;
+        };
+        matches.push(SearchMatch {
+            archive: None,
+            file: "progress.txt".to_string(),
+            story: story.clone(),
+            field: None,
+            line: Some(index + 1),
+            excerpt,
+            start,
+            end,
+        });
+    }
+    matches
+}
+
+/// Search the PRD and progress log of a ralph directory, or of one archive folder
+///
+/// Missing files are skipped, as is an archived PRD that no longer parses.
+/// Without `prd`, the PRD is read from `prd.json` at the JSON Pointer `prd_key`.
+pub fn search_dir(
+    dir: &Path,
+    prd: Option<&Prd>,
+    prd_key: &str,
+    query: &Query,
+) -> io::Result<Vec<SearchMatch>> {
+    let mut matches = match prd {
+        Some(prd) => search_prd(prd, query),
+        None => Prd::from_file_at(dir.join("prd.json"), prd_key)
+            .map(|prd| search_prd(&prd, query))
+            .unwrap_or_default(),
+    };
+    match fs::read_to_string(dir.join("progress.txt")) {
+        Ok(progress) => matches.extend(search_progress(&progress, query)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    Ok(matches)
+}
+
+/// Search the archived runs in `archive_dir`, newest first
+///
+/// Archived PRDs are read at the JSON Pointer `prd_key`, like the live one.
+pub fn search_archives(
+    archive_dir: &Path,
+    prd_key: &str,
+    query: &Query,
+) -> io::Result<Vec<SearchMatch>> {
+    let mut matches = Vec::new();
+    for archive in list_archives(archive_dir)? {
+        matches.extend(search_dir(&archive.path, None, prd_key, query)?.into_iter().map(|m| {
+            SearchMatch {
+                archive: Some(archive.name.clone()),
+                ..m
+            }
+        }));
+    }
+    Ok(matches)
+}
@@ -0,0 +1,319 @@
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::prd::Prd;
+
+/// File next to prd.json listing values that are not secrets, one per line
+///
+/// A line holds either the value itself or the fingerprint printed by
+/// `prd scan-secrets`; blank lines and `#` comments are ignored.
+pub const ALLOWLIST_FILE: &str = ".secrets-allowlist";
+
+/// Hex digits of the SHA-256 kept in fingerprints and redaction markers
+const FINGERPRINT_LEN: usize = 12;
+
+/// Shortest token considered by the high-entropy check
+const MIN_HIGH_ENTROPY_LEN: usize = 32;
+
+/// Bits per character above which a token looks random; hex tops out at 4.0,
+/// so commit hashes are never reported
+const HIGH_ENTROPY_BITS: f64 = 4.0;
+
+/// Patterns for well-known credential formats, checked before the entropy test
+static KNOWN_PATTERNS: LazyLock<Vec<(SecretKind, Regex)>> = LazyLock::new(|| {
+    [
+        (SecretKind::AwsAccessKey, r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+        (
+            SecretKind::GithubToken,
+            r"\b(?:gh[pousr]_[A-Za-z0-9]{36,255}|github_pat_[A-Za-z0-9_]{22,255})\b",
+        ),
+        (SecretKind::SlackToken, r"\bxox[abprs]-[A-Za-z0-9-]{10,}"),
+        (SecretKind::ApiKey, r"\bsk-[A-Za-z0-9_-]{20,}"),
+        (SecretKind::PrivateKey, r"-----BEGIN [A-Z ]*PRIVATE KEY-----"),
+    ]
+    .into_iter()
+    .map(|(kind, pattern)| (kind, Regex::new(pattern).unwrap()))
+    .collect()
+});
+
+/// Candidate tokens for the high-entropy check: base64 and URL-safe alphabets
+static TOKEN_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9+/_=-]{32,}").unwrap());
+
+/// The kind of credential a match looks like
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretKind {
+    AwsAccessKey,
+    GithubToken,
+    SlackToken,
+    ApiKey,
+    PrivateKey,
+    HighEntropy,
+}
+
+impl SecretKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SecretKind::AwsAccessKey => "AWS access key",
+            SecretKind::GithubToken => "GitHub token",
+            SecretKind::SlackToken => "Slack token",
+            SecretKind::ApiKey => "API key",
+            SecretKind::PrivateKey => "private key",
+            SecretKind::HighEntropy => "high-entropy string",
+        }
+    }
+}
+
+/// A possible secret inside a piece of text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretMatch {
+    pub kind: SecretKind,
+    /// Byte range of the match in the scanned text
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl SecretMatch {
+    /// The first few characters, safe to print
+    pub fn masked(&self) -> String {
+        let shown: String = self.text.chars().take(4).collect();
+        format!("{}… ({} chars)", shown, self.text.chars().count())
+    }
+
+    /// Fingerprint identifying the value without revealing it
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.text)
+    }
+}
+
+/// Where in the run's files a secret was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    /// A story text field, e.g. `notes` or `acceptanceCriteria[2]`
+    Story { id: String, field: String },
+    /// A 1-based line of progress.txt
+    Progress { line: usize },
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Story { id, field } => write!(f, "{} {}", id, field),
+            Location::Progress { line } => write!(f, "progress.txt line {}", line),
+        }
+    }
+}
+
+/// A possible secret and where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub location: Location,
+    pub secret: SecretMatch,
+}
+
+/// Finds secrets in text, skipping allowlisted values
+#[derive(Debug, Clone, Default)]
+pub struct SecretScanner {
+    /// Allowed values and fingerprints
+    allowed: HashSet<String>,
+}
+
+impl SecretScanner {
+    /// Create a scanner that ignores the given values or fingerprints
+    pub fn new<I, S>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed: allowed.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Create a scanner using the allowlist file in the ralph directory, if any
+    pub fn load(ralph_dir: &Path) -> io::Result<Self> {
+        let content = match fs::read_to_string(ralph_dir.join(ALLOWLIST_FILE)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        Ok(Self::new(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        ))
+    }
+
+    /// Find possible secrets in a piece of text, in order of appearance
+    pub fn scan(&self, text: &str) -> Vec<SecretMatch> {
+        let mut matches: Vec<SecretMatch> = Vec::new();
+        for (kind, re) in KNOWN_PATTERNS.iter() {
+            for m in re.find_iter(text) {
+                push_match(&mut matches, *kind, m.start(), m.as_str());
+            }
+        }
+        for m in TOKEN_RE.find_iter(text) {
+            if looks_random(m.as_str()) {
+                push_match(&mut matches, SecretKind::HighEntropy, m.start(), m.as_str());
+            }
+        }
+
+        matches.retain(|m| !self.is_allowed(&m.text));
+        matches.sort_by_key(|m| m.start);
+        matches
+    }
+
+    /// Replace every secret in the text with a redaction marker
+    ///
+    /// Returns the new text and the number of secrets replaced.
+    pub fn redact(&self, text: &str) -> (String, usize) {
+        let matches = self.scan(text);
+        let mut redacted = String::with_capacity(text.len());
+        let mut last = 0;
+        for m in &matches {
+            redacted.push_str(&text[last..m.start]);
+            redacted.push_str(&redaction_marker(&m.text));
+            last = m.end;
+        }
+        redacted.push_str(&text[last..]);
+        (redacted, matches.len())
+    }
+
+    fn is_allowed(&self, value: &str) -> bool {
+        self.allowed.contains(value) || self.allowed.contains(&fingerprint(value))
+    }
+}
+
+/// Marker left in place of a redacted secret, e.g. `[REDACTED:1a2b3c4d5e6f]`
+pub fn redaction_marker(secret: &str) -> String {
+    format!("[REDACTED:{}]", fingerprint(secret))
+}
+
+/// Leading hex digits of the value's SHA-256
+fn fingerprint(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    hex[..FINGERPRINT_LEN].to_string()
+}
+
+/// Add a match unless it overlaps one found by an earlier pattern
+fn push_match(matches: &mut Vec<SecretMatch>, kind: SecretKind, start: usize, text: &str) {
+    let end = start + text.len();
+    if matches.iter().any(|m| start < m.end && m.start < end) {
+        return;
+    }
+    matches.push(SecretMatch {
+        kind,
+        start,
+        end,
+        text: text.to_string(),
+    });
+}
+
+/// Whether a token mixes cases and digits with enough entropy to be a key
+fn looks_random(token: &str) -> bool {
+    let token = token.trim_end_matches('=');
+    token.len() >= MIN_HIGH_ENTROPY_LEN
+        && token.bytes().any(|b| b.is_ascii_uppercase())
+        && token.bytes().any(|b| b.is_ascii_lowercase())
+        && token.bytes().any(|b| b.is_ascii_digit())
+        && shannon_entropy(token) > HIGH_ENTROPY_BITS
+}
+
+/// Shannon entropy of the token's bytes, in bits per character
+fn shannon_entropy(token: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for b in token.bytes() {
+        counts[b as usize] += 1;
+    }
+    let len = token.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Scan every story text field of a PRD
+pub fn scan_prd(prd: &Prd, scanner: &SecretScanner) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for story in &prd.user_stories {
+        let mut fields = vec![
+            ("title".to_string(), &story.title),
+            ("description".to_string(), &story.description),
+        ];
+        for (index, criterion) in story.acceptance_criteria.iter().enumerate() {
+            fields.push((format!("acceptanceCriteria[{}]", index + 1), criterion));
+        }
+        fields.push(("notes".to_string(), &story.notes));
+
+        for (field, text) in fields {
+            findings.extend(scanner.scan(text).into_iter().map(|secret| Finding {
+                location: Location::Story {
+                    id: story.id.clone(),
+                    field: field.clone(),
+                },
+                secret,
+            }));
+        }
+    }
+    findings
+}
+
+/// Scan the lines of a progress log
+pub fn scan_progress(content: &str, scanner: &SecretScanner) -> Vec<Finding> {
+    content
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            scanner.scan(line).into_iter().map(move |secret| Finding {
+                location: Location::Progress { line: index + 1 },
+                secret,
+            })
+        })
+        .collect()
+}
+
+/// Redact every story text field of a PRD, returning the number of secrets replaced
+pub fn redact_prd(prd: &mut Prd, scanner: &SecretScanner) -> usize {
+    let mut redacted = 0;
+    for story in &mut prd.user_stories {
+        let fields = [&mut story.title, &mut story.description, &mut story.notes]
+            .into_iter()
+            .chain(story.acceptance_criteria.iter_mut());
+        for text in fields {
+            let (new_text, count) = scanner.redact(text);
+            if count > 0 {
+                *text = new_text;
+                redacted += count;
+            }
+        }
+    }
+    redacted
+}
+
+/// Scan the PRD and the progress log next to it
+///
+/// A missing progress log is not an error; the allowlist is read from the
+/// same directory.
+pub fn scan_run_files(ralph_dir: &Path, prd: &Prd) -> io::Result<Vec<Finding>> {
+    let scanner = SecretScanner::load(ralph_dir)?;
+    let mut findings = scan_prd(prd, &scanner);
+    match fs::read_to_string(ralph_dir.join("progress.txt")) {
+        Ok(progress) => findings.extend(scan_progress(&progress, &scanner)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    Ok(findings)
+}
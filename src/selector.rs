@@ -0,0 +1,113 @@
+use crate::prd::{Prd, StoryOrder, UserStory};
+
+/// Which stories a run, its prompt and `ralph status` cover
+///
+/// Holds the `--story`, `--epic`, `--tag`, `--max-priority` and
+/// `--exclude-story` filters together, so every view of the scope agrees.
+/// The default selector matches every story.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorySelector {
+    /// Only this story
+    pub story: Option<String>,
+    /// Only the stories of this epic
+    pub epic: Option<String>,
+    /// Only stories with at least one of these tags (case-insensitive)
+    pub tags: Vec<String>,
+    /// Only stories with this priority or a more urgent one (a lower number)
+    pub max_priority: Option<u32>,
+    /// Never these stories
+    pub excluded: Vec<String>,
+}
+
+/// How the pending stories of a PRD relate to a selector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// No story is pending at all
+    AllComplete,
+    /// Stories are pending, but none of them match the filters
+    NoMatch { pending: usize },
+    /// `matching` of the `pending` stories match the filters
+    Pending { matching: usize, pending: usize },
+}
+
+impl StorySelector {
+    /// Whether a story is in scope, passed or not
+    pub fn matches(&self, story: &UserStory) -> bool {
+        self.story.as_ref().is_none_or(|id| story.id == *id)
+            && self
+                .epic
+                .as_ref()
+                .is_none_or(|epic| story.epic.as_ref() == Some(epic))
+            && (self.tags.is_empty()
+                || story
+                    .tags
+                    .iter()
+                    .any(|tag| self.tags.iter().any(|want| want.eq_ignore_ascii_case(tag))))
+            && self.max_priority.is_none_or(|max| story.priority <= max)
+            && !self.excluded.contains(&story.id)
+    }
+
+    /// Whether any filter narrows the stories
+    pub fn is_filtered(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Whether `--tag` or `--max-priority` narrow the stories
+    ///
+    /// These have no dedicated stopping rule, unlike a target story or epic.
+    pub fn has_attribute_filters(&self) -> bool {
+        !self.tags.is_empty() || self.max_priority.is_some()
+    }
+
+    /// Pending stories in scope, in `order`
+    pub fn pending<'a>(&self, prd: &'a Prd, order: StoryOrder) -> Vec<&'a UserStory> {
+        prd.pending_ordered(order)
+            .into_iter()
+            .filter(|story| self.matches(story))
+            .collect()
+    }
+
+    /// Compare the pending stories in scope with all pending stories
+    pub fn select(&self, prd: &Prd) -> Selection {
+        let pending = prd.user_stories.iter().filter(|s| !s.passes).count();
+        let matching = prd
+            .user_stories
+            .iter()
+            .filter(|s| !s.passes && self.matches(s))
+            .count();
+        match (pending, matching) {
+            (0, _) => Selection::AllComplete,
+            (pending, 0) => Selection::NoMatch { pending },
+            (pending, matching) => Selection::Pending { matching, pending },
+        }
+    }
+
+    /// The active filters as command-line flags, e.g. `--tag backend --max-priority 2`
+    pub fn describe(&self) -> String {
+        let mut flags = Vec::new();
+        if let Some(story) = &self.story {
+            flags.push(format!("--story {}", story));
+        }
+        if let Some(epic) = &self.epic {
+            flags.push(format!("--epic {}", epic));
+        }
+        for tag in &self.tags {
+            flags.push(format!("--tag {}", tag));
+        }
+        if let Some(max) = self.max_priority {
+            flags.push(format!("--max-priority {}", max));
+        }
+        for id in &self.excluded {
+            flags.push(format!("--exclude-story {}", id));
+        }
+        flags.join(" ")
+    }
+}
+
+/// Message for a selection where nothing pending is in scope
+pub fn no_match_message(pending: usize) -> String {
+    format!(
+        "No pending stories match the active filters ({} pending overall)",
+        pending
+    )
+}
@@ -0,0 +1,304 @@
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::prd::{CriticalPath, Prd, StoryOrder, UserStory};
+use crate::selector::StorySelector;
+
+/// A story as shown in `ralph status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoryEntry {
+    pub id: String,
+    pub title: String,
+    pub priority: u32,
+}
+
+impl From<&UserStory> for StoryEntry {
+    fn from(story: &UserStory) -> Self {
+        Self {
+            id: story.id.clone(),
+            title: story.title.clone(),
+            priority: story.priority,
+        }
+    }
+}
+
+/// A pending story that cannot start yet, and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlockedStory {
+    #[serde(flatten)]
+    pub story: StoryEntry,
+    /// Dependencies that have not passed (or do not exist)
+    pub waiting_on: Vec<String>,
+}
+
+/// Completion of one epic, with its stories by priority
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EpicProgress {
+    pub name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    pub completed: usize,
+    pub total: usize,
+    pub stories: Vec<EpicStory>,
+}
+
+/// A story as listed under its epic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EpicStory {
+    #[serde(flatten)]
+    pub story: StoryEntry,
+    pub passes: bool,
+}
+
+/// Story counts of the project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StatusCounts {
+    pub total: usize,
+    pub completed: usize,
+    pub pending: usize,
+    pub blocked: usize,
+}
+
+/// The stories matching `ralph status` filters, next to the overall counts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusFilter {
+    /// The filters as command-line flags
+    pub filters: String,
+    pub matching: usize,
+    pub completed: usize,
+    pub pending: usize,
+}
+
+/// Every story with the fields dashboards need, in PRD order
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoryStatus {
+    pub id: String,
+    pub title: String,
+    pub priority: u32,
+    pub passes: bool,
+    pub tags: Vec<String>,
+    pub estimate: Option<f64>,
+}
+
+impl From<&UserStory> for StoryStatus {
+    fn from(story: &UserStory) -> Self {
+        Self {
+            id: story.id.clone(),
+            title: story.title.clone(),
+            priority: story.priority,
+            passes: story.passes,
+            tags: story.tags.clone(),
+            estimate: story.estimate,
+        }
+    }
+}
+
+/// An acceptance criterion in the checklist view
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChecklistItem {
+    /// 1-based number, as `ralph story check` takes it
+    pub number: usize,
+    pub text: String,
+    pub done: bool,
+}
+
+/// A story's acceptance criteria as a checklist, with its notes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoryChecklist {
+    pub id: String,
+    pub title: String,
+    pub passes: bool,
+    pub criteria: Vec<ChecklistItem>,
+    pub notes: String,
+}
+
+impl From<&UserStory> for StoryChecklist {
+    fn from(story: &UserStory) -> Self {
+        Self {
+            id: story.id.clone(),
+            title: story.title.clone(),
+            passes: story.passes,
+            criteria: story
+                .acceptance_criteria
+                .iter()
+                .enumerate()
+                .map(|(index, text)| ChecklistItem {
+                    number: index + 1,
+                    text: text.clone(),
+                    done: story.is_criterion_done(text),
+                })
+                .collect(),
+            notes: story.notes.clone(),
+        }
+    }
+}
+
+/// Acceptance checklists for `ralph status --acceptance` or `ralph status <id>`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AcceptanceReport {
+    pub project: String,
+    pub stories: Vec<StoryChecklist>,
+}
+
+impl AcceptanceReport {
+    /// Checklists of the one story `story_id`, or else of the pending stories
+    /// `selector` matches, by priority
+    pub fn from_prd(
+        prd: &Prd,
+        selector: &StorySelector,
+        story_id: Option<&str>,
+    ) -> Result<Self, String> {
+        let stories = match story_id {
+            Some(id) => vec![prd.find_story(id).ok_or_else(|| prd.unknown_story_message(id))?],
+            None => selector.pending(prd, StoryOrder::Priority),
+        };
+        Ok(Self {
+            project: prd.project.clone(),
+            stories: stories.into_iter().map(StoryChecklist::from).collect(),
+        })
+    }
+}
+
+/// Project status grouped into sections
+///
+/// Built once from the PRD so every `--format` shows the same data. The JSON
+/// form is the full machine-readable state of the project.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusReport {
+    pub project: String,
+    pub branch: String,
+    pub total: usize,
+    pub counts: StatusCounts,
+    /// Share of stories that pass, from 0 to 100
+    pub percentage: f64,
+    /// Share of the estimated work that passes; only when stories have estimates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weighted_percentage: Option<f64>,
+    /// Heaviest chain of pending stories through their dependencies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub critical_path: Option<CriticalPath>,
+    /// Id of the story the agent will pick next
+    pub next_story: Option<String>,
+    /// Counts of the filtered stories; the sections only list these
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<StatusFilter>,
+    pub stories: Vec<StoryStatus>,
+    /// Stories that pass, in PRD order
+    pub completed: Vec<StoryEntry>,
+    /// The story the agent will pick next
+    pub in_progress: Option<StoryEntry>,
+    /// Pending stories waiting on unmet dependencies, in PRD order
+    pub blocked: Vec<BlockedStory>,
+    /// Remaining actionable stories, by priority
+    pub up_next: Vec<StoryEntry>,
+    /// Pending stories with no acceptance criteria, which the agent cannot verify
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub without_criteria: Vec<StoryEntry>,
+    /// Stories grouped by epic; empty when the PRD does not use epics
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub epics: Vec<EpicProgress>,
+    /// Time since prd.json was last modified, shown in the table view only
+    #[serde(skip)]
+    pub prd_age: Option<Duration>,
+}
+
+impl StatusReport {
+    /// Group the stories of a PRD that `selector` matches into status sections
+    ///
+    /// `counts` and `percentage` still cover the whole PRD, so a filtered
+    /// view can be compared with it.
+    pub fn from_prd(prd: &Prd, selector: &StorySelector) -> Self {
+        let mut actionable = prd.actionable_stories();
+        actionable.retain(|s| selector.matches(s));
+        // Stable sort keeps PRD order between stories of equal priority
+        actionable.sort_by_key(|s| s.priority);
+        let next_story = actionable.first().map(|s| s.id.clone());
+        let mut actionable = actionable.into_iter().map(StoryEntry::from);
+        let stats = prd.stats();
+
+        Self {
+            project: prd.project.clone(),
+            branch: prd.branch_name().to_string(),
+            total: stats.total,
+            counts: StatusCounts {
+                total: stats.total,
+                completed: stats.completed,
+                pending: stats.pending,
+                blocked: prd.blocked_stories().len(),
+            },
+            percentage: stats.percentage(),
+            weighted_percentage: prd.weighted_percentage(),
+            critical_path: prd.critical_path(),
+            next_story,
+            filter: selector.is_filtered().then(|| {
+                let matching: Vec<&UserStory> =
+                    prd.user_stories.iter().filter(|s| selector.matches(s)).collect();
+                let completed = matching.iter().filter(|s| s.passes).count();
+                StatusFilter {
+                    filters: selector.describe(),
+                    matching: matching.len(),
+                    completed,
+                    pending: matching.len() - completed,
+                }
+            }),
+            stories: prd
+                .user_stories
+                .iter()
+                .filter(|s| selector.matches(s))
+                .map(StoryStatus::from)
+                .collect(),
+            completed: prd
+                .user_stories
+                .iter()
+                .filter(|s| s.passes && selector.matches(s))
+                .map(StoryEntry::from)
+                .collect(),
+            in_progress: actionable.next(),
+            blocked: prd
+                .blocked_stories()
+                .into_iter()
+                .filter(|s| selector.matches(s))
+                .map(|s| BlockedStory {
+                    story: StoryEntry::from(s),
+                    waiting_on: prd
+                        .unmet_dependencies(s)
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                })
+                .collect(),
+            up_next: actionable.collect(),
+            without_criteria: prd
+                .pending_without_criteria()
+                .into_iter()
+                .filter(|s| selector.matches(s))
+                .map(StoryEntry::from)
+                .collect(),
+            epics: epic_progress(prd),
+            prd_age: None,
+        }
+    }
+}
+
+fn epic_progress(prd: &Prd) -> Vec<EpicProgress> {
+    if !prd.has_epics() {
+        return Vec::new();
+    }
+    prd.epic_groups()
+        .into_iter()
+        .map(|group| EpicProgress {
+            name: group.name.to_string(),
+            description: group.description.to_string(),
+            completed: group.completed(),
+            total: group.stories.len(),
+            stories: group
+                .stories
+                .iter()
+                .map(|s| EpicStory {
+                    story: StoryEntry::from(*s),
+                    passes: s.passes,
+                })
+                .collect(),
+        })
+        .collect()
+}
@@ -1,5 +1,6 @@
 #[cfg(test)]
 use crate::agent::Agent;
+use crate::prd::{Prd, StoryOrder};
 
 /// Get the PRD skill content
 pub fn get_prd_skill_content() -> String {
@@ -16,6 +17,144 @@ pub fn get_agent_prompt() -> &'static str {
     include_str!("templates/prompt.md")
 }
 
+/// Marker rendered for a known placeholder that has no value, e.g. no pending story
+pub const UNFILLED_PLACEHOLDER: &str = "(none)";
+
+/// Characters per token assumed by [`prompt_token_estimate`]
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Rough number of tokens in a prompt: one per four characters, rounded up
+///
+/// Tokenizers differ between models, so this is only good for spotting a
+/// prompt that has grown out of proportion, e.g. from a large PRD. Built with
+/// the `tokenizer` feature, the prompt is counted with the cl100k_base BPE
+/// tokenizer instead, falling back to the heuristic if it cannot be loaded.
+pub fn prompt_token_estimate(prompt: &str) -> usize {
+    #[cfg(feature = "tokenizer")]
+    if let Some(bpe) = bpe_tokenizer() {
+        return bpe.encode_with_special_tokens(prompt).len();
+    }
+    prompt.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// The tokenizer behind [`prompt_token_estimate`], loaded once per process
+#[cfg(feature = "tokenizer")]
+fn bpe_tokenizer() -> Option<&'static tiktoken_rs::CoreBPE> {
+    static BPE: std::sync::OnceLock<Option<tiktoken_rs::CoreBPE>> = std::sync::OnceLock::new();
+    BPE.get_or_init(|| tiktoken_rs::cl100k_base().ok()).as_ref()
+}
+
+/// Substitute `{{placeholder}}` variables in a prompt template from the PRD
+///
+/// Supported placeholders are `project`, `branch`, `pending_count` and
+/// `next_story`, the first pending story in `order`; one without a value
+/// renders as [`UNFILLED_PLACEHOLDER`]. Fenced code blocks are copied as-is.
+/// Unknown placeholders are left untouched, and the rendered text is scanned
+/// for them afterwards so the caller can warn about them.
+pub fn render_prompt(template: &str, prd: &Prd, order: StoryOrder) -> (String, Vec<String>) {
+    let mut rendered = String::with_capacity(template.len());
+    for (line, in_fence) in fenced_lines(template) {
+        if in_fence {
+            rendered.push_str(line);
+            continue;
+        }
+        for_each_placeholder(line, |text, name| match name {
+            Some(name) => match prompt_variable(name, prd, order) {
+                Some(value) => rendered.push_str(value.as_deref().unwrap_or(UNFILLED_PLACEHOLDER)),
+                None => rendered.push_str(text),
+            },
+            None => rendered.push_str(text),
+        });
+    }
+
+    let unknown = unresolved_placeholders(&rendered);
+    (rendered, unknown)
+}
+
+/// Names of the `{{placeholder}}` tokens left in a prompt, outside code fences
+///
+/// Each name is listed once, in order of appearance. Brace pairs that do not
+/// hold a plain name, like `{{ a: 1 }}`, are not placeholders.
+pub fn unresolved_placeholders(prompt: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (line, _) in fenced_lines(prompt).filter(|(_, in_fence)| !in_fence) {
+        for_each_placeholder(line, |_, name| {
+            if let Some(name) = name {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        });
+    }
+    names
+}
+
+/// Split text into lines, marking those inside (or opening/closing) a code fence
+fn fenced_lines(text: &str) -> impl Iterator<Item = (&str, bool)> {
+    let mut in_fence = false;
+    text.split_inclusive('\n').map(move |line| {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            return (line, true);
+        }
+        (line, in_fence)
+    })
+}
+
+/// Walk a line in pieces, passing each `{{name}}` token with its name and
+/// everything else with `None`
+fn for_each_placeholder<'a>(line: &'a str, mut visit: impl FnMut(&'a str, Option<&'a str>)) {
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("}}") else {
+            break;
+        };
+        visit(&rest[..start], None);
+
+        let name = &after_open[..end];
+        let token = &rest[start..start + 2 + end + 2];
+        let is_placeholder =
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        visit(token, is_placeholder.then_some(name));
+        rest = &after_open[end + 2..];
+    }
+    visit(rest, None);
+}
+
+/// Resolve a single prompt placeholder
+///
+/// Returns `None` for an unknown name and `Some(None)` for a known one
+/// without a value.
+fn prompt_variable(name: &str, prd: &Prd, order: StoryOrder) -> Option<Option<String>> {
+    let value = match name {
+        "project" => prd.project.clone(),
+        "branch" => prd.branch_name.clone(),
+        "pending_count" => prd.pending_stories().to_string(),
+        "next_story" => prd
+            .pending_ordered(order)
+            .first()
+            .map(|s| s.display())
+            .unwrap_or_default(),
+        _ => return None,
+    };
+    Some(Some(value).filter(|v| !v.trim().is_empty()))
+}
+
+/// A new PRD from the prd.json template, for `ralph run --init-if-missing`
+///
+/// The branch is `ralph/` followed by the lowercased project name. Fails
+/// when the name is blank.
+pub fn starter_prd(project_name: &str, project_description: &str) -> Result<Prd, String> {
+    let mut prd: Prd = serde_json::from_str(include_str!("templates/prd_json_template.json"))
+        .map_err(|e| format!("Invalid PRD template: {}", e))?;
+    prd.set_branch(&project_name.to_lowercase())?;
+    prd.project = project_name.trim().to_string();
+    prd.description = project_description.trim().to_string();
+    Ok(prd)
+}
+
 /// Get the prd.json.example template content
 #[cfg(test)]
 pub fn get_prd_json_template(
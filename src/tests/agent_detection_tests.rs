@@ -1,9 +1,22 @@
 //! Agent Detection Tests
 //!
 //! Tests for the agent detection functionality in Ralph CLI.
-//! These tests verify that the system correctly detects installed AI agents.
+//! These tests verify that the system correctly detects installed AI agents,
+//! and that spawn failures point at agents installed outside PATH.
 
-use crate::agent::{Agent, detect_agents, is_command_available};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use tempfile::TempDir;
+
+use crate::agent::{
+    Agent, AgentInvocation, PromptDelivery, check_agent, command_version, common_install_dirs,
+    detect_agents, find_outside_path, is_command_available, spawn_error_guidance,
+};
+use crate::agent_cache::{find_in_path, find_in_search_path, AgentCache, CachedAgent};
 
 /// Test that detect_agents returns a list of available agents
 #[test]
@@ -193,3 +206,294 @@ fn test_detect_agents_manual_verification() {
         );
     }
 }
+
+/// Test that command_version returns the first line of --version output
+#[test]
+fn test_command_version_existing_command() {
+    let version = command_version("echo");
+    assert!(version.is_some(), "echo should be runnable");
+    assert!(!version.unwrap().contains('\n'));
+}
+
+/// Test that command_version returns None for missing commands
+#[test]
+fn test_command_version_nonexistent_command() {
+    assert!(command_version("nonexistent_command_67890").is_none());
+}
+
+/// Test that known agent names map to their commands
+#[test]
+fn test_agent_from_command() {
+    assert_eq!(Agent::from_command("claude"), Some(Agent::Claude));
+    assert_eq!(Agent::from_command("CodeBuddy"), Some(Agent::CodeBuddy));
+    assert_eq!(Agent::from_command("cargo"), None);
+}
+
+/// Test the invocation descriptor of every agent
+#[test]
+fn test_agent_invocations() {
+    assert_eq!(Agent::Aider.command(), "aider");
+    assert_eq!(Agent::Aider.name(), "Aider");
+    assert_eq!(
+        Agent::Aider.invocation(),
+        AgentInvocation {
+            unattended_flag: Some("--yes-always"),
+            prompt: PromptDelivery::Flag("--message"),
+            ..AgentInvocation::CUSTOM
+        }
+    );
+    assert_eq!(Agent::Aider.global_skills_dir(), None);
+
+    // The other agents read the prompt from stdin
+    for agent in [Agent::Amp, Agent::Claude, Agent::CodeBuddy] {
+        assert_eq!(agent.invocation().prompt, PromptDelivery::Stdin, "{:?}", agent);
+        assert!(agent.invocation().unattended_flag.is_some());
+    }
+    assert!(Agent::Claude.invocation().structured_output.is_some());
+}
+
+/// Test that tool commands map to their agent's invocation
+#[test]
+fn test_invocation_for_tool() {
+    assert_eq!(AgentInvocation::for_tool("aider"), Agent::Aider.invocation());
+    assert_eq!(AgentInvocation::for_tool("Claude"), Agent::Claude.invocation());
+    assert_eq!(AgentInvocation::for_tool("./my-agent.sh"), AgentInvocation::CUSTOM);
+    assert_eq!(Agent::from_command("aider"), Some(Agent::Aider));
+    assert_eq!(Agent::ALL.len(), 4);
+}
+
+/// Test that the unattended flag comes before the base args
+#[test]
+fn test_invocation_leading_args() {
+    let args: Vec<&str> = Agent::Claude.invocation().leading_args().collect();
+    assert_eq!(args, ["--dangerously-skip-permissions", "--print"]);
+
+    assert_eq!(AgentInvocation::CUSTOM.leading_args().count(), 0);
+}
+
+/// Test that check_agent accepts arbitrary commands
+#[test]
+fn test_check_agent_with_any_command() {
+    let version = check_agent("cargo").expect("cargo should be available in PATH");
+    assert!(version.contains("cargo"));
+
+    assert_eq!(check_agent("ralph_nonexistent_agent_xyz"), None);
+}
+
+/// Cache an existing file as a fake agent binary
+fn cached_binary(temp_dir: &TempDir) -> CachedAgent {
+    let path = temp_dir.path().join("claude");
+    fs::write(&path, "#!/bin/sh\n").unwrap();
+    let modified = fs::metadata(&path)
+        .unwrap()
+        .modified()
+        .unwrap()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    CachedAgent {
+        command: "claude".to_string(),
+        version: "1.0.0".to_string(),
+        path,
+        modified: Some(modified),
+    }
+}
+
+/// The PATH a cache was written with
+fn search_path(cache: &AgentCache) -> OsString {
+    OsString::from(cache.search_path.clone().unwrap_or_default())
+}
+
+/// Test that the agent cache expires after its TTL
+#[test]
+fn test_agent_cache_expires_after_ttl() {
+    let temp_dir = TempDir::new().unwrap();
+    let cache = AgentCache::new(vec![cached_binary(&temp_dir)]);
+    let ttl = Duration::from_secs(24 * 3600);
+    let now = SystemTime::now();
+    let path = search_path(&cache);
+
+    assert!(cache.is_valid(now, ttl, &path));
+    assert!(!cache.is_valid(now + ttl, ttl, &path));
+    // Clock moved backwards past the detection time
+    assert!(!cache.is_valid(SystemTime::UNIX_EPOCH, ttl, &path));
+}
+
+/// Test that the agent cache is invalidated when a binary is removed or replaced
+#[test]
+fn test_agent_cache_invalidated_by_binary_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    let agent = cached_binary(&temp_dir);
+    let ttl = Duration::from_secs(3600);
+
+    let mut replaced = AgentCache::new(vec![agent.clone()]);
+    replaced.agents[0].modified = agent.modified.map(|m| m - 60);
+    assert!(!replaced.is_valid(SystemTime::now(), ttl, &search_path(&replaced)));
+
+    let removed = AgentCache::new(vec![agent.clone()]);
+    fs::remove_file(&agent.path).unwrap();
+    assert!(!removed.is_valid(SystemTime::now(), ttl, &search_path(&removed)));
+}
+
+/// Test that the agent cache only holds for the PATH it was detected with
+#[test]
+fn test_agent_cache_invalidated_by_path_change() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cache = AgentCache::new(vec![cached_binary(&temp_dir)]);
+    let ttl = Duration::from_secs(3600);
+    let now = SystemTime::now();
+    cache.search_path = Some("/usr/bin:/bin".to_string());
+
+    assert!(cache.is_valid(now, ttl, OsStr::new("/usr/bin:/bin")));
+    assert!(!cache.is_valid(now, ttl, OsStr::new("/opt/agents/bin:/usr/bin:/bin")));
+
+    // Caches written before PATH was recorded are re-probed once
+    cache.search_path = None;
+    assert!(!cache.is_valid(now, ttl, OsStr::new("/usr/bin:/bin")));
+    let old_format = r#"{"detected_at": 1, "agents": []}"#;
+    let old: AgentCache = serde_json::from_str(old_format).unwrap();
+    assert_eq!(old.search_path, None);
+}
+
+/// Test that the agent cache round-trips and corrupt files are ignored
+#[test]
+fn test_agent_cache_load_and_save() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("nested").join("agents-cache.json");
+    assert_eq!(AgentCache::load(&path), None);
+
+    let cache = AgentCache::new(vec![cached_binary(&temp_dir)]);
+    cache.save(&path).unwrap();
+    assert_eq!(AgentCache::load(&path), Some(cache));
+
+    fs::write(&path, "{ not json").unwrap();
+    assert_eq!(AgentCache::load(&path), None);
+}
+
+/// Test that find_in_path resolves commands like the shell does
+#[test]
+fn test_find_in_path() {
+    let cargo = find_in_path("cargo").expect("cargo should be available in PATH");
+    assert!(cargo.is_file());
+    assert_eq!(find_in_path(cargo.to_str().unwrap()), Some(cargo));
+    assert_eq!(find_in_path("ralph_nonexistent_agent_xyz"), None);
+}
+
+/// Create a fake agent binary under a fabricated home directory
+fn install_fake_agent(home: &Path, dir: &str) -> std::path::PathBuf {
+    let bin = home.join(dir);
+    fs::create_dir_all(&bin).unwrap();
+    let agent = bin.join("ralph-fake-agent");
+    fs::write(&agent, "#!/bin/sh\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&agent, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    agent
+}
+
+/// Test that find_in_search_path only looks in the given PATH value
+#[test]
+fn test_find_in_search_path_uses_given_path() {
+    let home = TempDir::new().unwrap();
+    let agent = install_fake_agent(home.path(), "tools/bin");
+    let path = std::env::join_paths(["/nonexistent/bin".into(), home.path().join("tools/bin")])
+        .unwrap();
+
+    assert_eq!(find_in_search_path("ralph-fake-agent", &path), Some(agent));
+    assert_eq!(find_in_search_path("ralph-fake-agent", &OsString::from("/nonexistent")), None);
+}
+
+/// Test that version manager directories are listed for every installed version
+#[test]
+fn test_common_install_dirs_include_node_versions() {
+    let home = TempDir::new().unwrap();
+    fs::create_dir_all(home.path().join(".nvm/versions/node/v20.11.0/bin")).unwrap();
+    fs::create_dir_all(home.path().join(".nvm/versions/node/v18.19.0/bin")).unwrap();
+
+    let dirs = common_install_dirs(Some(home.path()));
+
+    assert!(dirs.contains(&home.path().join(".local/bin")));
+    let nvm: Vec<_> = dirs.iter().filter(|d| d.starts_with(home.path().join(".nvm"))).collect();
+    assert_eq!(
+        nvm,
+        [
+            &home.path().join(".nvm/versions/node/v18.19.0/bin"),
+            &home.path().join(".nvm/versions/node/v20.11.0/bin"),
+        ]
+    );
+    assert!(common_install_dirs(None).iter().all(|d| !d.starts_with(home.path())));
+}
+
+/// Test that an agent installed through nvm is found when PATH misses it
+#[test]
+fn test_find_outside_path_skips_dirs_already_on_path() {
+    let home = TempDir::new().unwrap();
+    let agent = install_fake_agent(home.path(), ".nvm/versions/node/v20.11.0/bin");
+    let bin = agent.parent().unwrap().to_path_buf();
+
+    let minimal = OsString::from("/usr/bin:/bin");
+    assert_eq!(find_outside_path("ralph-fake-agent", &minimal, Some(home.path())), [agent]);
+
+    let with_nvm = std::env::join_paths(["/usr/bin".into(), bin]).unwrap();
+    assert!(find_outside_path("ralph-fake-agent", &with_nvm, Some(home.path())).is_empty());
+}
+
+/// Test the guidance for an agent that is installed but not on PATH
+#[test]
+fn test_spawn_error_guidance_not_found_suggests_install_location() {
+    let home = TempDir::new().unwrap();
+    let agent = install_fake_agent(home.path(), ".local/bin");
+    let error = io::Error::from(io::ErrorKind::NotFound);
+    let path = OsString::from("/nonexistent/a:/nonexistent/b");
+
+    let message = spawn_error_guidance("ralph-fake-agent", &error, &path, Some(home.path()), false);
+
+    assert!(message.starts_with("Failed to spawn ralph-fake-agent: "));
+    assert!(message.contains("ralph-fake-agent is not on the PATH ralph sees."));
+    assert!(message.contains(&format!("  {}", agent.display())));
+    assert!(message.contains(&format!("--tool-path {}", agent.display())));
+    assert!(message.contains("ralph detect"));
+    assert!(!message.contains("/nonexistent/a"));
+
+    let verbose = spawn_error_guidance("ralph-fake-agent", &error, &path, Some(home.path()), true);
+    assert!(verbose.contains("PATH ralph saw:\n  /nonexistent/a\n  /nonexistent/b"));
+    assert!(verbose.contains(&format!("  {}", home.path().join(".volta/bin").display())));
+}
+
+/// Test the guidance when the agent is nowhere to be found
+#[test]
+fn test_spawn_error_guidance_not_installed() {
+    let home = TempDir::new().unwrap();
+    let error = io::Error::from(io::ErrorKind::NotFound);
+
+    let message =
+        spawn_error_guidance("ralph-fake-agent", &error, &OsString::new(), Some(home.path()), true);
+
+    assert!(message.contains("not found in the usual install locations"));
+    assert!(message.contains("PATH ralph saw:\n  (empty)"));
+}
+
+/// Test the guidance for agents that exist but cannot be run
+#[test]
+fn test_spawn_error_guidance_permission_denied_and_paths() {
+    let home = TempDir::new().unwrap();
+    let agent = install_fake_agent(home.path(), "bin");
+    let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+
+    let path = agent.parent().unwrap().as_os_str();
+    let message = spawn_error_guidance("ralph-fake-agent", &denied, path, None, false);
+    assert!(message.contains(&format!("Try: chmod +x {}", agent.display())));
+
+    let missing = home.path().join("missing-agent");
+    let message = spawn_error_guidance(
+        missing.to_str().unwrap(),
+        &io::Error::from(io::ErrorKind::NotFound),
+        &OsString::new(),
+        None,
+        false,
+    );
+    assert!(message.contains("There is no file at"));
+    assert!(!message.contains("not on the PATH"));
+}
@@ -0,0 +1,119 @@
+//! AGENTS.md Section Tests
+//!
+//! Tests for the ralph-managed AGENTS.md section:
+//! - Rendering with and without a PRD
+//! - Appending the section when markers are missing
+//! - Replacing only the section between the markers
+//! - Rejecting duplicated or unbalanced markers
+//! - Preserving CRLF line endings
+
+use crate::agents_md::{find_section, render_section, splice_section, BEGIN_MARKER, END_MARKER};
+use crate::prd::Prd;
+
+fn sample_prd() -> Prd {
+    serde_json::from_str(
+        r#"{
+            "project": "Widget Shop",
+            "branchName": "ralph/widgets",
+            "description": "Sell widgets online",
+            "userStories": [
+                {"id": "US-001", "title": "Catalog", "description": "Desc", "acceptanceCriteria": ["Works"], "priority": 1, "passes": true, "notes": ""},
+                {"id": "US-002", "title": "Cart", "description": "Desc", "acceptanceCriteria": ["Works"], "priority": 2, "passes": false, "notes": ""}
+            ]
+        }"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_render_section_with_prd() {
+    let section = render_section(Some(&sample_prd()));
+
+    assert!(section.starts_with(BEGIN_MARKER));
+    assert!(section.ends_with(END_MARKER));
+    assert!(section.contains("**Widget Shop** - Sell widgets online"));
+    assert!(section.contains("`ralph/widgets` (1/2 stories complete)"));
+    assert!(section.contains("ralph/prd.json"));
+    assert!(section.contains("ralph/progress.txt"));
+    assert!(section.contains("<promise>COMPLETE</promise>"));
+    assert!(section.contains("<promise>STORY_PASSED:"));
+}
+
+#[test]
+fn test_render_section_without_prd() {
+    let section = render_section(None);
+    assert!(section.contains("No PRD yet"));
+    assert!(section.contains("ralph agents-md refresh"));
+}
+
+#[test]
+fn test_splice_into_empty_file() {
+    let updated = splice_section("", "SECTION").unwrap();
+    assert_eq!(updated, "SECTION\n");
+}
+
+#[test]
+fn test_splice_appends_when_markers_missing() {
+    let existing = "# My Project\n\nHuman notes.\n\n";
+    let updated = splice_section(existing, "SECTION").unwrap();
+    assert_eq!(updated, "# My Project\n\nHuman notes.\n\nSECTION\n");
+}
+
+#[test]
+fn test_splice_replaces_only_ralph_section() {
+    let existing = format!(
+        "# My Project\n\nBefore.\n\n{}\nold ralph content\n{}\n\nAfter.\n",
+        BEGIN_MARKER, END_MARKER
+    );
+    let section = format!("{}\nnew ralph content\n{}", BEGIN_MARKER, END_MARKER);
+
+    let updated = splice_section(&existing, &section).unwrap();
+
+    assert_eq!(
+        updated,
+        format!("# My Project\n\nBefore.\n\n{}\n\nAfter.\n", section)
+    );
+    // Splicing is idempotent
+    assert_eq!(splice_section(&updated, &section).unwrap(), updated);
+}
+
+#[test]
+fn test_splice_rejects_duplicate_markers() {
+    let existing = format!(
+        "{b}\none\n{e}\n\n{b}\ntwo\n{e}\n",
+        b = BEGIN_MARKER,
+        e = END_MARKER
+    );
+    let err = splice_section(&existing, "SECTION").unwrap_err();
+    assert!(err.contains("expected one of each"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_find_section_rejects_unbalanced_markers() {
+    assert!(find_section(&format!("{}\nno end\n", BEGIN_MARKER)).is_err());
+    assert!(find_section(&format!("no begin\n{}\n", END_MARKER)).is_err());
+    assert!(find_section(&format!("{}\n{}\n", END_MARKER, BEGIN_MARKER)).is_err());
+    assert_eq!(find_section("plain text").unwrap(), None);
+}
+
+#[test]
+fn test_splice_preserves_crlf_line_endings() {
+    let existing = format!(
+        "# Title\r\n\r\n{}\r\nold\r\n{}\r\nFooter\r\n",
+        BEGIN_MARKER, END_MARKER
+    );
+    let section = render_section(None);
+
+    let updated = splice_section(&existing, &section).unwrap();
+
+    assert!(updated.starts_with("# Title\r\n\r\n"));
+    assert!(updated.ends_with(&format!("{}\r\nFooter\r\n", END_MARKER)));
+    assert!(!updated.replace("\r\n", "").contains('\n'), "found a bare LF");
+    assert!(updated.contains("No PRD yet"));
+}
+
+#[test]
+fn test_splice_appends_with_crlf_when_markers_missing() {
+    let updated = splice_section("# Title\r\nNotes\r\n", "A\nB").unwrap();
+    assert_eq!(updated, "# Title\r\nNotes\r\n\r\nA\r\nB\r\n");
+}
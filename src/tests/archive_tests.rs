@@ -0,0 +1,258 @@
+//! Archive Management Tests
+//!
+//! Tests for archive listing and `ralph archive clean` retention:
+//! - Date prefix parsing and newest-first ordering
+//! - --keep and --older-than selection
+//! - Undated folders are never pruned
+//! - Size formatting
+//! - Unique `-error` folders for failed runs and `-progress` folders for set-aside logs
+//! - Export/import bundles: round trip, README and structure checks
+
+use chrono::NaiveDate;
+use std::fs;
+use tempfile::TempDir;
+
+use crate::archive::{
+    archives_to_prune, error_archive_dir, export_archive, format_bytes, inspect_bundle,
+    list_archives, progress_archive_dir, unpack_bundle, ArchiveEntry, RetentionPolicy,
+    BUNDLE_README,
+};
+
+/// Create fabricated archive folders, each containing a small prd.json
+fn create_archives(temp_dir: &TempDir, names: &[&str]) -> std::path::PathBuf {
+    let archive_dir = temp_dir.path().join("archive");
+    for name in names {
+        let dir = archive_dir.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("prd.json"), "{}").unwrap();
+    }
+    archive_dir
+}
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 3, 1).unwrap()
+}
+
+fn names(archives: &[&ArchiveEntry]) -> Vec<String> {
+    archives.iter().map(|a| a.name.clone()).collect()
+}
+
+#[test]
+fn test_archive_entry_parses_date_prefix() {
+    let entry = ArchiveEntry::from_path("archive/2026-01-15-feature-x".into());
+    assert_eq!(entry.date, NaiveDate::from_ymd_opt(2026, 1, 15));
+
+    let undated = ArchiveEntry::from_path("archive/manual-backup".into());
+    assert!(undated.date.is_none());
+}
+
+#[test]
+fn test_list_archives_newest_first() {
+    let temp_dir = TempDir::new().unwrap();
+    let archive_dir = create_archives(
+        &temp_dir,
+        &["2026-01-10-a", "notes", "2026-02-20-b", "2025-12-31-c"],
+    );
+
+    let archives = list_archives(&archive_dir).unwrap();
+    let listed: Vec<&str> = archives.iter().map(|a| a.name.as_str()).collect();
+
+    assert_eq!(listed, vec!["2026-02-20-b", "2026-01-10-a", "2025-12-31-c", "notes"]);
+}
+
+#[test]
+fn test_list_archives_missing_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    assert!(list_archives(&temp_dir.path().join("archive")).unwrap().is_empty());
+}
+
+#[test]
+fn test_prune_keep_n() {
+    let temp_dir = TempDir::new().unwrap();
+    let archive_dir = create_archives(
+        &temp_dir,
+        &["2026-01-10-a", "2026-02-20-b", "2025-12-31-c", "2025-11-01-d"],
+    );
+    let archives = list_archives(&archive_dir).unwrap();
+
+    let policy = RetentionPolicy { keep: Some(2), older_than_days: None };
+    let pruned = archives_to_prune(&archives, policy, today());
+
+    assert_eq!(names(&pruned), vec!["2025-12-31-c", "2025-11-01-d"]);
+}
+
+#[test]
+fn test_prune_older_than_days() {
+    let temp_dir = TempDir::new().unwrap();
+    let archive_dir = create_archives(
+        &temp_dir,
+        &["2026-02-25-a", "2026-01-30-b", "2026-01-29-c"],
+    );
+    let archives = list_archives(&archive_dir).unwrap();
+
+    let policy = RetentionPolicy { keep: None, older_than_days: Some(30) };
+    let pruned = archives_to_prune(&archives, policy, today());
+
+    assert_eq!(names(&pruned), vec!["2026-01-29-c"]);
+}
+
+#[test]
+fn test_prune_combined_policy_keeps_if_either_rule_keeps() {
+    let temp_dir = TempDir::new().unwrap();
+    let archive_dir = create_archives(
+        &temp_dir,
+        &["2026-02-28-a", "2026-02-27-b", "2025-06-01-c", "2025-05-01-d"],
+    );
+    let archives = list_archives(&archive_dir).unwrap();
+
+    let policy = RetentionPolicy { keep: Some(3), older_than_days: Some(7) };
+    let pruned = archives_to_prune(&archives, policy, today());
+
+    assert_eq!(names(&pruned), vec!["2025-05-01-d"]);
+}
+
+#[test]
+fn test_prune_never_selects_undated_or_without_policy() {
+    let temp_dir = TempDir::new().unwrap();
+    let archive_dir = create_archives(&temp_dir, &["manual", "2020-01-01-old"]);
+    let archives = list_archives(&archive_dir).unwrap();
+
+    let policy = RetentionPolicy { keep: Some(0), older_than_days: None };
+    assert_eq!(names(&archives_to_prune(&archives, policy, today())), vec!["2020-01-01-old"]);
+
+    assert!(archives_to_prune(&archives, RetentionPolicy::default(), today()).is_empty());
+}
+
+#[test]
+fn test_format_bytes() {
+    assert_eq!(format_bytes(512), "512 B");
+    assert_eq!(format_bytes(1536), "1.5 KB");
+    assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+}
+
+#[test]
+fn test_error_archive_dir_is_unique_per_day() {
+    let temp_dir = TempDir::new().unwrap();
+    let archive_dir = temp_dir.path().join("archive");
+
+    let first = error_archive_dir(&archive_dir, today(), "feature");
+    assert_eq!(first, archive_dir.join("2026-03-01-feature-error"));
+
+    fs::create_dir_all(&first).unwrap();
+    let second = error_archive_dir(&archive_dir, today(), "feature");
+    assert_eq!(second, archive_dir.join("2026-03-01-feature-error-2"));
+
+    fs::create_dir_all(&second).unwrap();
+    assert_eq!(
+        error_archive_dir(&archive_dir, today(), "feature"),
+        archive_dir.join("2026-03-01-feature-error-3")
+    );
+
+    // The dated prefix keeps error archives under the retention policy
+    assert_eq!(ArchiveEntry::from_path(first).date, Some(today()));
+}
+
+#[test]
+fn test_progress_archive_dir_is_unique_per_day() {
+    let temp_dir = TempDir::new().unwrap();
+    let archive_dir = temp_dir.path().join("archive");
+
+    let first = progress_archive_dir(&archive_dir, today(), "old-feature");
+    assert_eq!(first, archive_dir.join("2026-03-01-old-feature-progress"));
+
+    fs::create_dir_all(&first).unwrap();
+    assert_eq!(
+        progress_archive_dir(&archive_dir, today(), "old-feature"),
+        archive_dir.join("2026-03-01-old-feature-progress-2")
+    );
+}
+
+/// An archived run with the usual files and a log folder
+fn create_full_archive(temp_dir: &TempDir) -> std::path::PathBuf {
+    let dir = temp_dir.path().join("archive/2026-02-01-feature-x");
+    fs::create_dir_all(dir.join("logs")).unwrap();
+    fs::write(dir.join("prd.json"), r#"{"project": "X"}"#).unwrap();
+    fs::write(dir.join("progress.txt"), "# Ralph Progress Log\n").unwrap();
+    fs::write(dir.join("metadata.json"), r#"{"branch": "ralph/feature-x"}"#).unwrap();
+    fs::write(dir.join("logs/iteration-1.log"), "agent output\n").unwrap();
+    dir
+}
+
+/// Write a bundle with the given files, bypassing `export_archive`
+fn write_bundle(path: &std::path::Path, files: &[(&str, &str)]) {
+    let out = fs::File::create(path).unwrap();
+    let encoder = flate2::write::GzEncoder::new(out, flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    for (name, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, content.as_bytes()).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap();
+}
+
+#[test]
+fn test_export_import_round_trip() {
+    let source = TempDir::new().unwrap();
+    let archive = create_full_archive(&source);
+    let bundle = source.path().join("bundle.tar.gz");
+
+    export_archive(&archive, &bundle).unwrap();
+
+    let contents = inspect_bundle(&bundle).unwrap();
+    assert_eq!(contents.name, "2026-02-01-feature-x");
+    assert_eq!(contents.files.len(), 4);
+
+    let target = TempDir::new().unwrap();
+    let imported = unpack_bundle(&bundle, &target.path().join("archive")).unwrap();
+    assert_eq!(imported, target.path().join("archive/2026-02-01-feature-x"));
+    for file in ["prd.json", "progress.txt", "metadata.json", "logs/iteration-1.log"] {
+        assert_eq!(
+            fs::read(imported.join(file)).unwrap(),
+            fs::read(archive.join(file)).unwrap(),
+            "{} differs",
+            file
+        );
+    }
+    assert!(!imported.join(BUNDLE_README).exists());
+    assert!(!target.path().join("archive").join(BUNDLE_README).exists());
+}
+
+#[test]
+fn test_export_writes_readme() {
+    let temp_dir = TempDir::new().unwrap();
+    let bundle = temp_dir.path().join("bundle.tar.gz");
+    export_archive(&create_full_archive(&temp_dir), &bundle).unwrap();
+
+    let file = fs::File::open(&bundle).unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+    assert_eq!(entry.path().unwrap().to_str(), Some(BUNDLE_README));
+    let mut readme = String::new();
+    std::io::Read::read_to_string(&mut entry, &mut readme).unwrap();
+
+    assert!(readme.starts_with("Ralph run archive: 2026-02-01-feature-x\n"));
+    assert!(readme.contains(&format!("by ralph {}", env!("CARGO_PKG_VERSION"))));
+    assert!(readme.contains("2026-02-01-feature-x/prd.json"));
+    assert!(readme.contains("2026-02-01-feature-x/logs/iteration-1.log"));
+}
+
+#[test]
+fn test_inspect_bundle_rejects_bad_structure() {
+    let temp_dir = TempDir::new().unwrap();
+    let bundle = temp_dir.path().join("bundle.tar.gz");
+    let error = |files: &[(&str, &str)]| {
+        write_bundle(&bundle, files);
+        inspect_bundle(&bundle).unwrap_err().to_string()
+    };
+
+    assert!(error(&[("a/progress.txt", "")]).contains("a has no prd.json"));
+    assert!(error(&[("a/prd.json", "{}"), ("b/prd.json", "{}")]).contains("more than one"));
+    assert!(error(&[("prd.json", "{}")]).contains("unexpected file prd.json"));
+    assert!(error(&[(BUNDLE_README, "hi")]).contains("no archive folder"));
+
+    fs::write(&bundle, "not a tarball").unwrap();
+    assert!(inspect_bundle(&bundle).is_err());
+}
@@ -0,0 +1,222 @@
+//! Branch Change Tests
+//!
+//! Tests for deciding whether the previous run belongs to another branch:
+//! - Parsing checkouts and renames out of HEAD's reflog
+//! - Combining the `.last-branch` and git signals
+//! - Real repositories: a renamed branch, a fresh clone, a deleted marker
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+use crate::branch_change::{
+    detect_branch_change, record_git_branch, GitBranchState, LAST_GIT_BRANCH_FILE,
+};
+use crate::git::{parse_branch_move, BranchMove};
+
+fn git(dir: &Path, args: &[&str]) {
+    let output = Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?}: {:?}", args, output);
+}
+
+/// A repository on `branch` with one commit and a ralph dir holding a progress log
+fn repo_on(branch: &str) -> TempDir {
+    let temp = TempDir::new().unwrap();
+    git(temp.path(), &["init", "-q", "-b", branch]);
+    fs::write(temp.path().join("progress.txt"), "# Ralph Progress Log\n").unwrap();
+    git(temp.path(), &["add", "-A"]);
+    git(temp.path(), &["commit", "-qm", "Initial commit"]);
+    temp
+}
+
+/// Date the progress log back, so later reflog entries count as after the run
+fn age_progress(dir: &Path) {
+    fs::File::options()
+        .write(true)
+        .open(dir.join("progress.txt"))
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(60))
+        .unwrap();
+}
+
+fn git_state(current: &str, recorded: Option<&str>, moves: Vec<BranchMove>) -> GitBranchState {
+    GitBranchState {
+        current: current.to_string(),
+        recorded: recorded.map(str::to_string),
+        moves,
+    }
+}
+
+fn checkout(from: &str, to: &str) -> BranchMove {
+    BranchMove {
+        at: 1_700_000_000,
+        from: from.to_string(),
+        to: to.to_string(),
+        renamed: false,
+    }
+}
+
+// ============================================================================
+// Reflog parsing
+// ============================================================================
+
+#[test]
+fn test_parse_checkout() {
+    let parsed = parse_branch_move("HEAD@{1792169207}\tcheckout: moving from main to ralph/b");
+    assert_eq!(
+        parsed,
+        Some(BranchMove {
+            at: 1792169207,
+            from: "main".to_string(),
+            to: "ralph/b".to_string(),
+            renamed: false,
+        })
+    );
+}
+
+#[test]
+fn test_parse_rename() {
+    let parsed = parse_branch_move(
+        "HEAD@{1792169207}\tBranch: renamed refs/heads/ralph/a to refs/heads/ralph/b",
+    )
+    .unwrap();
+    assert_eq!((parsed.from.as_str(), parsed.to.as_str()), ("ralph/a", "ralph/b"));
+    assert!(parsed.renamed);
+}
+
+#[test]
+fn test_parse_ignores_other_entries() {
+    assert_eq!(parse_branch_move("HEAD@{1792169207}\tcommit: Add login"), None);
+    assert_eq!(parse_branch_move("HEAD@{1792169207}\tcheckout: moving from main to main"), None);
+    assert_eq!(parse_branch_move("HEAD@{1}\tclone: from https://example.com/repo"), None);
+    assert_eq!(parse_branch_move("not a reflog line"), None);
+}
+
+// ============================================================================
+// Combining signals
+// ============================================================================
+
+#[test]
+fn test_last_branch_alone_outside_git() {
+    let change = detect_branch_change(Some("ralph/old\n"), "ralph/new", None).unwrap();
+    assert_eq!(change.previous, "ralph/old");
+    assert_eq!(change.signals, [".last-branch: ralph/old -> ralph/new"]);
+
+    assert_eq!(detect_branch_change(Some("ralph/new"), "ralph/new", None), None);
+    assert_eq!(detect_branch_change(Some(""), "ralph/new", None), None);
+    assert_eq!(detect_branch_change(None, "ralph/new", None), None);
+}
+
+#[test]
+fn test_git_branch_change_alone_triggers() {
+    let git = git_state("ralph/b", Some("ralph/a"), Vec::new());
+    let change = detect_branch_change(Some("ralph/b"), "ralph/b", Some(&git)).unwrap();
+    assert_eq!(change.previous, "ralph/a");
+    assert_eq!(change.signals, ["git branch: ralph/a -> ralph/b"]);
+}
+
+#[test]
+fn test_both_signals_recorded_and_last_branch_names_the_archive() {
+    let git = git_state("feature/new", Some("feature/old"), Vec::new());
+    let change = detect_branch_change(Some("ralph/old"), "ralph/new", Some(&git)).unwrap();
+    assert_eq!(change.previous, "ralph/old");
+    assert_eq!(
+        change.signals,
+        [".last-branch: ralph/old -> ralph/new", "git branch: feature/old -> feature/new"]
+    );
+}
+
+#[test]
+fn test_unchanged_git_branch_is_no_change() {
+    let git = git_state("ralph/a", Some("ralph/a"), Vec::new());
+    assert_eq!(detect_branch_change(Some("ralph/a"), "ralph/a", Some(&git)), None);
+}
+
+#[test]
+fn test_reflog_stands_in_for_missing_marker() {
+    let git = git_state(
+        "ralph/c",
+        None,
+        vec![checkout("ralph/a", "ralph/b"), checkout("ralph/b", "ralph/c")],
+    );
+    let change = detect_branch_change(None, "ralph/c", Some(&git)).unwrap();
+    assert_eq!(change.previous, "ralph/a");
+    assert_eq!(change.signals, ["git reflog (checkout): ralph/a -> ralph/c"]);
+}
+
+#[test]
+fn test_moving_away_and_back_is_no_change() {
+    let git = git_state(
+        "ralph/a",
+        None,
+        vec![checkout("ralph/a", "main"), checkout("main", "ralph/a")],
+    );
+    assert_eq!(detect_branch_change(None, "ralph/a", Some(&git)), None);
+}
+
+// ============================================================================
+// Real repositories
+// ============================================================================
+
+#[test]
+fn test_renamed_branch_detected() {
+    let repo = repo_on("ralph/a");
+    record_git_branch(repo.path()).unwrap();
+    assert_eq!(
+        fs::read_to_string(repo.path().join(LAST_GIT_BRANCH_FILE)).unwrap(),
+        "ralph/a"
+    );
+
+    git(repo.path(), &["branch", "-m", "ralph/b"]);
+
+    let state = GitBranchState::read(repo.path()).unwrap();
+    assert_eq!(state.current, "ralph/b");
+    assert_eq!(state.recorded.as_deref(), Some("ralph/a"));
+    let change = detect_branch_change(Some("ralph/b"), "ralph/b", Some(&state)).unwrap();
+    assert_eq!(change.previous, "ralph/a");
+}
+
+#[test]
+fn test_fresh_clone_is_no_change() {
+    let origin = repo_on("ralph/a");
+    let parent = TempDir::new().unwrap();
+    git(
+        parent.path(),
+        &["clone", "-q", origin.path().to_str().unwrap(), "clone"],
+    );
+    let clone = parent.path().join("clone");
+
+    let state = GitBranchState::read(&clone).unwrap();
+    assert_eq!(state.current, "ralph/a");
+    assert_eq!(state.recorded, None);
+    assert!(state.moves.is_empty());
+    assert_eq!(detect_branch_change(None, "ralph/a", Some(&state)), None);
+}
+
+#[test]
+fn test_deleted_marker_falls_back_to_reflog() {
+    let repo = repo_on("ralph/a");
+    age_progress(repo.path());
+    git(repo.path(), &["checkout", "-q", "-b", "ralph/b"]);
+
+    let state = GitBranchState::read(repo.path()).unwrap();
+    assert_eq!(state.recorded, None);
+    let change = detect_branch_change(None, "ralph/b", Some(&state)).unwrap();
+    assert_eq!(change.previous, "ralph/a");
+    assert_eq!(change.signals, ["git reflog (checkout): ralph/a -> ralph/b"]);
+}
+
+#[test]
+fn test_outside_git_has_no_state() {
+    let temp = TempDir::new().unwrap();
+    assert_eq!(GitBranchState::read(temp.path()), None);
+    record_git_branch(temp.path()).unwrap();
+    assert!(!temp.path().join(LAST_GIT_BRANCH_FILE).exists());
+}
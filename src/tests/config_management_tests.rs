@@ -1,10 +1,14 @@
 //! Configuration Management Tests
 //!
 //! Tests for the configuration management functionality in Ralph CLI.
-//! These tests verify that config loading, saving, and modification work correctly.
+//! These tests verify that config loading, saving, and modification work correctly,
+//! including a project `ralph/config.toml` laid over the global config.
 
-use crate::config::{Config, ConfigKey};
+use crate::config::{
+    BranchChangeMode, Config, ConfigKey, SecretScanMode, CONFIG_PATH_ENV, PROJECT_CONFIG_FILE,
+};
 use std::fs;
+use std::path::PathBuf;
 use tempfile::TempDir;
 
 /// Helper function to create a test config with specific values
@@ -13,6 +17,22 @@ fn create_test_config() -> Config {
         default_tool: Some("codebuddy".to_string()),
         max_iterations: Some(20),
         auto_archive: Some(false),
+        notes_warn_length: Some(300),
+        flush_interval_ms: Some(100),
+        agent_cache_ttl_hours: Some(12),
+        max_output_bytes: Some(1024),
+        sandbox_watch_paths: Some("~/.gitconfig".to_string()),
+        redact_patterns: Some("ghp_\\w+".to_string()),
+        redact_terminal: Some(true),
+        scan_secrets: Some(SecretScanMode::Warn),
+        archive_required: Some(true),
+        log_run_summary: Some(false),
+        require_criteria: Some(true),
+        extra_tools: Some("my-agent".to_string()),
+        auto_include_custom: Some(true),
+        readonly_ok: Some(true),
+        branch_change: Some(BranchChangeMode::Abort),
+        run_banner: Some(false),
     }
 }
 
@@ -193,12 +213,66 @@ fn test_config_key_description() {
 #[test]
 fn test_config_key_all() {
     let all_keys = ConfigKey::all();
-    assert_eq!(all_keys.len(), 3);
+    assert_eq!(all_keys.len(), 19);
     assert!(all_keys.contains(&ConfigKey::DefaultTool));
     assert!(all_keys.contains(&ConfigKey::MaxIterations));
     assert!(all_keys.contains(&ConfigKey::AutoArchive));
 }
 
+/// Test every key round-trips through its name and survives a TOML save
+#[test]
+fn test_every_config_key_is_consistent() {
+    let samples = |key: ConfigKey| match key {
+        ConfigKey::DefaultTool => "amp",
+        ConfigKey::MaxIterations => "7",
+        ConfigKey::AutoArchive => "false",
+        ConfigKey::NotesWarnLength => "200",
+        ConfigKey::FlushIntervalMs => "0",
+        ConfigKey::AgentCacheTtlHours => "48",
+        ConfigKey::MaxOutputBytes => "0",
+        ConfigKey::SandboxWatchPaths => "~/.ssh,~/.config/ralph",
+        ConfigKey::RedactPatterns => r"ghp_\w+ sk-\w+",
+        ConfigKey::RedactTerminal => "true",
+        ConfigKey::ScanSecrets => "block",
+        ConfigKey::ArchiveRequired => "true",
+        ConfigKey::LogRunSummary => "false",
+        ConfigKey::RequireCriteria => "true",
+        ConfigKey::ExtraTools => "my-agent,other-agent",
+        ConfigKey::AutoIncludeCustom => "true",
+        ConfigKey::ReadonlyOk => "true",
+        ConfigKey::BranchChange => "abort",
+        ConfigKey::RunBanner => "false",
+    };
+
+    let mut config = Config::default();
+    for key in ConfigKey::all() {
+        assert_eq!(ConfigKey::from_str(key.as_str()), Some(*key));
+        assert!(!key.description().is_empty());
+
+        config.set(*key, samples(*key)).unwrap();
+        assert_eq!(config.get(*key).as_deref(), Some(samples(*key)));
+    }
+
+    let reloaded: Config = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+    for key in ConfigKey::all() {
+        assert_eq!(reloaded.get(*key), config.get(*key), "{} was not saved", key.as_str());
+    }
+}
+
+/// Test scan_secrets only accepts its three modes
+#[test]
+fn test_config_set_scan_secrets() {
+    let mut config = Config::default();
+    assert_eq!(config.scan_secrets, Some(SecretScanMode::Off));
+
+    config.set(ConfigKey::ScanSecrets, "warn").unwrap();
+    assert_eq!(config.scan_secrets, Some(SecretScanMode::Warn));
+
+    let err = config.set(ConfigKey::ScanSecrets, "loud").unwrap_err();
+    assert_eq!(err, "scan_secrets must be warn, block or off");
+    assert_eq!(config.scan_secrets, Some(SecretScanMode::Warn));
+}
+
 /// Test TOML serialization of config
 #[test]
 fn test_config_toml_serialization() {
@@ -284,3 +358,231 @@ fn test_config_save_and_load_roundtrip() {
 }
 
 // Note: Debug, Clone, and Copy trait tests removed - they test derive macro functionality
+
+#[test]
+fn test_config_file_prefers_env_override() {
+    let resolved = Config::resolve_config_file(
+        Some(PathBuf::from("/custom/ralph.toml")),
+        Some(PathBuf::from("/home/user/.config/ralph")),
+    );
+    assert_eq!(resolved, Some(PathBuf::from("/custom/ralph.toml")));
+}
+
+#[test]
+fn test_config_file_falls_back_to_config_dir() {
+    let resolved = Config::resolve_config_file(
+        Some(PathBuf::new()),
+        Some(PathBuf::from("/home/user/.config/ralph")),
+    );
+    assert_eq!(
+        resolved,
+        Some(PathBuf::from("/home/user/.config/ralph/config.toml"))
+    );
+    assert_eq!(Config::resolve_config_file(None, None), None);
+}
+
+#[test]
+fn test_config_save_without_config_dir_gives_actionable_error() {
+    let config = Config::default();
+
+    let err = config.save_to(None).unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(err.to_string().contains(CONFIG_PATH_ENV));
+}
+
+#[test]
+fn test_config_save_to_creates_parent_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("nested/dir/config.toml");
+
+    create_test_config().save_to(Some(config_path.clone())).unwrap();
+
+    let content = fs::read_to_string(&config_path).unwrap();
+    let loaded: Config = toml::from_str(&content).unwrap();
+    assert_eq!(loaded.default_tool, Some("codebuddy".to_string()));
+}
+
+/// Make a read-only temp directory, or `None` when permissions are not
+/// enforced (e.g. when the tests run as root)
+#[cfg(unix)]
+fn read_only_dir() -> Option<TempDir> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    fs::set_permissions(temp_dir.path(), fs::Permissions::from_mode(0o555)).unwrap();
+    if fs::write(temp_dir.path().join("probe"), b"").is_ok() {
+        return None;
+    }
+    Some(temp_dir)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_config_save_to_read_only_dir_names_path() {
+    let Some(temp_dir) = read_only_dir() else {
+        return;
+    };
+    let config_path = temp_dir.path().join("ralph/config.toml");
+
+    let err = Config::default().save_to(Some(config_path.clone())).unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    let message = err.to_string();
+    assert!(message.contains(&config_path.display().to_string()));
+    assert!(message.contains("--config"));
+    assert!(message.contains(CONFIG_PATH_ENV));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_check_writable_detects_read_only_dir() {
+    let Some(temp_dir) = read_only_dir() else {
+        return;
+    };
+
+    assert!(Config::check_writable(&temp_dir.path().join("config.toml")).is_err());
+    assert!(Config::check_writable(&temp_dir.path().join("a/b/config.toml")).is_err());
+}
+
+#[test]
+fn test_check_writable_accepts_missing_nested_path() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("a/b/config.toml");
+
+    Config::check_writable(&config_path).unwrap();
+
+    // The check leaves nothing behind
+    assert!(!temp_dir.path().join("a").exists());
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_check_writable_existing_file_is_untouched() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, "max_iterations = 3\n").unwrap();
+
+    Config::check_writable(&config_path).unwrap();
+
+    assert_eq!(fs::read_to_string(&config_path).unwrap(), "max_iterations = 3\n");
+}
+
+#[test]
+fn test_config_with_defaults_fills_unset_keys() {
+    let mut config = Config::empty();
+    config.max_iterations = Some(3);
+
+    let effective = config.with_defaults();
+
+    assert_eq!(effective.max_iterations, Some(3));
+    assert_eq!(effective.auto_archive, Config::default().auto_archive);
+    assert_eq!(effective.default_tool, None);
+}
+
+#[test]
+fn test_config_overlay_prefers_keys_set_on_top() {
+    let mut top = Config::empty();
+    top.max_iterations = Some(2);
+
+    let merged = create_test_config().overlay(&top);
+
+    assert_eq!(merged.max_iterations, Some(2));
+    assert_eq!(merged.default_tool, Some("codebuddy".to_string()));
+    assert_eq!(merged.archive_required, Some(true));
+}
+
+#[test]
+fn test_project_config_overrides_global_value() {
+    let temp_dir = TempDir::new().unwrap();
+    let global = temp_dir.path().join("global.toml");
+    let ralph_dir = temp_dir.path().join("ralph");
+    fs::create_dir_all(&ralph_dir).unwrap();
+    fs::write(&global, "max_iterations = 20\ndefault_tool = \"claude\"\n").unwrap();
+    fs::write(ralph_dir.join(PROJECT_CONFIG_FILE), "max_iterations = 4\n").unwrap();
+
+    let config = Config::load_layered_from(Some(&global), &ralph_dir).unwrap();
+
+    assert_eq!(config.max_iterations, Some(4));
+    // Only set globally
+    assert_eq!(config.default_tool, Some("claude".to_string()));
+}
+
+#[test]
+fn test_layered_config_without_project_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let global = temp_dir.path().join("global.toml");
+    fs::write(&global, "auto_archive = false\n").unwrap();
+
+    let config = Config::load_layered_from(Some(&global), temp_dir.path()).unwrap();
+    assert_eq!(config.auto_archive, Some(false));
+    assert_eq!(config.max_iterations, None);
+
+    // No global file either: the built-in defaults, under the project file
+    fs::write(temp_dir.path().join(PROJECT_CONFIG_FILE), "auto_archive = false\n").unwrap();
+    let missing = temp_dir.path().join("missing.toml");
+    let config = Config::load_layered_from(Some(&missing), temp_dir.path()).unwrap();
+    assert_eq!(config.auto_archive, Some(false));
+    assert_eq!(config.max_iterations, Config::default().max_iterations);
+}
+
+#[test]
+fn test_invalid_project_config_names_the_file() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join(PROJECT_CONFIG_FILE), "max_iterations = \"many\"\n").unwrap();
+
+    let err = Config::load_layered_from(None, temp_dir.path()).unwrap_err();
+
+    assert!(err.to_string().contains("Invalid config"));
+    assert!(err.to_string().contains(PROJECT_CONFIG_FILE));
+}
+
+#[test]
+fn test_config_import_sets_values() {
+    let mut config = create_test_config();
+
+    config
+        .import_toml("default_tool = \"claude\"\nmax_iterations = 5\nauto_archive = true\n")
+        .unwrap();
+
+    assert_eq!(config.default_tool, Some("claude".to_string()));
+    assert_eq!(config.max_iterations, Some(5));
+    assert_eq!(config.auto_archive, Some(true));
+    // Keys missing from the file keep their values
+    assert_eq!(config.notes_warn_length, Some(300));
+}
+
+#[test]
+fn test_config_import_reports_every_problem_with_lines() {
+    let mut config = create_test_config();
+    let content = concat!(
+        "default_tool = \"claude\"\n",
+        "\n",
+        "bogus = 1\n",
+        "max_iterations = -2\n",
+        "auto_archive = [true]\n",
+    );
+
+    let errors = config.import_toml(content).unwrap_err();
+
+    let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, [3, 4, 5]);
+    assert!(errors[0].message.contains("unknown config key `bogus`"));
+    assert!(errors[1].message.contains("max_iterations must be a positive integer"));
+    assert!(errors[2].message.contains("not array"));
+    assert_eq!(errors[0].to_string(), "line 3: unknown config key `bogus`");
+
+    // Nothing was applied, not even the valid entry
+    assert_eq!(config.default_tool, Some("codebuddy".to_string()));
+}
+
+#[test]
+fn test_config_import_reports_syntax_error_line() {
+    let mut config = Config::empty();
+
+    let errors = config.import_toml("max_iterations = 5\nauto_archive = \n").unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, 2);
+    assert_eq!(config.max_iterations, None);
+}
@@ -0,0 +1,83 @@
+//! Agent Environment Tests
+//!
+//! Tests for `ralph run --env` and `--env-file` parsing:
+//! - Single `KEY=VALUE` assignments
+//! - Well-formed dotenv files
+//! - Comments, blank lines, `export` prefixes and quoted values
+//! - Reporting the offending line for malformed files
+
+use std::fs;
+use tempfile::TempDir;
+
+use crate::dotenv::{load_env_file, parse_dotenv, parse_env_assignment};
+
+fn pair(key: &str, value: &str) -> (String, String) {
+    (key.to_string(), value.to_string())
+}
+
+#[test]
+fn test_parse_env_assignment() {
+    assert_eq!(
+        parse_env_assignment("API_KEY=abc123").unwrap(),
+        pair("API_KEY", "abc123")
+    );
+    assert_eq!(
+        parse_env_assignment("URL=http://x?a=b").unwrap(),
+        pair("URL", "http://x?a=b")
+    );
+    assert_eq!(parse_env_assignment("EMPTY=").unwrap(), pair("EMPTY", ""));
+}
+
+#[test]
+fn test_parse_env_assignment_rejects_malformed() {
+    assert!(parse_env_assignment("NO_EQUALS").is_err());
+    assert!(parse_env_assignment("=value").is_err());
+    assert!(parse_env_assignment("BAD KEY=value").is_err());
+}
+
+#[test]
+fn test_parse_well_formed_dotenv() {
+    let vars = parse_dotenv("FOO=bar\nBAZ=qux\n").unwrap();
+    assert_eq!(vars, vec![pair("FOO", "bar"), pair("BAZ", "qux")]);
+}
+
+#[test]
+fn test_parse_dotenv_with_comments_and_quotes() {
+    let content = r#"
+# Agent credentials
+export TOKEN="secret value"
+SINGLE='it''s literal $HOME'
+
+GREETING="line1\nsay \"hi\""
+PLAIN=value # trailing comment
+HASH=abc#def
+"#;
+
+    let vars = parse_dotenv(content).unwrap();
+    assert_eq!(
+        vars,
+        vec![
+            pair("TOKEN", "secret value"),
+            pair("SINGLE", "it''s literal $HOME"),
+            pair("GREETING", "line1\nsay \"hi\""),
+            pair("PLAIN", "value"),
+            pair("HASH", "abc#def"),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_dotenv_reports_line_number() {
+    let err = parse_dotenv("# header\nGOOD=1\nnot an assignment\n").unwrap_err();
+    assert!(err.starts_with("line 3:"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_load_env_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join(".env");
+    fs::write(&path, "FOO=bar\n").unwrap();
+
+    assert_eq!(load_env_file(&path).unwrap(), vec![pair("FOO", "bar")]);
+    assert!(load_env_file(&temp_dir.path().join("missing.env")).is_err());
+}
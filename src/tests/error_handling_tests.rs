@@ -317,3 +317,66 @@ fn test_error_trait_object() {
     let display = format!("{}", err);
     assert!(display.contains("IO error:"));
 }
+
+/// Test that a missing agent is reported with an install hint
+#[test]
+fn test_agent_spawn_not_found_display() {
+    let err = RalphError::AgentSpawn {
+        tool: "claude".to_string(),
+        source: io::Error::from(io::ErrorKind::NotFound),
+    };
+    assert!(err.is_agent_not_found());
+    assert_eq!(
+        err.to_string(),
+        "Failed to spawn claude: command not found. Install it with: npm install -g @anthropic-ai/claude-code"
+    );
+
+    // A path to a known agent gets the same hint
+    let err = RalphError::AgentSpawn {
+        tool: "/opt/agents/amp".to_string(),
+        source: io::Error::from(io::ErrorKind::NotFound),
+    };
+    assert!(err.to_string().ends_with("Install it with: npm install -g @sourcegraph/amp"));
+
+    // There is nothing to suggest for unknown tools
+    let err = RalphError::AgentSpawn {
+        tool: "my-agent".to_string(),
+        source: io::Error::from(io::ErrorKind::NotFound),
+    };
+    assert_eq!(err.to_string(), "Failed to spawn my-agent: command not found");
+}
+
+/// Test that other spawn failures keep the underlying error
+#[test]
+fn test_agent_spawn_other_failure() {
+    let err = RalphError::AgentSpawn {
+        tool: "claude".to_string(),
+        source: io::Error::new(io::ErrorKind::PermissionDenied, "permission denied"),
+    };
+    assert!(!err.is_agent_not_found());
+    assert_eq!(err.to_string(), "Failed to spawn claude: permission denied");
+
+    let err_ref: &dyn std::error::Error = &err;
+    let source = err_ref.source().expect("spawn errors chain their cause");
+    assert_eq!(source.to_string(), "permission denied");
+
+    assert!(!RalphError::Other("claude not found".to_string()).is_agent_not_found());
+}
+
+/// Test that Ctrl+C at a dialoguer prompt becomes Interrupted, with its own exit code
+#[test]
+fn test_dialoguer_interrupt_maps_to_interrupted() {
+    let interrupted =
+        dialoguer::Error::IO(io::Error::new(io::ErrorKind::Interrupted, "read interrupted"));
+    let err: RalphError = interrupted.into();
+    assert!(matches!(err, RalphError::Interrupted));
+    assert_eq!(err.to_string(), "Aborted by user");
+    assert_eq!(err.exit_code(), 130);
+
+    // Other prompt failures stay dialog errors and exit with 1
+    let broken = dialoguer::Error::IO(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"));
+    let err: RalphError = broken.into();
+    assert!(matches!(err, RalphError::Dialoguer(_)));
+    assert_eq!(err.exit_code(), 1);
+    assert_eq!(RalphError::Other("failed".to_string()).exit_code(), 1);
+}
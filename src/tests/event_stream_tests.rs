@@ -0,0 +1,125 @@
+//! Event Stream Tests
+//!
+//! Tests for `ralph run --stream-to`:
+//! - JSON line serialization of run events
+//! - Bounded queue with drop-oldest semantics
+//! - Writer task delivery to a Unix socket reader
+//! - Dropped-event accounting when no reader connects
+
+use crate::events::{EventQueue, EventStream, RunEvent};
+use crate::metadata::RunOutcome;
+
+#[test]
+fn test_run_event_json_line() {
+    let line = RunEvent::Output {
+        stream: "stdout",
+        line: "hello".to_string(),
+    }
+    .to_json_line();
+
+    assert!(line.ends_with('\n'));
+    let json: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+    assert_eq!(json["type"], "output");
+    assert_eq!(json["stream"], "stdout");
+    assert_eq!(json["line"], "hello");
+    assert!(json["time"].is_string());
+}
+
+#[test]
+fn test_run_finished_event_outcome() {
+    let line = RunEvent::RunFinished {
+        outcome: RunOutcome::Interrupted,
+        iterations: 2,
+        completed_stories: 1,
+        total_stories: 3,
+    }
+    .to_json_line();
+
+    let json: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+    assert_eq!(json["type"], "run_finished");
+    assert_eq!(json["outcome"], "interrupted");
+}
+
+#[test]
+fn test_event_queue_drops_oldest_when_full() {
+    let queue = EventQueue::new(2);
+    queue.push("a".to_string());
+    queue.push("b".to_string());
+    queue.push("c".to_string());
+
+    assert_eq!(queue.dropped(), 1);
+    assert_eq!(queue.drain(), vec!["b".to_string(), "c".to_string()]);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_event_queue_requeue_preserves_order() {
+    let queue = EventQueue::new(3);
+    queue.push("c".to_string());
+    queue.requeue(vec!["a".to_string(), "b".to_string()]);
+
+    assert_eq!(queue.drain(), vec!["a", "b", "c"]);
+
+    queue.push("x".to_string());
+    queue.push("y".to_string());
+    queue.requeue(vec!["old1".to_string(), "old2".to_string()]);
+    assert_eq!(queue.len(), 3);
+    assert_eq!(queue.dropped(), 1);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_event_stream_delivers_to_socket_reader() {
+    use tokio::io::AsyncReadExt;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let socket_path = temp_dir.path().join("events.sock");
+
+    let stream = EventStream::start(socket_path.clone()).unwrap();
+    let mut reader = tokio::net::UnixStream::connect(&socket_path).await.unwrap();
+
+    stream.emit(&RunEvent::IterationStarted { iteration: 1 });
+    stream.emit(&RunEvent::IterationFinished {
+        iteration: 1,
+        completed: true,
+        failure: None,
+        exit_code: Some(0),
+        duration_ms: 1200,
+        stories_passed: vec!["US-001".to_string()],
+    });
+    let dropped = stream.finish().await;
+
+    let mut received = String::new();
+    reader.read_to_string(&mut received).await.unwrap();
+    let types: Vec<String> = received
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).expect(&received)["type"].to_string())
+        .collect();
+
+    assert_eq!(dropped, 0);
+    assert_eq!(types, vec!["\"iteration_started\"", "\"iteration_finished\""]);
+    assert!(!socket_path.exists(), "socket should be removed on shutdown");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_event_stream_without_reader_counts_dropped() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let stream = EventStream::start(temp_dir.path().join("events.sock")).unwrap();
+
+    for iteration in 0..5 {
+        stream.emit(&RunEvent::IterationStarted { iteration });
+    }
+
+    assert_eq!(stream.finish().await, 5);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_event_stream_rejects_regular_file() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("events.log");
+    std::fs::write(&path, "").unwrap();
+
+    assert!(EventStream::start(path).is_err());
+}
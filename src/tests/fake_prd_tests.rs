@@ -0,0 +1,109 @@
+//! Fake PRD Generator Tests
+//!
+//! Tests for the synthetic PRDs behind `ralph prd fake`:
+//! - The same seed always produces the same PRD
+//! - Completion ratio, priorities and ids
+//! - Dependencies never form cycles or block passing stories
+//! - Stories spread over epics
+
+use std::collections::HashSet;
+
+use crate::fake_prd::{fake_prd, FakePrdOptions};
+
+fn options(stories: usize, seed: u64) -> FakePrdOptions {
+    FakePrdOptions {
+        stories,
+        seed,
+        ..FakePrdOptions::default()
+    }
+}
+
+fn to_json(options: &FakePrdOptions) -> String {
+    serde_json::to_string_pretty(&fake_prd(options)).unwrap()
+}
+
+#[test]
+fn test_fake_prd_is_deterministic_per_seed() {
+    let options = FakePrdOptions {
+        completed: 0.3,
+        dependencies: true,
+        epics: 3,
+        ..options(50, 42)
+    };
+
+    assert_eq!(to_json(&options), to_json(&options));
+    assert_ne!(to_json(&options), to_json(&FakePrdOptions { seed: 43, ..options.clone() }));
+}
+
+#[test]
+fn test_fake_prd_is_valid() {
+    let prd = fake_prd(&FakePrdOptions {
+        dependencies: true,
+        ..options(200, 7)
+    });
+
+    assert!(prd.validate().is_empty(), "{:?}", prd.validate());
+    assert!(prd.weak_stories().is_empty());
+    assert_eq!(prd.user_stories[0].id, "US-001");
+    assert!(prd.branch_name.starts_with("ralph/"));
+}
+
+#[test]
+fn test_fake_prd_priorities_are_a_permutation() {
+    let prd = fake_prd(&options(30, 1));
+
+    let mut priorities: Vec<u32> = prd.user_stories.iter().map(|s| s.priority).collect();
+    priorities.sort();
+    assert_eq!(priorities, (1..=30).collect::<Vec<u32>>());
+}
+
+#[test]
+fn test_fake_prd_completion_ratio() {
+    let prd = fake_prd(&FakePrdOptions {
+        completed: 0.25,
+        ..options(40, 3)
+    });
+
+    assert_eq!(prd.completed_stories(), 10);
+    // The highest priority stories are the ones that pass
+    assert!(prd.user_stories.iter().all(|s| s.passes == (s.priority <= 10)));
+}
+
+#[test]
+fn test_fake_prd_dependencies_point_at_higher_priorities() {
+    let prd = fake_prd(&FakePrdOptions {
+        completed: 0.5,
+        dependencies: true,
+        ..options(100, 9)
+    });
+
+    let with_deps: Vec<_> = prd.user_stories.iter().filter(|s| !s.depends_on.is_empty()).collect();
+    assert!(!with_deps.is_empty());
+    for story in with_deps {
+        let dep = prd.find_story(&story.depends_on[0]).unwrap();
+        assert!(dep.priority < story.priority);
+        assert!(!story.passes || dep.passes);
+    }
+    assert!(fake_prd(&options(100, 9)).user_stories.iter().all(|s| s.depends_on.is_empty()));
+}
+
+#[test]
+fn test_fake_prd_spreads_stories_over_epics() {
+    let prd = fake_prd(&FakePrdOptions {
+        epics: 2,
+        ..options(20, 5)
+    });
+
+    assert_eq!(prd.epics.len(), 2);
+    let used: HashSet<&str> = prd.user_stories.iter().filter_map(|s| s.epic.as_deref()).collect();
+    assert!(used.iter().all(|name| prd.epics.iter().any(|e| e.name == *name)));
+    assert!(prd.undeclared_epic_stories().is_empty());
+    assert_eq!(prd.epic_groups().iter().map(|g| g.stories.len()).sum::<usize>(), 20);
+}
+
+#[test]
+fn test_fake_prd_without_stories() {
+    let prd = fake_prd(&options(0, 0));
+
+    assert_eq!(prd.total_stories(), 0);
+}
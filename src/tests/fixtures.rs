@@ -0,0 +1,30 @@
+//! Shared Test Fixtures
+//!
+//! Tests build stories from [`user_story`] with struct-update syntax, so a
+//! new `UserStory` field only has to be added here.
+
+use crate::prd::UserStory;
+
+/// A pending priority-1 story titled `Story <id>`, with one acceptance criterion
+pub fn user_story(id: &str) -> UserStory {
+    UserStory {
+        id: id.to_string(),
+        title: format!("Story {}", id),
+        description: "As a user, I want something".to_string(),
+        acceptance_criteria: vec!["It works".to_string()],
+        priority: 1,
+        passes: false,
+        notes: String::new(),
+        depends_on: Vec::new(),
+        epic: None,
+        context_files: Vec::new(),
+        tags: Vec::new(),
+        estimate: None,
+        criteria_done: Vec::new(),
+    }
+}
+
+/// Owned copies of `items`, e.g. for `depends_on` or `tags`
+pub fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
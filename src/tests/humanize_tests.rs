@@ -0,0 +1,78 @@
+//! Humanized Output Tests
+//!
+//! Tests for the display helpers shared by run, status and archive output:
+//! - format_duration() unit boundaries
+//! - format_relative_time() unit boundaries and plurals
+//! - render_table() alignment, including styled cells
+
+use std::time::Duration;
+
+use crate::humanize::{format_duration, format_relative_time, render_table, Align};
+
+fn secs(s: u64) -> Duration {
+    Duration::from_secs(s)
+}
+
+#[test]
+fn test_format_duration_boundaries() {
+    assert_eq!(format_duration(Duration::ZERO), "0ms");
+    assert_eq!(format_duration(Duration::from_millis(999)), "999ms");
+    assert_eq!(format_duration(Duration::from_millis(1500)), "1s");
+    assert_eq!(format_duration(secs(59)), "59s");
+    assert_eq!(format_duration(secs(60)), "1m");
+    assert_eq!(format_duration(secs(4 * 60 + 32)), "4m 32s");
+    assert_eq!(format_duration(secs(3599)), "59m 59s");
+    assert_eq!(format_duration(secs(3600)), "1h");
+    assert_eq!(format_duration(secs(3600 + 12 * 60 + 59)), "1h 12m");
+    assert_eq!(format_duration(secs(86_399)), "23h 59m");
+    assert_eq!(format_duration(secs(86_400)), "1d");
+    assert_eq!(format_duration(secs(3 * 86_400 + 5 * 3600)), "3d 5h");
+}
+
+#[test]
+fn test_format_relative_time_boundaries() {
+    assert_eq!(format_relative_time(secs(0)), "just now");
+    assert_eq!(format_relative_time(secs(59)), "just now");
+    assert_eq!(format_relative_time(secs(60)), "1 minute ago");
+    assert_eq!(format_relative_time(secs(119)), "1 minute ago");
+    assert_eq!(format_relative_time(secs(120)), "2 minutes ago");
+    assert_eq!(format_relative_time(secs(3600)), "1 hour ago");
+    assert_eq!(format_relative_time(secs(86_399)), "23 hours ago");
+    assert_eq!(format_relative_time(secs(86_400)), "1 day ago");
+    assert_eq!(format_relative_time(secs(6 * 86_400)), "6 days ago");
+    assert_eq!(format_relative_time(secs(7 * 86_400)), "1 week ago");
+    assert_eq!(format_relative_time(secs(21 * 86_400)), "3 weeks ago");
+    assert_eq!(format_relative_time(secs(30 * 86_400)), "1 month ago");
+    assert_eq!(format_relative_time(secs(364 * 86_400)), "12 months ago");
+    assert_eq!(format_relative_time(secs(365 * 86_400)), "1 year ago");
+    assert_eq!(format_relative_time(secs(800 * 86_400)), "2 years ago");
+}
+
+#[test]
+fn test_render_table_aligns_columns() {
+    let rows = vec![
+        vec!["alpha".to_string(), "1.5 MB".to_string(), "x".to_string()],
+        vec!["b".to_string(), "12 B".to_string(), "yy".to_string()],
+    ];
+    let lines = render_table(&rows, &[Align::Left, Align::Right]);
+
+    assert_eq!(lines, ["alpha  1.5 MB  x", "b        12 B  yy"]);
+}
+
+#[test]
+fn test_render_table_ignores_styling_in_widths() {
+    let styled = console::style("42").green().force_styling(true).to_string();
+    let rows = vec![
+        vec!["a".to_string(), styled.clone()],
+        vec!["b".to_string(), "1000".to_string()],
+    ];
+    let lines = render_table(&rows, &[Align::Left, Align::Right]);
+
+    assert_eq!(lines[0], format!("a    {}", styled));
+    assert_eq!(lines[1], "b  1000");
+}
+
+#[test]
+fn test_render_table_empty() {
+    assert!(render_table(&[], &[]).is_empty());
+}
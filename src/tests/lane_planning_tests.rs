@@ -0,0 +1,218 @@
+//! Lane Planning Tests
+//!
+//! Tests for splitting pending stories into `run --parallel` lanes:
+//! - Independent stories spread over lanes
+//! - Dependencies and shared contextFiles keep stories in one lane
+//! - Stories without contextFiles fall back to serial
+//! - Too few independent groups fall back to a serial run
+//! - Only the stories the selector covers are planned
+//! - `--budget` is refused, since every lane would get the whole cap
+
+use crate::commands::run::{run_run, RunOptions};
+use crate::lanes::{plan_lanes, LanePlan};
+use crate::prd::{Prd, UserStory};
+use crate::selector::StorySelector;
+use crate::tests::fixtures::{strings, user_story};
+use crate::usage::Budget;
+
+fn story(id: &str, priority: u32, depends_on: &[&str], files: &[&str]) -> UserStory {
+    UserStory {
+        priority,
+        depends_on: strings(depends_on),
+        context_files: strings(files),
+        ..user_story(id)
+    }
+}
+
+fn prd(user_stories: Vec<UserStory>) -> Prd {
+    Prd {
+        project: "Lanes Project".to_string(),
+        branch_name: "ralph/lanes".to_string(),
+        description: "Lane planning tests".to_string(),
+        epics: Vec::new(),
+        user_stories,
+    }
+}
+
+fn lanes(plan: &LanePlan) -> Vec<Vec<&str>> {
+    plan.lanes
+        .iter()
+        .map(|lane| lane.iter().map(String::as_str).collect())
+        .collect()
+}
+
+#[test]
+fn test_independent_stories_split_into_lanes() {
+    let plan = plan_lanes(
+        &prd(vec![
+            story("US-001", 1, &[], &["src/a.rs"]),
+            story("US-002", 2, &[], &["src/b.rs"]),
+            story("US-003", 3, &[], &["src/c.rs"]),
+        ]),
+        2,
+        &StorySelector::default(),
+    );
+
+    assert!(plan.is_parallel());
+    assert_eq!(lanes(&plan), [vec!["US-001", "US-003"], vec!["US-002"]]);
+    assert!(plan.serial.is_empty());
+}
+
+#[test]
+fn test_dependencies_keep_stories_in_one_lane() {
+    let plan = plan_lanes(
+        &prd(vec![
+            story("US-001", 1, &[], &["src/a.rs"]),
+            story("US-002", 2, &["US-001"], &["src/b.rs"]),
+            story("US-003", 3, &[], &["src/c.rs"]),
+        ]),
+        2,
+        &StorySelector::default(),
+    );
+
+    assert_eq!(lanes(&plan), [vec!["US-001", "US-002"], vec!["US-003"]]);
+}
+
+#[test]
+fn test_shared_context_files_conflict() {
+    // `./src/a.rs` and `src/a.rs` are the same file
+    let plan = plan_lanes(
+        &prd(vec![
+            story("US-001", 1, &[], &["./src/a.rs"]),
+            story("US-002", 2, &[], &["src/b.rs"]),
+            story("US-003", 3, &[], &["src/a.rs", "src/c.rs"]),
+        ]),
+        2,
+        &StorySelector::default(),
+    );
+
+    assert_eq!(lanes(&plan), [vec!["US-001", "US-003"], vec!["US-002"]]);
+}
+
+#[test]
+fn test_stories_without_context_files_run_serially() {
+    let plan = plan_lanes(
+        &prd(vec![
+            story("US-001", 1, &[], &["src/a.rs"]),
+            story("US-002", 2, &[], &["src/b.rs"]),
+            story("US-003", 3, &[], &[]),
+            // Tied to US-003, so it cannot be checked for overlaps either
+            story("US-004", 4, &["US-003"], &["src/d.rs"]),
+        ]),
+        2,
+        &StorySelector::default(),
+    );
+
+    assert_eq!(lanes(&plan), [vec!["US-001"], vec!["US-002"]]);
+    assert_eq!(plan.serial, ["US-003", "US-004"]);
+}
+
+#[test]
+fn test_single_group_falls_back_to_serial() {
+    let plan = plan_lanes(
+        &prd(vec![
+            story("US-002", 2, &[], &["src/shared.rs"]),
+            story("US-001", 1, &[], &["src/shared.rs"]),
+            story("US-003", 3, &[], &[]),
+        ]),
+        2,
+        &StorySelector::default(),
+    );
+
+    assert!(!plan.is_parallel());
+    assert!(plan.lanes.is_empty());
+    assert_eq!(plan.serial, ["US-001", "US-002", "US-003"]);
+}
+
+#[test]
+fn test_fewer_than_two_lanes_is_serial() {
+    let plan = plan_lanes(
+        &prd(vec![
+            story("US-001", 1, &[], &["src/a.rs"]),
+            story("US-002", 2, &[], &["src/b.rs"]),
+        ]),
+        1,
+        &StorySelector::default(),
+    );
+
+    assert!(!plan.is_parallel());
+    assert_eq!(plan.serial, ["US-001", "US-002"]);
+}
+
+#[test]
+fn test_completed_stories_are_not_planned() {
+    let mut done = story("US-001", 1, &[], &["src/a.rs"]);
+    done.passes = true;
+    // Both depend on the finished story, which no longer ties them together
+    let plan = plan_lanes(
+        &prd(vec![
+            done,
+            story("US-002", 2, &["US-001"], &["src/b.rs"]),
+            story("US-003", 3, &["US-001"], &["src/a.rs"]),
+        ]),
+        2,
+        &StorySelector::default(),
+    );
+
+    assert_eq!(lanes(&plan), [vec!["US-002"], vec!["US-003"]]);
+}
+
+#[test]
+fn test_only_selected_stories_are_planned() {
+    let mut tagged = story("US-003", 3, &[], &["src/c.rs"]);
+    tagged.tags = vec!["backend".to_string()];
+    let mut other = story("US-004", 4, &[], &["src/d.rs"]);
+    other.tags = vec!["backend".to_string()];
+    let selector = StorySelector {
+        tags: vec!["backend".to_string()],
+        excluded: vec!["US-004".to_string()],
+        ..StorySelector::default()
+    };
+    let plan = plan_lanes(
+        &prd(vec![
+            story("US-001", 1, &[], &["src/a.rs"]),
+            story("US-002", 2, &[], &[]),
+            tagged,
+            other,
+            story("US-005", 5, &[], &["src/e.rs"]),
+        ]),
+        2,
+        &selector,
+    );
+
+    // US-003 is the only story in scope, which is not enough for two lanes
+    assert!(!plan.is_parallel());
+    assert_eq!(plan.serial, ["US-003"]);
+
+    let selector = StorySelector {
+        max_priority: Some(3),
+        ..StorySelector::default()
+    };
+    let plan = plan_lanes(
+        &prd(vec![
+            story("US-001", 1, &[], &["src/a.rs"]),
+            story("US-002", 2, &[], &[]),
+            story("US-003", 3, &[], &["src/c.rs"]),
+            story("US-004", 4, &[], &["src/d.rs"]),
+        ]),
+        2,
+        &selector,
+    );
+
+    assert_eq!(lanes(&plan), [vec!["US-001"], vec!["US-003"]]);
+    assert_eq!(plan.serial, ["US-002"]);
+}
+
+#[test]
+fn test_budget_is_refused_with_parallel() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let options = RunOptions {
+        parallel: Some(2),
+        budget: Some(Budget::Tokens(500_000)),
+        ..RunOptions::default()
+    };
+
+    let err = rt.block_on(run_run(options)).unwrap_err().to_string();
+
+    assert!(err.contains("--budget cannot be used with --parallel"), "{}", err);
+}
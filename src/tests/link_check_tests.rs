@@ -0,0 +1,115 @@
+//! PRD Link Check Tests
+//!
+//! Tests for `ralph prd check-links` reference extraction and checking:
+//! - URL extraction from markdown links, angle brackets and prose
+//! - Path extraction from code spans, relative paths and bare file names
+//! - Ignoring prose, code and version numbers that only look like paths
+//! - Resolving paths against the work directory
+
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+use crate::links::{check_story_references, extract_paths, extract_urls, story_references, Reference};
+use crate::prd::UserStory;
+use crate::tests::fixtures::user_story;
+
+fn story_with(description: &str, notes: &str) -> UserStory {
+    UserStory {
+        description: description.to_string(),
+        acceptance_criteria: vec!["See `docs/guide.md`".to_string()],
+        notes: notes.to_string(),
+        ..user_story("US-001")
+    }
+}
+
+#[test]
+fn test_extract_urls_from_markdown_link() {
+    let urls = extract_urls("Read [the docs](https://example.com/docs/setup) first.");
+    assert_eq!(urls, vec!["https://example.com/docs/setup"]);
+}
+
+#[test]
+fn test_extract_urls_strips_trailing_punctuation_and_brackets() {
+    let text = "See https://example.com/a. Also <http://example.org/b?x=1&y=2>, and **https://example.net/c**!";
+    assert_eq!(
+        extract_urls(text),
+        vec![
+            "https://example.com/a",
+            "http://example.org/b?x=1&y=2",
+            "https://example.net/c"
+        ]
+    );
+}
+
+#[test]
+fn test_extract_urls_deduplicates() {
+    let urls = extract_urls("https://x.dev/a and again https://x.dev/a");
+    assert_eq!(urls.len(), 1);
+}
+
+#[test]
+fn test_extract_paths_from_code_spans() {
+    let paths = extract_paths("Update `src/main.rs` and `Cargo.toml`, not `prd.passes` or `foo(bar/baz)`.");
+    assert_eq!(paths, vec!["src/main.rs", "Cargo.toml"]);
+}
+
+#[test]
+fn test_extract_paths_relative_and_nested() {
+    let paths = extract_paths("Copy ./scripts/setup to ../shared/config, then edit app/models/user.py.");
+    assert_eq!(
+        paths,
+        vec!["./scripts/setup", "../shared/config", "app/models/user.py"]
+    );
+}
+
+#[test]
+fn test_extract_paths_bare_file_names() {
+    let paths = extract_paths("Document it in README.md (and CHANGELOG.md).");
+    assert_eq!(paths, vec!["README.md", "CHANGELOG.md"]);
+}
+
+#[test]
+fn test_extract_paths_ignores_prose_and_versions() {
+    let text = "Use and/or logic, e.g. v1.2.3 support, client/server split, 3.5 ratio.";
+    assert!(extract_paths(text).is_empty());
+}
+
+#[test]
+fn test_extract_paths_ignores_url_components() {
+    let paths = extract_paths("Spec at https://example.com/specs/api.json and local api/spec.json");
+    assert_eq!(paths, vec!["api/spec.json"]);
+}
+
+#[test]
+fn test_extract_paths_in_markdown_list_and_link_text() {
+    let text = "- [guide](docs/guide.md)\n- Files: src/lib.rs, src/cli.rs";
+    assert_eq!(
+        extract_paths(text),
+        vec!["docs/guide.md", "src/lib.rs", "src/cli.rs"]
+    );
+}
+
+#[test]
+fn test_story_references_collects_all_fields() {
+    let story = story_with("Edit src/app.ts", "Ref: https://example.com/issue/1");
+    let refs = story_references(&story);
+
+    assert!(refs.contains(&Reference::Path("src/app.ts".to_string())));
+    assert!(refs.contains(&Reference::Path("docs/guide.md".to_string())));
+    assert!(refs.contains(&Reference::Url("https://example.com/issue/1".to_string())));
+}
+
+#[test]
+fn test_check_story_references_reports_missing_paths_only() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("docs")).unwrap();
+    fs::write(temp_dir.path().join("docs/guide.md"), "guide").unwrap();
+
+    let story = story_with("Edit src/missing.rs", "See https://example.invalid/page");
+    let broken = check_story_references(&story, temp_dir.path(), false, Duration::from_secs(1));
+
+    assert_eq!(broken.len(), 1);
+    assert_eq!(broken[0].reference, Reference::Path("src/missing.rs".to_string()));
+    assert_eq!(broken[0].reason, "not found");
+}
@@ -0,0 +1,163 @@
+//! Run Metadata Tests
+//!
+//! Tests for version capture and the metadata files written by `ralph run`:
+//! - VersionInfo capture and summary
+//! - last-run.json round-trip
+//! - Archive metadata.json contents
+//! - .run-state.json round-trip, age and PRD matching
+
+use chrono::{NaiveDateTime, TimeDelta};
+use std::path::Path;
+use tempfile::TempDir;
+
+use crate::metadata::{
+    ArchiveMetadata, RunOutcome, RunRecord, RunState, VersionInfo, ARCHIVE_METADATA_FILE,
+    LAST_RUN_FILE, RUN_STATE_FILE,
+};
+use crate::prd::Prd;
+
+fn sample_record() -> RunRecord {
+    RunRecord {
+        versions: VersionInfo {
+            ralph_version: "0.1.0".to_string(),
+            tool: "claude".to_string(),
+            tool_version: Some("2.0.1 (Claude Code)".to_string()),
+        },
+        branch: "ralph/test".to_string(),
+        started_at: "2026-01-01 10:00:00".to_string(),
+        finished_at: "2026-01-01 10:30:00".to_string(),
+        iterations: 3,
+        completed_stories: 2,
+        total_stories: 4,
+        outcome: RunOutcome::MaxIterations,
+        output_limit_hits: 0,
+        usage: None,
+        branch_changed_to: None,
+    }
+}
+
+#[test]
+fn test_version_info_capture_uses_package_version() {
+    let versions = VersionInfo::capture("echo", Path::new("."));
+    assert_eq!(versions.ralph_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(versions.tool, "echo");
+    assert!(versions.tool_version.is_some());
+}
+
+#[test]
+fn test_version_info_capture_missing_tool() {
+    let versions = VersionInfo::capture("nonexistent_command_12345", Path::new("."));
+    assert!(versions.tool_version.is_none());
+    assert!(versions.summary().ends_with("nonexistent_command_12345 unknown"));
+}
+
+#[test]
+fn test_version_info_summary() {
+    let record = sample_record();
+    assert_eq!(
+        record.versions.summary(),
+        "ralph 0.1.0, claude 2.0.1 (Claude Code)"
+    );
+}
+
+#[test]
+fn test_run_record_save_and_load_roundtrip() {
+    let temp_dir = TempDir::new().unwrap();
+    sample_record().save(temp_dir.path()).unwrap();
+
+    let content = std::fs::read_to_string(temp_dir.path().join(LAST_RUN_FILE)).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(json["ralphVersion"], "0.1.0");
+    assert_eq!(json["toolVersion"], "2.0.1 (Claude Code)");
+    assert_eq!(json["outcome"], "max_iterations");
+
+    let loaded = RunRecord::load(temp_dir.path()).unwrap();
+    assert_eq!(loaded.versions, sample_record().versions);
+    assert_eq!(loaded.iterations, 3);
+}
+
+#[test]
+fn test_run_record_load_missing_file() {
+    let temp_dir = TempDir::new().unwrap();
+    assert!(RunRecord::load(temp_dir.path()).is_none());
+}
+
+#[test]
+fn test_archive_metadata_includes_last_run() {
+    let temp_dir = TempDir::new().unwrap();
+    ArchiveMetadata::new("ralph/old", Some(sample_record()))
+        .save(temp_dir.path())
+        .unwrap();
+
+    let content = std::fs::read_to_string(temp_dir.path().join(ARCHIVE_METADATA_FILE)).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(json["branch"], "ralph/old");
+    assert_eq!(json["ralphVersion"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(json["lastRun"]["tool"], "claude");
+}
+
+fn sample_state() -> RunState {
+    RunState {
+        project: "Test Project".to_string(),
+        branch: "ralph/test".to_string(),
+        tool: "claude".to_string(),
+        target_story: None,
+        started_at: "2026-01-01 10:00:00".to_string(),
+        updated_at: "2026-01-01 10:30:00".to_string(),
+        iterations_used: 6,
+        max_iterations: 20,
+    }
+}
+
+#[test]
+fn test_run_state_round_trip_and_clear() {
+    let temp_dir = TempDir::new().unwrap();
+    assert_eq!(RunState::load(temp_dir.path()), None);
+
+    let state = sample_state();
+    state.save(temp_dir.path()).unwrap();
+    assert_eq!(RunState::load(temp_dir.path()), Some(state.clone()));
+    assert_eq!(state.remaining(), 14);
+
+    RunState::clear(temp_dir.path()).unwrap();
+    assert!(!temp_dir.path().join(RUN_STATE_FILE).exists());
+    // Clearing again is fine
+    RunState::clear(temp_dir.path()).unwrap();
+}
+
+#[test]
+fn test_run_state_is_recent() {
+    let state = sample_state();
+    let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    let day = TimeDelta::hours(24);
+
+    assert!(state.is_recent(at("2026-01-02 10:00:00"), day));
+    assert!(!state.is_recent(at("2026-01-02 10:30:01"), day));
+
+    let garbled = RunState {
+        updated_at: "yesterday".to_string(),
+        ..sample_state()
+    };
+    assert!(!garbled.is_recent(at("2026-01-01 10:30:00"), day));
+}
+
+#[test]
+fn test_run_state_mismatch() {
+    let prd: Prd = serde_json::from_str(
+        r#"{"project": "Test Project", "branchName": "ralph/test", "description": "", "userStories": []}"#,
+    )
+    .unwrap();
+    assert_eq!(sample_state().mismatch(&prd), None);
+
+    let other_branch = RunState {
+        branch: "ralph/other".to_string(),
+        ..sample_state()
+    };
+    assert!(other_branch.mismatch(&prd).unwrap().contains("ralph/other"));
+
+    let other_project = RunState {
+        project: "Other Project".to_string(),
+        ..sample_state()
+    };
+    assert!(other_project.mismatch(&prd).unwrap().contains("Other Project"));
+}
@@ -0,0 +1,257 @@
+//! Legacy Migration Tests
+//!
+//! Tests for moving the old root layout into `ralph/`:
+//! - Step ordering, with prd.json moved last
+//! - Collisions and de-duplication
+//! - Re-running after a partial migration
+//! - Reporting where a failed migration stopped
+//! - Answering the migration prompt without a terminal
+//! - Declining or interrupting the prompt before anything moves
+
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+use crate::commands::run::{apply_migration_plan, migration_prompt, MigrationPrompt};
+use crate::error::RalphError;
+use crate::migration::{same_content, MigrationPlan, StepAction};
+
+fn legacy_project() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    fs::write(root.join("prd.json"), "{\"project\": \"legacy\"}").unwrap();
+    fs::write(root.join("progress.txt"), "# Progress").unwrap();
+    fs::create_dir_all(root.join("archive/2026-01-01-old")).unwrap();
+    fs::write(root.join("archive/2026-01-01-old/prd.json"), "old").unwrap();
+    fs::write(root.join("archive/notes.txt"), "notes").unwrap();
+    dir
+}
+
+fn described(plan: &MigrationPlan, root: &Path) -> Vec<String> {
+    plan.steps.iter().map(|step| step.describe(root)).collect()
+}
+
+#[test]
+fn test_migration_plan_orders_steps_with_prd_last() {
+    let dir = legacy_project();
+    let root = dir.path();
+
+    assert!(MigrationPlan::is_needed(root));
+    let plan = MigrationPlan::compute(root).unwrap();
+
+    assert!(plan.conflicts.is_empty());
+    assert_eq!(
+        described(&plan, root),
+        [
+            "archive/2026-01-01-old → ralph/archive/2026-01-01-old",
+            "archive/notes.txt → ralph/archive/notes.txt",
+            "remove empty archive/",
+            "progress.txt → ralph/progress.txt",
+            "prd.json → ralph/prd.json",
+        ]
+    );
+}
+
+#[test]
+fn test_migration_not_needed_without_legacy_prd() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("progress.txt"), "# Progress").unwrap();
+
+    assert!(!MigrationPlan::is_needed(dir.path()));
+}
+
+#[test]
+fn test_migration_execute_moves_everything() {
+    let dir = legacy_project();
+    let root = dir.path();
+
+    MigrationPlan::compute(root).unwrap().execute().unwrap();
+
+    assert!(!root.join("prd.json").exists());
+    assert!(!root.join("progress.txt").exists());
+    assert!(!root.join("archive").exists());
+    assert_eq!(
+        fs::read_to_string(root.join("ralph/prd.json")).unwrap(),
+        "{\"project\": \"legacy\"}"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("ralph/archive/2026-01-01-old/prd.json")).unwrap(),
+        "old"
+    );
+    assert!(!MigrationPlan::is_needed(root));
+}
+
+#[test]
+fn test_migration_conflict_when_destination_differs() {
+    let dir = legacy_project();
+    let root = dir.path();
+    fs::create_dir_all(root.join("ralph")).unwrap();
+    fs::write(root.join("ralph/prd.json"), "{\"project\": \"new\"}").unwrap();
+
+    let plan = MigrationPlan::compute(root).unwrap();
+
+    assert_eq!(plan.conflicts.len(), 1);
+    assert_eq!(plan.conflicts[0].from, root.join("prd.json"));
+    assert_eq!(plan.conflicts[0].to, root.join("ralph/prd.json"));
+    assert!(plan.steps.iter().all(|s| s.from != root.join("prd.json")));
+}
+
+#[test]
+fn test_migration_conflict_for_differing_archive_folder() {
+    let dir = legacy_project();
+    let root = dir.path();
+    fs::create_dir_all(root.join("ralph/archive/2026-01-01-old")).unwrap();
+    fs::write(root.join("ralph/archive/2026-01-01-old/prd.json"), "other").unwrap();
+
+    let plan = MigrationPlan::compute(root).unwrap();
+
+    assert_eq!(plan.conflicts.len(), 1);
+    assert_eq!(plan.conflicts[0].from, root.join("archive/2026-01-01-old"));
+}
+
+#[test]
+fn test_migration_removes_identical_duplicates() {
+    let dir = legacy_project();
+    let root = dir.path();
+    fs::create_dir_all(root.join("ralph")).unwrap();
+    fs::copy(root.join("progress.txt"), root.join("ralph/progress.txt")).unwrap();
+
+    let plan = MigrationPlan::compute(root).unwrap();
+    let progress = plan
+        .steps
+        .iter()
+        .find(|s| s.from == root.join("progress.txt"))
+        .unwrap();
+    assert_eq!(progress.action, StepAction::RemoveDuplicate);
+
+    plan.execute().unwrap();
+    assert!(!root.join("progress.txt").exists());
+    assert_eq!(
+        fs::read_to_string(root.join("ralph/progress.txt")).unwrap(),
+        "# Progress"
+    );
+}
+
+#[test]
+fn test_migration_partial_failure_reports_completed_steps() {
+    let dir = legacy_project();
+    let root = dir.path();
+    let plan = MigrationPlan::compute(root).unwrap();
+
+    // The progress file disappears between planning and executing
+    fs::remove_file(root.join("progress.txt")).unwrap();
+    let failure = plan.execute().unwrap_err();
+
+    assert_eq!(failure.completed, 3);
+    assert_eq!(plan.steps[failure.completed].from, root.join("progress.txt"));
+    assert!(root.join("ralph/archive/notes.txt").exists());
+    assert!(!root.join("archive").exists());
+    // prd.json is untouched, so the project still needs migrating
+    assert!(root.join("prd.json").exists());
+    assert!(!root.join("ralph/prd.json").exists());
+    assert!(MigrationPlan::is_needed(root));
+}
+
+#[test]
+fn test_migration_rerun_after_partial_failure_finishes() {
+    let dir = legacy_project();
+    let root = dir.path();
+    let plan = MigrationPlan::compute(root).unwrap();
+    fs::remove_file(root.join("progress.txt")).unwrap();
+    assert!(plan.execute().is_err());
+
+    let rerun = MigrationPlan::compute(root).unwrap();
+    assert!(rerun.conflicts.is_empty());
+    assert_eq!(described(&rerun, root), ["prd.json → ralph/prd.json"]);
+
+    rerun.execute().unwrap();
+    assert!(root.join("ralph/prd.json").exists());
+    assert!(!MigrationPlan::is_needed(root));
+}
+
+#[test]
+fn test_migration_rerun_with_copied_but_undeleted_sources() {
+    let dir = legacy_project();
+    let root = dir.path();
+    // An interrupted copy left both locations holding the same archive entry
+    fs::create_dir_all(root.join("ralph/archive")).unwrap();
+    fs::write(root.join("ralph/archive/notes.txt"), "notes").unwrap();
+
+    let plan = MigrationPlan::compute(root).unwrap();
+    assert!(plan.conflicts.is_empty());
+    assert_eq!(plan.steps[1].action, StepAction::RemoveDuplicate);
+
+    plan.execute().unwrap();
+    assert!(!root.join("archive").exists());
+    assert_eq!(
+        fs::read_to_string(root.join("ralph/archive/notes.txt")).unwrap(),
+        "notes"
+    );
+}
+
+#[test]
+fn test_same_content_compares_folders_recursively() {
+    let dir = TempDir::new().unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    for folder in [&a, &b] {
+        fs::create_dir_all(folder.join("nested")).unwrap();
+        fs::write(folder.join("nested/file.txt"), "same").unwrap();
+    }
+    assert!(same_content(&a, &b).unwrap());
+
+    fs::write(b.join("nested/extra.txt"), "extra").unwrap();
+    assert!(!same_content(&a, &b).unwrap());
+    assert!(!same_content(&a, &a.join("nested/file.txt")).unwrap());
+}
+
+#[test]
+fn test_migration_prompt_without_terminal() {
+    assert_eq!(migration_prompt(false, false), MigrationPrompt::Skip);
+    assert_eq!(migration_prompt(true, false), MigrationPrompt::Accept);
+}
+
+#[test]
+fn test_migration_prompt_at_terminal() {
+    assert_eq!(migration_prompt(false, true), MigrationPrompt::Ask);
+    assert_eq!(migration_prompt(true, true), MigrationPrompt::Accept);
+}
+
+#[test]
+fn test_interrupted_migration_prompt_moves_nothing() {
+    let dir = legacy_project();
+    let root = dir.path();
+    let plan = MigrationPlan::compute(root).unwrap();
+
+    let result = apply_migration_plan(root, &plan, MigrationPrompt::Ask, || {
+        Err(RalphError::Interrupted)
+    });
+    assert!(matches!(result, Err(RalphError::Interrupted)));
+
+    let declined = apply_migration_plan(root, &plan, MigrationPrompt::Ask, || Ok(false));
+    assert!(matches!(declined, Err(RalphError::Other(_))));
+
+    assert!(root.join("prd.json").exists());
+    assert!(root.join("archive/notes.txt").exists());
+    assert!(!root.join("ralph").exists());
+}
+
+#[test]
+fn test_accepted_migration_prompt_runs_the_plan() {
+    let dir = legacy_project();
+    let root = dir.path();
+    let plan = MigrationPlan::compute(root).unwrap();
+
+    apply_migration_plan(root, &plan, MigrationPrompt::Ask, || Ok(true)).unwrap();
+    assert!(root.join("ralph/prd.json").exists());
+    assert!(!MigrationPlan::is_needed(root));
+
+    // Accepting up front never asks
+    let dir = legacy_project();
+    let plan = MigrationPlan::compute(dir.path()).unwrap();
+    apply_migration_plan(dir.path(), &plan, MigrationPrompt::Accept, || {
+        panic!("should not ask")
+    })
+    .unwrap();
+    assert!(dir.path().join("ralph/prd.json").exists());
+}
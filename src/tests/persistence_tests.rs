@@ -0,0 +1,122 @@
+//! Persistence Tests
+//!
+//! Tests for the store `ralph run` writes its state through:
+//! - Writes run normally until the run is read-only
+//! - `--readonly` skips every write and records what was skipped
+//! - With `readonly_ok`, a failed write turns read-only mode on
+//! - Without it, the error ends the run as before
+//! - Runs that would write outside the store refuse `--readonly`
+
+use std::cell::Cell;
+use std::fs;
+use std::io;
+use tempfile::TempDir;
+
+use crate::commands::run::{run_run, RunOptions};
+use crate::error::RalphError;
+use crate::persistence::RunStore;
+
+#[test]
+fn test_writes_run_when_not_readonly() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("progress.txt");
+    let store = RunStore::new(false, false);
+
+    let written = store.write("progress.txt", || Ok(fs::write(&path, "log")?)).unwrap();
+
+    assert_eq!(written, Some(()));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "log");
+    assert!(!store.is_readonly());
+    assert!(store.skipped().is_empty());
+}
+
+#[test]
+fn test_readonly_skips_writes() {
+    let store = RunStore::new(true, false);
+    let called = Cell::new(false);
+
+    let written = store
+        .write("progress.txt", || {
+            called.set(true);
+            Ok(())
+        })
+        .unwrap();
+
+    assert_eq!(written, None);
+    assert!(!called.get());
+    assert_eq!(store.skipped(), ["progress.txt"]);
+}
+
+#[test]
+fn test_skipped_lists_each_write_once_in_order() {
+    let store = RunStore::new(true, false);
+    for what in ["progress.txt", ".last-branch", "progress.txt", "run state"] {
+        store.write(what, || Ok(())).unwrap();
+    }
+
+    assert_eq!(store.skipped(), ["progress.txt", ".last-branch", "run state"]);
+}
+
+#[test]
+fn test_readonly_ok_turns_a_failed_write_into_readonly() {
+    let store = RunStore::new(false, true);
+    let failing = || -> crate::error::RalphResult<()> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "read-only file system").into())
+    };
+
+    assert_eq!(store.write("progress.txt", failing).unwrap(), None);
+    assert!(store.is_readonly());
+
+    // Later writes are skipped without being attempted
+    let called = Cell::new(false);
+    store
+        .write("run state", || {
+            called.set(true);
+            Ok(())
+        })
+        .unwrap();
+    assert!(!called.get());
+    assert_eq!(store.skipped(), ["progress.txt", "run state"]);
+}
+
+#[test]
+fn test_failed_write_is_an_error_without_readonly_ok() {
+    let store = RunStore::new(false, false);
+    let result = store.write("progress.txt", || -> crate::error::RalphResult<()> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "read-only file system").into())
+    });
+
+    assert!(matches!(result, Err(RalphError::Io(_))));
+    assert!(!store.is_readonly());
+}
+
+#[test]
+fn test_readonly_ok_keeps_other_errors() {
+    let store = RunStore::new(false, true);
+    let result = store.write("prd.json", || -> crate::error::RalphResult<()> {
+        Err(RalphError::Other("not an I/O failure".to_string()))
+    });
+
+    assert!(matches!(result, Err(RalphError::Other(_))));
+    assert!(!store.is_readonly());
+}
+
+#[test]
+fn test_readonly_refuses_parallel_and_init_if_missing() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let parallel = RunOptions {
+        readonly: true,
+        parallel: Some(2),
+        ..RunOptions::default()
+    };
+    let init = RunOptions {
+        readonly: true,
+        init_if_missing: true,
+        ..RunOptions::default()
+    };
+
+    for (options, flag) in [(parallel, "--parallel"), (init, "--init-if-missing")] {
+        let err = rt.block_on(run_run(options)).unwrap_err().to_string();
+        assert!(err.contains(&format!("{} cannot be used with --readonly", flag)), "{}", err);
+    }
+}
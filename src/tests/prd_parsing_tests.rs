@@ -6,15 +6,34 @@
 //! - completed_stories() - counting completed stories
 //! - pending_stories() - counting pending stories
 //! - highest_priority_pending() - finding next story to work on
-//! - mark_story_passed() - updating story status
-//! - save_to_file() - persisting PRD changes
+//! - pending_ordered() - priority, file and seeded random story order
+//! - stats() - single-pass counts matching the direct methods
+//! - mark_story_passed() - updating story status, rejecting unknown ids
+//! - save_to_file() / is_dirty() - persisting PRD changes, skipping unchanged files
+//! - save_to_file() style - canonical output, kept indent and key order
+//! - blocked_stories() / actionable_stories() - dependency readiness
+//! - append_note() - appending to story notes
+//! - add_criterion() - appending acceptance criteria
+//! - normalize_ids() - renumbering ids and their dependency references
+//! - set_branch() - normalizing branch names to ralph/<name>
+//! - epic_groups() - grouping stories by epic with an "Ungrouped" bucket
+//! - priority_bands() / criteria_histogram() / dependency_depth() - complexity stats
+//! - from_file_with_defaults() / normalize_priorities() - safe fixes for `prd validate --fix`
+//! - move_story() - moving a story to a new priority, shifting the others
+//! - weighted_percentage() / critical_path() / dependency_cycle() - estimate-aware planning
+//! - extract() / cross_dependencies() - splitting stories into a new PRD
+//! - from_file_at() / save_to_file_at() / is_dirty_at() - PRDs nested under a JSON Pointer (`--prd-key`)
 //! - Error handling for invalid JSON
 //! - Default value handling for missing fields
 
+use std::fs;
 use std::io::Write;
+use std::time::{Duration, SystemTime};
 use tempfile::TempDir;
 
-use crate::prd::{Prd, UserStory};
+use crate::fake_prd::{fake_prd, FakePrdOptions};
+use crate::prd::{parse_json_pointer, Epic, Prd, StoryOrder, UserStory, UNGROUPED_EPIC};
+use crate::tests::fixtures::{strings, user_story};
 
 /// Helper function to create a temporary PRD JSON file
 fn create_temp_prd_file(temp_dir: &TempDir, content: &str) -> std::path::PathBuf {
@@ -315,7 +334,7 @@ fn test_mark_story_passed_updates_story_status() {
     assert!(!story.passes);
 
     // Mark it as passed
-    prd.mark_story_passed("US-002", &file_path).unwrap();
+    assert!(prd.mark_story_passed("US-002", &file_path).unwrap());
 
     // Reload and verify
     let updated_prd = Prd::from_file(&file_path).unwrap();
@@ -324,22 +343,34 @@ fn test_mark_story_passed_updates_story_status() {
 }
 
 #[test]
-fn test_mark_story_passed_does_nothing_for_invalid_story_id() {
+fn test_mark_story_passed_rejects_invalid_story_id() {
     let temp_dir = TempDir::new().unwrap();
     let file_path = create_temp_prd_file(&temp_dir, sample_valid_prd_json());
     let mut prd = Prd::from_file(&file_path).unwrap();
 
-    // Try to mark a non-existent story
-    let result = prd.mark_story_passed("US-999", &file_path);
+    // A near miss like US-01 for US-001 must not pass silently
+    let err = prd.mark_story_passed("US-01", &file_path).unwrap_err();
 
-    // Should not error, just do nothing
-    assert!(result.is_ok());
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("Unknown story id: US-01"));
+    assert!(err.to_string().contains("valid ids: US-001, US-002"));
 
     // Verify original file is unchanged
     let reloaded_prd = Prd::from_file(&file_path).unwrap();
     assert_eq!(reloaded_prd.completed_stories(), 1);
 }
 
+#[test]
+fn test_mark_story_passed_skips_passing_story() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, sample_valid_prd_json());
+    let before = fs::read_to_string(&file_path).unwrap();
+    let mut prd = Prd::from_file(&file_path).unwrap();
+
+    assert!(!prd.mark_story_passed("US-001", &file_path).unwrap());
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), before);
+}
+
 #[test]
 fn test_save_to_file_persists_changes() {
     let temp_dir = TempDir::new().unwrap();
@@ -397,13 +428,8 @@ fn test_user_story_structure_parsing() {
 #[test]
 fn test_user_story_display_format() {
     let story = UserStory {
-        id: "US-042".to_string(),
         title: "Test Story Display".to_string(),
-        description: "Description".to_string(),
-        acceptance_criteria: vec![],
-        priority: 1,
-        passes: false,
-        notes: "".to_string(),
+        ..user_story("US-042")
     };
 
     assert_eq!(story.display(), "US-042 - Test Story Display");
@@ -489,42 +515,19 @@ fn test_progress_percentage_calculation() {
 
 #[test]
 fn test_large_number_of_stories() {
-    let mut stories = Vec::new();
-    for i in 1..=100 {
-        stories.push(format!(
-            r#"{{
-                "id": "US-{}",
-                "title": "Story {}",
-                "description": "Description {}",
-                "acceptanceCriteria": [],
-                "priority": {},
-                "passes": {},
-                "notes": ""
-            }}"#,
-            i,
-            i,
-            i,
-            i,
-            i % 2 == 0 // Even numbers are completed
-        ));
-    }
-
-    let json = format!(
-        r#"{{
-            "project": "Large Project",
-            "branchName": "feature/large",
-            "description": "Test with many stories",
-            "userStories": [{}]
-        }}"#,
-        stories.join(",")
-    );
-
+    let options = FakePrdOptions {
+        stories: 100,
+        completed: 0.5,
+        ..FakePrdOptions::default()
+    };
     let temp_dir = TempDir::new().unwrap();
-    let file_path = create_temp_prd_file(&temp_dir, &json);
+    let file_path = temp_dir.path().join("prd.json");
+    fake_prd(&options).save_to_file(&file_path).unwrap();
+
     let prd = Prd::from_file(&file_path).unwrap();
 
     assert_eq!(prd.total_stories(), 100);
-    assert_eq!(prd.completed_stories(), 50); // Even numbers from 1-100
+    assert_eq!(prd.completed_stories(), 50);
     assert_eq!(prd.pending_stories(), 50);
 }
 
@@ -544,3 +547,1191 @@ fn test_prd_with_minimal_fields() {
     assert_eq!(prd.project, "Minimal");
     assert_eq!(prd.total_stories(), 0);
 }
+
+#[test]
+fn test_story_without_optional_fields_parses() {
+    let json = r#"{
+        "project": "Old",
+        "branchName": "feature/old",
+        "description": "Written before notes existed",
+        "userStories": [
+            {"id": "US-001", "title": "Bare", "priority": 1, "passes": false}
+        ]
+    }"#;
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, json);
+    let prd = Prd::from_file(&file_path).unwrap();
+
+    let story = &prd.user_stories[0];
+    assert_eq!(story.notes, "");
+    assert_eq!(story.description, "");
+    assert!(story.acceptance_criteria.is_empty());
+    assert!(story.tags.is_empty());
+    assert_eq!(story.estimate, None);
+    assert!(story.is_weak());
+
+    // Empty optional fields added later are not written back out
+    let json = serde_json::to_string(&prd).unwrap();
+    assert!(!json.contains("tags") && !json.contains("estimate"));
+}
+
+#[test]
+fn test_story_missing_core_field_fails() {
+    let temp_dir = TempDir::new().unwrap();
+    for missing in ["id", "title", "priority", "passes"] {
+        let mut story = serde_json::json!({
+            "id": "US-001", "title": "Core", "priority": 1, "passes": false
+        });
+        story.as_object_mut().unwrap().remove(missing);
+        let prd = serde_json::json!({
+            "project": "Core",
+            "branchName": "feature/core",
+            "description": "",
+            "userStories": [story]
+        });
+        let file_path = create_temp_prd_file(&temp_dir, &prd.to_string());
+
+        let err = Prd::from_file(&file_path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "missing {}", missing);
+        assert!(err.to_string().contains(missing), "{}", err);
+    }
+}
+
+/// Helper function to create a PRD JSON string with dependsOn links
+fn dependent_prd_json() -> &'static str {
+    r#"{
+        "project": "Deps",
+        "branchName": "feature/deps",
+        "description": "Stories with dependencies",
+        "userStories": [
+            {"id": "US-001", "title": "Schema", "description": "Desc", "acceptanceCriteria": [], "priority": 1, "passes": false, "notes": ""},
+            {"id": "US-002", "title": "API", "description": "Desc", "acceptanceCriteria": [], "priority": 2, "passes": false, "notes": "", "dependsOn": ["US-001"]},
+            {"id": "US-003", "title": "UI", "description": "Desc", "acceptanceCriteria": [], "priority": 3, "passes": false, "notes": "", "dependsOn": ["US-001", "US-002"]}
+        ]
+    }"#
+}
+
+#[test]
+fn test_depends_on_defaults_to_empty() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, sample_valid_prd_json());
+    let prd = Prd::from_file(&file_path).unwrap();
+
+    assert!(prd.user_stories.iter().all(|s| s.depends_on.is_empty()));
+
+    // Empty dependency lists are not written back out
+    let json = serde_json::to_string(&prd).unwrap();
+    assert!(!json.contains("dependsOn"));
+}
+
+#[test]
+fn test_dependents_of_lists_referencing_stories() {
+    let prd: Prd = serde_json::from_str(dependent_prd_json()).unwrap();
+
+    let dependents: Vec<&str> = prd
+        .dependents_of("US-001")
+        .iter()
+        .map(|s| s.id.as_str())
+        .collect();
+    assert_eq!(dependents, vec!["US-002", "US-003"]);
+    assert!(prd.dependents_of("US-003").is_empty());
+}
+
+#[test]
+fn test_remove_story_strips_dependency_references() {
+    let mut prd: Prd = serde_json::from_str(dependent_prd_json()).unwrap();
+
+    let removed = prd.remove_story("US-001").unwrap();
+
+    assert_eq!(removed.id, "US-001");
+    assert_eq!(prd.total_stories(), 2);
+    assert!(prd.user_stories[0].depends_on.is_empty());
+    assert_eq!(prd.user_stories[1].depends_on, vec!["US-002"]);
+}
+
+fn story_ids(stories: Vec<&UserStory>) -> Vec<&str> {
+    stories.iter().map(|s| s.id.as_str()).collect()
+}
+
+#[test]
+fn test_blocked_and_actionable_stories() {
+    let mut prd: Prd = serde_json::from_str(dependent_prd_json()).unwrap();
+
+    assert_eq!(story_ids(prd.actionable_stories()), vec!["US-001"]);
+    assert_eq!(story_ids(prd.blocked_stories()), vec!["US-002", "US-003"]);
+    assert_eq!(prd.unmet_dependencies(&prd.user_stories[2]), vec!["US-001", "US-002"]);
+
+    // Completing US-001 unblocks US-002 but not US-003
+    prd.user_stories[0].passes = true;
+    assert_eq!(story_ids(prd.actionable_stories()), vec!["US-002"]);
+    assert_eq!(story_ids(prd.blocked_stories()), vec!["US-003"]);
+    assert_eq!(prd.unmet_dependencies(&prd.user_stories[2]), vec!["US-002"]);
+
+    // Completed stories are neither actionable nor blocked
+    prd.user_stories[1].passes = true;
+    assert_eq!(story_ids(prd.actionable_stories()), vec!["US-003"]);
+    assert!(prd.blocked_stories().is_empty());
+}
+
+#[test]
+fn test_unknown_dependency_blocks_story() {
+    let mut prd: Prd = serde_json::from_str(dependent_prd_json()).unwrap();
+    prd.user_stories[0].depends_on.push("US-404".to_string());
+
+    assert!(prd.actionable_stories().is_empty());
+    assert_eq!(prd.unmet_dependencies(&prd.user_stories[0]), vec!["US-404"]);
+}
+
+#[test]
+fn test_remove_story_unknown_id() {
+    let mut prd: Prd = serde_json::from_str(dependent_prd_json()).unwrap();
+
+    assert!(prd.remove_story("US-999").is_none());
+    assert_eq!(prd.total_stories(), 3);
+}
+
+#[test]
+fn test_add_criterion_to_empty_and_existing_lists() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, sample_valid_prd_json());
+    let mut prd = Prd::from_file(&file_path).unwrap();
+
+    assert_eq!(prd.add_criterion("US-003", "  Shows a summary ").unwrap(), 1);
+    assert_eq!(prd.add_criterion("US-001", "Logs the result").unwrap(), 3);
+
+    assert_eq!(prd.find_story("US-003").unwrap().acceptance_criteria, ["Shows a summary"]);
+    assert_eq!(
+        prd.find_story("US-001").unwrap().acceptance_criteria,
+        ["Criteria 1", "Criteria 2", "Logs the result"]
+    );
+}
+
+#[test]
+fn test_add_criterion_rejects_unknown_id_and_blank_text() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, sample_valid_prd_json());
+    let mut prd = Prd::from_file(&file_path).unwrap();
+
+    let unknown = prd.add_criterion("US-9", "Works").unwrap_err();
+    assert!(unknown.starts_with("Unknown story id: US-9 (valid ids: US-001"));
+    assert!(prd.add_criterion("US-002", "  ").is_err());
+    assert_eq!(prd.find_story("US-002").unwrap().acceptance_criteria, ["Criteria 3"]);
+}
+
+#[test]
+fn test_save_to_file_leaves_no_temp_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, dependent_prd_json());
+    let mut prd = Prd::from_file(&file_path).unwrap();
+
+    prd.remove_story("US-002");
+    prd.save_to_file(&file_path).unwrap();
+
+    let entries: Vec<_> = std::fs::read_dir(temp_dir.path()).unwrap().collect();
+    assert_eq!(entries.len(), 1);
+    let reloaded = Prd::from_file(&file_path).unwrap();
+    assert_eq!(reloaded.total_stories(), 2);
+    assert_eq!(reloaded.user_stories[1].depends_on, vec!["US-001"]);
+}
+
+/// Move a file's mtime into the past, so a rewrite would be visible
+fn backdate(path: &std::path::Path) -> SystemTime {
+    let past = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    fs::File::options().write(true).open(path).unwrap().set_modified(past).unwrap();
+    past
+}
+
+#[test]
+fn test_save_untouched_prd_leaves_file_alone() {
+    let temp_dir = TempDir::new().unwrap();
+    // Hand-formatted, with a field ralph does not know about
+    let json = sample_valid_prd_json().replacen('{', "{\"$schema\": \"prd.schema.json\",", 1);
+    let file_path = create_temp_prd_file(&temp_dir, &json);
+    let mtime = backdate(&file_path);
+    let prd = Prd::from_file(&file_path).unwrap();
+
+    assert!(!prd.is_dirty(&file_path).unwrap());
+    prd.save_to_file(&file_path).unwrap();
+
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), json);
+    assert_eq!(fs::metadata(&file_path).unwrap().modified().unwrap(), mtime);
+}
+
+#[test]
+fn test_save_changed_prd_rewrites_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, sample_valid_prd_json());
+    let mtime = backdate(&file_path);
+    let mut prd = Prd::from_file(&file_path).unwrap();
+
+    prd.user_stories[1].notes = "Changed".to_string();
+    assert!(prd.is_dirty(&file_path).unwrap());
+    prd.save_to_file(&file_path).unwrap();
+
+    assert!(!prd.is_dirty(&file_path).unwrap());
+    assert_ne!(fs::metadata(&file_path).unwrap().modified().unwrap(), mtime);
+    assert_eq!(Prd::from_file(&file_path).unwrap().user_stories[1].notes, "Changed");
+    assert!(prd.is_dirty(temp_dir.path().join("missing.json")).unwrap());
+}
+
+/// A PRD as ralph writes it: two-space indent, field order, trailing newline
+const CANONICAL_PRD: &str = r#"{
+  "project": "Canonical",
+  "branchName": "ralph/canonical",
+  "description": "Written by ralph",
+  "userStories": [
+    {
+      "id": "US-001",
+      "title": "First",
+      "description": "As a user, I want one thing",
+      "acceptanceCriteria": [
+        "It works"
+      ],
+      "priority": 1,
+      "passes": false,
+      "notes": ""
+    }
+  ]
+}
+"#;
+
+#[test]
+fn test_save_new_prd_uses_canonical_style() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd: Prd = serde_json::from_str(CANONICAL_PRD).unwrap();
+    let file_path = temp_dir.path().join("prd.json");
+
+    prd.save_to_file(&file_path).unwrap();
+
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), CANONICAL_PRD);
+}
+
+#[test]
+fn test_save_canonical_prd_changes_only_the_edited_line() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, CANONICAL_PRD);
+    let mut prd = Prd::from_file(&file_path).unwrap();
+
+    prd.mark_story_passed("US-001", &file_path).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&file_path).unwrap(),
+        CANONICAL_PRD.replace(r#""passes": false"#, r#""passes": true"#)
+    );
+}
+
+#[test]
+fn test_save_keeps_indent_and_key_order_of_hand_written_prd() {
+    let hand_written = r#"{
+    "branchName": "ralph/hand",
+    "project": "Hand Written",
+    "description": "",
+    "userStories": [
+        {
+            "id": "US-001",
+            "priority": 1,
+            "passes": false,
+            "title": "First",
+            "description": "Story",
+            "acceptanceCriteria": [],
+            "notes": ""
+        }
+    ]
+}
+"#;
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, hand_written);
+    let mut prd = Prd::from_file(&file_path).unwrap();
+
+    prd.user_stories[0].notes = "Started".to_string();
+    prd.user_stories[0].depends_on = vec!["US-000".to_string()];
+    prd.save_to_file(&file_path).unwrap();
+
+    // New keys go after the existing ones
+    let notes = r#""notes": "Started",
+            "dependsOn": [
+                "US-000"
+            ]"#;
+    let expected = hand_written.replace(r#""notes": """#, notes);
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), expected);
+}
+
+#[test]
+fn test_weak_stories_flags_missing_criteria_and_description() {
+    let json = r#"{
+        "project": "Weak",
+        "branchName": "feature/weak",
+        "description": "Weak stories",
+        "userStories": [
+            {"id": "US-001", "title": "Well specified", "description": "Desc", "acceptanceCriteria": ["Works"], "priority": 1, "passes": false, "notes": ""},
+            {"id": "US-002", "title": "No criteria", "description": "Desc", "acceptanceCriteria": [], "priority": 2, "passes": false, "notes": ""},
+            {"id": "US-003", "title": "No description", "description": "  ", "acceptanceCriteria": ["Works"], "priority": 3, "passes": false, "notes": ""},
+            {"id": "US-004", "title": "Done already", "description": "", "acceptanceCriteria": [], "priority": 4, "passes": true, "notes": ""}
+        ]
+    }"#;
+    let prd: Prd = serde_json::from_str(json).unwrap();
+
+    let weak: Vec<&str> = prd.weak_stories().iter().map(|s| s.id.as_str()).collect();
+
+    assert_eq!(weak, vec!["US-002", "US-003"]);
+}
+
+#[test]
+fn test_weak_stories_empty_for_well_specified_prd() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, sample_valid_prd_json());
+    let mut prd = Prd::from_file(&file_path).unwrap();
+    prd.user_stories[2].acceptance_criteria.push("Criteria 4".to_string());
+
+    assert!(prd.weak_stories().is_empty());
+}
+
+#[test]
+fn test_validate_reports_duplicates_and_unknown_dependencies() {
+    let mut prd: Prd = serde_json::from_str(dependent_prd_json()).unwrap();
+    assert!(prd.validate().is_empty());
+
+    prd.user_stories[1].id = "US-001".to_string();
+    prd.user_stories[2].depends_on.push("US-404".to_string());
+
+    let problems = prd.validate();
+    assert!(problems.iter().any(|p| p.contains("Duplicate story id: US-001")));
+    assert!(problems.iter().any(|p| p.contains("unknown story US-404")));
+}
+
+#[test]
+fn test_append_note_preserves_existing_notes() {
+    let mut prd: Prd = serde_json::from_str(dependent_prd_json()).unwrap();
+    let story = prd.find_story_mut("US-002").unwrap();
+
+    story.append_note("[2026-02-01 14:03] waiting on design review");
+    assert_eq!(story.notes, "[2026-02-01 14:03] waiting on design review");
+
+    story.append_note("[2026-02-02 09:00] approved");
+    assert_eq!(
+        story.notes,
+        "[2026-02-01 14:03] waiting on design review\n[2026-02-02 09:00] approved"
+    );
+}
+
+#[test]
+fn test_multiline_notes_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, dependent_prd_json());
+
+    let mut prd = Prd::from_file(&file_path).unwrap();
+    let notes = "Line one\n[2026-02-01 14:03] \"quoted\" line\n\tindented line";
+    prd.find_story_mut("US-001").unwrap().notes = notes.to_string();
+    prd.save_to_file(&file_path).unwrap();
+
+    let reloaded = Prd::from_file(&file_path).unwrap();
+    assert_eq!(reloaded.find_story("US-001").unwrap().notes, notes);
+}
+
+/// Helper function to create a PRD with inconsistent ids
+fn messy_ids_prd() -> Prd {
+    serde_json::from_str(
+        r#"{
+            "project": "Messy",
+            "branchName": "ralph/messy",
+            "description": "Inconsistent ids",
+            "userStories": [
+                {"id": "US-1", "title": "A", "description": "Desc", "acceptanceCriteria": [], "priority": 1, "passes": true, "notes": ""},
+                {"id": "US-02", "title": "B", "description": "Desc", "acceptanceCriteria": [], "priority": 2, "passes": false, "notes": "", "dependsOn": ["US-1"]},
+                {"id": "US-003", "title": "C", "description": "Desc", "acceptanceCriteria": [], "priority": 3, "passes": false, "notes": ""},
+                {"id": "story-x", "title": "D", "description": "Desc", "acceptanceCriteria": [], "priority": 4, "passes": false, "notes": "", "dependsOn": ["US-02", "US-003", "US-404"]}
+            ]
+        }"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_normalize_ids_pads_in_order() {
+    let mut prd = messy_ids_prd();
+
+    let changed = prd.normalize_ids();
+
+    let ids: Vec<&str> = prd.user_stories.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec!["US-001", "US-002", "US-003", "US-004"]);
+    assert_eq!(
+        changed,
+        vec![
+            ("US-1".to_string(), "US-001".to_string()),
+            ("US-02".to_string(), "US-002".to_string()),
+            ("story-x".to_string(), "US-004".to_string()),
+        ]
+    );
+
+    // Running again is a no-op
+    assert!(prd.normalize_ids().is_empty());
+}
+
+#[test]
+fn test_normalize_ids_rewrites_dependencies() {
+    let mut prd = messy_ids_prd();
+
+    prd.normalize_ids();
+
+    assert_eq!(prd.user_stories[1].depends_on, vec!["US-001"]);
+    // Unknown references are left for `prd validate` to report
+    assert_eq!(prd.user_stories[3].depends_on, vec!["US-002", "US-003", "US-404"]);
+}
+
+#[test]
+fn test_normalize_ids_widens_padding_for_large_prds() {
+    let mut prd = messy_ids_prd();
+    let template = prd.user_stories[2].clone();
+    prd.user_stories = (0..1200)
+        .map(|i| UserStory {
+            id: format!("X{}", i),
+            ..template.clone()
+        })
+        .collect();
+
+    prd.normalize_ids();
+
+    assert_eq!(prd.user_stories[0].id, "US-0001");
+    assert_eq!(prd.user_stories[1199].id, "US-1200");
+}
+
+/// PRD whose file order and priority order differ
+fn unordered_prd() -> Prd {
+    let mut prd: Prd = serde_json::from_str(sample_valid_prd_json()).unwrap();
+    let template = prd.user_stories[1].clone();
+    prd.user_stories = [("US-010", 3), ("US-020", 1), ("US-030", 2), ("US-040", 1)]
+        .into_iter()
+        .map(|(id, priority)| UserStory {
+            id: id.to_string(),
+            priority,
+            ..template.clone()
+        })
+        .collect();
+    prd
+}
+
+#[test]
+fn test_pending_ordered_by_priority() {
+    let prd = unordered_prd();
+
+    let ordered = story_ids(prd.pending_ordered(StoryOrder::Priority));
+
+    // Equal priorities keep their file order
+    assert_eq!(ordered, vec!["US-020", "US-040", "US-030", "US-010"]);
+    assert_eq!(ordered[0], prd.highest_priority_pending().unwrap().id);
+}
+
+#[test]
+fn test_pending_ordered_by_file() {
+    let mut prd = unordered_prd();
+    prd.user_stories[1].passes = true;
+
+    assert_eq!(
+        story_ids(prd.pending_ordered(StoryOrder::File)),
+        vec!["US-010", "US-030", "US-040"]
+    );
+}
+
+#[test]
+fn test_pending_ordered_random_is_deterministic_per_seed() {
+    let prd = unordered_prd();
+
+    let first = story_ids(prd.pending_ordered(StoryOrder::Random(42)));
+    let again = story_ids(prd.pending_ordered(StoryOrder::Random(42)));
+    assert_eq!(first, again);
+
+    let mut sorted = first.clone();
+    sorted.sort();
+    assert_eq!(sorted, vec!["US-010", "US-020", "US-030", "US-040"]);
+
+    // Some seed among a handful must give a different order
+    assert!((0..20).any(|seed| story_ids(prd.pending_ordered(StoryOrder::Random(seed))) != first));
+}
+
+#[test]
+fn test_pending_ordered_random_stable_as_stories_pass() {
+    let mut prd = unordered_prd();
+    let before = story_ids(prd.pending_ordered(StoryOrder::Random(7)))
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let passed = before[0].clone();
+    prd.find_story_mut(&passed).unwrap().passes = true;
+
+    assert_eq!(story_ids(prd.pending_ordered(StoryOrder::Random(7))), before[1..]);
+}
+
+#[test]
+fn test_stats_match_direct_methods_on_large_prd() {
+    let mut prd = unordered_prd();
+    let template = prd.user_stories[0].clone();
+    prd.user_stories = (0..10_000u32)
+        .map(|i| UserStory {
+            id: format!("US-{:05}", i),
+            // Spread priorities so several stories share the lowest pending one
+            priority: (i * 7919) % 500,
+            passes: i % 3 == 0,
+            ..template.clone()
+        })
+        .collect();
+
+    let stats = prd.stats();
+
+    assert_eq!(stats.total, prd.total_stories());
+    assert_eq!(stats.completed, prd.completed_stories());
+    assert_eq!(stats.pending, prd.pending_stories());
+    assert_eq!(
+        stats.next_story.as_deref(),
+        prd.highest_priority_pending().map(|s| s.id.as_str())
+    );
+
+    for story in &mut prd.user_stories {
+        story.passes = true;
+    }
+    let done = prd.stats();
+    assert_eq!((done.completed, done.pending), (10_000, 0));
+    assert_eq!(done.next_story, None);
+}
+
+/// Unordered PRD with a declared epic, an undeclared one and an ungrouped story
+fn epic_prd() -> Prd {
+    let mut prd = unordered_prd();
+    prd.epics = vec![
+        Epic {
+            name: "Checkout".to_string(),
+            description: "Paying for the cart".to_string(),
+        },
+        Epic {
+            name: "Search".to_string(),
+            description: String::new(),
+        },
+    ];
+    for (story, epic) in prd.user_stories.iter_mut().zip([
+        Some("Checkout"),
+        None,
+        Some("Checkout"),
+        Some("Reports"),
+    ]) {
+        story.epic = epic.map(String::from);
+    }
+    prd
+}
+
+#[test]
+fn test_epic_groups_keep_declared_order_and_priority() {
+    let mut prd = epic_prd();
+    prd.user_stories[2].passes = true;
+
+    let groups = prd.epic_groups();
+    let summary: Vec<(&str, Vec<&str>, usize)> = groups
+        .iter()
+        .map(|g| (g.name, story_ids(g.stories.clone()), g.completed()))
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            ("Checkout", vec!["US-030", "US-010"], 1),
+            ("Search", vec![], 0),
+            ("Reports", vec!["US-040"], 0),
+            (UNGROUPED_EPIC, vec!["US-020"], 0),
+        ]
+    );
+    assert_eq!(groups[0].description, "Paying for the cart");
+}
+
+#[test]
+fn test_epic_groups_without_epics_is_one_ungrouped_bucket() {
+    let prd = unordered_prd();
+
+    let groups = prd.epic_groups();
+
+    assert!(!prd.has_epics());
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].name, UNGROUPED_EPIC);
+    assert_eq!(story_ids(groups[0].stories.clone()), vec!["US-020", "US-040", "US-030", "US-010"]);
+}
+
+#[test]
+fn test_undeclared_epics_are_not_validation_problems() {
+    let prd = epic_prd();
+
+    assert!(prd.has_epics());
+    assert_eq!(story_ids(prd.undeclared_epic_stories()), vec!["US-040"]);
+    assert!(prd.validate().is_empty());
+    assert_eq!(story_ids(prd.epic_stories("Checkout")), vec!["US-010", "US-030"]);
+}
+
+#[test]
+fn test_epics_round_trip_and_default_to_empty() {
+    let prd = epic_prd();
+    let json = serde_json::to_string(&prd).unwrap();
+    let parsed: Prd = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.epics, prd.epics);
+    assert_eq!(parsed.user_stories[0].epic.as_deref(), Some("Checkout"));
+
+    // PRDs without epics keep serializing without the new fields
+    let plain = serde_json::to_string(&unordered_prd()).unwrap();
+    assert!(!plain.contains("epic"));
+}
+
+#[test]
+fn test_set_branch_adds_missing_prefix() {
+    let mut prd = unordered_prd();
+
+    assert_eq!(prd.set_branch("checkout-flow").unwrap(), "ralph/checkout-flow");
+    assert_eq!(prd.branch_name(), "ralph/checkout-flow");
+}
+
+#[test]
+fn test_set_branch_keeps_existing_prefix() {
+    let mut prd = unordered_prd();
+
+    assert_eq!(prd.set_branch("ralph/checkout-flow").unwrap(), "ralph/checkout-flow");
+}
+
+#[test]
+fn test_set_branch_slugifies_spaces() {
+    let mut prd = unordered_prd();
+
+    assert_eq!(prd.set_branch("  New   checkout\tflow ").unwrap(), "ralph/New-checkout-flow");
+    assert_eq!(prd.set_branch("ralph/ billing v2").unwrap(), "ralph/billing-v2");
+}
+
+#[test]
+fn test_set_branch_rejects_empty_names() {
+    let mut prd = unordered_prd();
+    let before = prd.branch_name.clone();
+
+    assert!(prd.set_branch("   ").is_err());
+    assert!(prd.set_branch("ralph/").is_err());
+    assert_eq!(prd.branch_name, before);
+}
+
+/// Synthetic story with `criteria` acceptance criteria and the given dependencies
+fn sized_story(
+    id: &str,
+    priority: u32,
+    criteria: usize,
+    description: &str,
+    deps: &[&str],
+) -> UserStory {
+    UserStory {
+        description: description.to_string(),
+        acceptance_criteria: (1..=criteria).map(|i| format!("Criterion {}", i)).collect(),
+        priority,
+        depends_on: strings(deps),
+        ..user_story(id)
+    }
+}
+
+/// PRD with a three-link dependency chain, a story without criteria and a
+/// dependency on a missing story
+fn complexity_prd() -> Prd {
+    let mut prd = unordered_prd();
+    prd.user_stories = vec![
+        sized_story("US-001", 1, 2, "Short", &[]),
+        sized_story("US-002", 2, 4, "A much longer description", &["US-001"]),
+        sized_story("US-003", 6, 0, "Medium text", &["US-002"]),
+        sized_story("US-004", 12, 3, "Tiny", &["US-003", "US-404"]),
+    ];
+    prd
+}
+
+#[test]
+fn test_priority_bands() {
+    let prd = complexity_prd();
+
+    assert_eq!(prd.priority_bands(5), vec![(1, 2), (6, 1), (11, 1)]);
+    assert_eq!(prd.priority_bands(10), vec![(1, 3), (11, 1)]);
+    // A zero width is treated as one priority per band
+    assert_eq!(prd.priority_bands(0).len(), 4);
+}
+
+#[test]
+fn test_criteria_histogram_and_averages() {
+    let prd = complexity_prd();
+
+    let histogram: Vec<(usize, usize)> = prd.criteria_histogram().into_iter().collect();
+    assert_eq!(histogram, vec![(0, 1), (2, 1), (3, 1), (4, 1)]);
+    assert_eq!(prd.average_criteria(), 2.25);
+    assert_eq!(prd.max_criteria(), 4);
+    assert_eq!(story_ids(prd.stories_without_criteria()), vec!["US-003"]);
+}
+
+#[test]
+fn test_criteria_stats_on_empty_prd() {
+    let mut prd = complexity_prd();
+    prd.user_stories.clear();
+
+    assert!(prd.criteria_histogram().is_empty());
+    assert_eq!(prd.average_criteria(), 0.0);
+    assert_eq!(prd.max_criteria(), 0);
+    assert_eq!(prd.dependency_depth(), 0);
+}
+
+#[test]
+fn test_longest_descriptions() {
+    let prd = complexity_prd();
+
+    assert_eq!(story_ids(prd.longest_descriptions(2)), vec!["US-002", "US-003"]);
+    assert_eq!(prd.longest_descriptions(10).len(), 4);
+}
+
+#[test]
+fn test_dependency_depth_ignores_unknown_stories() {
+    let mut prd = complexity_prd();
+    assert_eq!(prd.dependency_depth(), 3);
+
+    prd.user_stories[3].depends_on = vec!["US-404".to_string()];
+    assert_eq!(prd.dependency_depth(), 2);
+}
+
+#[test]
+fn test_dependency_depth_stops_at_cycles() {
+    let mut prd = complexity_prd();
+    prd.user_stories[0].depends_on = vec!["US-004".to_string()];
+
+    // US-001 -> US-004 -> US-003 -> US-002 -> (US-001, already on the chain)
+    assert_eq!(prd.dependency_depth(), 3);
+}
+
+/// A pending story with an estimate and dependencies
+fn estimated_story(id: &str, estimate: Option<f64>, deps: &[&str]) -> UserStory {
+    UserStory {
+        estimate,
+        ..sized_story(id, 1, 1, "Estimated", deps)
+    }
+}
+
+/// Two chains, US-001 -> US-002 -> US-004 and US-003 -> US-004, and a lone US-005
+fn estimated_prd() -> Prd {
+    let mut prd = unordered_prd();
+    prd.user_stories = vec![
+        estimated_story("US-001", Some(2.0), &[]),
+        estimated_story("US-002", Some(1.0), &["US-001"]),
+        estimated_story("US-003", Some(5.0), &[]),
+        estimated_story("US-004", Some(3.0), &["US-002", "US-003"]),
+        estimated_story("US-005", Some(4.0), &[]),
+    ];
+    prd
+}
+
+fn path_of(prd: &Prd) -> (Vec<String>, f64) {
+    let path = prd.critical_path().unwrap();
+    (path.stories, path.estimate)
+}
+
+#[test]
+fn test_weighted_percentage() {
+    let mut prd = estimated_prd();
+    assert_eq!(prd.weighted_percentage(), Some(0.0));
+
+    // 5 of 15
+    prd.user_stories[2].passes = true;
+    assert_eq!(prd.weighted_percentage(), Some(100.0 / 3.0));
+    // One story of five, but a third of the work
+    assert_eq!(prd.stats().percentage(), 20.0);
+}
+
+#[test]
+fn test_weighted_percentage_needs_estimates() {
+    let mut prd = complexity_prd();
+    assert!(!prd.has_estimates());
+    assert_eq!(prd.weighted_percentage(), None);
+
+    // Stories without an estimate weigh 1: 1 of 3 + 1 + 1 + 1
+    prd.user_stories[3].estimate = Some(3.0);
+    prd.user_stories[0].passes = true;
+    assert_eq!(prd.weighted_percentage(), Some(100.0 / 6.0));
+}
+
+#[test]
+fn test_critical_path_follows_heaviest_chain() {
+    let prd = estimated_prd();
+
+    // US-003 -> US-004 (8) outweighs US-001 -> US-002 -> US-004 (6) and US-005 (4)
+    assert_eq!(path_of(&prd), (vec!["US-003".to_string(), "US-004".to_string()], 8.0));
+}
+
+#[test]
+fn test_critical_path_skips_passed_stories() {
+    let mut prd = estimated_prd();
+    prd.user_stories[2].passes = true;
+
+    let (stories, estimate) = path_of(&prd);
+    assert_eq!(stories, ["US-001", "US-002", "US-004"]);
+    assert_eq!(estimate, 6.0);
+
+    for story in &mut prd.user_stories {
+        story.passes = true;
+    }
+    assert_eq!(prd.critical_path(), None);
+}
+
+#[test]
+fn test_critical_path_missing_estimates_weigh_one() {
+    // US-001 -> US-002 -> US-003 -> US-004, plus an unknown dependency
+    let prd = complexity_prd();
+
+    let (stories, estimate) = path_of(&prd);
+    assert_eq!(stories, ["US-001", "US-002", "US-003", "US-004"]);
+    assert_eq!(estimate, 4.0);
+}
+
+#[test]
+fn test_critical_path_of_disconnected_stories() {
+    let mut prd = estimated_prd();
+    for story in &mut prd.user_stories {
+        story.depends_on.clear();
+    }
+
+    // No chains: the single heaviest story
+    assert_eq!(path_of(&prd), (vec!["US-003".to_string()], 5.0));
+
+    // Equal weights go to the first story in the PRD
+    prd.user_stories[4].estimate = Some(5.0);
+    assert_eq!(path_of(&prd).0, ["US-003"]);
+}
+
+#[test]
+fn test_dependency_cycle_rejected_by_validation() {
+    let mut prd = estimated_prd();
+    assert_eq!(prd.dependency_cycle(), None);
+    assert!(prd.validate().is_empty());
+
+    prd.user_stories[0].depends_on = vec!["US-004".to_string()];
+
+    assert_eq!(
+        prd.dependency_cycle().unwrap(),
+        ["US-001", "US-004", "US-002", "US-001"]
+    );
+    assert!(prd
+        .validate()
+        .contains(&"Dependency cycle: US-001 → US-004 → US-002 → US-001".to_string()));
+    // The path still ends instead of looping
+    assert!(prd.critical_path().is_some());
+}
+
+/// US-001..US-005 with tags; US-003 and US-005 are backend
+fn split_prd() -> Prd {
+    let mut prd = unordered_prd();
+    prd.epics = vec![
+        Epic { name: "api".to_string(), description: String::new() },
+        Epic { name: "ui".to_string(), description: String::new() },
+    ];
+    let stories = [
+        ("US-001", 5, "frontend", &[][..]),
+        ("US-002", 4, "frontend", &["US-001"][..]),
+        ("US-003", 3, "backend", &["US-001"][..]),
+        ("US-004", 2, "frontend", &["US-003"][..]),
+        ("US-005", 1, "backend", &["US-003"][..]),
+    ];
+    prd.user_stories = stories
+        .into_iter()
+        .map(|(id, priority, tag, deps)| UserStory {
+            tags: vec![tag.to_string()],
+            epic: Some(if tag == "backend" { "api" } else { "ui" }.to_string()),
+            ..sized_story(id, priority, 1, "Split", deps)
+        })
+        .collect();
+    prd
+}
+
+fn has_tag(tag: &str) -> impl Fn(&UserStory) -> bool + '_ {
+    move |story| story.tags.iter().any(|t| t == tag)
+}
+
+#[test]
+fn test_extract_by_tag() {
+    let mut prd = split_prd();
+
+    assert_eq!(
+        prd.cross_dependencies(has_tag("backend")),
+        [
+            ("US-003".to_string(), "US-001".to_string()),
+            ("US-004".to_string(), "US-003".to_string())
+        ]
+    );
+    let backend = prd.extract(has_tag("backend"));
+
+    // US-003 -> US-001 and US-005 -> US-002, priorities 3 and 1 -> 2 and 1
+    assert_eq!(backend.branch_name, prd.branch_name);
+    assert_eq!(priorities(&backend), [("US-001", 2), ("US-002", 1)]);
+    assert_eq!(backend.user_stories[0].title, "Story US-003");
+    // The dependency on the frontend story is gone; the internal one is renumbered
+    assert!(backend.user_stories[0].depends_on.is_empty());
+    assert_eq!(backend.user_stories[1].depends_on, ["US-001"]);
+    assert_eq!(backend.epics.len(), 1);
+    assert_eq!(backend.epics[0].name, "api");
+
+    // The original keeps its ids and loses references to moved stories
+    let kept: Vec<&str> = prd.user_stories.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(kept, ["US-001", "US-002", "US-004"]);
+    assert!(prd.find_story("US-004").unwrap().depends_on.is_empty());
+    assert_eq!(prd.find_story("US-002").unwrap().depends_on, ["US-001"]);
+    assert!(prd.validate().is_empty());
+    assert!(backend.validate().is_empty());
+}
+
+#[test]
+fn test_extract_by_id_list() {
+    let mut prd = split_prd();
+    let ids = ["US-002", "US-004"];
+
+    let extracted = prd.extract(|s| ids.contains(&s.id.as_str()));
+
+    assert_eq!(priorities(&extracted), [("US-001", 2), ("US-002", 1)]);
+    assert_eq!(extracted.user_stories[1].title, "Story US-004");
+    assert!(extracted.user_stories.iter().all(|s| s.depends_on.is_empty()));
+    assert_eq!(prd.total_stories(), 3);
+    assert!(prd.find_story("US-002").is_none());
+}
+
+#[test]
+fn test_extract_drops_unknown_dependencies_before_renumbering() {
+    let mut prd = split_prd();
+    // After renumbering, a stale "US-001" would point at the wrong story
+    prd.user_stories[4].depends_on.push("US-404".to_string());
+
+    let backend = prd.extract(has_tag("backend"));
+
+    assert_eq!(backend.user_stories[1].depends_on, ["US-001"]);
+}
+
+#[test]
+fn test_extract_nothing_leaves_prd_unchanged() {
+    let mut prd = split_prd();
+
+    let extracted = prd.extract(has_tag("missing"));
+
+    assert!(extracted.user_stories.is_empty());
+    assert!(extracted.epics.is_empty());
+    assert_eq!(prd.total_stories(), 5);
+    assert_eq!(prd.find_story("US-004").unwrap().depends_on, ["US-003"]);
+}
+
+#[test]
+fn test_self_dependency_is_not_reported_as_cycle() {
+    let mut prd = estimated_prd();
+    prd.user_stories[4].depends_on = vec!["US-005".to_string()];
+
+    assert_eq!(prd.dependency_cycle(), None);
+    assert_eq!(prd.validate(), ["US-005 depends on itself"]);
+    assert_eq!(path_of(&prd).1, 8.0);
+}
+
+#[test]
+fn test_from_file_with_defaults_fills_missing_story_fields() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = create_temp_prd_file(
+        &temp_dir,
+        r#"{
+            "project": "Loose",
+            "branchName": "ralph/loose",
+            "description": "",
+            "userStories": [
+                {"id": "US-001", "title": "Complete", "description": "d", "acceptanceCriteria": ["a"], "priority": 1, "passes": true, "notes": "n"},
+                {"id": "US-002", "title": "Sparse", "description": "d", "priority": 2, "notes": null}
+            ]
+        }"#,
+    );
+    // `passes` is required, and a null is not a missing field
+    assert!(Prd::from_file(&path).is_err());
+
+    let (prd, filled) = Prd::from_file_with_defaults(&path).unwrap();
+
+    assert_eq!(
+        filled,
+        [
+            "US-002: added missing notes",
+            "US-002: added missing acceptanceCriteria",
+            "US-002: added missing passes",
+        ]
+    );
+    assert!(prd.user_stories[0].passes);
+    assert!(!prd.user_stories[1].passes);
+    assert!(prd.user_stories[1].acceptance_criteria.is_empty());
+}
+
+#[test]
+fn test_normalize_priorities_removes_duplicates_and_gaps() {
+    let mut prd = unordered_prd();
+    // US-010: 3, US-020: 1, US-030: 2, US-040: 1
+    prd.user_stories[0].priority = 7;
+
+    let changed = prd.normalize_priorities();
+
+    let priorities: Vec<u32> = prd.user_stories.iter().map(|s| s.priority).collect();
+    assert_eq!(priorities, [4, 1, 3, 2]);
+    assert_eq!(
+        changed,
+        [("US-040".to_string(), 1, 2), ("US-030".to_string(), 2, 3), ("US-010".to_string(), 7, 4)]
+    );
+    assert!(prd.normalize_priorities().is_empty());
+}
+
+/// Priorities by id, in PRD order
+fn priorities(prd: &Prd) -> Vec<(&str, u32)> {
+    prd.user_stories.iter().map(|s| (s.id.as_str(), s.priority)).collect()
+}
+
+/// US-010: 1, US-020: 2, US-030: 3, US-040: 4
+fn contiguous_prd() -> Prd {
+    let mut prd = unordered_prd();
+    for (n, story) in prd.user_stories.iter_mut().enumerate() {
+        story.priority = n as u32 + 1;
+    }
+    prd
+}
+
+#[test]
+fn test_move_story_up_shifts_stories_down() {
+    let mut prd = contiguous_prd();
+
+    let changed = prd.move_story("US-040", 2).unwrap();
+
+    assert_eq!(priorities(&prd), [("US-010", 1), ("US-020", 3), ("US-030", 4), ("US-040", 2)]);
+    assert_eq!(
+        changed,
+        [("US-040".to_string(), 4, 2), ("US-020".to_string(), 2, 3), ("US-030".to_string(), 3, 4)]
+    );
+}
+
+#[test]
+fn test_move_story_down_shifts_stories_up() {
+    let mut prd = contiguous_prd();
+
+    let changed = prd.move_story("US-010", 3).unwrap();
+
+    assert_eq!(priorities(&prd), [("US-010", 3), ("US-020", 1), ("US-030", 2), ("US-040", 4)]);
+    assert_eq!(changed.len(), 3);
+}
+
+#[test]
+fn test_move_story_to_same_position_is_a_no_op() {
+    let mut prd = contiguous_prd();
+
+    assert!(prd.move_story("US-030", 3).unwrap().is_empty());
+    assert_eq!(priorities(&prd), [("US-010", 1), ("US-020", 2), ("US-030", 3), ("US-040", 4)]);
+}
+
+#[test]
+fn test_move_story_closes_gaps_and_rejects_bad_input() {
+    // US-010: 3, US-020: 1, US-030: 2, US-040: 1
+    let mut prd = unordered_prd();
+
+    prd.move_story("US-010", 1).unwrap();
+    assert_eq!(priorities(&prd), [("US-010", 1), ("US-020", 2), ("US-030", 4), ("US-040", 3)]);
+
+    assert!(prd.move_story("US-999", 1).unwrap_err().contains("US-999"));
+    assert_eq!(prd.move_story("US-010", 5).unwrap_err(), "Priority must be between 1 and 4");
+    assert!(prd.move_story("US-010", 0).is_err());
+}
+
+#[test]
+fn test_has_duplicate_or_empty_ids() {
+    let mut prd = unordered_prd();
+    assert!(!prd.has_duplicate_or_empty_ids());
+
+    prd.user_stories[1].id = "US-010".to_string();
+    assert!(prd.has_duplicate_or_empty_ids());
+
+    prd.user_stories[1].id = "  ".to_string();
+    assert!(prd.has_duplicate_or_empty_ids());
+}
+
+// ============================================================================
+// Nested PRDs (--prd-key)
+// ============================================================================
+
+/// A larger document holding the sample PRD under `tools` -> `ralph/v1`
+fn nested_document() -> String {
+    format!(
+        r#"{{"name": "monorepo", "tools": {{"lint": true, "ralph/v1": {}}}}}"#,
+        sample_valid_prd_json()
+    )
+}
+
+#[test]
+fn test_from_file_at_reads_nested_prd() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, &nested_document());
+
+    let prd = Prd::from_file_at(&file_path, "/tools/ralph~1v1").unwrap();
+
+    assert_eq!(prd.project, "Test Project");
+    assert_eq!(prd.total_stories(), 3);
+    assert!(Prd::from_file(&file_path).is_err());
+}
+
+#[test]
+fn test_save_to_file_at_keeps_the_rest_of_the_document() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, &nested_document());
+    let mut prd = Prd::from_file_at(&file_path, "/tools/ralph~1v1").unwrap();
+
+    prd.user_stories[1].passes = true;
+    prd.save_to_file_at(&file_path, "/tools/ralph~1v1").unwrap();
+
+    let document: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&file_path).unwrap()).unwrap();
+    assert_eq!(document["name"], "monorepo");
+    assert_eq!(document["tools"]["lint"], true);
+    let reloaded = Prd::from_file_at(&file_path, "/tools/ralph~1v1").unwrap();
+    assert_eq!(reloaded.completed_stories(), 2);
+}
+
+#[test]
+fn test_save_to_file_at_skips_untouched_nested_prd() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, &nested_document());
+    let mtime = backdate(&file_path);
+    let prd = Prd::from_file_at(&file_path, "/tools/ralph~1v1").unwrap();
+
+    prd.save_to_file_at(&file_path, "/tools/ralph~1v1").unwrap();
+
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), nested_document());
+    assert_eq!(fs::metadata(&file_path).unwrap().modified().unwrap(), mtime);
+}
+
+#[test]
+fn test_is_dirty_at_compares_only_the_nested_prd() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, &nested_document());
+    let mut prd = Prd::from_file_at(&file_path, "/tools/ralph~1v1").unwrap();
+
+    assert!(!prd.is_dirty_at(&file_path, "/tools/ralph~1v1").unwrap());
+    // The whole document is not this PRD
+    assert!(prd.is_dirty(&file_path).unwrap());
+
+    prd.user_stories[1].passes = true;
+    assert!(prd.is_dirty_at(&file_path, "/tools/ralph~1v1").unwrap());
+}
+
+#[test]
+fn test_from_file_at_reports_missing_or_invalid_pointer() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = create_temp_prd_file(&temp_dir, &nested_document());
+
+    let missing = Prd::from_file_at(&file_path, "/tools/ralph").unwrap_err();
+    assert_eq!(missing.kind(), std::io::ErrorKind::InvalidData);
+    assert!(missing.to_string().contains("no PRD at JSON Pointer /tools/ralph"));
+
+    let invalid = Prd::from_file_at(&file_path, "tools").unwrap_err();
+    assert_eq!(invalid.kind(), std::io::ErrorKind::InvalidInput);
+
+    // The pointer exists but holds something else
+    assert!(Prd::from_file_at(&file_path, "/name").is_err());
+}
+
+#[test]
+fn test_parse_json_pointer() {
+    assert_eq!(parse_json_pointer("").unwrap(), "");
+    assert_eq!(parse_json_pointer("/ralph").unwrap(), "/ralph");
+    assert!(parse_json_pointer("/a~0b~1c").is_ok());
+    assert!(parse_json_pointer("ralph").unwrap_err().contains("must start with /"));
+    assert!(parse_json_pointer("/a~2").unwrap_err().contains("~ must be followed"));
+    assert!(parse_json_pointer("/a~").is_err());
+}
@@ -6,12 +6,23 @@
 //! - prd.json.example template generation
 //! - Default value handling
 //! - Existing directory handling
+//! - InitPlan for fresh, partial and complete project layouts
+//! - Detecting an already initialized project
+//! - Seeding default_tool from the selected agent
+//! - Creating a missing PRD for `ralph run --init-if-missing`
 
 use std::fs;
 use tempfile::TempDir;
 
+use crate::agent::Agent;
+use crate::commands::init::{
+    backup_file, default_tool_config_file, default_tool_step, init_missing_prd,
+    is_initialized_project, save_default_tool, DefaultToolStep, InitAction, InitPlan,
+};
+use crate::config::Config;
+use crate::prd::Prd;
 // Import the functions from templates module
-use crate::templates::get_prd_json_template;
+use crate::templates::{get_prd_json_template, starter_prd};
 
 /// Helper function to create a temporary directory for testing
 fn setup_temp_dir() -> TempDir {
@@ -193,3 +204,276 @@ fn test_prd_template_field_types() {
     assert!(first_story["priority"].is_number());
     assert!(first_story["passes"].is_boolean());
 }
+
+// ============================================================================
+// Nested ralph/ directory detection
+// ============================================================================
+
+use crate::workspace::{is_ralph_workspace_dir, nested_workspace_root};
+
+#[test]
+fn test_project_root_is_not_workspace_dir() {
+    let temp_dir = setup_temp_dir();
+    let ralph_dir = temp_dir.path().join("ralph");
+    fs::create_dir_all(&ralph_dir).unwrap();
+    fs::write(ralph_dir.join("prd.json"), "{}").unwrap();
+
+    assert!(!is_ralph_workspace_dir(temp_dir.path()));
+    assert!(nested_workspace_root(temp_dir.path()).is_none());
+}
+
+#[test]
+fn test_inside_ralph_dir_detected() {
+    let temp_dir = setup_temp_dir();
+    let ralph_dir = temp_dir.path().join("ralph");
+    fs::create_dir_all(&ralph_dir).unwrap();
+    fs::write(ralph_dir.join("progress.txt"), "# Ralph Progress Log").unwrap();
+
+    assert!(is_ralph_workspace_dir(&ralph_dir));
+    assert_eq!(
+        nested_workspace_root(&ralph_dir),
+        Some(temp_dir.path().to_path_buf())
+    );
+}
+
+#[test]
+fn test_renamed_workspace_dir_detected_by_prd_and_last_branch() {
+    let temp_dir = setup_temp_dir();
+    let work_dir = temp_dir.path().join("agent-work");
+    fs::create_dir_all(&work_dir).unwrap();
+    fs::write(work_dir.join("prd.json"), "{}").unwrap();
+
+    assert!(!is_ralph_workspace_dir(&work_dir), "prd.json alone is not enough");
+
+    fs::write(work_dir.join(".last-branch"), "ralph/feature").unwrap();
+    assert!(is_ralph_workspace_dir(&work_dir));
+}
+
+#[test]
+fn test_empty_dir_named_ralph_not_detected() {
+    let temp_dir = setup_temp_dir();
+    let ralph_dir = temp_dir.path().join("ralph");
+    fs::create_dir_all(&ralph_dir).unwrap();
+
+    assert!(!is_ralph_workspace_dir(&ralph_dir));
+}
+
+/// Map each planned path to its action, for compact assertions
+fn plan_actions(plan: &InitPlan) -> Vec<(String, InitAction)> {
+    plan.items
+        .iter()
+        .map(|i| (i.path.display().to_string().replace('\\', "/"), i.action))
+        .collect()
+}
+
+/// Test that a fresh directory plans to create everything
+#[test]
+fn test_init_plan_fresh_directory() {
+    let temp_dir = setup_temp_dir();
+    let plan = InitPlan::compute(temp_dir.path(), false);
+
+    assert!(!plan.is_partially_initialized());
+    assert!(plan.items.iter().all(|i| i.action == InitAction::Create));
+}
+
+/// Test that a partially initialized project only creates what is missing
+#[test]
+fn test_init_plan_partial_project() {
+    let temp_dir = setup_temp_dir();
+    fs::create_dir_all(temp_dir.path().join("ralph")).unwrap();
+    fs::write(temp_dir.path().join("AGENTS.md"), "# Human notes").unwrap();
+
+    let plan = InitPlan::compute(temp_dir.path(), false);
+
+    assert!(plan.is_partially_initialized());
+    assert_eq!(
+        plan_actions(&plan),
+        vec![
+            ("ralph".to_string(), InitAction::Keep),
+            ("ralph/tasks".to_string(), InitAction::Create),
+            ("AGENTS.md".to_string(), InitAction::Keep),
+        ]
+    );
+}
+
+/// Test that --force regenerates existing files but never directories
+#[test]
+fn test_init_plan_force_overwrites_files_only() {
+    let temp_dir = setup_temp_dir();
+    fs::create_dir_all(temp_dir.path().join("ralph/tasks")).unwrap();
+    fs::write(temp_dir.path().join("AGENTS.md"), "# Human notes").unwrap();
+
+    let plan = InitPlan::compute(temp_dir.path(), true);
+
+    assert_eq!(
+        plan_actions(&plan),
+        vec![
+            ("ralph".to_string(), InitAction::Keep),
+            ("ralph/tasks".to_string(), InitAction::Keep),
+            ("AGENTS.md".to_string(), InitAction::Overwrite),
+        ]
+    );
+}
+
+/// Test that computing a plan does not touch the filesystem
+#[test]
+fn test_init_plan_does_not_mutate() {
+    let temp_dir = setup_temp_dir();
+    InitPlan::compute(temp_dir.path(), true);
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+}
+
+/// Test that backups keep the original content next to the file
+#[test]
+fn test_backup_file() {
+    let temp_dir = setup_temp_dir();
+    let path = temp_dir.path().join("AGENTS.md");
+    fs::write(&path, "original").unwrap();
+
+    let backup = backup_file(&path).unwrap();
+
+    assert_eq!(backup, temp_dir.path().join("AGENTS.md.bak"));
+    assert_eq!(fs::read_to_string(backup).unwrap(), "original");
+    assert_eq!(fs::read_to_string(path).unwrap(), "original");
+}
+
+/// Test that only a ralph directory with content counts as initialized
+#[test]
+fn test_is_initialized_project() {
+    let temp_dir = setup_temp_dir();
+    assert!(!is_initialized_project(temp_dir.path()));
+
+    fs::create_dir_all(temp_dir.path().join("ralph")).unwrap();
+    assert!(!is_initialized_project(temp_dir.path()));
+
+    fs::create_dir_all(temp_dir.path().join("ralph/tasks")).unwrap();
+    assert!(is_initialized_project(temp_dir.path()));
+}
+
+/// Test that a configured, installed tool skips the selection
+#[test]
+fn test_default_tool_step() {
+    let installed = |tool: &str| tool == "claude";
+    let detected = [Agent::Amp, Agent::Claude];
+
+    assert_eq!(
+        default_tool_step(Some("claude"), &detected, installed),
+        DefaultToolStep::Configured("claude".to_string())
+    );
+    // A configured tool that is no longer installed is asked about again
+    assert_eq!(
+        default_tool_step(Some("amp"), &detected, installed),
+        DefaultToolStep::Select(detected.to_vec())
+    );
+    assert_eq!(
+        default_tool_step(Some(" "), &detected, installed),
+        DefaultToolStep::Select(detected.to_vec())
+    );
+    assert_eq!(default_tool_step(None, &[], installed), DefaultToolStep::NoneDetected);
+}
+
+/// Test that --local saves into the project's ralph/config.toml
+#[test]
+fn test_default_tool_config_file_local() {
+    let temp_dir = setup_temp_dir();
+    assert_eq!(
+        default_tool_config_file(temp_dir.path(), true),
+        Some(temp_dir.path().join("ralph/config.toml"))
+    );
+}
+
+/// Test that saving default_tool keeps the file's other keys
+#[test]
+fn test_save_default_tool_keeps_other_keys() {
+    let temp_dir = setup_temp_dir();
+    let config_file = temp_dir.path().join("ralph/config.toml");
+    fs::create_dir_all(config_file.parent().unwrap()).unwrap();
+    fs::write(&config_file, "max_iterations = 7\n").unwrap();
+
+    let written = save_default_tool(Some(config_file.clone()), Agent::Claude).unwrap();
+
+    assert_eq!(written, config_file);
+    let config = Config::load_from(Some(&config_file)).unwrap();
+    assert_eq!(config.default_tool.as_deref(), Some("claude"));
+    assert_eq!(config.max_iterations, Some(7));
+}
+
+/// Test that a new config file gets default_tool and nothing else
+#[test]
+fn test_save_default_tool_writes_only_default_tool() {
+    let temp_dir = setup_temp_dir();
+    let config_file = temp_dir.path().join("ralph/config.toml");
+
+    save_default_tool(Some(config_file.clone()), Agent::Claude).unwrap();
+
+    let content = fs::read_to_string(&config_file).unwrap();
+    let table: toml::Table = toml::from_str(&content).unwrap();
+    assert_eq!(table.keys().collect::<Vec<_>>(), vec!["default_tool"]);
+    assert_eq!(table["default_tool"].as_str(), Some("claude"));
+}
+
+/// Test that a missing config directory is created
+#[test]
+fn test_save_default_tool_creates_config_dir() {
+    let temp_dir = setup_temp_dir();
+    let config_file = temp_dir.path().join("nested/config.toml");
+
+    save_default_tool(Some(config_file.clone()), Agent::Amp).unwrap();
+
+    let config = Config::load_from(Some(&config_file)).unwrap();
+    assert_eq!(config.default_tool.as_deref(), Some("amp"));
+}
+
+/// Test that an unwritable config is reported instead of panicking
+#[test]
+fn test_save_default_tool_reports_unwritable_config() {
+    let temp_dir = setup_temp_dir();
+    // A directory where the file should be
+    let config_file = temp_dir.path().join("config.toml");
+    fs::create_dir_all(&config_file).unwrap();
+
+    assert!(save_default_tool(Some(config_file), Agent::Amp).is_err());
+}
+
+/// Test that the starter PRD fills in the template and escapes its values
+#[test]
+fn test_starter_prd_from_template() {
+    let prd = starter_prd("Shop \"Beta\"", "  A small shop  ").unwrap();
+
+    assert_eq!(prd.project, "Shop \"Beta\"");
+    assert_eq!(prd.branch_name, "ralph/shop-\"beta\"");
+    assert_eq!(prd.description, "A small shop");
+    assert_eq!(prd.user_stories.len(), 1);
+    assert_eq!(prd.user_stories[0].id, "US-001");
+    assert!(!prd.user_stories[0].passes);
+
+    assert!(starter_prd("   ", "Description").is_err());
+}
+
+/// Test that a missing PRD is created, including its directory
+#[test]
+fn test_init_missing_prd_creates_prd() {
+    let temp_dir = setup_temp_dir();
+    let prd_path = temp_dir.path().join("ralph/prd.json");
+
+    let created = init_missing_prd(&prd_path, Some("Checkout Flow"), Some("Faster checkout")).unwrap();
+
+    assert!(created);
+    let prd = Prd::from_file(&prd_path).unwrap();
+    assert_eq!(prd.project, "Checkout Flow");
+    assert_eq!(prd.branch_name, "ralph/checkout-flow");
+    assert_eq!(prd.description, "Faster checkout");
+}
+
+/// Test that an existing PRD is never overwritten
+#[test]
+fn test_init_missing_prd_keeps_existing_prd() {
+    let temp_dir = setup_temp_dir();
+    let prd_path = temp_dir.path().join("prd.json");
+    fs::write(&prd_path, "not even json").unwrap();
+
+    let created = init_missing_prd(&prd_path, Some("Other"), None).unwrap();
+
+    assert!(!created);
+    assert_eq!(fs::read_to_string(&prd_path).unwrap(), "not even json");
+}
@@ -0,0 +1,234 @@
+//! Output Format Tests
+//!
+//! Tests for the shared `--format table|json|yaml` rendering:
+//! - status, detect, story list and prd stats in each format
+//! - JSON and YAML carry the same data
+
+use console::strip_ansi_codes;
+
+use crate::cli::OutputFormat;
+use crate::commands::detect::{
+    first_installed_custom_tool, parse_extra_tools, AgentStatus, DetectReport,
+};
+use crate::commands::prd::PrdStatsReport;
+use crate::commands::story::StoryList;
+use crate::config::Config;
+use crate::prd::{Prd, UserStory};
+use crate::report::render;
+use crate::selector::StorySelector;
+use crate::status::StatusReport;
+use crate::tests::fixtures::{strings, user_story};
+
+fn story(id: &str, priority: u32, passes: bool, depends_on: &[&str]) -> UserStory {
+    UserStory {
+        priority,
+        passes,
+        depends_on: strings(depends_on),
+        ..user_story(id)
+    }
+}
+
+fn sample_prd() -> Prd {
+    Prd {
+        project: "Format Project".to_string(),
+        branch_name: "ralph/format".to_string(),
+        description: "Format tests".to_string(),
+        epics: Vec::new(),
+        user_stories: vec![
+            story("US-001", 1, true, &[]),
+            story("US-002", 2, false, &["US-001"]),
+            story("US-003", 3, false, &["US-404"]),
+        ],
+    }
+}
+
+fn sample_detect_report() -> DetectReport {
+    let agent = |name: &str, command: &str, version: Option<&str>| AgentStatus {
+        name: name.to_string(),
+        command: command.to_string(),
+        installed: version.is_some(),
+        version: version.map(String::from),
+    };
+    DetectReport {
+        agents: vec![
+            agent("Amp", "amp", None),
+            agent("Claude Code", "claude", Some("2.0.1 (Claude Code)")),
+        ],
+        installed: 1,
+        total: 2,
+        extra_tools: Vec::new(),
+    }
+}
+
+fn table<R: crate::report::Report>(report: &R) -> String {
+    strip_ansi_codes(&render(report, OutputFormat::Table).unwrap()).into_owned()
+}
+
+#[test]
+fn test_status_table_format() {
+    let output = table(&StatusReport::from_prd(&sample_prd(), &StorySelector::default()));
+
+    assert!(output.starts_with("Format Project\n"));
+    assert!(output.contains("Branch: ralph/format (1/3 stories complete)"));
+    assert!(output.contains("✓ US-001 - Story US-001"));
+    assert!(output.contains("→ US-002 - Story US-002"));
+    assert!(output.contains("✗ US-003 - Story US-003 (waiting on US-404)"));
+    assert!(!output.contains("Up next"));
+}
+
+#[test]
+fn test_status_json_and_yaml_formats() {
+    let report = StatusReport::from_prd(&sample_prd(), &StorySelector::default());
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&report, OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["in_progress"]["id"], "US-002");
+    assert_eq!(json["blocked"][0]["waiting_on"][0], "US-404");
+
+    let yaml: serde_json::Value =
+        serde_yaml_ng::from_str(&render(&report, OutputFormat::Yaml).unwrap()).unwrap();
+    assert_eq!(yaml, json);
+}
+
+#[test]
+fn test_detect_table_format() {
+    let output = table(&sample_detect_report());
+
+    assert!(output.contains("  Amp: ✗ Not found\n"));
+    assert!(output.contains("  Claude Code: ✓ Installed\n"));
+    assert!(output.contains("Total: 1/2 agents installed"));
+}
+
+#[test]
+fn test_detect_json_and_yaml_formats() {
+    let report = sample_detect_report();
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&report, OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["installed"], 1);
+    assert_eq!(json["agents"][0]["installed"], false);
+    assert!(json["agents"][0]["version"].is_null());
+    assert_eq!(json["agents"][1]["version"], "2.0.1 (Claude Code)");
+
+    let yaml = render(&report, OutputFormat::Yaml).unwrap();
+    assert!(yaml.contains("command: claude"));
+    let parsed: serde_json::Value = serde_yaml_ng::from_str(&yaml).unwrap();
+    assert_eq!(parsed, json);
+}
+
+#[test]
+fn test_detect_extra_tools_present_and_absent() {
+    // `sh` is on every Unix PATH; the other name is nowhere
+    let tools = parse_extra_tools("sh, ralph-missing-extra-tool,,claude, sh");
+    assert_eq!(tools, ["sh", "ralph-missing-extra-tool", "claude"]);
+
+    let report = sample_detect_report().with_extra_tools(&tools);
+    // claude is a known agent and already listed above
+    assert_eq!(report.extra_tools.len(), 2);
+    assert!(report.extra_tools[0].installed);
+    assert!(report.extra_tools[0].version.is_some());
+    assert!(!report.extra_tools[1].installed);
+    assert_eq!(report.total, 2, "extra tools do not change the agent totals");
+
+    let output = table(&report);
+    assert!(output.contains("Custom tools:"));
+    assert!(output.contains("  sh: ✓ Installed ("));
+    assert!(output.contains("  ralph-missing-extra-tool: ✗ Not found\n"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&report, OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["extra_tools"][0]["command"], "sh");
+    assert_eq!(json["extra_tools"][1]["installed"], false);
+}
+
+#[test]
+fn test_first_installed_custom_tool_skips_missing_and_known_agents() {
+    let config = |tools: &str| Config {
+        extra_tools: Some(tools.to_string()),
+        ..Config::default()
+    };
+
+    assert_eq!(
+        first_installed_custom_tool(&config("ralph-missing-extra-tool, claude, sh")).as_deref(),
+        Some("sh")
+    );
+    assert_eq!(first_installed_custom_tool(&config("ralph-missing-extra-tool")), None);
+    assert_eq!(first_installed_custom_tool(&Config::default()), None);
+}
+
+#[test]
+fn test_detect_without_extra_tools_omits_section() {
+    let report = sample_detect_report();
+    assert!(!table(&report).contains("Custom tools"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&report, OutputFormat::Json).unwrap()).unwrap();
+    assert!(json.get("extra_tools").is_none());
+}
+
+#[test]
+fn test_story_list_table_format() {
+    let output = table(&StoryList::from_prd(&sample_prd()));
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines[0], "Format Project");
+    assert_eq!(lines[1], "  ✓  US-001  P1  Story US-001");
+    assert_eq!(lines[2], "  ·  US-002  P2  Story US-002  after US-001");
+    assert_eq!(lines.len(), 4);
+}
+
+#[test]
+fn test_story_list_empty_prd() {
+    let mut prd = sample_prd();
+    prd.user_stories.clear();
+
+    assert_eq!(table(&StoryList::from_prd(&prd)), "Format Project has no stories");
+}
+
+#[test]
+fn test_story_list_json_and_yaml_formats() {
+    let list = StoryList::from_prd(&sample_prd());
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&list, OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["stories"].as_array().unwrap().len(), 3);
+    assert_eq!(json["stories"][0]["passes"], true);
+    assert_eq!(json["stories"][1]["depends_on"][0], "US-001");
+
+    let yaml: serde_json::Value =
+        serde_yaml_ng::from_str(&render(&list, OutputFormat::Yaml).unwrap()).unwrap();
+    assert_eq!(yaml, json);
+}
+
+#[test]
+fn test_prd_stats_table_format() {
+    let mut prd = sample_prd();
+    prd.user_stories[2].acceptance_criteria.clear();
+
+    let output = table(&PrdStatsReport::from_prd(&prd));
+
+    assert!(output.starts_with("Format Project\n"));
+    assert!(output.contains("Stories: 3 (1 blocked)"));
+    assert!(output.contains("Dependency depth: 1"));
+    assert!(output.contains("  P1-5     3\n"));
+    assert!(output.contains("  0.7 average, 1 max"));
+    assert!(output.contains("  1 criteria: 2 stories"));
+    assert!(output.contains("No acceptance criteria (risky)\n  ! US-003 - Story US-003"));
+}
+
+#[test]
+fn test_prd_stats_json_and_yaml_formats() {
+    let report = PrdStatsReport::from_prd(&sample_prd());
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&report, OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["total"], 3);
+    assert_eq!(json["priority_bands"][0]["to"], 5);
+    assert_eq!(json["criteria_histogram"][0]["criteria"], 1);
+    assert_eq!(json["longest_descriptions"][0]["id"], "US-001");
+    assert!(json["without_criteria"].as_array().unwrap().is_empty());
+
+    let yaml: serde_json::Value =
+        serde_yaml_ng::from_str(&render(&report, OutputFormat::Yaml).unwrap()).unwrap();
+    assert_eq!(yaml, json);
+}
@@ -0,0 +1,137 @@
+//! Sandbox Check Tests
+//!
+//! Tests for the snapshot/compare engine behind `ralph run --sandbox-check`:
+//! - Created, modified and removed files under watched paths
+//! - Touched-but-unchanged files are not reported
+//! - Each change is reported once
+//! - Parsing the configured watch list
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+use crate::sandbox_check::{parse_watch_paths, ChangeKind, Snapshot};
+
+/// A watched tree with a dotfile and a config folder
+fn watched_tree() -> (TempDir, Vec<PathBuf>) {
+    let dir = TempDir::new().unwrap();
+    let dotfile = dir.path().join(".gitconfig");
+    let config_dir = dir.path().join("config");
+    fs::write(&dotfile, "[user]\n").unwrap();
+    fs::create_dir_all(config_dir.join("nested")).unwrap();
+    fs::write(config_dir.join("config.toml"), "max_iterations = 3\n").unwrap();
+    fs::write(config_dir.join("nested/state.json"), "{}").unwrap();
+    (dir, vec![dotfile, config_dir])
+}
+
+/// Move a file's mtime so the size+mtime check notices it
+fn bump_mtime(path: &std::path::Path) {
+    let later = SystemTime::now() + Duration::from_secs(120);
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+}
+
+#[test]
+fn test_snapshot_records_files_under_roots() {
+    let (_dir, roots) = watched_tree();
+
+    let snapshot = Snapshot::take(&roots);
+
+    assert_eq!(snapshot.file_count(), 3);
+}
+
+#[test]
+fn test_snapshot_unchanged_tree_reports_nothing() {
+    let (_dir, roots) = watched_tree();
+    let mut snapshot = Snapshot::take(&roots);
+
+    assert!(snapshot.refresh().is_empty());
+}
+
+#[test]
+fn test_snapshot_reports_created_modified_and_removed() {
+    let (dir, roots) = watched_tree();
+    let mut snapshot = Snapshot::take(&roots);
+
+    fs::write(&roots[0], "[user]\n\tname = agent\n").unwrap();
+    fs::write(roots[1].join("new.txt"), "hello").unwrap();
+    fs::remove_file(roots[1].join("nested/state.json")).unwrap();
+
+    let changes = snapshot.refresh();
+    let summary: Vec<(PathBuf, ChangeKind)> =
+        changes.into_iter().map(|c| (c.path, c.kind)).collect();
+    assert_eq!(
+        summary,
+        [
+            (dir.path().join(".gitconfig"), ChangeKind::Modified),
+            (roots[1].join("nested/state.json"), ChangeKind::Removed),
+            (roots[1].join("new.txt"), ChangeKind::Created),
+        ]
+    );
+}
+
+#[test]
+fn test_snapshot_same_size_edit_detected_by_hash() {
+    let (_dir, roots) = watched_tree();
+    let config_file = roots[1].join("config.toml");
+    let mut snapshot = Snapshot::take(&roots);
+
+    fs::write(&config_file, "max_iterations = 9\n").unwrap();
+    bump_mtime(&config_file);
+
+    let changes = snapshot.refresh();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].path, config_file);
+    assert_eq!(changes[0].kind, ChangeKind::Modified);
+}
+
+#[test]
+fn test_snapshot_touch_without_edit_is_not_reported() {
+    let (_dir, roots) = watched_tree();
+    let mut snapshot = Snapshot::take(&roots);
+
+    bump_mtime(&roots[0]);
+
+    assert!(snapshot.refresh().is_empty());
+}
+
+#[test]
+fn test_snapshot_reports_each_change_once() {
+    let (_dir, roots) = watched_tree();
+    let mut snapshot = Snapshot::take(&roots);
+
+    fs::write(&roots[0], "changed and longer\n").unwrap();
+    assert_eq!(snapshot.refresh().len(), 1);
+    assert!(snapshot.refresh().is_empty());
+}
+
+#[test]
+fn test_snapshot_watches_missing_paths_for_creation() {
+    let dir = TempDir::new().unwrap();
+    let missing = dir.path().join(".npmrc");
+    let mut snapshot = Snapshot::take(std::slice::from_ref(&missing));
+    assert_eq!(snapshot.file_count(), 0);
+
+    fs::write(&missing, "registry=https://example.invalid\n").unwrap();
+
+    let changes = snapshot.refresh();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].kind, ChangeKind::Created);
+}
+
+#[test]
+fn test_parse_watch_paths() {
+    let paths = parse_watch_paths(" /etc/hosts, ,relative/dir,~/.ssh ");
+
+    assert_eq!(paths.len(), 3);
+    assert_eq!(paths[0], PathBuf::from("/etc/hosts"));
+    assert_eq!(paths[1], PathBuf::from("relative/dir"));
+    if let Some(home) = dirs::home_dir() {
+        assert_eq!(paths[2], home.join(".ssh"));
+    }
+}
@@ -0,0 +1,191 @@
+//! Search Tests
+//!
+//! Tests for `ralph search` over fixture runs:
+//! - Case-insensitive matching in story fields and progress entries
+//! - Story ids taken from progress.txt headings
+//! - Excerpts around matches in long lines
+//! - Archived runs, newest first
+//! - Grouped table output and JSON
+
+use console::strip_ansi_codes;
+use std::fs;
+use tempfile::TempDir;
+
+use crate::cli::OutputFormat;
+use crate::commands::search::SearchReport;
+use crate::prd::{Prd, UserStory};
+use crate::report::render;
+use crate::search::{search_archives, search_dir, search_prd, search_progress, Query};
+use crate::tests::fixtures::{strings, user_story};
+
+fn story(id: &str, title: &str, criteria: &[&str], notes: &str) -> UserStory {
+    UserStory {
+        title: title.to_string(),
+        description: "As a user, I want to find things".to_string(),
+        acceptance_criteria: strings(criteria),
+        notes: notes.to_string(),
+        ..user_story(id)
+    }
+}
+
+fn fixture_prd() -> Prd {
+    Prd {
+        project: "Search Project".to_string(),
+        branch_name: "ralph/search".to_string(),
+        description: String::new(),
+        epics: Vec::new(),
+        user_stories: vec![
+            story("US-001", "Add login form", &["Form posts to /login"], ""),
+            story(
+                "US-002",
+                "Export invoices",
+                &["CSV has a header", "Dates use ISO 8601"],
+                "Blocked on the CSV library\nCheck the Login page too",
+            ),
+        ],
+    }
+}
+
+const FIXTURE_PROGRESS: &str = "# Ralph Progress Log\n\
+Started: today\n\
+\n\
+## 2026-01-01 - US-001\n\
+- Added the LOGIN form\n\
+\n\
+## 2026-01-02 - US-002\n\
+- Wrote the csv exporter\n";
+
+fn query(text: &str) -> Query {
+    Query::new(text).unwrap()
+}
+
+#[test]
+fn test_blank_query_is_rejected() {
+    assert!(Query::new("").is_none());
+    assert!(Query::new("   ").is_none());
+}
+
+#[test]
+fn test_search_prd_matches_fields_case_insensitively() {
+    let matches = search_prd(&fixture_prd(), &query("login"));
+
+    let places: Vec<(&str, &str)> = matches
+        .iter()
+        .map(|m| (m.story.as_deref().unwrap(), m.field.as_deref().unwrap()))
+        .collect();
+    assert_eq!(
+        places,
+        [
+            ("US-001", "title"),
+            ("US-001", "acceptanceCriteria[1]"),
+            ("US-002", "notes"),
+        ]
+    );
+    // Multi-line notes report the matching line only
+    assert_eq!(matches[2].excerpt, "Check the Login page too");
+    assert_eq!(&matches[2].excerpt[matches[2].start..matches[2].end], "Login");
+}
+
+#[test]
+fn test_search_query_is_literal() {
+    let matches = search_prd(&fixture_prd(), &query("/login"));
+
+    assert_eq!(matches.len(), 1);
+    assert!(search_prd(&fixture_prd(), &query("l.gin")).is_empty());
+}
+
+#[test]
+fn test_search_progress_uses_entry_story_ids() {
+    let matches = search_progress(FIXTURE_PROGRESS, &query("login"));
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].line, Some(5));
+    assert_eq!(matches[0].story.as_deref(), Some("US-001"));
+    assert_eq!(matches[0].excerpt, "- Added the LOGIN form");
+
+    let matches = search_progress(FIXTURE_PROGRESS, &query("US-002"));
+    assert_eq!(matches[0].line, Some(7));
+    assert_eq!(matches[0].story.as_deref(), Some("US-002"));
+
+    let matches = search_progress(FIXTURE_PROGRESS, &query("started"));
+    assert_eq!(matches[0].story, None);
+}
+
+#[test]
+fn test_long_lines_are_cut_around_the_match() {
+    let line = format!("{}needle{}", "é".repeat(100), "x".repeat(100));
+
+    let matches = search_progress(&line, &query("NEEDLE"));
+
+    let m = &matches[0];
+    assert!(m.excerpt.starts_with('…'));
+    assert!(m.excerpt.ends_with('…'));
+    assert!(m.excerpt.len() < line.len());
+    assert_eq!(&m.excerpt[m.start..m.end], "needle");
+}
+
+#[test]
+fn test_search_dir_covers_prd_and_progress() {
+    let dir = TempDir::new().unwrap();
+    let prd = fixture_prd();
+    assert_eq!(search_dir(dir.path(), Some(&prd), "", &query("csv")).unwrap().len(), 2);
+
+    fs::write(dir.path().join("progress.txt"), FIXTURE_PROGRESS).unwrap();
+    let matches = search_dir(dir.path(), Some(&prd), "", &query("csv")).unwrap();
+
+    let files: Vec<&str> = matches.iter().map(|m| m.file.as_str()).collect();
+    assert_eq!(files, ["prd.json", "prd.json", "progress.txt"]);
+}
+
+#[test]
+fn test_search_archives_names_each_archive() {
+    let dir = TempDir::new().unwrap();
+    let older = dir.path().join("2026-01-01-ralph-old");
+    let newer = dir.path().join("2026-02-01-ralph-new");
+    fs::create_dir_all(&older).unwrap();
+    fs::create_dir_all(&newer).unwrap();
+    fixture_prd().save_to_file(older.join("prd.json")).unwrap();
+    fs::write(newer.join("progress.txt"), FIXTURE_PROGRESS).unwrap();
+    fs::write(newer.join("prd.json"), "not json").unwrap();
+
+    let matches = search_archives(dir.path(), "", &query("invoices")).unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].archive.as_deref(), Some("2026-01-01-ralph-old"));
+
+    let matches = search_archives(dir.path(), "", &query("login")).unwrap();
+    let archives: Vec<&str> = matches.iter().map(|m| m.archive.as_deref().unwrap()).collect();
+    assert_eq!(archives[0], "2026-02-01-ralph-new");
+    assert_eq!(archives[1..], ["2026-01-01-ralph-old"; 3]);
+    assert!(search_archives(&dir.path().join("missing"), "", &query("login")).unwrap().is_empty());
+}
+
+#[test]
+fn test_search_report_groups_by_file() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("progress.txt"), FIXTURE_PROGRESS).unwrap();
+    let matches = search_dir(dir.path(), Some(&fixture_prd()), "", &query("login")).unwrap();
+    let report = SearchReport::new("login", matches);
+
+    let output = strip_ansi_codes(&render(&report, OutputFormat::Table).unwrap()).into_owned();
+
+    assert!(output.starts_with("prd.json\n  US-001 title: Add login form\n"));
+    assert!(output.contains("\n\nprogress.txt\n  line 5 (US-001): - Added the LOGIN form\n"));
+    assert!(output.ends_with("4 matches for \"login\"\n"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&report, OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["total"], 4);
+    assert_eq!(json["matches"][0]["story"], "US-001");
+    assert_eq!(json["matches"][3]["line"], 5);
+    assert!(json["matches"][0].get("archive").is_none());
+}
+
+#[test]
+fn test_search_report_without_matches() {
+    let report = SearchReport::new("nothing", Vec::new());
+
+    assert_eq!(
+        render(&report, OutputFormat::Table).unwrap(),
+        "No matches for \"nothing\"\n"
+    );
+}
@@ -0,0 +1,213 @@
+//! Story Selector Tests
+//!
+//! Tests for the filters that narrow a run or `ralph status`:
+//! - Each filter alone: story, epic, tags, max priority, excluded stories
+//! - Filters combined, which must all match
+//! - AllComplete / NoMatch / Pending selections
+//! - Describing the active filters as flags
+
+use crate::prd::{Prd, StoryOrder, UserStory};
+use crate::selector::{no_match_message, Selection, StorySelector};
+use crate::tests::fixtures::{strings, user_story};
+
+fn story(id: &str, priority: u32, passes: bool, epic: Option<&str>, tags: &[&str]) -> UserStory {
+    UserStory {
+        priority,
+        passes,
+        epic: epic.map(str::to_string),
+        tags: strings(tags),
+        ..user_story(id)
+    }
+}
+
+/// Five stories over two epics and two tags; US-001 already passes
+fn sample_prd() -> Prd {
+    Prd {
+        project: "Selector Project".to_string(),
+        branch_name: "ralph/selector".to_string(),
+        description: "Selector tests".to_string(),
+        epics: Vec::new(),
+        user_stories: vec![
+            story("US-001", 1, true, Some("api"), &["backend"]),
+            story("US-002", 2, false, Some("api"), &["backend"]),
+            story("US-003", 3, false, Some("ui"), &["frontend"]),
+            story("US-004", 4, false, Some("api"), &["frontend", "backend"]),
+            story("US-005", 5, false, None, &[]),
+        ],
+    }
+}
+
+fn pending_ids(selector: &StorySelector) -> Vec<String> {
+    let prd = sample_prd();
+    selector
+        .pending(&prd, StoryOrder::Priority)
+        .into_iter()
+        .map(|s| s.id.clone())
+        .collect()
+}
+
+fn tags(tags: &[&str]) -> Vec<String> {
+    tags.iter().map(|t| t.to_string()).collect()
+}
+
+#[test]
+fn test_default_selector_matches_everything() {
+    let selector = StorySelector::default();
+
+    assert!(!selector.is_filtered());
+    assert_eq!(pending_ids(&selector), ["US-002", "US-003", "US-004", "US-005"]);
+    assert_eq!(
+        selector.select(&sample_prd()),
+        Selection::Pending { matching: 4, pending: 4 }
+    );
+}
+
+#[test]
+fn test_single_filters() {
+    let by_story = StorySelector {
+        story: Some("US-003".to_string()),
+        ..StorySelector::default()
+    };
+    assert_eq!(pending_ids(&by_story), ["US-003"]);
+
+    let by_epic = StorySelector {
+        epic: Some("api".to_string()),
+        ..StorySelector::default()
+    };
+    assert_eq!(pending_ids(&by_epic), ["US-002", "US-004"]);
+
+    let by_priority = StorySelector {
+        max_priority: Some(3),
+        ..StorySelector::default()
+    };
+    assert_eq!(pending_ids(&by_priority), ["US-002", "US-003"]);
+
+    let excluding = StorySelector {
+        excluded: tags(&["US-002", "US-005"]),
+        ..StorySelector::default()
+    };
+    assert_eq!(pending_ids(&excluding), ["US-003", "US-004"]);
+}
+
+#[test]
+fn test_any_tag_matches_case_insensitively() {
+    let selector = StorySelector {
+        tags: tags(&["Frontend"]),
+        ..StorySelector::default()
+    };
+    assert_eq!(pending_ids(&selector), ["US-003", "US-004"]);
+
+    let either = StorySelector {
+        tags: tags(&["frontend", "backend"]),
+        ..StorySelector::default()
+    };
+    assert_eq!(pending_ids(&either), ["US-002", "US-003", "US-004"]);
+}
+
+#[test]
+fn test_combined_filters_must_all_match() {
+    let epic_and_tag = StorySelector {
+        epic: Some("api".to_string()),
+        tags: tags(&["frontend"]),
+        ..StorySelector::default()
+    };
+    assert_eq!(pending_ids(&epic_and_tag), ["US-004"]);
+
+    let tag_and_priority = StorySelector {
+        tags: tags(&["backend"]),
+        max_priority: Some(3),
+        ..StorySelector::default()
+    };
+    assert_eq!(pending_ids(&tag_and_priority), ["US-002"]);
+
+    let all = StorySelector {
+        epic: Some("api".to_string()),
+        tags: tags(&["backend"]),
+        max_priority: Some(4),
+        excluded: tags(&["US-002"]),
+        ..StorySelector::default()
+    };
+    assert_eq!(pending_ids(&all), ["US-004"]);
+}
+
+#[test]
+fn test_passed_stories_match_but_are_not_pending() {
+    let selector = StorySelector {
+        tags: tags(&["backend"]),
+        ..StorySelector::default()
+    };
+    let prd = sample_prd();
+
+    assert!(selector.matches(&prd.user_stories[0]));
+    assert_eq!(pending_ids(&selector), ["US-002", "US-004"]);
+}
+
+#[test]
+fn test_no_match_while_stories_are_pending() {
+    let selector = StorySelector {
+        epic: Some("ui".to_string()),
+        tags: tags(&["backend"]),
+        ..StorySelector::default()
+    };
+
+    assert_eq!(selector.select(&sample_prd()), Selection::NoMatch { pending: 4 });
+    assert_eq!(
+        no_match_message(4),
+        "No pending stories match the active filters (4 pending overall)"
+    );
+
+    // Only the completed story matches: still no pending match
+    let done_only = StorySelector {
+        max_priority: Some(1),
+        ..StorySelector::default()
+    };
+    assert_eq!(done_only.select(&sample_prd()), Selection::NoMatch { pending: 4 });
+}
+
+#[test]
+fn test_all_complete_wins_over_no_match() {
+    let mut prd = sample_prd();
+    for story in &mut prd.user_stories {
+        story.passes = true;
+    }
+    let selector = StorySelector {
+        tags: tags(&["missing"]),
+        ..StorySelector::default()
+    };
+
+    assert_eq!(selector.select(&prd), Selection::AllComplete);
+}
+
+#[test]
+fn test_attribute_filters() {
+    let by_epic = StorySelector {
+        epic: Some("api".to_string()),
+        excluded: tags(&["US-002"]),
+        ..StorySelector::default()
+    };
+    assert!(by_epic.is_filtered());
+    assert!(!by_epic.has_attribute_filters());
+
+    let by_tag = StorySelector {
+        tags: tags(&["backend"]),
+        ..StorySelector::default()
+    };
+    assert!(by_tag.has_attribute_filters());
+}
+
+#[test]
+fn test_describe_lists_flags() {
+    let selector = StorySelector {
+        story: None,
+        epic: Some("api".to_string()),
+        tags: tags(&["backend", "db"]),
+        max_priority: Some(2),
+        excluded: tags(&["US-004"]),
+    };
+
+    assert_eq!(
+        selector.describe(),
+        "--epic api --tag backend --tag db --max-priority 2 --exclude-story US-004"
+    );
+    assert_eq!(StorySelector::default().describe(), "");
+}
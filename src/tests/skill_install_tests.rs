@@ -0,0 +1,84 @@
+//! Skill Install Tests
+//!
+//! Tests for planning and writing the skill files of `ralph install`:
+//! - A fresh install writes both skills
+//! - Existing skills are only replaced when the user agrees
+//! - Ctrl+C at an overwrite prompt writes nothing
+
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+use crate::commands::install::SkillInstallPlan;
+use crate::error::RalphError;
+use crate::templates::{get_prd_skill_content, get_ralph_skill_content};
+
+fn existing_skills(dir: &Path) {
+    for skill in ["ralph", "prd"] {
+        fs::create_dir_all(dir.join(skill)).unwrap();
+        fs::write(dir.join(skill).join("SKILL.md"), "old skill").unwrap();
+    }
+}
+
+#[test]
+fn test_fresh_install_writes_both_skills_without_asking() {
+    let dir = TempDir::new().unwrap();
+
+    let plan = SkillInstallPlan::compute(dir.path(), |_| panic!("nothing to overwrite")).unwrap();
+    assert!(plan.kept.is_empty());
+    plan.execute().unwrap();
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("ralph/SKILL.md")).unwrap(),
+        get_ralph_skill_content()
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("prd/SKILL.md")).unwrap(),
+        get_prd_skill_content()
+    );
+    assert!(!dir.path().join("ralph/SKILL.md.tmp").exists());
+}
+
+#[test]
+fn test_existing_skills_follow_the_answers() {
+    let dir = TempDir::new().unwrap();
+    existing_skills(dir.path());
+
+    // Replace the ralph skill, keep the prd one
+    let ralph_dir = dir.path().join("ralph");
+    let plan = SkillInstallPlan::compute(dir.path(), |path| Ok(path.starts_with(&ralph_dir))).unwrap();
+    assert_eq!(plan.kept, ["prd/SKILL.md"]);
+    plan.execute().unwrap();
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("ralph/SKILL.md")).unwrap(),
+        get_ralph_skill_content()
+    );
+    assert_eq!(fs::read_to_string(dir.path().join("prd/SKILL.md")).unwrap(), "old skill");
+}
+
+#[test]
+fn test_interrupt_at_second_prompt_writes_nothing() {
+    let dir = TempDir::new().unwrap();
+    existing_skills(dir.path());
+
+    let mut asked = 0;
+    let result = SkillInstallPlan::compute(dir.path(), |_| {
+        asked += 1;
+        if asked == 1 {
+            Ok(true)
+        } else {
+            Err(RalphError::Interrupted)
+        }
+    });
+    assert!(matches!(result, Err(RalphError::Interrupted)));
+    assert_eq!(asked, 2);
+
+    // The first answer was yes, but nothing is written before all are in
+    for skill in ["ralph", "prd"] {
+        assert_eq!(
+            fs::read_to_string(dir.path().join(skill).join("SKILL.md")).unwrap(),
+            "old skill"
+        );
+    }
+}
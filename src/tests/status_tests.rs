@@ -0,0 +1,315 @@
+//! Status Report Tests
+//!
+//! Tests for grouping PRD stories into `ralph status` sections:
+//! - Completed, in progress, blocked and up next
+//! - Priority ordering of actionable stories
+//! - Empty sections
+//! - Pending stories without acceptance criteria
+//! - Per-epic rollups
+//! - JSON shape
+//! - Full JSON document: counts, percentage, next story and every story
+//! - Acceptance checklists of pending stories or one story
+//! - Weighted completion and the critical path
+
+use console::strip_ansi_codes;
+
+use crate::cli::OutputFormat;
+use crate::prd::{Prd, UserStory};
+use crate::report::render;
+use crate::selector::StorySelector;
+use crate::status::{AcceptanceReport, StatusReport};
+use crate::templates::get_prd_json_template;
+use crate::tests::fixtures::{strings, user_story};
+
+fn story(id: &str, priority: u32, passes: bool, depends_on: &[&str]) -> UserStory {
+    UserStory {
+        priority,
+        passes,
+        depends_on: strings(depends_on),
+        ..user_story(id)
+    }
+}
+
+fn prd(user_stories: Vec<UserStory>) -> Prd {
+    Prd {
+        project: "Status Project".to_string(),
+        branch_name: "ralph/status".to_string(),
+        description: "Status tests".to_string(),
+        epics: Vec::new(),
+        user_stories,
+    }
+}
+
+fn status_of(prd: &Prd) -> StatusReport {
+    StatusReport::from_prd(prd, &StorySelector::default())
+}
+
+fn ids<'a>(entries: impl IntoIterator<Item = &'a crate::status::StoryEntry>) -> Vec<&'a str> {
+    entries.into_iter().map(|e| e.id.as_str()).collect()
+}
+
+#[test]
+fn test_status_report_groups_stories() {
+    let report = status_of(&prd(vec![
+        story("US-001", 1, true, &[]),
+        story("US-002", 4, false, &[]),
+        story("US-003", 2, false, &["US-001"]),
+        story("US-004", 3, false, &["US-002", "US-999"]),
+        story("US-005", 5, false, &[]),
+    ]));
+
+    assert_eq!(report.total, 5);
+    assert_eq!(ids(&report.completed), ["US-001"]);
+    assert_eq!(report.in_progress.as_ref().unwrap().id, "US-003");
+    assert_eq!(ids(report.blocked.iter().map(|b| &b.story)), ["US-004"]);
+    assert_eq!(report.blocked[0].waiting_on, ["US-002", "US-999"]);
+    assert_eq!(ids(&report.up_next), ["US-002", "US-005"]);
+}
+
+#[test]
+fn test_status_report_equal_priority_keeps_prd_order() {
+    let report = status_of(&prd(vec![
+        story("US-002", 1, false, &[]),
+        story("US-001", 1, false, &[]),
+        story("US-003", 1, false, &[]),
+    ]));
+
+    assert_eq!(report.in_progress.unwrap().id, "US-002");
+    assert_eq!(ids(&report.up_next), ["US-001", "US-003"]);
+}
+
+#[test]
+fn test_status_report_empty_sections() {
+    let done = status_of(&prd(vec![story("US-001", 1, true, &[])]));
+    assert!(done.in_progress.is_none());
+    assert!(done.blocked.is_empty() && done.up_next.is_empty());
+
+    let empty = status_of(&prd(Vec::new()));
+    assert_eq!(empty.total, 0);
+    assert!(empty.completed.is_empty() && empty.in_progress.is_none());
+}
+
+#[test]
+fn test_status_report_lists_pending_stories_without_criteria() {
+    let mut stories = vec![
+        story("US-001", 1, true, &[]),
+        story("US-002", 2, false, &[]),
+        story("US-003", 3, false, &[]),
+    ];
+    stories[0].acceptance_criteria.clear();
+    stories[2].acceptance_criteria.clear();
+    let report = status_of(&prd(stories));
+
+    // US-001 already passes, so it no longer needs criteria
+    assert_eq!(ids(&report.without_criteria), ["US-003"]);
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["without_criteria"][0]["id"], "US-003");
+
+    let complete = status_of(&prd(vec![story("US-001", 1, false, &[])]));
+    assert!(complete.without_criteria.is_empty());
+    let json = serde_json::to_value(&complete).unwrap();
+    assert!(json.get("without_criteria").is_none());
+}
+
+#[test]
+fn test_status_report_json_shape() {
+    let report = status_of(&prd(vec![
+        story("US-001", 1, false, &[]),
+        story("US-002", 2, false, &["US-001"]),
+    ]));
+    let json = serde_json::to_value(&report).unwrap();
+
+    assert_eq!(json["project"], "Status Project");
+    assert_eq!(json["in_progress"]["id"], "US-001");
+    assert_eq!(json["blocked"][0]["id"], "US-002");
+    assert_eq!(json["blocked"][0]["waiting_on"][0], "US-001");
+    assert!(json["completed"].as_array().unwrap().is_empty());
+    assert!(json.get("epics").is_none());
+}
+
+#[test]
+fn test_status_json_document_for_init_template() {
+    let template = get_prd_json_template("Dashboard Demo", "A template PRD", None);
+    let prd: Prd = serde_json::from_str(&template).unwrap();
+    let json = serde_json::to_value(status_of(&prd)).unwrap();
+
+    assert_eq!(json["project"], "Dashboard Demo");
+    assert_eq!(json["branch"], "ralph/dashboard-demo");
+    assert_eq!(
+        json["counts"],
+        serde_json::json!({"total": 1, "completed": 0, "pending": 1, "blocked": 0})
+    );
+    assert_eq!(json["percentage"], 0.0);
+    assert_eq!(json["next_story"], "US-001");
+    assert_eq!(
+        json["stories"],
+        serde_json::json!([{
+            "id": "US-001",
+            "title": "Initial setup",
+            "priority": 1,
+            "passes": false,
+            "tags": [],
+            "estimate": null
+        }])
+    );
+}
+
+#[test]
+fn test_status_json_document_counts_and_story_fields() {
+    let mut tagged = story("US-002", 2, false, &["US-001"]);
+    tagged.tags = vec!["backend".to_string()];
+    tagged.estimate = Some(2.5);
+    let report = status_of(&prd(vec![
+        story("US-001", 1, true, &[]),
+        tagged,
+        story("US-003", 3, false, &["US-004"]),
+        story("US-004", 4, false, &[]),
+    ]));
+    let json = serde_json::to_value(&report).unwrap();
+
+    assert_eq!(
+        json["counts"],
+        serde_json::json!({"total": 4, "completed": 1, "pending": 3, "blocked": 1})
+    );
+    assert_eq!(json["percentage"], 25.0);
+    assert_eq!(json["next_story"], "US-002");
+    assert_eq!(json["stories"][1]["tags"], serde_json::json!(["backend"]));
+    assert_eq!(json["stories"][1]["estimate"], 2.5);
+    assert_eq!(json["stories"][0]["passes"], true);
+
+    let done = status_of(&prd(vec![story("US-001", 1, true, &[])]));
+    assert_eq!(done.percentage, 100.0);
+    assert_eq!(done.next_story, None);
+}
+
+#[test]
+fn test_status_filter_lists_matching_stories_with_overall_counts() {
+    let mut tagged = story("US-002", 2, false, &[]);
+    tagged.tags = vec!["ui".to_string()];
+    let prd = prd(vec![story("US-001", 1, true, &[]), tagged, story("US-003", 3, false, &[])]);
+    let selector = StorySelector {
+        tags: vec!["ui".to_string()],
+        ..StorySelector::default()
+    };
+
+    let report = StatusReport::from_prd(&prd, &selector);
+
+    assert_eq!(report.counts.total, 3);
+    assert_eq!(report.counts.pending, 2);
+    let filter = report.filter.as_ref().unwrap();
+    assert_eq!(filter.filters, "--tag ui");
+    assert_eq!((filter.matching, filter.completed, filter.pending), (1, 0, 1));
+    assert!(report.completed.is_empty());
+    assert_eq!(report.in_progress.as_ref().unwrap().id, "US-002");
+    assert!(report.up_next.is_empty());
+    assert_eq!(report.next_story.as_deref(), Some("US-002"));
+    assert_eq!(report.stories.len(), 1);
+
+    // Unfiltered reports leave the filter out of the JSON
+    let json = serde_json::to_value(status_of(&prd)).unwrap();
+    assert!(json.get("filter").is_none());
+}
+
+#[test]
+fn test_status_report_rolls_up_epics() {
+    let mut stories = vec![
+        story("US-001", 3, true, &[]),
+        story("US-002", 1, false, &[]),
+        story("US-003", 2, false, &[]),
+    ];
+    stories[0].epic = Some("Checkout".to_string());
+    stories[1].epic = Some("Checkout".to_string());
+    let report = status_of(&prd(stories));
+
+    let summary: Vec<(&str, usize, usize)> = report
+        .epics
+        .iter()
+        .map(|e| (e.name.as_str(), e.completed, e.total))
+        .collect();
+    assert_eq!(summary, [("Checkout", 1, 2), ("Ungrouped", 0, 1)]);
+    assert_eq!(ids(report.epics[0].stories.iter().map(|s| &s.story)), ["US-002", "US-001"]);
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["epics"][0]["stories"][1]["passes"], true);
+}
+
+#[test]
+fn test_acceptance_checklists_of_pending_stories() {
+    let mut second = story("US-002", 3, false, &[]);
+    second.acceptance_criteria.push("It is fast".to_string());
+    second.toggle_criterion(2).unwrap();
+    second.notes = "Watch the cache".to_string();
+    let prd = prd(vec![
+        story("US-001", 1, true, &[]),
+        second,
+        story("US-003", 2, false, &[]),
+    ]);
+
+    let report = AcceptanceReport::from_prd(&prd, &StorySelector::default(), None).unwrap();
+    let ids: Vec<&str> = report.stories.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, ["US-003", "US-002"]);
+    let checklist = &report.stories[1];
+    assert_eq!(checklist.notes, "Watch the cache");
+    let items: Vec<(usize, &str, bool)> = checklist
+        .criteria
+        .iter()
+        .map(|c| (c.number, c.text.as_str(), c.done))
+        .collect();
+    assert_eq!(items, [(1, "It works", false), (2, "It is fast", true)]);
+}
+
+#[test]
+fn test_acceptance_checklist_of_one_story() {
+    let prd = prd(vec![story("US-001", 1, true, &[]), story("US-002", 2, false, &[])]);
+
+    let report = AcceptanceReport::from_prd(&prd, &StorySelector::default(), Some("US-001")).unwrap();
+    assert_eq!(report.stories.len(), 1);
+    assert!(report.stories[0].passes);
+
+    let err = AcceptanceReport::from_prd(&prd, &StorySelector::default(), Some("US-009")).unwrap_err();
+    assert!(err.starts_with("Unknown story id: US-009"), "{}", err);
+}
+
+fn table_of(report: &StatusReport) -> String {
+    strip_ansi_codes(&render(report, OutputFormat::Table).unwrap()).into_owned()
+}
+
+#[test]
+fn test_status_report_weighted_completion_and_critical_path() {
+    let mut stories = vec![
+        story("US-001", 1, true, &[]),
+        story("US-002", 2, false, &["US-001"]),
+        story("US-003", 3, false, &["US-002"]),
+        story("US-004", 4, false, &[]),
+    ];
+    for (story, estimate) in stories.iter_mut().zip([6.0, 1.0, 2.5, 0.5]) {
+        story.estimate = Some(estimate);
+    }
+    let report = status_of(&prd(stories));
+
+    assert_eq!(report.weighted_percentage, Some(60.0));
+    let path = report.critical_path.as_ref().unwrap();
+    assert_eq!(path.stories, ["US-002", "US-003"]);
+    assert_eq!(path.estimate, 3.5);
+
+    let table = table_of(&report);
+    assert!(table.contains("Weighted by estimate: 60% complete"), "{}", table);
+    assert!(table.contains("Critical path: US-002 → US-003 (~3.5 estimated)"), "{}", table);
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["weighted_percentage"], 60.0);
+    assert_eq!(json["critical_path"]["stories"][1], "US-003");
+}
+
+#[test]
+fn test_status_report_without_estimates_omits_weights() {
+    let report = status_of(&prd(vec![story("US-001", 1, false, &[]), story("US-002", 2, false, &[])]));
+
+    assert_eq!(report.weighted_percentage, None);
+    let table = table_of(&report);
+    assert!(!table.contains("Weighted"));
+    // A lone story is no path worth showing
+    assert!(!table.contains("Critical path"));
+    let json = serde_json::to_value(&report).unwrap();
+    assert!(json.get("weighted_percentage").is_none());
+}
@@ -0,0 +1,121 @@
+//! Story Edit Tests
+//!
+//! Tests for `ralph story edit`:
+//! - Priority validation
+//! - Setting each field from `--field`/`--value` text
+//! - Detecting edits that change nothing
+//! - Checking off acceptance criteria (`ralph story check`)
+
+use crate::cli::StoryField;
+use crate::commands::story::{parse_priority, set_story_field};
+use crate::prd::UserStory;
+use crate::tests::fixtures::user_story;
+
+fn story() -> UserStory {
+    UserStory {
+        title: "Checkout".to_string(),
+        description: "As a buyer, I want to pay".to_string(),
+        acceptance_criteria: vec!["Card payments work".to_string()],
+        priority: 4,
+        ..user_story("US-004")
+    }
+}
+
+#[test]
+fn test_parse_priority() {
+    assert_eq!(parse_priority("3"), Ok(3));
+    assert_eq!(parse_priority(" 12 "), Ok(12));
+    for value in ["0", "-1", "1.5", "high", ""] {
+        let err = parse_priority(value).unwrap_err();
+        assert!(err.starts_with("Priority must be a positive integer"), "{}: {}", value, err);
+    }
+}
+
+#[test]
+fn test_set_story_field_text_fields() {
+    let mut edited = story();
+
+    assert_eq!(set_story_field(&mut edited, StoryField::Title, "  Pay at checkout "), Ok(true));
+    assert_eq!(edited.title, "Pay at checkout");
+    assert_eq!(set_story_field(&mut edited, StoryField::Description, "As a buyer, I want receipts"), Ok(true));
+    assert_eq!(edited.description, "As a buyer, I want receipts");
+    assert_eq!(set_story_field(&mut edited, StoryField::Notes, "See tasks/pay.md"), Ok(true));
+    assert_eq!(edited.notes, "See tasks/pay.md");
+    assert_eq!(set_story_field(&mut edited, StoryField::Priority, "2"), Ok(true));
+    assert_eq!(edited.priority, 2);
+}
+
+#[test]
+fn test_set_story_field_acceptance_criteria_one_per_line() {
+    let mut edited = story();
+
+    let changed = set_story_field(
+        &mut edited,
+        StoryField::AcceptanceCriteria,
+        "Card payments work\n\n  Receipts are emailed  \n",
+    );
+    assert_eq!(changed, Ok(true));
+    assert_eq!(edited.acceptance_criteria, ["Card payments work", "Receipts are emailed"]);
+}
+
+#[test]
+fn test_set_story_field_unchanged_and_invalid_values() {
+    let mut edited = story();
+
+    assert_eq!(set_story_field(&mut edited, StoryField::Title, "Checkout"), Ok(false));
+    assert_eq!(set_story_field(&mut edited, StoryField::Priority, "4"), Ok(false));
+
+    assert!(set_story_field(&mut edited, StoryField::Title, "   ").is_err());
+    assert!(set_story_field(&mut edited, StoryField::Priority, "0").is_err());
+    assert_eq!(edited, story(), "a rejected value leaves the story alone");
+}
+
+#[test]
+fn test_toggle_criterion() {
+    let mut checked = story();
+    checked.acceptance_criteria.push("Receipts are emailed".to_string());
+
+    assert_eq!(checked.toggle_criterion(2), Ok(true));
+    assert!(checked.is_criterion_done("Receipts are emailed"));
+    assert!(!checked.all_criteria_done());
+    assert_eq!(checked.toggle_criterion(1), Ok(true));
+    assert_eq!(checked.done_criteria_count(), 2);
+    assert!(checked.all_criteria_done());
+
+    // Toggling again unchecks
+    assert_eq!(checked.toggle_criterion(2), Ok(false));
+    assert_eq!(checked.criteria_done, ["Card payments work"]);
+
+    let err = checked.toggle_criterion(3).unwrap_err();
+    assert_eq!(err, "Story US-004 has 2 acceptance criteria; pick a number from 1 to 2");
+    assert!(checked.toggle_criterion(0).is_err());
+}
+
+#[test]
+fn test_no_criteria_are_never_all_done() {
+    let mut empty = story();
+    empty.acceptance_criteria.clear();
+
+    assert!(!empty.all_criteria_done());
+    assert_eq!(
+        empty.toggle_criterion(1),
+        Err("Story US-004 has no acceptance criteria".to_string())
+    );
+}
+
+#[test]
+fn test_editing_criteria_keeps_marks_of_unchanged_ones() {
+    let mut edited = story();
+    edited.toggle_criterion(1).unwrap();
+
+    set_story_field(
+        &mut edited,
+        StoryField::AcceptanceCriteria,
+        "Receipts are emailed\nCard payments work",
+    )
+    .unwrap();
+    assert!(edited.is_criterion_done("Card payments work"));
+
+    set_story_field(&mut edited, StoryField::AcceptanceCriteria, "Card and wallet payments work").unwrap();
+    assert!(edited.criteria_done.is_empty());
+}
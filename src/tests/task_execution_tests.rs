@@ -9,16 +9,45 @@
 //! - <promise>COMPLETE</promise> marker detection
 //! - Ctrl+C signal handling
 //! - Error handling for invalid PRD files
+//! - IterationOutcome of a single agent iteration
+//! - progress.txt header parsing and branch mismatch detection
+//! - The run summary entry appended to progress.txt
+//! - prd.json snapshots and restoring a corrupted PRD
+//! - `--checkpoint-every` copies of prd.json
+//! - Prompt placeholders: filled, unfilled, unknown and inside code fences
+//! - The prompt token estimate, heuristic and with the `tokenizer` feature
+//! - The `--list-stories` work queue
+//! - STORY_PASSED signals for passing and unknown stories
+//! - `--exclude-story` in the prompt and the work queue
+//! - `require_criteria` and `--allow-empty-criteria`
 
 use std::fs;
 
 use tempfile::TempDir;
 
+use crate::cli::{ColorChoice, StoryOrderChoice};
+use crate::color::apply_color_choice;
 use crate::config::Config;
-use crate::prd::{Prd, UserStory};
-use crate::agent::is_command_available;
-use crate::commands::run::{colorize_output, determine_tool};
+use crate::prd::{Prd, StoryOrder, UserStory};
+use crate::agent::{detect_agents, is_command_available, Agent, AgentInvocation, PromptDelivery};
+use crate::commands::run::{
+    append_progress_entry, apply_story_passed_signal, assemble_prompt, build_agent_command,
+    checkpoint_prd, compact_header,
+    changed_project, check_excluded_stories, check_required_criteria, check_unknown_placeholders, colorize_output,
+    determine_tool, parse_story_passed, prompt_file_path, prompt_size_summary, resolve_story_order,
+    restamp_progress_header, restorable_prd_backup, restore_prd_backup, run_run, run_summary_items,
+    snapshot_prd_file, tool_type_for_path, validate_tool_path, write_prompt_history,
+    ProgressHeader, RunOptions, WorkQueue, CHECKPOINT_DIR, PRD_BACKUP_FILE, PRD_CORRUPT_FILE,
+    PROMPT_HISTORY_DIR,
+};
+use crate::metadata::RunOutcome;
+use crate::selector::StorySelector;
 use crate::error::RalphError;
+use crate::templates::{
+    get_agent_prompt, prompt_token_estimate, render_prompt, unresolved_placeholders,
+    UNFILLED_PLACEHOLDER,
+};
+use crate::tests::fixtures::{strings, user_story};
 
 // ============================================================================
 // Helper Functions
@@ -61,6 +90,31 @@ fn create_temp_prd_file(temp_dir: &TempDir, content: &str) -> std::path::PathBuf
     prd_path
 }
 
+/// Selector for `--story <id>`
+fn story_selector(id: &str) -> StorySelector {
+    StorySelector {
+        story: Some(id.to_string()),
+        ..StorySelector::default()
+    }
+}
+
+/// Selector for `--epic <name>`, plus `--exclude-story` ids
+fn epic_selector(epic: &str, excluded: &[String]) -> StorySelector {
+    StorySelector {
+        epic: Some(epic.to_string()),
+        excluded: excluded.to_vec(),
+        ..StorySelector::default()
+    }
+}
+
+/// Selector for `--exclude-story` ids
+fn excluding(excluded: &[String]) -> StorySelector {
+    StorySelector {
+        excluded: excluded.to_vec(),
+        ..StorySelector::default()
+    }
+}
+
 // ============================================================================
 // PRD File Loading and Parsing Tests
 // ============================================================================
@@ -242,6 +296,25 @@ fn test_determine_tool_auto_no_config_no_agents() {
     }
 }
 
+#[test]
+fn test_determine_tool_auto_includes_custom_tools_only_when_enabled() {
+    // `sh` stands in for an installed custom tool; known agents still come first
+    let known = detect_agents().first().map(|agent| agent.command().to_string());
+    let mut config = Config {
+        extra_tools: Some("ralph-missing-extra-tool, sh".to_string()),
+        ..Default::default()
+    };
+
+    match determine_tool("auto", &config) {
+        Ok(tool) => assert_eq!(Some(tool), known),
+        Err(e) => assert!(known.is_none(), "unexpected error: {}", e),
+    }
+
+    config.auto_include_custom = Some(true);
+    let expected = known.unwrap_or_else(|| "sh".to_string());
+    assert_eq!(determine_tool("auto", &config).unwrap(), expected);
+}
+
 #[test]
 fn test_determine_tool_explicit_overrides_config() {
     // Explicit tool specification should take priority over config default
@@ -259,6 +332,18 @@ fn test_determine_tool_explicit_overrides_config() {
 // Colorize Output Tests
 // ============================================================================
 
+#[test]
+fn test_colorize_output_follows_color_choice() {
+    apply_color_choice(ColorChoice::Always);
+    assert!(colorize_output("Error: failed").contains("\x1b["));
+
+    apply_color_choice(ColorChoice::Never);
+    assert_eq!(colorize_output("Error: failed"), "Error: failed");
+
+    // Restore the defaults for the other tests
+    colored::control::unset_override();
+}
+
 #[test]
 fn test_colorize_output_error() {
     let line = "This is an Error message";
@@ -459,6 +544,7 @@ fn test_empty_prd_stories_array() {
         project: "Empty".to_string(),
         branch_name: "ralph/empty".to_string(),
         description: "No stories".to_string(),
+        epics: Vec::new(),
         user_stories: vec![],
     };
 
@@ -473,24 +559,17 @@ fn test_all_stories_passing() {
         project: "Complete".to_string(),
         branch_name: "ralph/complete".to_string(),
         description: "All done".to_string(),
+        epics: Vec::new(),
         user_stories: vec![
             UserStory {
-                id: "US-001".to_string(),
-                title: "Story 1".to_string(),
-                description: "Desc".to_string(),
-                acceptance_criteria: vec![],
                 priority: 1,
                 passes: true,
-                notes: "".to_string(),
+                ..user_story("US-001")
             },
             UserStory {
-                id: "US-002".to_string(),
-                title: "Story 2".to_string(),
-                description: "Desc".to_string(),
-                acceptance_criteria: vec![],
                 priority: 2,
                 passes: true,
-                notes: "".to_string(),
+                ..user_story("US-002")
             },
         ],
     };
@@ -498,3 +577,925 @@ fn test_all_stories_passing() {
     assert_eq!(prd.pending_stories(), 0);
     assert_eq!(prd.completed_stories(), 2);
 }
+
+// ============================================================================
+// Prompt Variable Substitution
+// ============================================================================
+
+fn sample_prd() -> Prd {
+    serde_json::from_str(&create_sample_prd_json()).unwrap()
+}
+
+#[test]
+fn test_render_prompt_project() {
+    let (rendered, unknown) =
+        render_prompt("Working on {{project}}.", &sample_prd(), StoryOrder::Priority);
+    assert_eq!(rendered, "Working on Test Project.");
+    assert!(unknown.is_empty());
+}
+
+#[test]
+fn test_render_prompt_branch() {
+    let (rendered, _) =
+        render_prompt("git checkout {{branch}}", &sample_prd(), StoryOrder::Priority);
+    assert_eq!(rendered, "git checkout ralph/test");
+}
+
+#[test]
+fn test_render_prompt_pending_count() {
+    let (rendered, _) =
+        render_prompt("{{pending_count}} left", &sample_prd(), StoryOrder::Priority);
+    assert_eq!(rendered, "1 left");
+}
+
+#[test]
+fn test_render_prompt_next_story() {
+    let (rendered, _) =
+        render_prompt("Next: {{next_story}}", &sample_prd(), StoryOrder::Priority);
+    assert_eq!(rendered, "Next: US-002 - Second Story");
+
+    let mut prd = sample_prd();
+    prd.user_stories[1].passes = true;
+    let (rendered, unknown) = render_prompt("Next: {{next_story}}", &prd, StoryOrder::Priority);
+    assert_eq!(rendered, "Next: (none)");
+    assert!(unknown.is_empty());
+}
+
+#[test]
+fn test_render_prompt_unfilled_placeholder_marker() {
+    let mut prd = sample_prd();
+    prd.branch_name.clear();
+
+    let (rendered, unknown) = render_prompt("Branch: {{branch}}", &prd, StoryOrder::Priority);
+
+    assert_eq!(rendered, format!("Branch: {}", UNFILLED_PLACEHOLDER));
+    assert!(unknown.is_empty());
+}
+
+#[test]
+fn test_render_prompt_unknown_placeholder_left_untouched() {
+    let (rendered, unknown) = render_prompt(
+        "{{project}} uses {{framework}} and {{framework}}",
+        &sample_prd(),
+        StoryOrder::Priority,
+    );
+    assert_eq!(rendered, "Test Project uses {{framework}} and {{framework}}");
+    assert_eq!(unknown, vec!["framework".to_string()]);
+}
+
+#[test]
+fn test_render_prompt_ignores_non_placeholder_braces() {
+    let template = "const x = {{ a: 1 }}; unterminated {{project";
+    let (rendered, unknown) = render_prompt(template, &sample_prd(), StoryOrder::Priority);
+    assert_eq!(rendered, template);
+    assert!(unknown.is_empty());
+}
+
+#[test]
+fn test_render_prompt_skips_fenced_code_blocks() {
+    let template = "Project: {{project}}\n\n```handlebars\n<h1>{{title}}</h1> {{project}}\n```\n\
+                    ~~~\n{{#each items}}{{name}}{{/each}}\n~~~\nNext: {{next_stroy}}\n";
+    let (rendered, unknown) = render_prompt(template, &sample_prd(), StoryOrder::Priority);
+
+    assert!(rendered.starts_with("Project: Test Project\n"));
+    assert!(rendered.contains("<h1>{{title}}</h1> {{project}}\n"));
+    assert!(rendered.contains("{{#each items}}{{name}}{{/each}}"));
+    assert_eq!(unknown, vec!["next_stroy".to_string()]);
+}
+
+#[test]
+fn test_unresolved_placeholders_lists_each_name_once() {
+    let prompt = "{{next_story_tittle}} and {{ a: 1 }}\n{{framework}} {{next_story_tittle}}\n";
+
+    assert_eq!(unresolved_placeholders(prompt), ["next_story_tittle", "framework"]);
+    assert!(unresolved_placeholders("```\n{{inside}}\n```\n").is_empty());
+}
+
+#[test]
+fn test_check_unknown_placeholders_strict_fails() {
+    let unknown = vec!["next_story_tittle".to_string()];
+
+    assert!(check_unknown_placeholders(&[], true).is_ok());
+    assert!(check_unknown_placeholders(&unknown, false).is_ok());
+    let err = check_unknown_placeholders(&unknown, true).unwrap_err().to_string();
+    assert!(err.contains("{{next_story_tittle}}"), "{}", err);
+}
+
+#[test]
+fn test_embedded_prompt_has_no_unknown_placeholders() {
+    let (rendered, unknown) =
+        render_prompt(get_agent_prompt(), &sample_prd(), StoryOrder::Priority);
+    assert!(unknown.is_empty());
+    assert!(rendered.contains("Test Project"));
+}
+
+#[cfg(not(feature = "tokenizer"))]
+#[test]
+fn test_prompt_token_estimate_known_inputs() {
+    assert_eq!(prompt_token_estimate(""), 0);
+    assert_eq!(prompt_token_estimate("a"), 1);
+    assert_eq!(prompt_token_estimate("abcd"), 1);
+    assert_eq!(prompt_token_estimate("abcde"), 2);
+    assert_eq!(prompt_token_estimate(&"x".repeat(4000)), 1000);
+    // Characters, not bytes: "é" is two bytes
+    assert_eq!(prompt_token_estimate("éééé"), 1);
+}
+
+#[cfg(not(feature = "tokenizer"))]
+#[test]
+fn test_prompt_token_estimate_counts_the_assembled_prompt() {
+    let prd = sample_prd();
+    let (plain, _) = assemble_prompt(&prd, &StorySelector::default(), StoryOrder::Priority, None, None);
+    let prefix = "x".repeat(400);
+    let (prefixed, _) =
+        assemble_prompt(&prd, &StorySelector::default(), StoryOrder::Priority, Some(&prefix), None);
+    // The prefix adds 400 characters and a blank line
+    assert_eq!(prompt_token_estimate(&prefixed), (plain.chars().count() + 402).div_ceil(4));
+    assert!(prompt_token_estimate(&prefixed) >= prompt_token_estimate(&plain) + 100);
+    assert_eq!(
+        prompt_size_summary("abcdefgh"),
+        "Prompt size: 8 characters, ~2 tokens (estimated)"
+    );
+}
+
+#[test]
+fn test_prompt_size_summary_reports_the_estimate() {
+    assert_eq!(
+        prompt_size_summary("abcdefgh"),
+        format!(
+            "Prompt size: 8 characters, ~{} tokens (estimated)",
+            prompt_token_estimate("abcdefgh")
+        )
+    );
+}
+
+#[cfg(feature = "tokenizer")]
+#[test]
+fn test_prompt_token_estimate_uses_the_tokenizer() {
+    assert_eq!(prompt_token_estimate(""), 0);
+    assert_eq!(prompt_token_estimate("hello world"), 2);
+    // A run of one character merges into far fewer tokens than chars/4
+    assert!(prompt_token_estimate(&"x".repeat(4000)) < 1000);
+}
+
+#[test]
+fn test_compact_header_format() {
+    assert_eq!(
+        compact_header("My App", "ralph/my-app", "claude", 2, 5),
+        "Ralph: My App [ralph/my-app] via claude — 2/5 done"
+    );
+}
+
+// ============================================================================
+// Per-Story Completion Signals
+// ============================================================================
+
+#[test]
+fn test_parse_story_passed_signal() {
+    assert_eq!(
+        parse_story_passed("<promise>STORY_PASSED:US-002</promise>"),
+        Some("US-002")
+    );
+    assert_eq!(
+        parse_story_passed("Done! <promise>STORY_PASSED: US-010 </promise> moving on"),
+        Some("US-010")
+    );
+}
+
+#[test]
+fn test_parse_story_passed_rejects_other_lines() {
+    assert_eq!(parse_story_passed("<promise>COMPLETE</promise>"), None);
+    assert_eq!(parse_story_passed("<promise>STORY_PASSED:</promise>"), None);
+    assert_eq!(parse_story_passed("<promise>STORY_PASSED:US-002"), None);
+    assert_eq!(parse_story_passed("Working on US-002"), None);
+}
+
+#[test]
+fn test_story_passed_signal_updates_prd_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd_path = create_temp_prd_file(&temp_dir, &create_sample_prd_json());
+
+    let marked =
+        apply_story_passed_signal("<promise>STORY_PASSED:US-002</promise>", &prd_path, "").unwrap();
+
+    assert_eq!(marked.as_deref(), Some("US-002"));
+    let prd = Prd::from_file(&prd_path).unwrap();
+    assert!(prd.find_story("US-002").unwrap().passes);
+    assert_eq!(prd.pending_stories(), 0);
+}
+
+#[test]
+fn test_story_passed_signal_updates_nested_prd() {
+    let temp_dir = TempDir::new().unwrap();
+    let document = format!(r#"{{"name": "monorepo", "ralph": {}}}"#, create_sample_prd_json());
+    let prd_path = create_temp_prd_file(&temp_dir, &document);
+
+    let marked =
+        apply_story_passed_signal("<promise>STORY_PASSED:US-002</promise>", &prd_path, "/ralph")
+            .unwrap();
+
+    assert_eq!(marked.as_deref(), Some("US-002"));
+    let prd = Prd::from_file_at(&prd_path, "/ralph").unwrap();
+    assert!(prd.find_story("US-002").unwrap().passes);
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&prd_path).unwrap()).unwrap();
+    assert_eq!(saved["name"], "monorepo");
+}
+
+/// Test that library callers pick the nested PRD through `RunOptions::prd_key`
+#[test]
+fn test_run_options_prd_key_selects_nested_prd() {
+    let temp_dir = TempDir::new().unwrap();
+    let document = format!(r#"{{"name": "monorepo", "ralph": {}}}"#, create_sample_prd_json());
+    let prd_path = create_temp_prd_file(&temp_dir, &document);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let options = |prd_key: &str| RunOptions {
+        prd_path: prd_path.to_string_lossy().into_owned(),
+        prd_key: prd_key.to_string(),
+        list_stories: true,
+        readonly: true,
+        ..RunOptions::default()
+    };
+
+    assert!(rt.block_on(run_run(options("/ralph"))).is_ok());
+    let err = rt.block_on(run_run(options(""))).unwrap_err();
+    assert!(err.to_string().contains("Failed to load PRD"), "{}", err);
+}
+
+#[test]
+fn test_story_passed_signal_ignores_passing_story() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd_path = create_temp_prd_file(&temp_dir, &create_sample_prd_json());
+    let before = fs::read_to_string(&prd_path).unwrap();
+
+    let already =
+        apply_story_passed_signal("<promise>STORY_PASSED:US-001</promise>", &prd_path, "").unwrap();
+    let plain = apply_story_passed_signal("regular output", &prd_path, "").unwrap();
+
+    assert!(already.is_none() && plain.is_none());
+    assert_eq!(fs::read_to_string(&prd_path).unwrap(), before);
+}
+
+#[test]
+fn test_story_passed_signal_reports_unknown_story() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd_path = create_temp_prd_file(&temp_dir, &create_sample_prd_json());
+    let before = fs::read_to_string(&prd_path).unwrap();
+
+    let err = apply_story_passed_signal("<promise>STORY_PASSED:US-01</promise>", &prd_path, "")
+        .unwrap_err();
+
+    assert!(matches!(&err, RalphError::Other(msg) if msg.starts_with("Unknown story id: US-01")));
+    assert!(err.to_string().contains("valid ids: US-001"));
+    assert_eq!(fs::read_to_string(&prd_path).unwrap(), before);
+}
+
+#[test]
+fn test_changed_project_detects_swapped_prd() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd: Prd = serde_json::from_str(&create_sample_prd_json()).unwrap();
+
+    // No marker yet (first run, or a ralph dir from before markers existed)
+    assert_eq!(changed_project(temp_dir.path(), &prd), None);
+
+    fs::write(temp_dir.path().join(".last-project"), format!("{}\n", prd.project)).unwrap();
+    assert_eq!(changed_project(temp_dir.path(), &prd), None);
+
+    fs::write(temp_dir.path().join(".last-project"), "Another Project").unwrap();
+    assert_eq!(
+        changed_project(temp_dir.path(), &prd),
+        Some("Another Project".to_string())
+    );
+}
+
+#[test]
+fn test_tool_path_is_the_spawned_command() {
+    let path = std::path::Path::new("/opt/claude-2.1/bin/claude");
+    let tool_cmd = tool_type_for_path("auto", path);
+    let invocation = AgentInvocation::for_tool(&tool_cmd);
+    let cmd = build_agent_command(&invocation, path.as_os_str(), "prompt").unwrap();
+
+    assert_eq!(tool_cmd, "claude");
+    assert_eq!(cmd.as_std().get_program(), path.as_os_str());
+    let args: Vec<_> = cmd.as_std().get_args().collect();
+    assert_eq!(args, ["--dangerously-skip-permissions", "--print"]);
+}
+
+#[test]
+fn test_aider_receives_prompt_as_message() {
+    let invocation = Agent::Aider.invocation();
+    let cmd =
+        build_agent_command(&invocation, std::ffi::OsStr::new("aider"), "Do the next story")
+            .unwrap();
+
+    assert_eq!(cmd.as_std().get_program(), "aider");
+    let args: Vec<_> = cmd.as_std().get_args().collect();
+    assert_eq!(args, ["--yes-always", "--message", "Do the next story"]);
+}
+
+#[test]
+fn test_custom_tool_gets_no_agent_flags() {
+    let invocation = AgentInvocation::for_tool("my-agent");
+    let cmd = build_agent_command(&invocation, std::ffi::OsStr::new("my-agent"), "prompt").unwrap();
+    assert_eq!(cmd.as_std().get_args().count(), 0);
+}
+
+#[test]
+fn test_descriptor_drives_command_without_a_known_agent() {
+    let invocation = AgentInvocation {
+        args: &["--quiet"],
+        unattended_flag: Some("--auto-approve"),
+        prompt: PromptDelivery::Flag("--task"),
+        ..AgentInvocation::CUSTOM
+    };
+
+    let cmd = build_agent_command(&invocation, std::ffi::OsStr::new("future-agent"), "Go")
+        .unwrap();
+
+    let args: Vec<_> = cmd.as_std().get_args().collect();
+    assert_eq!(args, ["--auto-approve", "--quiet", "--task", "Go"]);
+}
+
+#[test]
+fn test_file_prompt_delivery_writes_prompt_file() {
+    let invocation = AgentInvocation {
+        prompt: PromptDelivery::File("--prompt-file"),
+        ..AgentInvocation::CUSTOM
+    };
+
+    let cmd = build_agent_command(&invocation, std::ffi::OsStr::new("file-agent"), "Do it")
+        .unwrap();
+
+    let args: Vec<_> = cmd.as_std().get_args().collect();
+    let path = prompt_file_path();
+    assert_eq!(args, [std::ffi::OsStr::new("--prompt-file"), path.as_os_str()]);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "Do it");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_tool_type_for_path_prefers_explicit_tool() {
+    let path = std::path::Path::new("/usr/local/bin/my-agent");
+
+    assert_eq!(tool_type_for_path("amp", path), "amp");
+    // Unknown file names run as a custom tool, without agent flags
+    assert_eq!(tool_type_for_path("auto", path), "/usr/local/bin/my-agent");
+}
+
+#[test]
+fn test_validate_tool_path() {
+    let temp_dir = TempDir::new().unwrap();
+    let missing = temp_dir.path().join("missing");
+    assert!(validate_tool_path(&missing).is_err());
+    assert!(validate_tool_path(temp_dir.path()).is_err());
+
+    let binary = temp_dir.path().join("agent");
+    fs::write(&binary, "#!/bin/sh\n").unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(validate_tool_path(&binary).is_err());
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    assert!(validate_tool_path(&binary).is_ok());
+}
+
+#[test]
+fn test_assemble_prompt_orders_prefix_embedded_injected_suffix() {
+    let prd: Prd = serde_json::from_str(&create_sample_prd_json()).unwrap();
+    let (prompt, unknown) = assemble_prompt(
+        &prd,
+        &story_selector("US-002"),
+        StoryOrder::Priority,
+        Some("PREFIX: be brief"),
+        Some("SUFFIX: run the linter"),
+    );
+
+    assert!(unknown.is_empty());
+    assert!(prompt.starts_with("PREFIX: be brief\n\n"));
+    assert!(prompt.ends_with("\nSUFFIX: run the linter\n"));
+
+    let embedded = prompt.find(prd.project.as_str()).expect("PRD values are injected");
+    let target = prompt.find("## Target Story").unwrap();
+    let suffix = prompt.find("SUFFIX").unwrap();
+    assert!(prompt.find("PREFIX").unwrap() < embedded);
+    assert!(embedded < target && target < suffix);
+}
+
+#[test]
+fn test_assemble_prompt_without_wrapping_is_rendered_template() {
+    let prd: Prd = serde_json::from_str(&create_sample_prd_json()).unwrap();
+    let (prompt, _) = assemble_prompt(&prd, &StorySelector::default(), StoryOrder::Priority, None, None);
+    let (rendered, _) = render_prompt(get_agent_prompt(), &prd, StoryOrder::Priority);
+
+    assert_eq!(prompt, rendered);
+}
+
+#[test]
+fn test_assemble_prompt_lists_story_order() {
+    let mut prd = sample_prd();
+    prd.user_stories[0].passes = false;
+    prd.user_stories[0].priority = 5;
+
+    let (prompt, _) = assemble_prompt(&prd, &StorySelector::default(), StoryOrder::File, None, None);
+    assert!(prompt.contains("## Story Order"));
+    assert!(prompt.contains("whose dependencies have passed: US-001, US-002."));
+    assert!(prompt.contains("Next story: US-001"));
+
+    // A target story takes over from the order
+    let (targeted, _) =
+        assemble_prompt(&prd, &story_selector("US-002"), StoryOrder::File, None, None);
+    assert!(!targeted.contains("## Story Order"));
+}
+
+#[test]
+fn test_assemble_prompt_restricts_to_epic() {
+    let mut prd = sample_prd();
+    for story in &mut prd.user_stories {
+        story.passes = false;
+    }
+    prd.user_stories[1].epic = Some("Checkout".to_string());
+
+    let (prompt, _) =
+        assemble_prompt(&prd, &epic_selector("Checkout", &[]), StoryOrder::File, None, None);
+    assert!(prompt.contains("## Target Epic"));
+    assert!(prompt.contains("restricted to the \"Checkout\" epic"));
+    assert!(prompt.contains("whose dependencies have passed: US-002."));
+    assert!(!prompt.contains("## Story Order"));
+}
+
+#[test]
+fn test_assemble_prompt_restricts_to_filtered_stories() {
+    let mut prd = sample_prd();
+    for story in &mut prd.user_stories {
+        story.passes = false;
+    }
+    prd.user_stories[1].tags = vec!["backend".to_string()];
+    let selector = StorySelector {
+        tags: vec!["backend".to_string()],
+        ..StorySelector::default()
+    };
+
+    let (prompt, _) = assemble_prompt(&prd, &selector, StoryOrder::Priority, None, None);
+    assert!(prompt.contains("## Story Filter"));
+    assert!(prompt.contains("restricted to these pending stories: US-002."));
+    assert!(!prompt.contains("## Story Order"));
+}
+
+#[test]
+fn test_assemble_prompt_leaves_out_excluded_stories() {
+    let mut prd = sample_prd();
+    for story in &mut prd.user_stories {
+        story.passes = false;
+    }
+    let excluded = vec!["US-001".to_string()];
+
+    let (prompt, _) = assemble_prompt(&prd, &excluding(&excluded), StoryOrder::File, None, None);
+    assert!(prompt.contains("## Excluded Stories"));
+    assert!(prompt.contains("Do not work on these stories in this run"));
+    assert!(prompt.contains("whose dependencies have passed: US-002."));
+    assert!(prompt.contains("Next story: US-002"));
+    assert!(!prompt.contains(&prd.user_stories[0].title));
+
+    let (plain, _) = assemble_prompt(&prd, &StorySelector::default(), StoryOrder::File, None, None);
+    assert!(!plain.contains("## Excluded Stories"));
+}
+
+#[test]
+fn test_resolve_story_order() {
+    assert_eq!(resolve_story_order(StoryOrderChoice::Priority, Some(3)), StoryOrder::Priority);
+    assert_eq!(resolve_story_order(StoryOrderChoice::File, None), StoryOrder::File);
+    assert_eq!(resolve_story_order(StoryOrderChoice::Random, Some(3)), StoryOrder::Random(3));
+    assert!(matches!(
+        resolve_story_order(StoryOrderChoice::Random, None),
+        StoryOrder::Random(_)
+    ));
+}
+
+// ============================================================================
+// Iteration Outcome Tests
+// ============================================================================
+
+/// Run one iteration of `sh -c <script>` against a PRD written to `temp_dir`
+#[cfg(unix)]
+async fn run_shell_iteration(
+    temp_dir: &TempDir,
+    script: &'static [&'static str],
+) -> crate::commands::run::IterationOutcome {
+    use crate::commands::run::{run_agent_iteration, IterationContext};
+    use crate::output::OutputRedactor;
+    use crate::persistence::RunStore;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    let prd_path = create_temp_prd_file(temp_dir, &create_sample_prd_json());
+    let prd = Prd::from_file(&prd_path).unwrap();
+    let redactor = OutputRedactor::default();
+    let context = IterationContext {
+        tool_cmd: "sh",
+        invocation: AgentInvocation {
+            args: script,
+            ..AgentInvocation::CUSTOM
+        },
+        program: std::ffi::OsStr::new("sh"),
+        ralph_dir: temp_dir.path(),
+        prd_path: &prd_path,
+        prd_key: "",
+        env: &[],
+        events: None,
+        selector: &StorySelector::default(),
+        flush_interval: std::time::Duration::from_millis(10),
+        max_output: 0,
+        redactor: &redactor,
+        redact_terminal: false,
+        prompt_prefix: None,
+        prompt_suffix: None,
+        story_order: StoryOrder::Priority,
+        strict_prompt: false,
+        show_progress: false,
+        verbose_spawn_errors: false,
+        track_usage: false,
+        prompt_history: false,
+        store: &RunStore::default(),
+    };
+
+    run_agent_iteration(&context, &prd, 1, Arc::new(AtomicBool::new(true)))
+        .await
+        .unwrap()
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_iteration_outcome_reports_exit_code_bytes_and_stories() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let outcome = run_shell_iteration(
+        &temp_dir,
+        &[
+            "-c",
+            "cat >/dev/null; echo hello; echo '<promise>STORY_PASSED:US-002</promise>'; \
+             echo oops >&2; exit 3",
+        ],
+    )
+    .await;
+
+    assert!(!outcome.completed);
+    assert_eq!(outcome.exit_code, Some(3));
+    let expected_bytes = "hello\n".len() + "<promise>STORY_PASSED:US-002</promise>\n".len() + 5;
+    assert_eq!(outcome.bytes_out, expected_bytes);
+    assert_eq!(outcome.stories_passed, ["US-002"]);
+    assert!(!outcome.output_limit_exceeded);
+    assert!(outcome.duration > std::time::Duration::ZERO);
+
+    let prd = Prd::from_file(temp_dir.path().join("prd.json")).unwrap();
+    assert!(prd.find_story("US-002").unwrap().passes);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_iteration_outcome_reports_completion_signal() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let outcome = run_shell_iteration(
+        &temp_dir,
+        &["-c", "cat >/dev/null; echo '<promise>COMPLETE</promise>'"],
+    )
+    .await;
+
+    assert!(outcome.completed);
+    assert_eq!(outcome.exit_code, Some(0));
+    assert!(outcome.stories_passed.is_empty());
+}
+
+// ============================================================================
+// Progress Header Tests
+// ============================================================================
+
+const PROGRESS_WITH_HEADER: &str = "# Ralph Progress Log
+Project: Test Project
+Branch: ralph/old-feature
+Started: 2026-03-01 09:00:00
+---
+## US-001
+Branch: notes written by the agent are not part of the header
+";
+
+#[test]
+fn test_progress_header_parses_project_and_branch() {
+    let header = ProgressHeader::parse(PROGRESS_WITH_HEADER);
+
+    assert_eq!(header.project.as_deref(), Some("Test Project"));
+    assert_eq!(header.branch.as_deref(), Some("ralph/old-feature"));
+}
+
+#[test]
+fn test_progress_header_tolerates_older_files() {
+    let legacy = "# Ralph Progress Log\nStarted: 2025-01-01 10:00:00\n---\n";
+    assert_eq!(ProgressHeader::parse(legacy), ProgressHeader::default());
+
+    // Files that are not ralph progress logs have no header at all
+    assert_eq!(ProgressHeader::parse("Branch: ralph/x\n---\n"), ProgressHeader::default());
+    assert_eq!(ProgressHeader::parse(""), ProgressHeader::default());
+}
+
+#[test]
+fn test_progress_header_mismatch() {
+    let prd: Prd = serde_json::from_str(&create_sample_prd_json()).unwrap();
+    let header = ProgressHeader::parse(PROGRESS_WITH_HEADER);
+
+    assert_eq!(
+        header.mismatch(&prd).as_deref(),
+        Some("branch ralph/old-feature (the PRD targets ralph/test)")
+    );
+    assert_eq!(ProgressHeader::default().mismatch(&prd), None);
+
+    let matching = ProgressHeader {
+        project: Some("Test Project".to_string()),
+        branch: Some("ralph/test".to_string()),
+    };
+    assert_eq!(matching.mismatch(&prd), None);
+}
+
+#[test]
+fn test_restamp_progress_header_keeps_the_log() {
+    let prd: Prd = serde_json::from_str(&create_sample_prd_json()).unwrap();
+
+    let updated = restamp_progress_header(PROGRESS_WITH_HEADER, &prd).unwrap();
+
+    assert_eq!(ProgressHeader::parse(&updated).branch.as_deref(), Some("ralph/test"));
+    assert!(updated.ends_with("Branch: notes written by the agent are not part of the header\n"));
+    assert_eq!(
+        restamp_progress_header("# Ralph Progress Log\nStarted: today\n---\n", &prd),
+        None
+    );
+}
+
+#[test]
+fn test_run_summary_entry_is_appended() {
+    let temp_dir = TempDir::new().unwrap();
+    let progress = temp_dir.path().join("progress.txt");
+    fs::write(&progress, PROGRESS_WITH_HEADER).unwrap();
+
+    let passed = ["US-001".to_string(), "US-004".to_string()];
+    let items = run_summary_items(3, 10, &passed, RunOutcome::Complete);
+    append_progress_entry(&progress, "Ralph run summary - 2026-01-05 10:00:00", &items).unwrap();
+
+    let content = fs::read_to_string(&progress).unwrap();
+    assert!(content.starts_with(PROGRESS_WITH_HEADER));
+    assert!(content.ends_with(
+        "## Ralph run summary - 2026-01-05 10:00:00\n\
+         - Iterations: 3/10\n\
+         - Stories completed this run: US-001, US-004\n\
+         - Ended: complete\n\
+         ---\n"
+    ));
+    let none = run_summary_items(10, 10, &[], RunOutcome::MaxIterations);
+    assert_eq!(none[1], "Stories completed this run: none");
+    assert_eq!(none[2], "Ended: maximum iterations reached");
+}
+
+// ============================================================================
+// PRD Snapshot Tests
+// ============================================================================
+
+#[test]
+fn test_snapshot_prd_copies_readable_prd() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd_path = create_temp_prd_file(&temp_dir, &create_sample_prd_json());
+
+    assert!(snapshot_prd_file(&prd_path, "", temp_dir.path()).unwrap());
+    assert!(Prd::from_file(temp_dir.path().join(PRD_BACKUP_FILE)).is_ok());
+    assert!(restorable_prd_backup(&prd_path, "", temp_dir.path()).is_none());
+}
+
+#[test]
+fn test_snapshot_prd_keeps_backup_when_prd_is_corrupt() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd_path = create_temp_prd_file(&temp_dir, &create_sample_prd_json());
+    snapshot_prd_file(&prd_path, "", temp_dir.path()).unwrap();
+
+    // The agent was interrupted halfway through rewriting the file
+    fs::write(&prd_path, r#"{"project": "Test Project", "userStories": [{"id": "#).unwrap();
+
+    assert!(!snapshot_prd_file(&prd_path, "", temp_dir.path()).unwrap());
+    assert_eq!(
+        restorable_prd_backup(&prd_path, "", temp_dir.path()),
+        Some(temp_dir.path().join(PRD_BACKUP_FILE))
+    );
+}
+
+#[test]
+fn test_checkpoint_prd_writes_numbered_copies() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd_path = create_temp_prd_file(&temp_dir, &create_sample_prd_json());
+
+    let first = checkpoint_prd(&prd_path, "", temp_dir.path(), 2).unwrap().unwrap();
+    assert_eq!(first, temp_dir.path().join(CHECKPOINT_DIR).join("prd-0002.json"));
+    let second = checkpoint_prd(&prd_path, "", temp_dir.path(), 4).unwrap().unwrap();
+    assert_eq!(second.file_name().unwrap(), "prd-0004.json");
+
+    // Earlier checkpoints are kept
+    assert!(first.exists());
+    assert_eq!(Prd::from_file(&second).unwrap().project, "Test Project");
+}
+
+#[test]
+fn test_checkpoint_prd_skips_corrupt_prd() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd_path = create_temp_prd_file(&temp_dir, "{ truncated");
+
+    assert_eq!(checkpoint_prd(&prd_path, "", temp_dir.path(), 1).unwrap(), None);
+    assert!(!temp_dir.path().join(CHECKPOINT_DIR).exists());
+    assert_eq!(fs::read_to_string(&prd_path).unwrap(), "{ truncated");
+}
+
+#[test]
+fn test_write_prompt_history_names_files_by_iteration() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let first = write_prompt_history(temp_dir.path(), 1, "first prompt").unwrap();
+    assert_eq!(first, temp_dir.path().join(PROMPT_HISTORY_DIR).join("iter-1.md"));
+    let second = write_prompt_history(temp_dir.path(), 12, "second prompt").unwrap();
+    assert_eq!(second.file_name().unwrap(), "iter-12.md");
+    assert_eq!(fs::read_to_string(&first).unwrap(), "first prompt");
+
+    // Saving the same iteration again replaces the prompt
+    write_prompt_history(temp_dir.path(), 1, "rerun prompt").unwrap();
+    assert_eq!(fs::read_to_string(&first).unwrap(), "rerun prompt");
+}
+
+#[test]
+fn test_restore_prd_backup_keeps_corrupt_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd_path = create_temp_prd_file(&temp_dir, &create_sample_prd_json());
+    snapshot_prd_file(&prd_path, "", temp_dir.path()).unwrap();
+    fs::write(&prd_path, "{ truncated").unwrap();
+
+    restore_prd_backup(&prd_path, temp_dir.path()).unwrap();
+
+    assert_eq!(Prd::from_file(&prd_path).unwrap().project, "Test Project");
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(PRD_CORRUPT_FILE)).unwrap(),
+        "{ truncated"
+    );
+}
+
+#[test]
+fn test_no_restore_without_readable_backup() {
+    let temp_dir = TempDir::new().unwrap();
+    let prd_path = create_temp_prd_file(&temp_dir, "{ truncated");
+
+    assert!(restorable_prd_backup(&prd_path, "", temp_dir.path()).is_none());
+
+    fs::write(temp_dir.path().join(PRD_BACKUP_FILE), "not json either").unwrap();
+    assert!(restorable_prd_backup(&prd_path, "", temp_dir.path()).is_none());
+    let missing = temp_dir.path().join("missing.json");
+    assert!(restorable_prd_backup(&missing, "", temp_dir.path()).is_none());
+}
+
+// ============================================================================
+// Work Queue Tests
+// ============================================================================
+
+/// Five stories: one passed, one blocked, two in the "api" epic
+fn queue_prd() -> Prd {
+    let story = |id: &str, title: &str, priority: u32, depends_on: &[&str], epic: Option<&str>| {
+        UserStory {
+            title: title.to_string(),
+            description: String::new(),
+            acceptance_criteria: Vec::new(),
+            priority,
+            passes: id == "US-002",
+            depends_on: strings(depends_on),
+            epic: epic.map(String::from),
+            ..user_story(id)
+        }
+    };
+    Prd {
+        project: "Queue Project".to_string(),
+        branch_name: "ralph/queue".to_string(),
+        description: String::new(),
+        epics: Vec::new(),
+        user_stories: vec![
+            story("US-001", "Setup", 3, &[], None),
+            story("US-002", "Schema", 1, &[], None),
+            story("US-003", "Client", 2, &["US-004"], None),
+            story("US-004", "Server", 5, &[], Some("api")),
+            story("US-005", "Routes", 4, &["US-002"], Some("api")),
+        ],
+    }
+}
+
+fn queue_ids(stories: &[&UserStory]) -> Vec<String> {
+    stories.iter().map(|s| s.id.clone()).collect()
+}
+
+#[test]
+fn test_work_queue_buckets_in_priority_order() {
+    let prd = queue_prd();
+    let queue = WorkQueue::from_prd(&prd, StoryOrder::Priority, &StorySelector::default());
+
+    assert_eq!(queue_ids(&queue.actionable), ["US-001", "US-005", "US-004"]);
+    assert_eq!(queue.blocked.len(), 1);
+    assert_eq!(queue.blocked[0].0.id, "US-003");
+    assert_eq!(queue.blocked[0].1, ["US-004"]);
+    assert_eq!(queue_ids(&queue.completed), ["US-002"]);
+}
+
+#[test]
+fn test_work_queue_follows_story_order() {
+    let prd = queue_prd();
+    let queue = WorkQueue::from_prd(&prd, StoryOrder::File, &StorySelector::default());
+
+    assert_eq!(queue_ids(&queue.actionable), ["US-001", "US-004", "US-005"]);
+}
+
+#[test]
+fn test_work_queue_narrowed_to_epic_or_story() {
+    let prd = queue_prd();
+
+    let epic = WorkQueue::from_prd(&prd, StoryOrder::Priority, &epic_selector("api", &[]));
+    assert_eq!(queue_ids(&epic.actionable), ["US-005", "US-004"]);
+    assert!(epic.blocked.is_empty() && epic.completed.is_empty());
+
+    let story = WorkQueue::from_prd(&prd, StoryOrder::Priority, &story_selector("US-003"));
+    assert!(story.actionable.is_empty());
+    assert_eq!(story.blocked[0].0.id, "US-003");
+}
+
+#[test]
+fn test_work_queue_skips_excluded_stories() {
+    let prd = queue_prd();
+    let excluded = vec!["US-005".to_string(), "US-004".to_string()];
+
+    let queue = WorkQueue::from_prd(&prd, StoryOrder::Priority, &excluding(&excluded));
+    assert_eq!(queue_ids(&queue.actionable), ["US-001"]);
+    assert_eq!(queue.blocked[0].0.id, "US-003");
+
+    let epic = WorkQueue::from_prd(&prd, StoryOrder::Priority, &epic_selector("api", &excluded[..1]));
+    assert_eq!(queue_ids(&epic.actionable), ["US-004"]);
+}
+
+#[test]
+fn test_check_excluded_stories() {
+    let prd = queue_prd();
+    let excluded = vec!["US-002".to_string()];
+
+    assert!(check_excluded_stories(&prd, &excluded, None).is_ok());
+    assert!(check_excluded_stories(&prd, &excluded, Some("US-001")).is_ok());
+    let err = check_excluded_stories(&prd, &excluded, Some("US-002")).unwrap_err();
+    assert!(err.to_string().contains("also the target of this run"));
+    let err = check_excluded_stories(&prd, &["US-404".to_string()], None).unwrap_err();
+    assert!(err.to_string().contains("Unknown story id: US-404"));
+}
+
+#[test]
+fn test_check_required_criteria() {
+    let mut prd = queue_prd();
+
+    // Not required: only the startup warning applies
+    assert!(check_required_criteria(&prd, false).is_ok());
+
+    // Passing stories do not count
+    let err = check_required_criteria(&prd, true).unwrap_err().to_string();
+    assert!(
+        err.contains("4 pending stories have no acceptance criteria: US-001, US-003, US-004, US-005"),
+        "{}",
+        err
+    );
+    assert!(err.contains("--allow-empty-criteria"));
+
+    for id in ["US-001", "US-003", "US-004"] {
+        prd.add_criterion(id, "It works").unwrap();
+    }
+    let err = check_required_criteria(&prd, true).unwrap_err().to_string();
+    assert!(err.contains("1 pending story has no acceptance criteria: US-005"), "{}", err);
+
+    prd.add_criterion("US-005", "It works").unwrap();
+    assert!(check_required_criteria(&prd, true).is_ok());
+}
+
+#[test]
+fn test_work_queue_render() {
+    let prd = queue_prd();
+    let output = WorkQueue::from_prd(&prd, StoryOrder::Priority, &StorySelector::default()).render();
+    let output = console::strip_ansi_codes(&output);
+
+    assert_eq!(
+        output,
+        "Work queue for Queue Project\n\
+         \n\
+         Actionable (3):\n  \
+         1. US-001 - Setup\n  \
+         2. US-005 - Routes\n  \
+         3. US-004 - Server\n\
+         \n\
+         Blocked (1):\n  \
+         - US-003 - Client (waiting on US-004)\n\
+         \n\
+         Completed (1):\n  \
+         ✓ US-002 - Schema\n"
+    );
+}
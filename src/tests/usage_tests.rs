@@ -0,0 +1,88 @@
+//! Usage and Budget Tests
+//!
+//! Tests for `ralph run --budget`:
+//! - Parsing token counts and dollar amounts
+//! - Rejecting malformed, zero and negative budgets
+//! - Reading usage from Claude's stream-json result event
+//! - Adding up usage and checking it against a budget
+
+use crate::usage::{group_thousands, parse_budget, Budget, Usage};
+
+#[test]
+fn test_parse_budget_token_counts() {
+    assert_eq!(parse_budget("500000"), Ok(Budget::Tokens(500_000)));
+    assert_eq!(parse_budget("500k"), Ok(Budget::Tokens(500_000)));
+    assert_eq!(parse_budget("500K"), Ok(Budget::Tokens(500_000)));
+    assert_eq!(parse_budget("1.5M"), Ok(Budget::Tokens(1_500_000)));
+    assert_eq!(parse_budget("2m"), Ok(Budget::Tokens(2_000_000)));
+    assert_eq!(parse_budget(" 10k "), Ok(Budget::Tokens(10_000)));
+}
+
+#[test]
+fn test_parse_budget_dollar_amounts() {
+    assert_eq!(parse_budget("$5.00"), Ok(Budget::Cost(5.0)));
+    assert_eq!(parse_budget("$5"), Ok(Budget::Cost(5.0)));
+    assert_eq!(parse_budget("$0.25"), Ok(Budget::Cost(0.25)));
+}
+
+#[test]
+fn test_parse_budget_rejects_invalid_values() {
+    for value in [
+        "", "k", "abc", "5x", "-5", "$", "$abc", "$-1", "$0", "0", "0k", "1.5", "1e6", "inf",
+        "$inf", "5 tokens",
+    ] {
+        let error = parse_budget(value).expect_err(value);
+        assert!(error.contains("is not a budget"), "{}: {}", value, error);
+    }
+}
+
+#[test]
+fn test_budget_display() {
+    assert_eq!(Budget::Tokens(500_000).to_string(), "500,000 tokens");
+    assert_eq!(Budget::Cost(5.0).to_string(), "$5.00");
+    assert_eq!(group_thousands(0), "0");
+    assert_eq!(group_thousands(999), "999");
+    assert_eq!(group_thousands(1_234_567), "1,234,567");
+}
+
+#[test]
+fn test_usage_from_stream_json_result_event() {
+    let line = r#"{"type":"result","subtype":"success","result":"Done","total_cost_usd":0.42,
+        "usage":{"input_tokens":100,"cache_creation_input_tokens":20,"cache_read_input_tokens":5,"output_tokens":50}}"#
+        .replace('\n', "");
+    let usage = Usage::from_stream_json(&line).unwrap();
+    assert_eq!(usage.input_tokens, 125);
+    assert_eq!(usage.output_tokens, 50);
+    assert_eq!(usage.tokens(), 175);
+    assert_eq!(usage.cost_usd, 0.42);
+    assert_eq!(usage.to_string(), "175 tokens (125 in, 50 out), $0.42");
+}
+
+#[test]
+fn test_usage_ignores_other_lines() {
+    // Per-message usage would double count the result's totals
+    let assistant = r#"{"type":"assistant","message":{"usage":{"input_tokens":100,"output_tokens":5}}}"#;
+    assert_eq!(Usage::from_stream_json(assistant), None);
+    assert_eq!(Usage::from_stream_json("plain text output"), None);
+    assert_eq!(Usage::from_stream_json(""), None);
+}
+
+#[test]
+fn test_budget_is_exceeded_by_cumulative_usage() {
+    let iteration = Usage {
+        input_tokens: 300,
+        output_tokens: 100,
+        cost_usd: 1.5,
+    };
+    let mut total = Usage::default();
+    total.add(&iteration);
+    assert!(!Budget::Tokens(500).is_exceeded(&total));
+    assert!(!Budget::Cost(2.0).is_exceeded(&total));
+
+    total.add(&iteration);
+    assert_eq!(total.tokens(), 800);
+    assert!(Budget::Tokens(500).is_exceeded(&total));
+    assert!(Budget::Cost(2.0).is_exceeded(&total));
+    // Reaching the cap exactly is still within budget
+    assert!(!Budget::Tokens(800).is_exceeded(&total));
+}
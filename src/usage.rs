@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Tokens and cost an agent reported for its work
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    /// Prompt tokens, including tokens written to and read from the prompt cache
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl Usage {
+    /// Read the usage from a line of Claude's `stream-json` output
+    ///
+    /// Only the final `result` event is used: it carries the totals for the
+    /// whole iteration, while the per-message usage before it would be
+    /// counted twice.
+    pub fn from_stream_json(line: &str) -> Option<Self> {
+        let event: serde_json::Value = serde_json::from_str(line.trim()).ok()?;
+        if event.get("type")?.as_str()? != "result" {
+            return None;
+        }
+        let usage = event.get("usage");
+        let count = |field: &str| {
+            usage
+                .and_then(|u| u.get(field))
+                .and_then(serde_json::Value::as_u64)
+                .unwrap_or(0)
+        };
+        Some(Self {
+            input_tokens: count("input_tokens")
+                + count("cache_creation_input_tokens")
+                + count("cache_read_input_tokens"),
+            output_tokens: count("output_tokens"),
+            cost_usd: event
+                .get("total_cost_usd")
+                .and_then(serde_json::Value::as_f64)
+                .unwrap_or(0.0),
+        })
+    }
+
+    /// Input and output tokens together
+    pub fn tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    /// Add the usage of another iteration
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+impl fmt::Display for Usage {
+    /// e.g. `12,345 tokens (12,000 in, 345 out), $0.42`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} tokens ({} in, {} out), ${:.2}",
+            group_thousands(self.tokens()),
+            group_thousands(self.input_tokens),
+            group_thousands(self.output_tokens),
+            self.cost_usd
+        )
+    }
+}
+
+/// Cap on the usage of a run (`ralph run --budget`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Budget {
+    /// Input and output tokens, e.g. `500k`
+    Tokens(u64),
+    /// Dollars, e.g. `$5.00`
+    Cost(f64),
+}
+
+impl Budget {
+    /// Whether the usage has gone over the cap
+    pub fn is_exceeded(&self, usage: &Usage) -> bool {
+        match *self {
+            Budget::Tokens(cap) => usage.tokens() > cap,
+            Budget::Cost(cap) => usage.cost_usd > cap,
+        }
+    }
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Budget::Tokens(cap) => write!(f, "{} tokens", group_thousands(*cap)),
+            Budget::Cost(cap) => write!(f, "${:.2}", cap),
+        }
+    }
+}
+
+/// Parse a `--budget` value: a token count (`500000`, `500k`, `1.5M`) or a
+/// dollar amount (`$5`, `$5.00`)
+pub fn parse_budget(value: &str) -> Result<Budget, String> {
+    let invalid = || {
+        format!(
+            "\"{}\" is not a budget; use a token count like 500k or a dollar amount like $5.00",
+            value
+        )
+    };
+    let trimmed = value.trim();
+
+    if let Some(amount) = trimmed.strip_prefix('$') {
+        let amount: f64 = amount.trim().parse().map_err(|_| invalid())?;
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(invalid());
+        }
+        return Ok(Budget::Cost(amount));
+    }
+
+    let (number, multiplier) = match trimmed.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&trimmed[..index], 1_000.0),
+        Some((index, 'm' | 'M')) => (&trimmed[..index], 1_000_000.0),
+        _ => (trimmed, 1.0),
+    };
+    // Only plain decimal digits; "inf", "1e6" and signs are rejected
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return Err(invalid());
+    }
+    let tokens = number.parse::<f64>().map_err(|_| invalid())? * multiplier;
+    if tokens < 1.0 || tokens.fract() != 0.0 || tokens > u64::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(Budget::Tokens(tokens as u64))
+}
+
+/// Format a number with commas between groups of three digits
+pub fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
@@ -0,0 +1,34 @@
+use std::path::{Path, PathBuf};
+
+/// Name of the directory holding Ralph's project files
+pub const RALPH_DIR_NAME: &str = "ralph";
+
+/// Files that only appear inside a ralph workspace directory
+const WORKSPACE_MARKERS: &[&str] = &["prd.json", "progress.txt", ".last-branch"];
+
+/// Check whether `dir` is itself a ralph workspace directory rather than a project root
+///
+/// A directory qualifies when it holds both `prd.json` and `.last-branch`, or
+/// when it is named `ralph`, holds any workspace file, and has a parent
+/// project. A directory that contains its own `ralph/` is a project root.
+pub fn is_ralph_workspace_dir(dir: &Path) -> bool {
+    if dir.join(RALPH_DIR_NAME).is_dir() {
+        return false;
+    }
+
+    let has_prd_and_branch = dir.join("prd.json").is_file() && dir.join(".last-branch").is_file();
+    let named_ralph = dir.file_name().is_some_and(|n| n == RALPH_DIR_NAME)
+        && dir.parent().is_some()
+        && WORKSPACE_MARKERS.iter().any(|m| dir.join(m).exists());
+
+    has_prd_and_branch || named_ralph
+}
+
+/// Get the project root when `dir` is a ralph workspace directory
+pub fn nested_workspace_root(dir: &Path) -> Option<PathBuf> {
+    if is_ralph_workspace_dir(dir) {
+        dir.parent().map(Path::to_path_buf)
+    } else {
+        None
+    }
+}